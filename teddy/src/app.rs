@@ -1,14 +1,21 @@
 use crate::prelude::f;
-use std::{error::Error, io::Stdout};
+use std::{
+  error::Error,
+  io::Stdout,
+  path::{Path, PathBuf},
+};
 
 use chrono::Utc;
 use clier_parser::Argv;
 use ratatui::prelude::CrosstermBackend;
 use teddy_core::action::{Action, Notification};
-use teddy_events::{Event, EventStream};
+use teddy_events::{Event, EventName, EventStream};
 use tokio::sync::mpsc;
 
-use crate::{editor::Editor, frame::notification_manager::NotificationMessage, ui::Renderer};
+use crate::{
+  editor::Editor, frame::notification_manager::NotificationMessage, session::Session, swap,
+  ui::Renderer,
+};
 
 /// This should only hold state and not do any rendering..
 pub struct Teddy {
@@ -16,6 +23,8 @@ pub struct Teddy {
   action_receiver: mpsc::UnboundedReceiver<Action>,
   /// The sender of actions to components
   action_sender: mpsc::UnboundedSender<Action>,
+  /// Events from background tasks, merged into the [`EventStream`] once running.
+  background_events: Option<mpsc::UnboundedReceiver<Event>>,
 
   editor: Editor,
   renderer: Renderer,
@@ -26,20 +35,63 @@ pub struct Teddy {
 impl Teddy {
   pub fn with_backend(tui: CrosstermBackend<Stdout>) -> Self {
     let (action_sender, action_receiver) = mpsc::unbounded_channel();
+    let (background_sender, background_receiver) = mpsc::unbounded_channel();
 
     let config = teddy_config::Config::default();
 
+    let mut editor = Editor::new(&config, background_sender);
+    let mut actions = editor.load_options();
+    actions.extend(editor.load_macros());
+    actions.extend(editor.load_snippets(&config));
+    for action in actions {
+      let _ = action_sender.send(action);
+    }
+
     Teddy {
-      editor: Editor::default(),
+      editor,
       renderer: Renderer::with_backend(tui, config),
       action_receiver,
       action_sender,
+      background_events: Some(background_receiver),
       should_quit: false,
     }
   }
 
-  pub fn init(&mut self, _args: Argv) -> crate::prelude::Result<()> {
-    self.editor.frames.add_window().unwrap();
+  /// Opens the files given on the command line. Without any, or with `--session[=file]`, the
+  /// session of the working directory (or `file`) is restored. `-d a b` diffs `a` and `b`.
+  pub fn init(&mut self, args: Argv) -> crate::prelude::Result<()> {
+    if let Some(left) = args.flags.get("d").filter(|left| *left != "true") {
+      let right = args.commands.first().ok_or("Usage: teddy -d {file} {file}")?;
+      let mut actions = self.editor.open_file(Path::new(left))?;
+      actions.extend(self.editor.diff_split(Path::new(right))?);
+      for action in actions {
+        self.action_sender.send(action)?;
+      }
+      return Ok(());
+    }
+
+    let session = match args.flags.get("session").map(String::as_str) {
+      Some("true") => Session::default_path(),
+      Some(path) => Some(PathBuf::from(path)),
+      None if args.commands.is_empty() => Session::default_path().filter(|path| path.exists()),
+      None => None,
+    };
+    if let Some(path) = &session {
+      for action in self.editor.source_session(Some(path.clone())) {
+        self.action_sender.send(action)?;
+      }
+    }
+
+    let session = session.as_deref().and_then(Path::to_str);
+    for path in args.commands.iter().filter(|path| Some(path.as_str()) != session) {
+      for action in self.editor.open_file(Path::new(path))? {
+        self.action_sender.send(action)?;
+      }
+    }
+
+    if self.editor.frames.active_frame().is_none() {
+      self.editor.open_scratch()?;
+    }
     Ok(())
   }
 
@@ -49,8 +101,24 @@ impl Teddy {
       Event::Crossterm(CrosstermEvent::Key(key)) => self.editor.keyevent(key),
       Event::Crossterm(CrosstermEvent::Resize(_, _)) => Some(Vec::from_iter([Action::Render])),
       Event::Crossterm(CrosstermEvent::Mouse(_mouse)) => None,
-      Event::Render => None,
-      _ => unimplemented!("{:?}", event),
+      Event::Render => {
+        self.editor.frames.notification_manager.tick();
+        self.editor.snapshot_swaps();
+        self.editor.request_blame();
+        None
+      }
+      Event::Lsp(event) => self.editor.lsp_event(event),
+      Event::FileLoad(event) => self.editor.file_load_event(event),
+      Event::FileChanged(path) => self.editor.file_changed(&path),
+      Event::Vcs(event) => self.editor.vcs_event(event),
+      Event::Terminal(event) => self.editor.terminal_event(event),
+      Event::Shell(event) => self.editor.shell_event(event),
+      Event::Task(event) => self.editor.task_event(event),
+      Event::Format(event) => self.editor.format_event(event),
+      event => {
+        tracing::trace!("Ignoring {event:?}");
+        None
+      }
     };
 
     Ok(output)
@@ -58,21 +126,170 @@ impl Teddy {
 
   fn handle_action(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
     match action {
-      Action::Quit => self.should_quit = true,
+      Action::Quit => {
+        // Keep the working directory's session up to date once it has one.
+        if let Some(path) = Session::default_path().filter(|path| path.exists()) {
+          if let Err(err) = self.editor.session().save(&path) {
+            tracing::warn!("Failed to save the session: {err}");
+          }
+        }
+        swap::forget_all();
+        self.should_quit = true
+      }
       Action::Render => self.renderer.ui(&mut self.editor)?,
-      Action::CloseActiveBuffer => self.editor.remove_active_buffer()?,
-      Action::WriteActiveBuffer => self.editor.write_active_buffer()?,
+      Action::CloseActiveBuffer => {
+        if let Err(err) = self.editor.remove_active_buffer() {
+          let notification = Notification::error(f!("Failed to close: {err}"));
+          self.action_sender.send(Action::AttachNotification(notification, 4))?;
+        }
+      }
+      Action::WriteActiveBuffer => match self.editor.write_active_buffer() {
+        Ok(action) => send_optional(&self.action_sender, action)?,
+        Err(err) => {
+          let notification = Notification::error(f!("Failed to write: {err}"));
+          self.action_sender.send(Action::AttachNotification(notification, 6))?;
+        }
+      },
       Action::AttachNotification(notification, time) => {
         let date = Utc::now().timestamp() + time as i64;
         let notification = NotificationMessage::new(notification, date);
         self.editor.frames.notification_manager.append(notification)
       }
-      _ => tracing::error!("Error: {action:?} is not implemented"),
+      Action::WriteDiagnostic(diagnostic) => self.editor.write_diagnostic(diagnostic),
+      Action::ClearDiagnostics(path) => self.editor.clear_diagnostics(&path),
+      Action::NextDiagnostic => {
+        send_optional(&self.action_sender, self.editor.goto_diagnostic(true))?
+      }
+      Action::PreviousDiagnostic => {
+        send_optional(&self.action_sender, self.editor.goto_diagnostic(false))?
+      }
+      Action::ShowDiagnostics => self.editor.show_diagnostics(),
+      Action::NextHunk => send_optional(&self.action_sender, self.editor.goto_hunk(true))?,
+      Action::PreviousHunk => send_optional(&self.action_sender, self.editor.goto_hunk(false))?,
+      Action::StageHunk => send_optional(&self.action_sender, self.editor.stage_hunk())?,
+      Action::ResetHunk => send_optional(&self.action_sender, self.editor.reset_hunk())?,
+      Action::DiffSplit(path) => match self.editor.diff_split(&path) {
+        Ok(actions) => {
+          for action in actions {
+            self.action_sender.send(action)?;
+          }
+        }
+        Err(err) => {
+          let notification = Notification::error(f!("{}: {err}", path.display()));
+          self.action_sender.send(Action::AttachNotification(notification, 6))?;
+        }
+      },
+      Action::DiffOff => self.editor.diff = None,
+      Action::NextChange => send_optional(&self.action_sender, self.editor.goto_change(true))?,
+      Action::PreviousChange => send_optional(&self.action_sender, self.editor.goto_change(false))?,
+      Action::DiffGet => send_optional(&self.action_sender, self.editor.diff_get(false))?,
+      Action::DiffPut => send_optional(&self.action_sender, self.editor.diff_get(true))?,
+      Action::SwitchDiffSide => self.editor.switch_diff_side(),
+      Action::Format => send_optional(&self.action_sender, self.editor.format())?,
+      Action::Shell { command, output } => {
+        send_optional(&self.action_sender, self.editor.shell(command, output))?
+      }
+      Action::CancelShell => self.editor.cancel_shell(),
+      Action::RunTask(name) => send_optional(&self.action_sender, self.editor.run_task(name))?,
+      Action::NextQuickfix => {
+        for action in self.editor.goto_quickfix(true) {
+          self.action_sender.send(action)?;
+        }
+      }
+      Action::PreviousQuickfix => {
+        for action in self.editor.goto_quickfix(false) {
+          self.action_sender.send(action)?;
+        }
+      }
+      Action::ShowQuickfix => self.editor.show_quickfix(),
+      Action::OpenTerminal => {
+        if let Err(err) = self.editor.open_terminal() {
+          let notification = Notification::error(f!("Failed to open a terminal: {err}"));
+          self.action_sender.send(Action::AttachNotification(notification, 6))?;
+        }
+      }
+      Action::GotoDefinition => send_optional(&self.action_sender, self.editor.goto_definition())?,
+      Action::Hover => send_optional(&self.action_sender, self.editor.hover())?,
+      Action::RequestCompletion => {
+        send_optional(&self.action_sender, self.editor.request_completion())?
+      }
+      Action::IndentLine => send_optional(&self.action_sender, self.editor.shift_line(true))?,
+      Action::DedentLine => send_optional(&self.action_sender, self.editor.shift_line(false))?,
+      Action::JumpTo(path, char_idx) => match self.editor.jump_to(&path, char_idx) {
+        Ok(actions) => {
+          for action in actions {
+            self.action_sender.send(action)?;
+          }
+        }
+        Err(err) => {
+          let notification = Notification::error(f!("Failed to open {}: {err}", path.display()));
+          self.action_sender.send(Action::AttachNotification(notification, 6))?;
+        }
+      },
+      Action::ClosePopup => self.editor.popup = None,
+      Action::SetOption { argument, local } => {
+        send_optional(&self.action_sender, self.editor.set_option(&argument, local))?
+      }
+      Action::ResolveDiskChange(choice) => {
+        send_optional(&self.action_sender, self.editor.resolve_disk_change(choice))?
+      }
+      Action::Recover { discard } => {
+        send_optional(&self.action_sender, self.editor.recover(discard))?
+      }
+      Action::MakeSession(path) => {
+        send_optional(&self.action_sender, self.editor.make_session(path))?
+      }
+      Action::SourceSession(path) => {
+        for action in self.editor.source_session(path) {
+          self.action_sender.send(action)?;
+        }
+      }
+      Action::SetMark(mark) => send_optional(&self.action_sender, self.editor.set_mark(mark))?,
+      Action::GotoMark { mark, linewise } => {
+        for action in self.editor.goto_mark(mark, linewise) {
+          self.action_sender.send(action)?;
+        }
+      }
+      Action::Jump { newer } => {
+        for action in self.editor.jump(newer) {
+          self.action_sender.send(action)?;
+        }
+      }
+      Action::ShowMarks => self.editor.show_marks(),
+      Action::ShowJumps => self.editor.show_jumps(),
+      Action::Operate { operator, object, inner } => {
+        send_optional(&self.action_sender, self.editor.operate(operator, object, inner))?
+      }
+      Action::SelectObject { object, inner } => {
+        send_optional(&self.action_sender, self.editor.select_object(object, inner))?
+      }
+      Action::OperateSelection(operator) => {
+        send_optional(&self.action_sender, self.editor.operate_selection(operator))?
+      }
+      Action::Put => send_optional(&self.action_sender, self.editor.put())?,
+      Action::Undo { redo } => send_optional(&self.action_sender, self.editor.undo(redo))?,
+      Action::AddCursorAtNextMatch => {
+        send_optional(&self.action_sender, self.editor.add_cursor_at_next_match())?
+      }
+      Action::SplitSelection => send_optional(&self.action_sender, self.editor.split_selection())?,
+      Action::KeepPrimaryCursor => self.editor.keep_primary_cursor(),
+      Action::Fold(command) => send_optional(&self.action_sender, self.editor.fold(command))?,
+      Action::ShowMacros => self.editor.show_macros(),
+      Action::EditMacro(label) => {
+        send_optional(&self.action_sender, self.editor.edit_macro(label))?
+      }
+      Action::SetMacro { label, keys } => {
+        send_optional(&self.action_sender, self.editor.set_macro(label, keys))?
+      }
     };
     Ok(())
   }
 
   pub async fn run(&mut self, mut events: EventStream) -> Result<(), Box<dyn Error>> {
+    if let Some(background_events) = self.background_events.take() {
+      events.attach(EventName::Background, background_events);
+    }
+
     loop {
       // Executing action part of event loop
       while let Ok(action) = self.action_receiver.try_recv() {
@@ -82,6 +299,7 @@ impl Teddy {
 
       // Self explanatory
       if self.should_quit {
+        self.editor.lsp.shutdown().await;
         break;
       }
 
@@ -109,3 +327,13 @@ impl Teddy {
 }
 
 impl Teddy {}
+
+fn send_optional(
+  sender: &mpsc::UnboundedSender<Action>,
+  action: Option<Action>,
+) -> Result<(), Box<dyn Error>> {
+  if let Some(action) = action {
+    sender.send(action)?;
+  }
+  Ok(())
+}
@@ -0,0 +1,42 @@
+use std::{io, path::Path};
+
+use ropey::Rope;
+use teddy_core::buffer::{Buffer, ChangeListener, Edit, TextBuffer, WritableBuffer};
+
+use crate::options::buffer::FileEncoding;
+
+/// A buffer holding the contents of a file. Files that don't exist yet start out empty.
+#[derive(Default)]
+pub struct FileBuffer(TextBuffer);
+
+impl FileBuffer {
+  /// Opens `path`, along with the encoding it was decoded from.
+  pub fn open(path: &Path) -> io::Result<(Self, FileEncoding)> {
+    let (text, encoding) = match std::fs::read(path) {
+      Ok(bytes) => FileEncoding::decode(&bytes),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Default::default(),
+      Err(err) => return Err(err),
+    };
+    Ok((Self(TextBuffer::new(Rope::from(text))), encoding))
+  }
+}
+
+impl Buffer for FileBuffer {
+  fn rope(&self) -> &Rope {
+    self.0.rope()
+  }
+
+  fn revision(&self) -> u64 {
+    self.0.revision()
+  }
+}
+
+impl WritableBuffer for FileBuffer {
+  fn apply(&mut self, edit: &Edit) -> Result<(), ropey::Error> {
+    self.0.apply(edit)
+  }
+
+  fn on_change(&mut self, listener: ChangeListener) {
+    self.0.on_change(listener)
+  }
+}
@@ -0,0 +1,52 @@
+use std::{
+  fs::File,
+  io::{self, Read},
+  path::Path,
+};
+
+use teddy_events::{Event, FileLoadEvent};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::options::buffer::FileEncoding;
+
+/// How many bytes are read and decoded at once.
+const CHUNK_SIZE: u64 = 4 << 20;
+
+/// Reads `path` on a background thread and sends it to `events` one decoded chunk at a time,
+/// followed by [`FileLoadEvent::Done`] or [`FileLoadEvent::Failed`].
+pub fn load_in_background(path: &Path, events: UnboundedSender<Event>) {
+  let path = path.to_path_buf();
+  std::thread::spawn(move || {
+    let event = match load(&path, &events) {
+      Ok(encoding) => FileLoadEvent::Done { path, encoding: encoding.name() },
+      Err(err) => FileLoadEvent::Failed { path, error: err.to_string() },
+    };
+    let _ = events.send(Event::FileLoad(event));
+  });
+}
+
+fn load(path: &Path, events: &UnboundedSender<Event>) -> io::Result<FileEncoding> {
+  let mut file = File::open(path)?;
+  let total = file.metadata()?.len();
+  let mut loaded = 0;
+
+  let mut bytes = Vec::new();
+  (&mut file).take(CHUNK_SIZE).read_to_end(&mut bytes)?;
+  let encoding = FileEncoding::sniff(&bytes, bytes.len() as u64 == total);
+  let mut decoder = encoding.encoding.new_decoder_with_bom_removal();
+
+  loop {
+    loaded += bytes.len() as u64;
+    let last = bytes.is_empty();
+    let mut text = String::with_capacity(decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(0));
+    let _ = decoder.decode_to_string(&bytes, &mut text, last);
+
+    let chunk = FileLoadEvent::Chunk { path: path.to_path_buf(), text, loaded, total };
+    if events.send(Event::FileLoad(chunk)).is_err() || last {
+      return Ok(encoding);
+    }
+
+    bytes.clear();
+    (&mut file).take(CHUNK_SIZE).read_to_end(&mut bytes)?;
+  }
+}
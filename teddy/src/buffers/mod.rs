@@ -1 +1,4 @@
+pub mod file;
+pub mod loader;
 pub mod placeholder;
+pub mod scrollback;
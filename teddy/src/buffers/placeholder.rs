@@ -4,41 +4,49 @@ use ratatui::text::Text;
 use ratatui::widgets::Widget;
 use ropey::Rope;
 use teddy_core::action::Action;
-use teddy_core::buffer::{Buffer, WritableBuffer};
+use teddy_core::buffer::{Buffer, ChangeListener, Edit, TextBuffer, WritableBuffer};
 use teddy_core::component::Component;
 
-pub struct PlaceholderBuffer(ropey::Rope);
+pub struct PlaceholderBuffer(TextBuffer);
 
 impl Default for PlaceholderBuffer {
   fn default() -> Self {
-    Self(ropey::Rope::from_str("Placeholder text for a placeholder thing\nanother row"))
+    Self(TextBuffer::new(Rope::from_str("Placeholder text for a placeholder thing\nanother row")))
   }
 }
 
 impl Buffer for PlaceholderBuffer {
-  fn buff(&self) -> ropey::Rope {
-    self.0.clone()
+  fn rope(&self) -> &Rope {
+    self.0.rope()
+  }
+
+  fn revision(&self) -> u64 {
+    self.0.revision()
   }
 }
 
 impl WritableBuffer for PlaceholderBuffer {
-  fn buff_mut(&mut self) -> &mut Rope {
-    &mut self.0
+  fn apply(&mut self, edit: &Edit) -> std::result::Result<(), ropey::Error> {
+    self.0.apply(edit)
+  }
+
+  fn on_change(&mut self, listener: ChangeListener) {
+    self.0.on_change(listener)
   }
 }
 
 impl Component for PlaceholderBuffer {
   fn draw(&self, frame: &mut ratatui::buffer::Buffer, area: ratatui::prelude::Rect) -> Result<()> {
-    let text = Text::from(self.0.to_string());
+    let text = Text::from(self.0.rope().to_string());
     text.render(area, frame);
     Ok(())
   }
 
   fn handle_key_event(&mut self, _key: crossterm::event::KeyEvent) -> Result<Option<Action>> {
-    todo!()
+    Ok(None)
   }
 
   fn handle_mouse_event(&mut self, _mouse: crossterm::event::MouseEvent) -> Result<Option<Action>> {
-    todo!()
+    Ok(None)
   }
 }
@@ -0,0 +1,27 @@
+use ropey::Rope;
+use teddy_core::buffer::Buffer;
+
+/// The scrollback and screen of a terminal frame, as they were when it last left terminal mode.
+/// Read only, the next time a new one replaces it.
+pub struct ScrollbackBuffer {
+  rope: Rope,
+  revision: u64,
+}
+
+impl ScrollbackBuffer {
+  /// `revision` has to go up with every snapshot of the same terminal, so nothing cached from the
+  /// previous one is kept.
+  pub fn new(rope: Rope, revision: u64) -> Self {
+    Self { rope, revision }
+  }
+}
+
+impl Buffer for ScrollbackBuffer {
+  fn rope(&self) -> &Rope {
+    &self.rope
+  }
+
+  fn revision(&self) -> u64 {
+    self.revision
+  }
+}
@@ -5,12 +5,9 @@ use ratatui::{
   widgets::{Block, Borders, List, ListDirection, ListItem},
 };
 
-use std::{
-  fs::DirEntry,
-  path::{Path, PathBuf},
-};
+use std::{fs::DirEntry, path::PathBuf};
 
-use crate::{buffers::buffer::FileBuffer, prelude::*};
+use crate::prelude::*;
 use crossterm::event::{KeyCode, KeyEvent};
 use teddy_core::{action::Action, buffer::Buffer, component::Component};
 
@@ -92,12 +89,7 @@ impl FilePicker {
   }
 
   fn open_file(&mut self, path: PathBuf) -> Option<Action> {
-    let _boxpath: Box<Path> = path.into();
-    unimplemented!()
-
-    // let file = FileBuffer::with_path(boxpath);
-    // None
-    //Some(Action::ReplaceActiveBuffer(Box::new(file)))
+    Some(Action::JumpTo(path, 0))
   }
 
   fn open_parent(&mut self) {
@@ -188,7 +180,7 @@ impl Component for FilePicker {
     Ok(None)
   }
 
-  fn handle_mouse_event(&mut self, mouse: crossterm::event::MouseEvent) -> Result<Option<Action>> {
-    unimplemented!()
+  fn handle_mouse_event(&mut self, _mouse: crossterm::event::MouseEvent) -> Result<Option<Action>> {
+    Ok(None)
   }
 }
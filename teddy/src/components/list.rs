@@ -0,0 +1,107 @@
+use std::{error::Error, path::PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent};
+use ratatui::{
+  layout::Rect,
+  style::{Color, Style},
+  text::{Line, Span},
+  widgets::{Block, Borders, List, ListItem, ListState, StatefulWidget, Widget},
+};
+use ropey::Rope;
+use teddy_core::{action::Action, buffer::Buffer, component::Component};
+
+/// A row of a [`ListComponent`].
+pub struct ListEntry {
+  pub label: String,
+  pub detail: String,
+  pub style: Style,
+  /// Where Enter jumps to, as a file and char index.
+  pub location: Option<(PathBuf, usize)>,
+}
+
+/// A popup list of locations, used by `:diagnostics` and friends.
+pub struct ListComponent {
+  title: String,
+  entries: Vec<ListEntry>,
+  focus: usize,
+  /// The labels, one per line.
+  text: Rope,
+}
+
+impl ListComponent {
+  pub fn new(title: impl Into<String>, entries: Vec<ListEntry>) -> Self {
+    let labels: Vec<&str> = entries.iter().map(|entry| entry.label.as_str()).collect();
+    let text = Rope::from_str(&labels.join("\n"));
+    Self { title: title.into(), entries, focus: 0, text }
+  }
+
+  fn focus_down(&mut self) {
+    if !self.entries.is_empty() {
+      self.focus = (self.focus + 1) % self.entries.len();
+    }
+  }
+
+  fn focus_up(&mut self) {
+    if !self.entries.is_empty() {
+      self.focus = self.focus.checked_sub(1).unwrap_or(self.entries.len() - 1);
+    }
+  }
+
+  fn open_entry(&self) -> Action {
+    match self.entries.get(self.focus).and_then(|entry| entry.location.clone()) {
+      Some((path, char_idx)) => Action::JumpTo(path, char_idx),
+      None => Action::ClosePopup,
+    }
+  }
+}
+
+impl Buffer for ListComponent {
+  fn rope(&self) -> &Rope {
+    &self.text
+  }
+
+  fn revision(&self) -> u64 {
+    0
+  }
+}
+
+impl Component for ListComponent {
+  fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>, Box<dyn Error>> {
+    match key.code {
+      KeyCode::Up | KeyCode::Char('k') => self.focus_up(),
+      KeyCode::Down | KeyCode::Char('j') => self.focus_down(),
+      KeyCode::Enter => return Ok(Some(self.open_entry())),
+      KeyCode::Esc | KeyCode::Char('q') => return Ok(Some(Action::ClosePopup)),
+      _ => {}
+    }
+    Ok(None)
+  }
+
+  fn handle_mouse_event(&mut self, _mouse: MouseEvent) -> Result<Option<Action>, Box<dyn Error>> {
+    Ok(None)
+  }
+
+  fn draw(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) -> Result<(), Box<dyn Error>> {
+    let block = Block::new().borders(Borders::ALL).title(format!(" {} ", self.title));
+
+    if self.entries.is_empty() {
+      let inner = block.inner(area);
+      block.render(area, buf);
+      Line::styled("Nothing here", Style::default().fg(Color::Gray)).render(inner, buf);
+      return Ok(());
+    }
+
+    let items = self.entries.iter().map(|entry| {
+      ListItem::new(Line::from_iter([
+        Span::styled(entry.label.clone(), entry.style),
+        Span::raw("  "),
+        Span::styled(entry.detail.clone(), Style::default().fg(Color::Gray)),
+      ]))
+    });
+    let list = List::new(items).block(block).highlight_style(Style::default().bg(Color::DarkGray));
+
+    let mut state = ListState::default().with_selected(Some(self.focus));
+    StatefulWidget::render(list, area, buf, &mut state);
+    Ok(())
+  }
+}
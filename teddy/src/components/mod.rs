@@ -1,3 +1,5 @@
+pub mod list;
+
 //use crate::{buffers::Buffer, prelude::Result};
 //use crossterm::event::{KeyEvent, MouseEvent};
 //use ratatui::{layout::Rect, Frame};
@@ -0,0 +1,281 @@
+//! Line diffs between two texts, for the git signs and for diff mode, where two buffers are shown
+//! side by side with their differences highlighted.
+
+use std::{
+  collections::BTreeMap,
+  ops::Range,
+  time::{Duration, Instant},
+};
+
+use ropey::Rope;
+use similar::{DiffTag, TextDiff};
+
+/// Lines longer than this, in bytes, aren't diffed char by char, they're only shown as changed.
+const MAX_CHAR_DIFF: usize = 1000;
+/// How long finding the changed chars of a diff's lines may take, the lines left after that are
+/// only shown as changed.
+const CHAR_DIFF_TIME: Duration = Duration::from_millis(20);
+/// How long the buffers have to stay unchanged while typing before they're diffed again.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+  Added,
+  Changed,
+  Removed,
+}
+
+/// A run of changed lines. `lines` are the lines in the new text, empty for removed lines, which
+/// were before `lines.start`, and `base` the lines they replace in the old one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+  pub kind: HunkKind,
+  pub lines: Range<usize>,
+  pub base: Range<usize>,
+}
+
+impl Hunk {
+  /// The line the sign goes on, removed lines are marked on the line above them.
+  pub fn sign_line(&self) -> usize {
+    match self.kind {
+      HunkKind::Removed => self.lines.start.saturating_sub(1),
+      _ => self.lines.start,
+    }
+  }
+
+  pub fn contains(&self, line: usize) -> bool {
+    self.lines.contains(&line) || self.sign_line() == line
+  }
+
+  /// The lines of the hunk on one side of a diff.
+  pub fn side(&self, side: Side) -> Range<usize> {
+    match side {
+      Side::Left => self.base.clone(),
+      Side::Right => self.lines.clone(),
+    }
+  }
+}
+
+/// The hunks turning `base` into `text`, line by line.
+pub fn hunks(base: &str, text: &str) -> Vec<Hunk> {
+  let diff = TextDiff::from_lines(base, text);
+  let hunks = diff.ops().iter().filter_map(|op| {
+    let (tag, base, lines) = op.as_tag_tuple();
+    let kind = match tag {
+      DiffTag::Equal => return None,
+      DiffTag::Insert => HunkKind::Added,
+      DiffTag::Delete => HunkKind::Removed,
+      DiffTag::Replace => HunkKind::Changed,
+    };
+    Some(Hunk { kind, lines, base })
+  });
+  hunks.collect()
+}
+
+/// The chars that differ between two versions of a line, as ranges of each. Nothing when the
+/// lines are too long or `deadline` passed.
+fn changed_chars(
+  old: &str,
+  new: &str,
+  deadline: Instant,
+) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+  let (mut removed, mut added) = (Vec::new(), Vec::new());
+  if old.len() > MAX_CHAR_DIFF || new.len() > MAX_CHAR_DIFF || Instant::now() >= deadline {
+    return (removed, added);
+  }
+  let diff = TextDiff::configure().deadline(deadline).diff_chars(old, new);
+  for op in diff.ops() {
+    let (tag, old, new) = op.as_tag_tuple();
+    if tag != DiffTag::Equal {
+      removed.extend((!old.is_empty()).then_some(old));
+      added.extend((!new.is_empty()).then_some(new));
+    }
+  }
+  (removed, added)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+  Left,
+  Right,
+}
+
+impl Side {
+  pub fn other(self) -> Self {
+    match self {
+      Side::Left => Side::Right,
+      Side::Right => Side::Left,
+    }
+  }
+}
+
+/// Two frames side by side, the left one diffed as the old text, see `:diffsplit`.
+#[derive(Debug)]
+pub struct DiffView {
+  pub left: u16,
+  pub right: u16,
+  hunks: Vec<Hunk>,
+  /// The changed chars of the lines in changed hunks, by line, for the left and the right.
+  changed: [BTreeMap<usize, Vec<Range<usize>>>; 2],
+  /// The revisions of the two buffers the hunks were found at.
+  revisions: Option<(u64, u64)>,
+  /// The revisions the buffers last changed to and when, while waiting for typing to pause.
+  pending: Option<((u64, u64), Instant)>,
+}
+
+impl DiffView {
+  pub fn new(left: u16, right: u16) -> Self {
+    let changed = Default::default();
+    Self { left, right, hunks: Vec::new(), changed, revisions: None, pending: None }
+  }
+
+  pub fn side(&self, id: u16) -> Option<Side> {
+    match id {
+      id if id == self.left => Some(Side::Left),
+      id if id == self.right => Some(Side::Right),
+      _ => None,
+    }
+  }
+
+  pub fn id(&self, side: Side) -> u16 {
+    match side {
+      Side::Left => self.left,
+      Side::Right => self.right,
+    }
+  }
+
+  /// Diffs the buffers again when either changed since the last time. Unless `now`, that waits
+  /// until they stop changing for a moment, so typing doesn't diff both of them on every key.
+  pub fn update(&mut self, left: &Rope, right: &Rope, revisions: (u64, u64), now: bool) {
+    if self.revisions == Some(revisions) {
+      return;
+    }
+    if !now && self.revisions.is_some() {
+      if self.pending.map(|(pending, _)| pending) != Some(revisions) {
+        self.pending = Some((revisions, Instant::now()));
+      }
+      if self.pending.is_some_and(|(_, since)| since.elapsed() < DEBOUNCE) {
+        return;
+      }
+    }
+    self.revisions = Some(revisions);
+    self.pending = None;
+    self.hunks = hunks(&left.to_string(), &right.to_string());
+
+    let [left_changed, right_changed] = &mut self.changed;
+    left_changed.clear();
+    right_changed.clear();
+    let deadline = Instant::now() + CHAR_DIFF_TIME;
+    for hunk in self.hunks.iter().filter(|hunk| hunk.kind == HunkKind::Changed) {
+      for (old, new) in hunk.base.clone().zip(hunk.lines.clone()) {
+        let (removed, added) =
+          changed_chars(&left.line(old).to_string(), &right.line(new).to_string(), deadline);
+        left_changed.insert(old, removed);
+        right_changed.insert(new, added);
+      }
+    }
+  }
+
+  pub fn hunks(&self) -> &[Hunk] {
+    &self.hunks
+  }
+
+  /// How `line` of `side` differs from the other side, if it does.
+  pub fn kind_at(&self, side: Side, line: usize) -> Option<HunkKind> {
+    self.hunks.iter().find(|hunk| hunk.side(side).contains(&line)).map(|hunk| hunk.kind)
+  }
+
+  /// The chars of `line` of `side` that differ from the line across from it.
+  pub fn changed_chars(&self, side: Side, line: usize) -> &[Range<usize>] {
+    let changed = &self.changed[side as usize];
+    changed.get(&line).map(Vec::as_slice).unwrap_or_default()
+  }
+
+  /// The hunk with `line` of `side` in it, or right after it when its lines were removed there.
+  pub fn hunk_at(&self, side: Side, line: usize) -> Option<&Hunk> {
+    self.hunks.iter().find(|hunk| {
+      let lines = hunk.side(side);
+      lines.contains(&line) || (lines.is_empty() && lines.start.saturating_sub(1) == line)
+    })
+  }
+
+  /// The line of the other side across from `line` of `side`.
+  pub fn across(&self, side: Side, line: usize) -> usize {
+    let mut across = line;
+    for hunk in &self.hunks {
+      let (mine, theirs) = (hunk.side(side), hunk.side(side.other()));
+      if line < mine.start {
+        break;
+      }
+      if mine.contains(&line) {
+        return theirs.start + (line - mine.start).min(theirs.len().saturating_sub(1));
+      }
+      across = line + theirs.end - mine.end;
+    }
+    across
+  }
+
+  /// The first line of the next (or previous) hunk of `side` after `line`.
+  pub fn next_change(&self, side: Side, line: usize, forward: bool) -> Option<usize> {
+    let starts = self.hunks.iter().map(|hunk| hunk.side(side).start);
+    match forward {
+      true => starts.filter(|start| *start > line).min(),
+      false => starts.filter(|start| *start < line).max(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hunks() {
+    let found = hunks("a\nb\nc\nd\n", "a\nB\nc\nx\ny\n");
+    assert_eq!(
+      found,
+      Vec::from_iter([
+        Hunk { kind: HunkKind::Changed, lines: 1..2, base: 1..2 },
+        Hunk { kind: HunkKind::Changed, lines: 3..5, base: 3..4 },
+      ])
+    );
+    let removed = hunks("a\nb\nc\n", "a\nc\n");
+    assert_eq!(
+      removed,
+      Vec::from_iter([Hunk { kind: HunkKind::Removed, lines: 1..1, base: 1..2 }])
+    );
+    assert_eq!(removed[0].sign_line(), 0);
+  }
+
+  #[test]
+  fn test_diff_view() {
+    let left = Rope::from_str("a\nold line\nb\nc\n");
+    let right = Rope::from_str("a\nnew line\nb\nx\ny\nc\n");
+    let mut view = DiffView::new(1, 2);
+    view.update(&left, &right, (0, 0), false);
+
+    assert_eq!(view.kind_at(Side::Right, 1), Some(HunkKind::Changed));
+    assert_eq!(view.kind_at(Side::Right, 3), Some(HunkKind::Added));
+    assert_eq!(view.kind_at(Side::Left, 2), None);
+    assert_eq!(view.changed_chars(Side::Left, 1), std::slice::from_ref(&(0..3)));
+    assert_eq!(view.changed_chars(Side::Right, 1), std::slice::from_ref(&(0..3)));
+
+    assert_eq!(view.across(Side::Left, 3), 5);
+    assert_eq!(view.across(Side::Right, 4), 3);
+    assert_eq!(view.next_change(Side::Right, 1, true), Some(3));
+    assert_eq!(view.next_change(Side::Left, 3, false), Some(1));
+    assert_eq!(view.hunk_at(Side::Left, 2).map(|hunk| hunk.kind), Some(HunkKind::Added));
+
+    // Changes while typing wait for a pause, unless the diff is needed right away.
+    let right = Rope::from_str("a\nold line\nb\nc\n");
+    view.update(&left, &right, (0, 1), false);
+    assert_eq!(view.hunks().len(), 2);
+    view.update(&left, &right, (0, 1), true);
+    assert!(view.hunks().is_empty());
+    let long = "x".repeat(MAX_CHAR_DIFF + 1);
+    assert_eq!(
+      changed_chars(&long, "y", Instant::now() + CHAR_DIFF_TIME),
+      (Vec::new(), Vec::new())
+    );
+  }
+}
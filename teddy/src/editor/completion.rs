@@ -0,0 +1,373 @@
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf},
+};
+
+use ropey::Rope;
+use teddy_core::buffer::Edit;
+use teddy_events::LspCompletionItem;
+
+/// How many words the buffer source offers at most.
+const MAX_BUFFER_WORDS: usize = 200;
+
+/// Also the order items are listed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompletionKind {
+  Lsp,
+  Snippet,
+  Path,
+  Word,
+}
+
+impl CompletionKind {
+  pub fn label(&self) -> &'static str {
+    match self {
+      CompletionKind::Lsp => "lsp",
+      CompletionKind::Snippet => "snip",
+      CompletionKind::Word => "word",
+      CompletionKind::Path => "path",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionItem {
+  pub kind: CompletionKind,
+  pub label: String,
+  pub detail: Option<String>,
+  pub documentation: Option<String>,
+  /// What replaces the typed prefix, `label` if `None`.
+  pub insert_text: Option<String>,
+  /// Where the prefix this item completes starts, as a char index.
+  pub start: usize,
+}
+
+impl CompletionItem {
+  pub fn new(kind: CompletionKind, label: impl Into<String>, start: usize) -> Self {
+    Self { kind, label: label.into(), detail: None, documentation: None, insert_text: None, start }
+  }
+
+  pub fn text(&self) -> &str {
+    self.insert_text.as_deref().unwrap_or(&self.label)
+  }
+}
+
+/// What a [`CompletionSource`] gets to look at.
+pub struct CompletionContext<'a> {
+  pub rope: &'a Rope,
+  /// The cursor as a char index.
+  pub cursor: usize,
+  pub path: Option<&'a Path>,
+}
+
+impl CompletionContext<'_> {
+  /// The start of the identifier that ends at the cursor.
+  pub fn word_start(&self) -> usize {
+    let mut start = self.cursor;
+    while start > 0 && is_word_char(self.rope.char(start - 1)) {
+      start -= 1;
+    }
+    start
+  }
+
+  pub fn word(&self) -> String {
+    self.rope.slice(self.word_start()..self.cursor).to_string()
+  }
+}
+
+pub fn is_word_char(char: char) -> bool {
+  char.is_alphanumeric() || char == '_'
+}
+
+/// Somewhere completion items come from. Language servers answer asynchronously and are merged in
+/// with [`CompletionEngine::lsp_items`] instead.
+pub trait CompletionSource {
+  fn complete(&mut self, context: &CompletionContext) -> Vec<CompletionItem>;
+}
+
+/// Words found in the buffer.
+pub struct BufferWords;
+
+impl CompletionSource for BufferWords {
+  fn complete(&mut self, context: &CompletionContext) -> Vec<CompletionItem> {
+    let start = context.word_start();
+    let word = context.word();
+    if word.is_empty() {
+      return Vec::new();
+    }
+
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    let mut current = String::new();
+    // The trailing space flushes a word ending the buffer.
+    for (idx, char) in context.rope.chars().chain([' ']).enumerate() {
+      if is_word_char(char) {
+        current.push(char);
+        continue;
+      }
+      // The word being typed shouldn't complete to itself.
+      let is_typed_word = idx == context.cursor;
+      let candidate = std::mem::take(&mut current);
+      if !is_typed_word
+        && candidate != word
+        && candidate.starts_with(&word)
+        && seen.insert(candidate.clone())
+      {
+        items.push(CompletionItem::new(CompletionKind::Word, candidate, start));
+        if items.len() == MAX_BUFFER_WORDS {
+          break;
+        }
+      }
+    }
+    items
+  }
+}
+
+/// Entries of the directory being typed, relative to the working directory.
+pub struct Paths;
+
+impl CompletionSource for Paths {
+  fn complete(&mut self, context: &CompletionContext) -> Vec<CompletionItem> {
+    let mut token_start = context.cursor;
+    while token_start > 0 && is_path_char(context.rope.char(token_start - 1)) {
+      token_start -= 1;
+    }
+    let token = context.rope.slice(token_start..context.cursor).to_string();
+    let Some(slash) = token.rfind('/') else {
+      return Vec::new();
+    };
+    let (dir, name) = (&token[..=slash], &token[slash + 1..]);
+    let start = context.cursor - name.chars().count();
+
+    let dir = match dir.strip_prefix("~/") {
+      Some(rest) => std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default().join(rest),
+      None => PathBuf::from(dir),
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+      return Vec::new();
+    };
+
+    let mut items: Vec<CompletionItem> = entries
+      .flatten()
+      .filter_map(|entry| {
+        let mut file_name = entry.file_name().into_string().ok()?;
+        if !file_name.starts_with(name) || (name.is_empty() && file_name.starts_with('.')) {
+          return None;
+        }
+        if entry.path().is_dir() {
+          file_name.push('/');
+        }
+        Some(CompletionItem::new(CompletionKind::Path, file_name, start))
+      })
+      .collect();
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items
+  }
+}
+
+fn is_path_char(char: char) -> bool {
+  !char.is_whitespace() && !matches!(char, '"' | '\'' | '(' | ')' | '<' | '>' | '`' | '=' | ',')
+}
+
+/// The open completion menu.
+pub struct CompletionMenu {
+  /// Every candidate, in source order.
+  candidates: Vec<CompletionItem>,
+  /// Indexes into `candidates` that match what has been typed.
+  visible: Vec<usize>,
+  selected: usize,
+  /// A language server was asked and hasn't answered yet.
+  lsp_pending: bool,
+}
+
+impl CompletionMenu {
+  pub fn items(&self) -> impl Iterator<Item = &CompletionItem> {
+    self.visible.iter().map(|idx| &self.candidates[*idx])
+  }
+
+  pub fn len(&self) -> usize {
+    self.visible.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.visible.is_empty()
+  }
+
+  pub fn selected_idx(&self) -> usize {
+    self.selected
+  }
+
+  pub fn selected(&self) -> Option<&CompletionItem> {
+    self.visible.get(self.selected).map(|idx| &self.candidates[*idx])
+  }
+
+  fn filter(&mut self, rope: &Rope, cursor: usize) {
+    let selected = self.visible.get(self.selected).copied();
+
+    self.visible = (0..self.candidates.len())
+      .filter(|idx| {
+        let item = &self.candidates[*idx];
+        if item.start > cursor {
+          return false;
+        }
+        let typed = rope.slice(item.start..cursor).to_string().to_lowercase();
+        let label = item.label.to_lowercase();
+        label.starts_with(&typed) && label != typed
+      })
+      .collect();
+
+    self.selected =
+      selected.and_then(|old| self.visible.iter().position(|idx| *idx == old)).unwrap_or(0);
+  }
+}
+
+/// Collects completion items from every source and keeps track of the menu.
+pub struct CompletionEngine {
+  sources: Vec<Box<dyn CompletionSource>>,
+  menu: Option<CompletionMenu>,
+}
+
+impl Default for CompletionEngine {
+  fn default() -> Self {
+    let mut engine = Self { sources: Vec::new(), menu: None };
+    engine.setup();
+    engine
+  }
+}
+
+impl CompletionEngine {
+  pub fn setup(&mut self) {
+    self.register(Paths);
+    self.register(BufferWords);
+  }
+
+  /// Sources registered later are listed after earlier ones.
+  pub fn register(&mut self, source: impl CompletionSource + 'static) {
+    self.sources.push(Box::new(source));
+  }
+
+  pub fn menu(&self) -> Option<&CompletionMenu> {
+    self.menu.as_ref()
+  }
+
+  /// Whether the menu is showing and should get keys first.
+  pub fn is_open(&self) -> bool {
+    self.menu.as_ref().is_some_and(|menu| !menu.is_empty())
+  }
+
+  /// Opens the menu with the items of every source. `lsp_pending` keeps the menu around while
+  /// waiting for a language server even if no other source had anything.
+  pub fn trigger(&mut self, context: &CompletionContext, lsp_pending: bool) {
+    let mut candidates: Vec<CompletionItem> =
+      self.sources.iter_mut().flat_map(|source| source.complete(context)).collect();
+    candidates.sort_by_key(|item| item.kind);
+    let mut menu = CompletionMenu { candidates, visible: Vec::new(), selected: 0, lsp_pending };
+    menu.filter(context.rope, context.cursor);
+
+    self.menu = (!menu.is_empty() || lsp_pending).then_some(menu);
+  }
+
+  /// Opens the menu on its own while typing an identifier or a path.
+  pub fn auto_trigger(&mut self, context: &CompletionContext) {
+    if self.menu.is_some() || context.cursor == 0 {
+      return;
+    }
+    let typed = context.rope.char(context.cursor - 1);
+    if typed == '/' || (is_word_char(typed) && context.word().chars().count() >= 2) {
+      self.trigger(context, false);
+    }
+  }
+
+  /// Narrows the menu down after the buffer changed, closing it once nothing matches.
+  pub fn refresh(&mut self, context: &CompletionContext) {
+    let Some(menu) = self.menu.as_mut() else {
+      return;
+    };
+    let typed = context.cursor.checked_sub(1).map(|idx| context.rope.char(idx));
+    if typed.is_none_or(|char| char.is_whitespace() || char == '/') {
+      self.menu = None;
+      return;
+    }
+
+    menu.filter(context.rope, context.cursor);
+    if menu.is_empty() && !menu.lsp_pending {
+      self.menu = None;
+    }
+  }
+
+  /// Merges what a language server answered into the menu, prepending them to the other items.
+  pub fn lsp_items(&mut self, items: Vec<LspCompletionItem>, context: &CompletionContext) {
+    let Some(menu) = self.menu.as_mut().filter(|menu| menu.lsp_pending) else {
+      return;
+    };
+    menu.lsp_pending = false;
+
+    let start = context.word_start();
+    let lsp_items = items.into_iter().map(|item| CompletionItem {
+      kind: CompletionKind::Lsp,
+      label: item.label,
+      detail: item.detail,
+      documentation: item.documentation,
+      insert_text: item.insert_text,
+      start,
+    });
+    let mut candidates: Vec<CompletionItem> = lsp_items.collect();
+    let labels: HashSet<String> = candidates.iter().map(|item| item.label.clone()).collect();
+    candidates.extend(
+      menu
+        .candidates
+        .drain(..)
+        .filter(|item| item.kind != CompletionKind::Word || !labels.contains(&item.label)),
+    );
+    menu.candidates = candidates;
+
+    menu.filter(context.rope, context.cursor);
+    if menu.is_empty() {
+      self.menu = None;
+    }
+  }
+
+  pub fn select_next(&mut self) {
+    if let Some(menu) = self.menu.as_mut().filter(|menu| !menu.is_empty()) {
+      menu.selected = (menu.selected + 1) % menu.len();
+    }
+  }
+
+  pub fn select_previous(&mut self) {
+    if let Some(menu) = self.menu.as_mut().filter(|menu| !menu.is_empty()) {
+      menu.selected = menu.selected.checked_sub(1).unwrap_or(menu.len() - 1);
+    }
+  }
+
+  /// Closes the menu and returns the edit replacing the typed prefix with the selected item.
+  pub fn accept(&mut self, cursor: usize) -> Option<(Edit, CompletionItem)> {
+    let item = self.menu.take()?.selected()?.clone();
+    Some((Edit { from: item.start, to: cursor, text: item.text().to_string() }, item))
+  }
+
+  pub fn cancel(&mut self) {
+    self.menu = None;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_buffer_words() {
+    let rope = Rope::from_str("value valid other va");
+    let mut engine = CompletionEngine::default();
+    let context = CompletionContext { rope: &rope, cursor: rope.len_chars(), path: None };
+
+    engine.trigger(&context, false);
+    let labels: Vec<&str> =
+      engine.menu().unwrap().items().map(|item| item.label.as_str()).collect();
+    assert_eq!(labels, ["value", "valid"]);
+
+    engine.select_next();
+    let (edit, _) = engine.accept(rope.len_chars()).unwrap();
+    assert_eq!(edit, Edit { from: 18, to: 20, text: "valid".to_string() });
+    assert!(!engine.is_open());
+  }
+}
@@ -0,0 +1,122 @@
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+
+use ratatui::style::Color;
+use teddy_config::ThemeConfig;
+use teddy_core::{
+  action::{Diagnostic, DiagnosticLevel, DiagnosticSource},
+  buffer::Edit,
+};
+
+/// Diagnostics of every file, kept sorted by their start.
+#[derive(Default)]
+pub struct DiagnosticStore {
+  files: HashMap<PathBuf, Vec<Diagnostic>>,
+}
+
+impl DiagnosticStore {
+  pub fn insert(&mut self, diagnostic: Diagnostic) {
+    let diagnostics = self.files.entry(diagnostic.file.clone()).or_default();
+    let idx = diagnostics.partition_point(|existing| existing.from <= diagnostic.from);
+    diagnostics.insert(idx, diagnostic);
+  }
+
+  /// Removes the diagnostics `source` reported for `path`, leaving those of other sources.
+  pub fn clear(&mut self, path: &Path, source: &DiagnosticSource) {
+    if let Some(diagnostics) = self.files.get_mut(path) {
+      diagnostics.retain(|diagnostic| &diagnostic.source != source);
+    }
+  }
+
+  /// Removes the diagnostics `source` reported for any file.
+  pub fn clear_source(&mut self, source: &DiagnosticSource) {
+    for diagnostics in self.files.values_mut() {
+      diagnostics.retain(|diagnostic| &diagnostic.source != source);
+    }
+  }
+
+  pub fn get(&self, path: &Path) -> &[Diagnostic] {
+    self.files.get(path).map(Vec::as_slice).unwrap_or_default()
+  }
+
+  /// Every diagnostic, grouped by file.
+  pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+    let mut paths: Vec<&PathBuf> = self.files.keys().collect();
+    paths.sort();
+    paths.into_iter().flat_map(|path| self.files[path].iter())
+  }
+
+  /// The number of errors and warnings in `path`.
+  pub fn counts(&self, path: &Path) -> (usize, usize) {
+    self.get(path).iter().fold((0, 0), |(errors, warnings), diagnostic| match diagnostic.level {
+      DiagnosticLevel::Error => (errors + 1, warnings),
+      DiagnosticLevel::Warn => (errors, warnings + 1),
+      DiagnosticLevel::Info => (errors, warnings),
+    })
+  }
+
+  /// The first diagnostic starting after `char_idx`, wrapping around to the start of the file.
+  pub fn next(&self, path: &Path, char_idx: usize) -> Option<&Diagnostic> {
+    let diagnostics = self.get(path);
+    diagnostics.iter().find(|diagnostic| diagnostic.from > char_idx).or(diagnostics.first())
+  }
+
+  /// The last diagnostic starting before `char_idx`, wrapping around to the end of the file.
+  pub fn previous(&self, path: &Path, char_idx: usize) -> Option<&Diagnostic> {
+    let diagnostics = self.get(path);
+    diagnostics.iter().rev().find(|diagnostic| diagnostic.from < char_idx).or(diagnostics.last())
+  }
+
+  /// Shifts the diagnostics of `path` so they keep pointing at the same text until the language
+  /// server publishes new ones.
+  pub fn apply_edit(&mut self, path: &Path, edit: &Edit) {
+    let Some(diagnostics) = self.files.get_mut(path) else {
+      return;
+    };
+    for diagnostic in diagnostics.iter_mut() {
+      diagnostic.from = edit.shift(diagnostic.from);
+      diagnostic.to = edit.shift(diagnostic.to);
+    }
+  }
+}
+
+pub fn level_color(theme: &ThemeConfig, level: DiagnosticLevel) -> Color {
+  match level {
+    DiagnosticLevel::Error => theme.error,
+    DiagnosticLevel::Warn => theme.warning,
+    DiagnosticLevel::Info => theme.info,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_navigation_and_edits() {
+    let path = PathBuf::from("/tmp/file.rs");
+    let mut store = DiagnosticStore::default();
+    store.insert(Diagnostic::warn("b".into(), path.clone(), 10, 12));
+    store.insert(Diagnostic::error("a".into(), path.clone(), 2, 4));
+
+    assert_eq!(store.counts(&path), (1, 1));
+    assert_eq!(store.next(&path, 2).unwrap().message, "b");
+    assert_eq!(store.next(&path, 10).unwrap().message, "a");
+    assert_eq!(store.previous(&path, 2).unwrap().message, "b");
+
+    store.apply_edit(&path, &Edit::insert(5, "xyz"));
+    assert_eq!((store.get(&path)[1].from, store.get(&path)[1].to), (13, 15));
+    store.apply_edit(&path, &Edit::remove(0, 3));
+    assert_eq!((store.get(&path)[0].from, store.get(&path)[0].to), (0, 1));
+
+    // A language server publishing again leaves the diagnostics of tasks alone.
+    let task = DiagnosticSource::Task("check".into());
+    store.insert(Diagnostic::error("c".into(), path.clone(), 5, 6).with_source(task.clone()));
+    store.clear(&path, &DiagnosticSource::LanguageServer);
+    assert_eq!(store.get(&path).len(), 1);
+    store.clear_source(&task);
+    assert!(store.get(&path).is_empty());
+  }
+}
@@ -0,0 +1,110 @@
+//! Diff mode: two frames side by side with their differences highlighted, `:diffsplit`, `do`
+//! and `dp`.
+
+use std::{ops::Range, path::Path};
+
+use ropey::Rope;
+use teddy_core::{
+  action::{Action, Notification},
+  buffer::Edit,
+};
+
+use super::Editor;
+use crate::{
+  diff::{DiffView, Side},
+  prelude::{f, Result},
+};
+
+impl Editor {
+  /// Runs `:diffsplit`, opening `path` to the right of the active buffer and diffing the two.
+  pub fn diff_split(&mut self, path: &Path) -> Result<Vec<Action>> {
+    let left = self.frames.active_id().ok_or("No buffer to diff")?;
+    let actions = self.open_file(path)?;
+    let right = self.frames.active_id().ok_or("No buffer to diff")?;
+    if left == right {
+      return Err("Can't diff a buffer against itself".into());
+    }
+    self.diff = Some(DiffView::new(left, right));
+    Ok(actions)
+  }
+
+  /// Diffs the two buffers again if either changed, and returns the side of the active one. Unless
+  /// `now`, changes made while typing are diffed once it pauses. Diff mode ends once either frame
+  /// is gone.
+  pub fn update_diff(&mut self, now: bool) -> Option<Side> {
+    let diff = self.diff.as_mut()?;
+    let frames = &self.frames.frames;
+    let (Some(left), Some(right)) = (frames.get(&diff.left), frames.get(&diff.right)) else {
+      self.diff = None;
+      return None;
+    };
+    let revisions = (left.buffer.revision(), right.buffer.revision());
+    diff.update(left.rope(), right.rope(), revisions, now);
+    diff.side(self.frames.active_id()?)
+  }
+
+  pub fn switch_diff_side(&mut self) {
+    let Some(side) = self.update_diff(true) else {
+      return;
+    };
+    if let Some(diff) = &self.diff {
+      self.frames.focus(diff.id(side.other()));
+    }
+  }
+
+  /// Moves the cursor to the next (or previous) change of the diff, `]c` and `[c`.
+  pub fn goto_change(&mut self, forward: bool) -> Option<Action> {
+    let Some(side) = self.update_diff(true) else {
+      let notification = Notification::info("Not in diff mode".to_string());
+      return Some(Action::AttachNotification(notification, 2));
+    };
+    let active_frame = self.frames.active_frame()?;
+    let line = active_frame.cursor.primary().get().1;
+    let Some(start) = self.diff.as_ref()?.next_change(side, line, forward) else {
+      let notification = Notification::info("No more changes".to_string());
+      return Some(Action::AttachNotification(notification, 2));
+    };
+    let rope = active_frame.rope();
+    let char_idx = rope.line_to_char(start.min(rope.len_lines() - 1));
+    self.record_jump();
+    self.frames.active_frame_mut()?.set_cursor_char_idx(char_idx);
+    None
+  }
+
+  /// Copies the change at the cursor from the other side, or to it when `put`: `do` and `dp`.
+  pub fn diff_get(&mut self, put: bool) -> Option<Action> {
+    let result = self.diff_edit(put);
+    let notification = match result {
+      Ok(()) => return Some(Action::Render),
+      Err(err) => Notification::error(f!("{err}")),
+    };
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  fn diff_edit(&mut self, put: bool) -> Result<()> {
+    let side = self.update_diff(true).ok_or("Not in diff mode")?;
+    let diff = self.diff.as_ref().ok_or("Not in diff mode")?;
+    let active = self.frames.active_frame().ok_or("No buffer")?;
+    let line = active.cursor.primary().get().1;
+    let hunk = diff.hunk_at(side, line).ok_or("No change at the cursor")?;
+
+    let (from, to) = if put { (side, side.other()) } else { (side.other(), side) };
+    let frame = |side| self.frames.frames.get(&diff.id(side)).ok_or("No buffer");
+    let chars = |rope: &Rope, lines: Range<usize>| {
+      let end = |line: usize| rope.line_to_char(line.min(rope.len_lines()));
+      end(lines.start)..end(lines.end)
+    };
+    let source = frame(from)?.rope();
+    let text = source.slice(chars(source, hunk.side(from))).to_string();
+    let target = chars(frame(to)?.rope(), hunk.side(to));
+    let edit = Edit { from: target.start, to: target.end, text };
+
+    let (active, target) = (self.frames.active_id(), diff.id(to));
+    self.frames.focus(target);
+    let result = self.apply_edit(&edit);
+    if let Some(id) = active {
+      self.frames.focus(id);
+    }
+    result
+  }
+}
@@ -0,0 +1,118 @@
+//! Files changed on disk by other programs, reloaded or resolved with `:reload`, `:keep` and
+//! `:diffdisk`.
+
+use std::path::Path;
+
+use ratatui::style::{Color, Style};
+use similar::{ChangeTag, TextDiff};
+use teddy_core::{
+  action::{Action, DiskChoice, Notification},
+  buffer::Buffer,
+};
+
+use super::Editor;
+use crate::{
+  buffers::file::FileBuffer,
+  components::list::{ListComponent, ListEntry},
+  frame::modified_time,
+  options::buffer::LineEnding,
+  prelude::{f, Result},
+};
+
+impl Editor {
+  /// Reacts to `path` changing on disk. Buffers without changes of their own are reloaded, for
+  /// the others the user picks between `:reload`, `:keep` and `:diffdisk`.
+  pub fn file_changed(&mut self, path: &Path) -> Option<Vec<Action>> {
+    let id = self.frames.frame_id_by_path(path)?;
+    let buffer = &mut self.frames.frames.get_mut(&id)?.buffer;
+    let mtime = modified_time(path);
+    let context = &buffer.context;
+    if context.loading || mtime == context.mtime || mtime == context.changed_on_disk {
+      return None;
+    }
+    buffer.context.changed_on_disk = mtime;
+    let name = buffer.context.name.clone();
+
+    let notification = if mtime.is_none() {
+      Notification::warn(f!("{name} was deleted on disk"))
+    } else if buffer.is_modified() {
+      Notification::warn(f!("{name} changed on disk, use :reload, :keep or :diffdisk"))
+    } else {
+      match self.reload_file(path) {
+        Ok(()) => Notification::info(f!("Reloaded {name}")),
+        Err(err) => Notification::error(f!("Failed to reload {name}: {err}")),
+      }
+    };
+    Some(Vec::from_iter([Action::AttachNotification(notification, 8), Action::Render]))
+  }
+
+  /// Replaces the buffer showing `path` with what's on disk. Only the changed text is replaced,
+  /// like any other edit, so the cursor, marks and diagnostics elsewhere stay where they were.
+  pub fn reload_file(&mut self, path: &Path) -> Result<()> {
+    let id = self.frames.frame_id_by_path(path).ok_or("File isn't open")?;
+    let old = self.frames.frames.get(&id).ok_or("File isn't open")?.rope().clone();
+    let (buffer, encoding) = FileBuffer::open(path)?;
+    self.in_frame(id, |editor| editor.replace_active(&old, &buffer.rope().to_string()))?;
+
+    let frame = self.frames.frames.get_mut(&id).ok_or("File isn't open")?;
+    let options = &mut frame.buffer.context.options;
+    options.encoding = encoding;
+    options.line_ending = LineEnding::detect(buffer.rope().chars());
+    frame.buffer.mark_saved();
+    Ok(())
+  }
+
+  /// Runs `:reload`, `:keep` or `:diffdisk` on the active buffer.
+  pub fn resolve_disk_change(&mut self, choice: DiskChoice) -> Option<Action> {
+    let active_frame = self.frames.active_frame_mut()?;
+    let path = active_frame.path()?.to_path_buf();
+    let result = match choice {
+      DiskChoice::Reload => self.reload_file(&path),
+      DiskChoice::Keep => {
+        // Only ask again when the file changes once more.
+        let context = &mut active_frame.buffer.context;
+        context.mtime = modified_time(&path);
+        context.changed_on_disk = None;
+        Ok(())
+      }
+      DiskChoice::Diff => self.diff_with_disk(&path),
+    };
+    match result {
+      Ok(()) => Some(Action::Render),
+      Err(err) => Some(Action::AttachNotification(Notification::error(f!("{err}")), 4)),
+    }
+  }
+
+  /// Lists the lines that differ between the buffer showing `path` and the file on disk.
+  fn diff_with_disk(&mut self, path: &Path) -> Result<()> {
+    let id = self.frames.frame_id_by_path(path).ok_or("File isn't open")?;
+    let rope = self.frames.frames.get(&id).ok_or("File isn't open")?.rope();
+    let (disk, _) = FileBuffer::open(path)?;
+    let (disk, mine) = (disk.rope().to_string(), rope.to_string());
+
+    let diff = TextDiff::from_lines(&disk, &mine);
+    let mut line = 0;
+    let mut entries = Vec::new();
+    for change in diff.iter_all_changes() {
+      line = change.new_index().unwrap_or(line);
+      let (sign, color) = match change.tag() {
+        ChangeTag::Equal => continue,
+        ChangeTag::Delete => ('-', Color::Red),
+        ChangeTag::Insert => ('+', Color::Green),
+      };
+      let char_idx = rope.line_to_char(line.min(rope.len_lines() - 1));
+      entries.push(ListEntry {
+        label: f!("{sign} {}", change.value().trim_end_matches(['\n', '\r'])),
+        detail: f!("line {}", line + 1),
+        style: Style::default().fg(color),
+        location: Some((path.to_path_buf(), char_idx)),
+      });
+    }
+    if entries.is_empty() {
+      return Err("No differences with the file on disk".into());
+    }
+
+    self.popup = Some(Box::new(ListComponent::new("Buffer vs disk", entries)));
+    Ok(())
+  }
+}
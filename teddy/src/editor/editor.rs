@@ -1,54 +1,238 @@
+use std::{path::Path, time::Instant};
+
+use chrono::Utc;
 use crossterm::event::KeyEvent;
-use teddy_core::{action::Action, component::Component};
+use ropey::Rope;
+use teddy_config::{Config, FormatterConfig, TaskConfig, ThemeConfig};
+use teddy_core::{
+  action::{Action, Notification, NotificationLevel},
+  buffer::Edit,
+  component::Component,
+  input_mode::InputMode,
+};
+use teddy_events::{Event, FileLoadEvent, FileWatcher};
+use teddy_lsp::LspManager;
+use tokio::sync::mpsc::UnboundedSender;
 
+use super::{
+  completion::CompletionEngine,
+  diagnostics::DiagnosticStore,
+  marks::{JumpList, Location, Marks},
+  register::Register,
+};
 use crate::{
-  frame::manager::FrameManager,
-  inputresolver::{
-    context::Context, input::input_manager::InputResult, CursorMovement, MacroResolver,
+  buffers::loader,
+  diff::DiffView,
+  editorconfig,
+  format::{self, FormatJobs, FormatTarget},
+  frame::{
+    cursors, indent, manager::FrameManager, notification_manager::NotificationMessage, Frame,
+    FrameBuffer,
+  },
+  inputresolver::{input::input_manager::InputResult, CursorMovement, MacroResolver},
+  motions,
+  options::{
+    buffer::{FileEncoding, LineEnding},
+    Options,
   },
-  prelude::Result,
+  prelude::{f, Result},
+  shell::ShellJobs,
+  snippets::session::SnippetSession,
+  swap,
+  task::{Quickfix, Tasks},
+  vcs::Vcs,
 };
 
-#[derive(Default)]
 pub struct Editor {
   pub frames: FrameManager,
   pub macro_key_resolver: MacroResolver,
+  pub lsp: LspManager,
+  pub diagnostics: DiagnosticStore,
+  pub vcs: Vcs,
+  /// The two frames shown side by side in diff mode.
+  pub diff: Option<DiffView>,
+  /// Commands started with `:!` that haven't finished.
+  pub shell: ShellJobs,
+  /// Tasks started with `:make` or `:task` that haven't finished.
+  pub tasks: Tasks,
+  pub(super) task_configs: Vec<TaskConfig>,
+  /// The errors of the last task.
+  pub quickfix: Quickfix,
+  /// The formatters of each language, run by `:format` and on save.
+  pub formatters: Vec<FormatterConfig>,
+  /// The language server formatting asked for by `:format` or a write, until it answers.
+  pub(super) pending_format: Option<FormatTarget>,
+  /// External formatters started by `:format` or a write that haven't finished.
+  pub(super) format_jobs: FormatJobs,
+  pub completion: CompletionEngine,
+  pub marks: Marks,
+  pub jumps: JumpList,
+  /// The texts of the last delete or yank, put back with `p`.
+  pub register: Register,
+  /// The expanded snippet whose tabstops Tab jumps between.
+  pub snippet: Option<SnippetSession>,
+  /// Shown above the frames and given every key while open.
+  pub popup: Option<Box<dyn Component>>,
+  pub theme: ThemeConfig,
+  /// Global values of the options, local ones are given to every new frame and buffer before
+  /// `.editorconfig` overrides them.
+  pub options: Options,
+  /// Where background work like file loading reports back, see [`Event::FileLoad`].
+  pub(super) events: UnboundedSender<Event>,
+  /// Watches open files for changes made outside the editor.
+  watcher: FileWatcher,
+  /// When swap files were last written.
+  pub(super) last_swap: Instant,
+}
+
+impl Editor {
+  /// `events` is where language servers and background loads send their results, see
+  /// [`Event::Lsp`] and [`Event::FileLoad`].
+  pub fn new(config: &Config, events: UnboundedSender<Event>) -> Self {
+    let root = std::env::current_dir().unwrap_or_default();
+
+    Self {
+      frames: FrameManager::default(),
+      macro_key_resolver: MacroResolver::default(),
+      lsp: LspManager::new(config.language_servers.clone(), root, events.clone()),
+      diagnostics: DiagnosticStore::default(),
+      vcs: Vcs::default(),
+      diff: None,
+      shell: ShellJobs::default(),
+      tasks: Tasks::default(),
+      task_configs: config.tasks.clone(),
+      quickfix: Quickfix::default(),
+      formatters: config.formatters.clone(),
+      pending_format: None,
+      format_jobs: FormatJobs::default(),
+      completion: CompletionEngine::default(),
+      marks: Marks::default(),
+      jumps: JumpList::default(),
+      register: Register::default(),
+      snippet: None,
+      popup: None,
+      theme: config.theme,
+      options: Options::new(config),
+      watcher: FileWatcher::new(events.clone()),
+      last_swap: Instant::now(),
+      events,
+    }
+  }
 }
 
 // Event Loop
 impl Editor {
   pub fn keyevent(&mut self, event: KeyEvent) -> Option<Vec<Action>> {
-    let context =
-      Context::new(self.macro_key_resolver.input_manager.editor_mode_mut(), &mut self.frames);
+    if let Some(popup) = self.popup.as_mut() {
+      return match popup.handle_key_event(event) {
+        Ok(action) => action.map(|action| Vec::from_iter([action])),
+        Err(err) => {
+          let notification = Notification::error(f!("{err}"));
+          Some(Vec::from_iter([Action::AttachNotification(notification, 4)]))
+        }
+      };
+    }
+
+    let completion_open = self.completion.is_open();
+    let input_manager = &mut self.macro_key_resolver.input_manager;
+    input_manager.set_completion_open(completion_open);
+    input_manager.set_snippet_active(self.snippet.is_some());
+    let previous_mode = input_manager.editor_mode().clone();
+
+    // A key typed while idle starts a new change, which is undone on its own.
+    if self.macro_key_resolver.is_idle() {
+      self.frames.frames.values_mut().for_each(|frame| frame.history.close());
+    }
 
-    //self.input_resolver.input_manager.keybind_manager.match_keybind(event, &mut context);
     let mut stuff = Vec::new();
-    for item in self.macro_key_resolver.input(event).unwrap_or(Vec::default()) {
+    for item in self.macro_key_resolver.input(event).unwrap_or_default() {
       let action = match item {
         InputResult::Insert(test) => {
-          if let Some(active_frame) = self.frames.active_frame_mut() {
-            active_frame.insert(test).unwrap();
+          if self.type_over_placeholder(test) {
+            continue;
+          }
+          let mut edited = false;
+          let result = self.for_each_cursor(|editor, cursor| {
+            let active_frame = editor.frames.active_frame_mut().ok_or("No buffer")?;
+            if let Some(edit) = active_frame.insert_at(test, cursor)? {
+              editor.buffer_changed(&edit);
+              edited = true;
+            }
+            Ok(())
+          });
+          if let Err(err) = result {
+            let notification = Notification::error(f!("{err}"));
+            stuff.push(Action::AttachNotification(notification, 4));
+          }
+          if edited {
+            let autocomplete = self.options.global.autocomplete;
+            self.complete_with(|engine, context| {
+              engine.refresh(context);
+              if autocomplete {
+                engine.auto_trigger(context);
+              }
+            });
           }
           None
         }
         InputResult::CausedAction(action) => Some(action),
+        InputResult::Completion(intent) => self.completion_intent(intent),
+        InputResult::NextTabstop => self.jump_tabstop(1),
+        InputResult::PreviousTabstop => self.jump_tabstop(-1),
         InputResult::CursorIntent(test) => {
+          self.completion.cancel();
+          if let Some(session) = self.snippet.as_mut() {
+            session.deselect();
+          }
+          if matches!(
+            test,
+            CursorMovement::FileStart | CursorMovement::FileEnd | CursorMovement::Line(_)
+          ) {
+            self.record_jump();
+          }
           let active_frame = self.frames.active_frame_mut()?;
-          let buff = active_frame.buff();
-          match test {
-            CursorMovement::Down => active_frame.cursor.cursor.move_down(&buff),
-            CursorMovement::Up => active_frame.cursor.cursor.move_up(&buff),
-            CursorMovement::Left => active_frame.cursor.cursor.move_left(),
-            CursorMovement::Right => {
-              let mode = self.macro_key_resolver.input_manager.editor_mode();
-              active_frame.cursor.cursor.move_right(&buff, mode)
+          let rope = active_frame.buffer.rope();
+          for selection in active_frame.cursor.iter_mut() {
+            let cursor = &mut selection.cursor;
+            match test {
+              CursorMovement::Down => cursor.move_down(rope),
+              CursorMovement::Up => cursor.move_up(rope),
+              CursorMovement::Left => cursor.move_left(),
+              CursorMovement::Right => {
+                let mode = self.macro_key_resolver.input_manager.editor_mode();
+                cursor.move_right(rope, mode)
+              }
+              CursorMovement::Readjust => cursor.readjust(rope),
+              CursorMovement::Custom(char_idx) => cursors::goto_char(rope, cursor, char_idx),
+              movement => {
+                let char_idx = cursors::char_idx(rope, cursor);
+                if let Some(target) = motions::target(rope, char_idx, movement) {
+                  cursors::goto_char(rope, cursor, target);
+                }
+              }
+            }
+            // Closed folds are stepped over as a single line.
+            let folds = &active_frame.folds;
+            if test == CursorMovement::Down {
+              while folds.is_hidden(cursor.get().1) && cursor.get().1 + 1 < rope.len_lines() {
+                cursor.move_down(rope);
+              }
+            }
+            while folds.is_hidden(cursor.get().1) {
+              cursor.move_up(rope);
             }
-            CursorMovement::Readjust => active_frame.cursor.cursor.readjust(&buff),
-            CursorMovement::Custom(_) => todo!(),
           }
+          active_frame.cursor.merge(rope);
+          None
+        }
+        InputResult::Operate { operator, movement, count } => {
+          self.operate_motion(operator, movement, count)
+        }
+        InputResult::ChangeInputMode(mode) => {
+          *self.macro_key_resolver.input_manager.editor_mode_mut() = mode;
           None
         }
-        InputResult::ChangeInputMode(data) => todo!(),
+        InputResult::TerminalKey(key) => self.terminal_key(key),
       };
 
       if let Some(existing_action) = action {
@@ -56,6 +240,27 @@ impl Editor {
       }
     }
 
+    // Inserting into a running terminal is typing to its program.
+    let terminal = self.frames.active_frame().and_then(|frame| frame.terminal.as_ref());
+    let input_manager = &mut self.macro_key_resolver.input_manager;
+    if terminal.is_some_and(|terminal| terminal.running)
+      && matches!(input_manager.editor_mode(), InputMode::Insert { .. })
+    {
+      *input_manager.editor_mode_mut() = InputMode::Terminal;
+    }
+
+    let mode = self.macro_key_resolver.input_manager.editor_mode();
+    if !matches!(mode, InputMode::Insert { .. }) {
+      self.snippet = None;
+    }
+    if std::mem::discriminant(mode) != std::mem::discriminant(&previous_mode) {
+      self.mode_left(&previous_mode);
+    }
+    self.sync_selection(&previous_mode);
+    if self.macro_key_resolver.take_recorded() {
+      stuff.extend(self.save_macros());
+    }
+
     if !stuff.is_empty() {
       Some(stuff)
     } else {
@@ -64,42 +269,295 @@ impl Editor {
   }
 }
 impl Editor {
-  pub fn replace_active_buffer(&mut self, _buffer: Box<dyn Component>) -> Result<()> {
-    let manager = &mut self.frames;
-    if let Some(_active) = manager.active_frame() {
-      unimplemented!()
-      //manager.fill_window(*active, buffer).unwrap();
+  /// Tells everything tracking the active buffer about `edit`, which was already applied.
+  pub(super) fn buffer_changed(&mut self, edit: &Edit) {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return;
+    };
+    if let Some(path) = active_frame.path().map(Path::to_path_buf) {
+      self.diagnostics.apply_edit(&path, edit);
+      self.marks.apply_edit(&path, edit);
+      self.jumps.apply_edit(&path, edit);
+      self.marks.set('.', Location::new(path.clone(), edit.from));
+      self.lsp.did_change(&path, edit, active_frame.rope());
     }
+    self.sync_snippet(edit);
+  }
+
+  /// Applies `edit` to the active buffer.
+  pub fn apply_edit(&mut self, edit: &Edit) -> Result<()> {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return Ok(());
+    };
+    active_frame.apply_edit(edit)?;
+    self.buffer_changed(edit);
+    Ok(())
+  }
+
+  /// Turns the active buffer, which is `old`, into `new` with as few edits as it takes, so what
+  /// tracks the text outside the changes stays where it was. The cursor stays on the same text.
+  pub(super) fn replace_active(&mut self, old: &Rope, new: &str) -> Result<()> {
+    let mut cursor = self.frames.active_frame().map_or(0, Frame::cursor_char_idx);
+    for edit in format::edits(old, new) {
+      self.apply_edit(&edit)?;
+      cursor = edit.shift(cursor);
+    }
+    if let Some(active_frame) = self.frames.active_frame_mut() {
+      active_frame.set_cursor_char_idx(cursor);
+    }
+    Ok(())
+  }
+
+  /// Runs `f` with frame `id` focused, then focuses the frame that was before.
+  pub(super) fn in_frame<T>(&mut self, id: u16, f: impl FnOnce(&mut Self) -> T) -> T {
+    let active = self.frames.active_id();
+    self.frames.focus(id);
+    let result = f(self);
+    if let Some(active) = active {
+      self.frames.focus(active);
+    }
+    result
+  }
+
+  /// Shows `buffer` as the popup in place of the one open.
+  pub fn replace_active_buffer(&mut self, buffer: Box<dyn Component>) -> Result<()> {
+    self.popup = Some(buffer);
     Ok(())
   }
 }
 // Buffer Modification
 impl Editor {
-  pub fn write_active_buffer(&mut self) -> Result<()> {
-    //unimplemented!();
+  /// Writes the active buffer. When its formatter runs on save, it's then formatted in the
+  /// background and written again once that's done, see [`Editor::format_event`]. Returns a
+  /// notification when it can't be formatted.
+  pub fn write_active_buffer(&mut self) -> Result<Option<Action>> {
+    self.write_active()?;
+    let on_save = self.active_formatter().is_some_and(|(_, config)| config.on_save);
+    let large = self.frames.active_frame().is_some_and(|frame| frame.buffer.context.large);
+    if !on_save || large {
+      return Ok(None);
+    }
+    Ok(self.format_active(true).err().map(|err| {
+      let notification = Notification::error(f!("Written unformatted, {err}"));
+      Action::AttachNotification(notification, 4)
+    }))
+  }
+
+  pub(super) fn write_active(&mut self) -> Result<()> {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return Ok(());
+    };
+    let mut cursor = active_frame.cursor_char_idx();
+    let rope = active_frame.rope();
+    let edits = match active_frame.buffer.context.large {
+      true => Vec::new(),
+      false => active_frame.buffer.context.options.save_edits(rope),
+    };
+    for edit in edits {
+      self.apply_edit(&edit)?;
+      cursor = edit.shift(cursor);
+    }
+
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return Ok(());
+    };
+    active_frame.set_cursor_char_idx(cursor);
+    active_frame.write()?;
+
+    if let Some(path) = active_frame.path() {
+      self.lsp.did_save(path);
+      self.vcs.refresh(path, &self.events);
+    }
     Ok(())
   }
-  pub fn open_buffer(&mut self, _buffer: Box<dyn Component>) -> Result<()> {
-    tracing::info!("Opening buffer");
-    unimplemented!();
-    //let index = self.frames.add_window().unwrap();
-    //
-    //self.frames.fill_window(index, buffer);
 
-    //Ok(())
+  /// Runs one `:set` (or `:setlocal` when `local`) argument. Returns the option's value when asked
+  /// for it, and errors as notifications.
+  pub fn set_option(&mut self, argument: &str, local: bool) -> Option<Action> {
+    let active_frame = self.frames.active_frame_mut();
+    let (window, buffer) = match active_frame {
+      Some(frame) => (Some(&mut frame.options), Some(&mut frame.buffer.context.options)),
+      None => (None, None),
+    };
+
+    let notification = match self.options.set(argument, local, window, buffer) {
+      Ok(None) => return Some(Action::Render),
+      Ok(Some(message)) => Notification::new(NotificationLevel::Info, message),
+      Err(err) => Notification::error(f!("{err}")),
+    };
+    Some(Action::AttachNotification(notification, 4))
   }
 
-  pub fn remove_buffer(&mut self, _index: u16) -> Result<()> {
-    unimplemented!();
-    //self.frames.remove_window(index);
-    //Ok(())
+  /// Loads the global option values from `<config dir>/teddyrc`.
+  pub fn load_options(&mut self) -> Vec<Action> {
+    let Some(dir) = Config::config_dir() else {
+      return Vec::new();
+    };
+    let errors = self.options.load(&dir.join("teddyrc"));
+    let notifications = errors.into_iter().map(Notification::error);
+    notifications.map(|notification| Action::AttachNotification(notification, 6)).collect()
   }
 
-  pub fn remove_active_buffer(&mut self) -> Result<()> {
-    if let Some(_active) = self.frames.active_frame() {
-      unimplemented!()
-      //self.frames.remove_window(*active);
+  /// Opens `path` in a new frame, or focuses the frame already showing it. Files above the
+  /// `largefile` option are read in the background instead. The returned actions are notifications
+  /// about language servers that couldn't be started.
+  pub fn open_file(&mut self, path: &Path) -> Result<Vec<Action>> {
+    let path = std::path::absolute(path)?;
+
+    if let Some(id) = self.frames.frame_id_by_path(&path) {
+      self.frames.focus(id);
+      return Ok(Vec::new());
+    }
+
+    self.watcher.watch(&path);
+    let size = std::fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+    if size >= (self.options.global.largefile as u64) << 20 {
+      return self.open_large_file(&path);
+    }
+
+    let mut frame_buffer = FrameBuffer::file(&path, self.options.buffer.clone())?;
+    editorconfig::apply(&editorconfig::properties(&path), &mut frame_buffer.context.options);
+    frame_buffer.on_change(swap::tracker(&path));
+    self.vcs.track(&path, &self.events);
+    let mut frame = Frame::with_buffer(frame_buffer);
+    frame.options = self.options.window.clone();
+    let opened = self.lsp.did_open(&path, frame.rope());
+    self.frames.add_frame(frame)?;
+
+    let mut actions = Vec::from_iter(self.swap_notice(&path));
+    if let Err(err) = opened {
+      let notification = Notification::warn(f!("Failed to start language server: {err}"));
+      actions.push(Action::AttachNotification(notification, 6));
+    }
+
+    Ok(actions)
+  }
+
+  fn open_large_file(&mut self, path: &Path) -> Result<Vec<Action>> {
+    let mut frame_buffer = FrameBuffer::loading(path, self.options.buffer.clone());
+    editorconfig::apply(&editorconfig::properties(path), &mut frame_buffer.context.options);
+    let mut frame = Frame::with_buffer(frame_buffer);
+    frame.options = self.options.window.clone();
+    self.frames.add_frame(frame)?;
+
+    loader::load_in_background(path, self.events.clone());
+    self.load_progress(path, "0%".to_string(), NotificationLevel::Info);
+    Ok(Vec::new())
+  }
+
+  /// Shows how far loading `path` got, in place of the previous progress notification.
+  fn load_progress(&mut self, path: &Path, status: String, level: NotificationLevel) {
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    let notification = Notification::new(level, f!("Loading {name}: {status}"));
+    let lasts_to = Utc::now().timestamp() + 4;
+    let message = NotificationMessage::new(notification, lasts_to).with_id(f!("load {name}"));
+    self.frames.notification_manager.replace(message);
+  }
+
+  /// Fills in a buffer opened in large-file mode as the background loader reads it.
+  pub fn file_load_event(&mut self, event: FileLoadEvent) -> Option<Vec<Action>> {
+    match event {
+      FileLoadEvent::Chunk { path, text, loaded, total } => {
+        let id = self.frames.frame_id_by_path(&path)?;
+        let frame = self.frames.frames.get_mut(&id)?;
+        let first = frame.rope().len_chars() == 0;
+        frame.buffer.append(&text).ok()?;
+        if first {
+          let line_ending = LineEnding::detect(text.chars());
+          frame.buffer.context.options.line_ending = line_ending;
+        }
+        let percent = (loaded * 100).checked_div(total).unwrap_or(100);
+        self.load_progress(&path, f!("{percent}%"), NotificationLevel::Info);
+      }
+      FileLoadEvent::Done { path, encoding } => {
+        let id = self.frames.frame_id_by_path(&path)?;
+        let frame = self.frames.frames.get_mut(&id)?;
+        let context = &mut frame.buffer.context;
+        context.options.encoding = FileEncoding::from_name(&encoding).unwrap_or_default();
+        context.loading = false;
+        frame.buffer.mark_saved();
+        self.load_progress(&path, "done".to_string(), NotificationLevel::Success);
+      }
+      FileLoadEvent::Failed { path, error } => {
+        self.load_progress(&path, error, NotificationLevel::Error);
+      }
+    }
+    Some(Vec::from_iter([Action::Render]))
+  }
+
+  /// Opens `path` and puts the cursor on `char_idx`, closing any popup. Remembered in the jumplist.
+  pub fn jump_to(&mut self, path: &Path, char_idx: usize) -> Result<Vec<Action>> {
+    self.record_jump();
+    self.go_to(path, char_idx)
+  }
+
+  pub(super) fn go_to(&mut self, path: &Path, char_idx: usize) -> Result<Vec<Action>> {
+    self.popup = None;
+    let actions = self.open_file(path)?;
+    if let Some(active_frame) = self.frames.active_frame_mut() {
+      active_frame.set_cursor_char_idx(char_idx);
     }
+    Ok(actions)
+  }
+
+  /// Opens an empty buffer without a file.
+  pub fn open_scratch(&mut self) -> Result<()> {
+    let mut frame = Frame::default();
+    frame.options = self.options.window.clone();
+    frame.buffer.context.options = self.options.buffer.clone();
+    self.frames.add_frame(frame)?;
     Ok(())
   }
+
+  /// Indents (or dedents) the line under the cursor by one level.
+  pub fn shift_line(&mut self, indent: bool) -> Option<Action> {
+    let active_frame = self.frames.active_frame_mut()?;
+    let rope = active_frame.rope();
+    let line = rope.char_to_line(active_frame.cursor_char_idx());
+    let edit = indent::shift_line(rope, line, indent, &active_frame.buffer.context.options.indent)?;
+
+    if let Err(err) = self.apply_edit(&edit) {
+      let notification = Notification::error(f!("{err}"));
+      return Some(Action::AttachNotification(notification, 4));
+    }
+    // Like vim, leave the cursor on the first non-blank.
+    let first_non_blank = edit.from + edit.text.chars().count();
+    self.frames.active_frame_mut()?.set_cursor_char_idx(first_non_blank);
+    None
+  }
+
+  /// Shows `buffer`, a component rather than a file, as the popup.
+  pub fn open_buffer(&mut self, buffer: Box<dyn Component>) -> Result<()> {
+    tracing::info!("Opening buffer");
+    if self.popup.is_some() {
+      return Err("A popup is already open".into());
+    }
+    self.popup = Some(buffer);
+    Ok(())
+  }
+
+  /// Closes the frame `id`. Fails when it has changes that weren't written.
+  pub fn remove_buffer(&mut self, id: u16) -> Result<()> {
+    let Some(frame) = self.frames.frames.get(&id) else {
+      return Err(f!("No buffer {id}").into());
+    };
+    if frame.buffer.is_modified() {
+      return Err("No write since last change".into());
+    }
+    if let Some(path) = self.frames.remove_frame(id).as_ref().and_then(Frame::path) {
+      self.lsp.did_close(path);
+    }
+    Ok(())
+  }
+
+  /// Closes the popup when one is open, the active frame otherwise.
+  pub fn remove_active_buffer(&mut self) -> Result<()> {
+    if self.popup.take().is_some() {
+      return Ok(());
+    }
+    match self.frames.active_id() {
+      Some(id) => self.remove_buffer(id),
+      None => Ok(()),
+    }
+  }
 }
@@ -0,0 +1,33 @@
+//! Folding with `zf`, `zo`, `zc` and the other `z` commands.
+
+use teddy_core::action::{Action, FoldCommand, Notification};
+
+use super::Editor;
+
+impl Editor {
+  /// Runs `zo`, `zc` and the like at the primary cursor.
+  pub fn fold(&mut self, command: FoldCommand) -> Option<Action> {
+    let active_frame = self.frames.active_frame_mut()?;
+    active_frame.update_folds();
+    let line = active_frame.cursor.primary().get().1;
+    let folds = &mut active_frame.folds;
+    let found = match command {
+      FoldCommand::Open => folds.open(line),
+      FoldCommand::Close => folds.close(line),
+      FoldCommand::Toggle => folds.toggle(line),
+      FoldCommand::OpenAll => {
+        folds.set_all(false);
+        true
+      }
+      FoldCommand::CloseAll => {
+        folds.set_all(true);
+        true
+      }
+    };
+    active_frame.unhide_cursors();
+    match found {
+      true => Some(Action::Render),
+      false => Some(Action::AttachNotification(Notification::error("No fold found".into()), 2)),
+    }
+  }
+}
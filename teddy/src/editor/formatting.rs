@@ -0,0 +1,150 @@
+//! Formatting buffers with `:format` and on save, through external programs or the language
+//! server.
+
+use std::path::PathBuf;
+
+use ropey::Rope;
+use teddy_config::{Formatter, FormatterConfig};
+use teddy_core::action::{Action, Diagnostic, DiagnosticSource, Notification};
+use teddy_events::{FormatEvent, LspTextEdit};
+
+use super::Editor;
+use crate::{
+  format::FormatTarget,
+  prelude::{f, Result},
+};
+
+impl Editor {
+  /// The path of the active buffer and the formatter of its language.
+  pub(super) fn active_formatter(&self) -> Option<(PathBuf, FormatterConfig)> {
+    let path = self.frames.active_frame()?.path()?.to_path_buf();
+    let language = &self.lsp.config_for(&path)?.language_id;
+    let config = self.formatters.iter().find(|config| &config.language_id == language)?;
+    Some((path, config.clone()))
+  }
+
+  /// Runs `:format`.
+  pub fn format(&mut self) -> Option<Action> {
+    match self.format_active(false) {
+      Ok(()) => Some(Action::Render),
+      Err(err) => Some(Action::AttachNotification(Notification::error(f!("{err}")), 4)),
+    }
+  }
+
+  /// Formats the active buffer in the background. An external formatter answers with a
+  /// [`FormatEvent`], a language server with an [`LspEvent::Formatting`], after which the buffer is
+  /// written if `write`. When the formatter rejects the text, the buffer is left alone and its
+  /// error is a diagnostic.
+  pub(super) fn format_active(&mut self, write: bool) -> Result<()> {
+    let (path, config) = self.active_formatter().ok_or("No formatter for this file type")?;
+    let active_frame = self.frames.active_frame().ok_or("No buffer")?;
+    if active_frame.buffer.context.large {
+      return Err("Files opened in large-file mode aren't formatted".into());
+    }
+    let target = FormatTarget { path, revision: active_frame.buffer.revision(), write };
+    match config.formatter {
+      Formatter::Command { command, args } => {
+        let text = active_frame.rope().to_string();
+        self.format_jobs.start(&command, &args, text, target, &self.events);
+      }
+      Formatter::Lsp => {
+        let indent = &active_frame.buffer.context.options.indent;
+        if !self.lsp.formatting(&target.path, indent.shift_width, indent.expand_tab) {
+          return Err("No language server to format with".into());
+        }
+        self.pending_format = Some(target);
+      }
+    }
+    Ok(())
+  }
+
+  pub fn format_event(&mut self, event: FormatEvent) -> Option<Vec<Action>> {
+    let id = match &event {
+      FormatEvent::Formatted { id, .. }
+      | FormatEvent::Rejected { id, .. }
+      | FormatEvent::Failed { id, .. } => *id,
+    };
+    let job = self.format_jobs.finish(id)?;
+    let command = &job.command;
+    // The error of an earlier run is gone or about to be replaced.
+    self.diagnostics.clear(&job.target.path, &DiagnosticSource::Formatter);
+    let result = match event {
+      FormatEvent::Formatted { text, .. } => self
+        .format_target(&job.target)
+        .and_then(|(frame, rope)| self.apply_formatted(frame, &rope, &text, job.target.write)),
+      FormatEvent::Rejected { message, position, .. } => {
+        if let Ok((_, rope)) = self.format_target(&job.target) {
+          let from = position.map_or(0, |(line, column)| {
+            let line = line.min(rope.len_lines() - 1);
+            rope.line_to_char(line) + column.min(rope.line(line).len_chars().saturating_sub(1))
+          });
+          let to = (from + 1).min(rope.len_chars());
+          let path = job.target.path.clone();
+          let diagnostic = Diagnostic::error(f!("{command}: {message}"), path, from, to);
+          self.diagnostics.insert(diagnostic.with_source(DiagnosticSource::Formatter));
+        }
+        Err(f!("{command} rejected it: {message}").into())
+      }
+      FormatEvent::Failed { error, .. } => Err(f!("{command} {error}").into()),
+    };
+    let notification = match result {
+      Ok(()) => return Some(Vec::from_iter([Action::Render])),
+      Err(err) if job.target.write => Notification::error(f!("Written unformatted, {err}")),
+      Err(err) => Notification::error(f!("Failed to format: {err}")),
+    };
+    Some(Vec::from_iter([Action::AttachNotification(notification, 4)]))
+  }
+
+  /// The buffer a format was started for and its text, as long as it hasn't changed since.
+  fn format_target(&self, target: &FormatTarget) -> Result<(u16, Rope)> {
+    let frame = self.frames.frame_id_by_path(&target.path).ok_or("The buffer was closed")?;
+    let buffer = &self.frames.frames.get(&frame).ok_or("The buffer was closed")?.buffer;
+    if buffer.revision() != target.revision {
+      return Err("The buffer changed while it was formatted".into());
+    }
+    Ok((frame, buffer.rope().clone()))
+  }
+
+  /// Turns the buffer of `frame`, which is `old`, into `formatted` and writes it if `write`.
+  fn apply_formatted(
+    &mut self,
+    frame: u16,
+    old: &Rope,
+    formatted: &str,
+    write: bool,
+  ) -> Result<()> {
+    self.in_frame(frame, |editor| {
+      editor.replace_active(old, formatted)?;
+      match write {
+        true => editor.write_active(),
+        false => Ok(()),
+      }
+    })
+  }
+
+  /// Applies the answer to the pending formatting request, unless the buffer changed since.
+  pub(super) fn lsp_formatted(&mut self, edits: Vec<LspTextEdit>) -> Result<()> {
+    let Some(pending) = self.pending_format.take() else {
+      return Ok(());
+    };
+    let (frame, rope) = self.format_target(&pending)?;
+    let mut edits: Vec<(usize, usize, usize, String)> = edits
+      .into_iter()
+      .enumerate()
+      .map(|(idx, edit)| {
+        let from = self.lsp.position_to_char(&pending.path, &rope, edit.start);
+        let to = self.lsp.position_to_char(&pending.path, &rope, edit.end);
+        (from, idx, to.max(from), edit.text)
+      })
+      .collect();
+    // They all refer to the text as it was, so they go in from the last. Of inserts at the same
+    // place the last goes in first, which keeps them in order.
+    edits.sort_by_key(|(from, idx, _, _)| std::cmp::Reverse((*from, *idx)));
+    let mut formatted = rope.clone();
+    for (from, _, to, text) in edits {
+      formatted.remove(from..to);
+      formatted.insert(from, &text);
+    }
+    self.apply_formatted(frame, &rope, &formatted.to_string(), pending.write)
+  }
+}
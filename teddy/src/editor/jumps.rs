@@ -0,0 +1,151 @@
+//! Marks and the jumplist: `m`, `'`, `` ` ``, `Ctrl-o` and `Ctrl-i`.
+
+use ratatui::style::Style;
+use teddy_core::{
+  action::{Action, Notification},
+  input_mode::InputMode,
+};
+
+use super::marks::{Location, Marks};
+use super::Editor;
+use crate::{
+  components::list::{ListComponent, ListEntry},
+  prelude::f,
+};
+
+impl Editor {
+  fn cursor_location(&self) -> Option<Location> {
+    let active_frame = self.frames.active_frame()?;
+    Some(Location::new(active_frame.path()?.to_path_buf(), active_frame.cursor_char_idx()))
+  }
+
+  /// Remembers the cursor as the start of a jump, in the jumplist and the `'` mark.
+  pub(super) fn record_jump(&mut self) {
+    if let Some(location) = self.cursor_location() {
+      self.marks.set('\'', location.clone());
+      self.jumps.push(location);
+    }
+  }
+
+  /// Sets the marks that remember where a mode was left.
+  pub(super) fn mode_left(&mut self, mode: &InputMode) {
+    let Some(location) = self.cursor_location() else {
+      return;
+    };
+    match mode {
+      InputMode::Insert { .. } => self.marks.set('^', location),
+      InputMode::Visual(selection) => {
+        let (from, to) = selection.range();
+        self.set_selection_marks(from, to);
+      }
+      InputMode::Terminal => self.snapshot_terminal(),
+      InputMode::Normal | InputMode::Command(_) => {}
+    }
+  }
+
+  /// Sets `'<` and `'>` to the first and last char of a visual selection.
+  pub(super) fn set_selection_marks(&mut self, from: usize, to: usize) {
+    let Some(location) = self.cursor_location() else {
+      return;
+    };
+    self.marks.set('<', Location::new(location.path.clone(), from));
+    self.marks.set('>', Location::new(location.path, to));
+  }
+
+  pub fn set_mark(&mut self, mark: char) -> Option<Action> {
+    let notification = match self.cursor_location() {
+      Some(_) if !Marks::is_settable(mark) => Notification::error(f!("Invalid mark '{mark}'")),
+      Some(location) => {
+        self.marks.set(mark, location);
+        return None;
+      }
+      None => Notification::error("Marks need a buffer with a file".to_string()),
+    };
+    Some(Action::AttachNotification(notification, 2))
+  }
+
+  /// Moves the cursor to `mark`, or to the first non-blank of its line when `linewise`.
+  pub fn goto_mark(&mut self, mark: char, linewise: bool) -> Vec<Action> {
+    let path = self.cursor_location().map(|location| location.path).unwrap_or_default();
+    let Some(location) = self.marks.get(&path, mark) else {
+      let notification = Notification::error(f!("Mark '{mark}' isn't set"));
+      return Vec::from_iter([Action::AttachNotification(notification, 2)]);
+    };
+    self.record_jump();
+    let mut actions = self.go_to_location(&location);
+
+    if let Some(active_frame) = self.frames.active_frame_mut().filter(|_| linewise) {
+      let rope = active_frame.rope();
+      let line = rope.char_to_line(active_frame.cursor_char_idx());
+      let start = rope.line_to_char(line);
+      let blanks = rope.line(line).chars().take_while(|char| *char == ' ' || *char == '\t');
+      active_frame.set_cursor_char_idx(start + blanks.count());
+    }
+    actions.push(Action::Render);
+    actions
+  }
+
+  /// Walks the jumplist with `Ctrl-o`, or `Ctrl-i` when `newer`.
+  pub fn jump(&mut self, newer: bool) -> Vec<Action> {
+    let location = match newer {
+      true => self.jumps.newer(),
+      false => self.cursor_location().and_then(|here| self.jumps.older(here)),
+    };
+    let Some(location) = location else {
+      return Vec::new();
+    };
+    let mut actions = self.go_to_location(&location);
+    actions.push(Action::Render);
+    actions
+  }
+
+  fn go_to_location(&mut self, location: &Location) -> Vec<Action> {
+    match self.go_to(&location.path, location.char_idx) {
+      Ok(actions) => actions,
+      Err(err) => {
+        let path = location.path.display();
+        let notification = Notification::error(f!("Failed to open {path}: {err}"));
+        Vec::from_iter([Action::AttachNotification(notification, 4)])
+      }
+    }
+  }
+
+  /// A list entry for `location`, showing its line and where it is.
+  fn location_entry(&mut self, name: String, location: &Location) -> ListEntry {
+    let root = std::env::current_dir().unwrap_or_default();
+    let rope = self.rope_for(&location.path).unwrap_or_default();
+    let char_idx = location.char_idx.min(rope.len_chars());
+    let line = rope.char_to_line(char_idx);
+    let column = char_idx - rope.line_to_char(line);
+    let text = rope.get_line(line).map(|line| line.to_string()).unwrap_or_default();
+    let file = location.path.strip_prefix(&root).unwrap_or(&location.path);
+
+    ListEntry {
+      label: f!("{name} {}", text.trim()),
+      detail: f!("{}:{}:{}", file.display(), line + 1, column + 1),
+      style: Style::default(),
+      location: Some((location.path.clone(), location.char_idx)),
+    }
+  }
+
+  pub fn show_marks(&mut self) {
+    let path = self.cursor_location().map(|location| location.path).unwrap_or_default();
+    let marks: Vec<(char, Location)> = self.marks.iter(&path).collect();
+    let entries =
+      marks.iter().map(|(mark, location)| self.location_entry(mark.to_string(), location));
+    let entries = entries.collect::<Vec<_>>();
+    self.popup = Some(Box::new(ListComponent::new("Marks", entries)));
+  }
+
+  pub fn show_jumps(&mut self) {
+    let current = self.jumps.current();
+    let jumps: Vec<(usize, Location)> =
+      self.jumps.iter().map(|(idx, location)| (idx, location.clone())).collect();
+    let entries = jumps.iter().map(|(idx, location)| {
+      let name = if *idx == current { ">" } else { " " };
+      self.location_entry(name.to_string(), location)
+    });
+    let entries = entries.collect::<Vec<_>>();
+    self.popup = Some(Box::new(ListComponent::new("Jumps", entries)));
+  }
+}
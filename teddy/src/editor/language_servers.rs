@@ -0,0 +1,249 @@
+//! Diagnostics, hover, definitions and completion from language servers.
+
+use std::path::{Path, PathBuf};
+
+use ratatui::style::Style;
+use ropey::Rope;
+use teddy_core::{
+  action::{
+    Action, Diagnostic, DiagnosticLevel, DiagnosticSource, Notification, NotificationLevel,
+  },
+  buffer::Buffer,
+};
+use teddy_events::{LspEvent, LspSeverity};
+use teddy_lsp::LspManager;
+
+use super::Editor;
+use super::{
+  completion::{CompletionContext, CompletionEngine, CompletionKind},
+  diagnostics::level_color,
+};
+use crate::{
+  buffers::file::FileBuffer,
+  components::list::{ListComponent, ListEntry},
+  inputresolver::input::input_manager::CompletionIntent,
+  prelude::f,
+};
+
+impl Editor {
+  /// The contents of `path`, taken from the frame showing it or else read from disk.
+  pub(super) fn rope_for(&mut self, path: &Path) -> Option<Rope> {
+    match self.frames.frame_id_by_path(path) {
+      Some(id) => self.frames.frames.get(&id).map(|frame| frame.rope().clone()),
+      None => FileBuffer::open(path).ok().map(|(buffer, _)| buffer.rope().clone()),
+    }
+  }
+
+  fn active_lsp_position(&mut self) -> Option<(PathBuf, usize)> {
+    let active_frame = self.frames.active_frame_mut()?;
+    let path = active_frame.path()?.to_path_buf();
+    Some((path, active_frame.cursor_char_idx()))
+  }
+
+  fn lsp_request(&mut self, request: fn(&mut LspManager, &Path, usize) -> bool) -> Option<Action> {
+    let requested = match self.active_lsp_position() {
+      Some((path, char_idx)) => request(&mut self.lsp, &path, char_idx),
+      None => false,
+    };
+
+    if requested {
+      return None;
+    }
+    let notification = Notification::info("No language server for this buffer".to_string());
+    Some(Action::AttachNotification(notification, 2))
+  }
+
+  pub fn goto_definition(&mut self) -> Option<Action> {
+    self.lsp_request(LspManager::goto_definition)
+  }
+
+  pub fn hover(&mut self) -> Option<Action> {
+    self.lsp_request(LspManager::hover)
+  }
+
+  /// Opens the completion menu and asks the language server for more items.
+  pub fn request_completion(&mut self) -> Option<Action> {
+    if self.frames.active_frame().is_some_and(|frame| frame.buffer.context.large) {
+      let notification = Notification::info("Completion is off for large files".to_string());
+      return Some(Action::AttachNotification(notification, 2));
+    }
+
+    let lsp_requested = match self.active_lsp_position() {
+      Some((path, char_idx)) => self.lsp.completion(&path, char_idx),
+      None => false,
+    };
+    self.complete_with(|engine, context| engine.trigger(context, lsp_requested));
+
+    if lsp_requested || self.completion.is_open() {
+      return None;
+    }
+    let notification = Notification::info("No completions".to_string());
+    Some(Action::AttachNotification(notification, 2))
+  }
+
+  /// Runs `f` with the completion engine and the state of the active buffer.
+  pub(super) fn complete_with(
+    &mut self,
+    f: impl FnOnce(&mut CompletionEngine, &CompletionContext),
+  ) {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return;
+    };
+    if active_frame.buffer.context.large {
+      return;
+    }
+    let rope = active_frame.rope();
+    let cursor = active_frame.cursor_char_idx();
+    let path = active_frame.path().map(Path::to_path_buf);
+
+    let context = CompletionContext { rope, cursor, path: path.as_deref() };
+    f(&mut self.completion, &context);
+  }
+
+  pub(super) fn completion_intent(&mut self, intent: CompletionIntent) -> Option<Action> {
+    match intent {
+      CompletionIntent::Trigger => return self.request_completion(),
+      CompletionIntent::Next => self.completion.select_next(),
+      CompletionIntent::Previous => self.completion.select_previous(),
+      CompletionIntent::Cancel => self.completion.cancel(),
+      CompletionIntent::Accept => {
+        let cursor = self.frames.active_frame_mut()?.cursor_char_idx();
+        let (edit, item) = self.completion.accept(cursor)?;
+        let applied = match item.kind {
+          CompletionKind::Snippet => self.expand_snippet(edit.from, edit.to, &edit.text),
+          _ => self.apply_edit(&edit),
+        };
+        if let Err(err) = applied {
+          let notification = Notification::error(f!("Failed to complete: {err}"));
+          return Some(Action::AttachNotification(notification, 4));
+        }
+      }
+    }
+    None
+  }
+
+  pub fn write_diagnostic(&mut self, diagnostic: Diagnostic) {
+    self.diagnostics.insert(diagnostic);
+  }
+
+  /// Drops the language server's diagnostics of `path`, before it publishes new ones.
+  pub fn clear_diagnostics(&mut self, path: &Path) {
+    self.diagnostics.clear(path, &DiagnosticSource::LanguageServer);
+  }
+
+  /// Moves the cursor to the next (or previous) diagnostic of the active buffer.
+  pub fn goto_diagnostic(&mut self, forward: bool) -> Option<Action> {
+    let (path, char_idx) = self.active_lsp_position()?;
+    let diagnostic = match forward {
+      true => self.diagnostics.next(&path, char_idx),
+      false => self.diagnostics.previous(&path, char_idx),
+    };
+
+    let Some(diagnostic) = diagnostic else {
+      let notification = Notification::info("No diagnostics".to_string());
+      return Some(Action::AttachNotification(notification, 2));
+    };
+    let from = diagnostic.from;
+    self.record_jump();
+    self.frames.active_frame_mut()?.set_cursor_char_idx(from);
+    None
+  }
+
+  pub fn show_diagnostics(&mut self) {
+    let root = std::env::current_dir().unwrap_or_default();
+    let diagnostics: Vec<Diagnostic> = self.diagnostics.iter().cloned().collect();
+
+    let entries = diagnostics.into_iter().map(|diagnostic| {
+      let rope = self.rope_for(&diagnostic.file).unwrap_or_default();
+      let from = diagnostic.from.min(rope.len_chars());
+      let line = rope.char_to_line(from);
+      let column = from - rope.line_to_char(line);
+      let file = diagnostic.file.strip_prefix(&root).unwrap_or(&diagnostic.file);
+
+      ListEntry {
+        label: diagnostic.message.lines().next().unwrap_or_default().to_string(),
+        detail: f!("{}:{}:{}", file.display(), line + 1, column + 1),
+        style: Style::default().fg(level_color(&self.theme, diagnostic.level)),
+        location: Some((diagnostic.file.clone(), diagnostic.from)),
+      }
+    });
+    let entries = entries.collect::<Vec<_>>();
+
+    self.popup = Some(Box::new(ListComponent::new("Diagnostics", entries)));
+  }
+
+  pub fn lsp_event(&mut self, event: LspEvent) -> Option<Vec<Action>> {
+    let actions = match event {
+      LspEvent::Diagnostics { path, diagnostics } => {
+        let rope = self.rope_for(&path)?;
+        let mut actions = Vec::from_iter([Action::ClearDiagnostics(path.clone())]);
+
+        for diagnostic in diagnostics {
+          let level = match diagnostic.severity {
+            LspSeverity::Error => DiagnosticLevel::Error,
+            LspSeverity::Warning => DiagnosticLevel::Warn,
+            LspSeverity::Information | LspSeverity::Hint => DiagnosticLevel::Info,
+          };
+          let from = self.lsp.position_to_char(&path, &rope, diagnostic.start);
+          let to = self.lsp.position_to_char(&path, &rope, diagnostic.end);
+
+          let diagnostic = Diagnostic::new(level, diagnostic.message, path.clone(), from, to);
+          actions.push(Action::WriteDiagnostic(diagnostic));
+        }
+        actions
+      }
+      LspEvent::Hover { contents } => {
+        let text: Vec<&str> = contents
+          .lines()
+          .map(str::trim)
+          .filter(|line| !line.is_empty() && !line.starts_with("```"))
+          .collect();
+        let notification = Notification::info(text.join(" "));
+        Vec::from_iter([Action::AttachNotification(notification, 8)])
+      }
+      LspEvent::Definition { path, position } => {
+        self.record_jump();
+        let mut actions = match self.open_file(&path) {
+          Ok(actions) => actions,
+          Err(err) => {
+            let notification = Notification::error(f!("Failed to open {}: {err}", path.display()));
+            return Some(Vec::from_iter([Action::AttachNotification(notification, 4)]));
+          }
+        };
+
+        let active_frame = self.frames.active_frame_mut()?;
+        let char_idx = self.lsp.position_to_char(&path, active_frame.rope(), position);
+        active_frame.set_cursor_char_idx(char_idx);
+
+        actions.push(Action::Render);
+        actions
+      }
+      LspEvent::Completion { items } => {
+        self.complete_with(|engine, context| engine.lsp_items(items, context));
+        Vec::from_iter([Action::Render])
+      }
+      LspEvent::Formatting { edits } => match self.lsp_formatted(edits) {
+        Ok(()) => Vec::from_iter([Action::Render]),
+        Err(err) => {
+          let notification = Notification::error(f!("Failed to format: {err}"));
+          Vec::from_iter([Action::AttachNotification(notification, 4)])
+        }
+      },
+      LspEvent::Message { severity, message } => {
+        let level = match severity {
+          LspSeverity::Error => NotificationLevel::Error,
+          LspSeverity::Warning => NotificationLevel::Warn,
+          LspSeverity::Information | LspSeverity::Hint => NotificationLevel::Info,
+        };
+        Vec::from_iter([Action::AttachNotification(Notification::new(level, message), 4)])
+      }
+      LspEvent::Exited { language } => {
+        self.lsp.server_exited(&language);
+        let notification = Notification::warn(f!("{language} language server exited"));
+        Vec::from_iter([Action::AttachNotification(notification, 4)])
+      }
+    };
+
+    Some(actions)
+  }
+}
@@ -0,0 +1,76 @@
+//! Recording, saving and editing macros.
+
+use std::path::PathBuf;
+
+use ratatui::style::Style;
+use teddy_config::Config;
+use teddy_core::action::{Action, Notification};
+
+use super::{register::Register, Editor};
+use crate::{
+  components::list::{ListComponent, ListEntry},
+  prelude::f,
+};
+
+impl Editor {
+  /// `$XDG_STATE_HOME/teddy/macros`, where macros are kept between runs.
+  fn macros_path() -> Option<PathBuf> {
+    Some(Config::state_dir()?.join("macros"))
+  }
+
+  /// Loads the macros saved by earlier runs. Returns notifications about lines that couldn't be
+  /// read.
+  pub fn load_macros(&mut self) -> Vec<Action> {
+    let Some(text) = Self::macros_path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+      return Vec::new();
+    };
+    let errors = self.macro_key_resolver.load_macros(&text);
+    let notifications = errors.into_iter().map(Notification::error);
+    notifications.map(|notification| Action::AttachNotification(notification, 6)).collect()
+  }
+
+  /// Writes every macro to disk, returns a notification if that failed.
+  pub(super) fn save_macros(&self) -> Option<Action> {
+    let path = Self::macros_path()?;
+    let text = self.macro_key_resolver.macros_text();
+    let result = path.parent().map_or(Ok(()), std::fs::create_dir_all);
+    let err = result.and_then(|()| std::fs::write(&path, text)).err()?;
+    let notification = Notification::error(f!("Failed to save macros: {err}"));
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  pub fn show_macros(&mut self) {
+    let entries = self.macro_key_resolver.macros().map(|(label, keys)| ListEntry {
+      label: f!("{label} {keys}"),
+      detail: String::new(),
+      style: Style::default(),
+      location: None,
+    });
+    self.popup = Some(Box::new(ListComponent::new("Macros", entries.collect())));
+  }
+
+  /// Puts macro `label` in the register, so `p` puts it in a buffer to be edited.
+  pub fn edit_macro(&mut self, label: char) -> Option<Action> {
+    let notification = match self.macro_key_resolver.macro_notation(label) {
+      Some(keys) => {
+        self.register = Register { texts: Vec::from_iter([keys]), linewise: false };
+        Notification::info(f!(
+          "Put macro '{label}' with p, yank it when edited and :setmacro {label}"
+        ))
+      }
+      None => Notification::error(f!("Macro '{label}' is empty")),
+    };
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  /// Sets macro `label` from `keys`, or from the register when there are none, and saves it.
+  pub fn set_macro(&mut self, label: char, keys: Option<String>) -> Option<Action> {
+    let keys = keys.unwrap_or_else(|| self.register.texts.join("\n"));
+    if let Err(err) = self.macro_key_resolver.set_macro(label, keys.trim_end_matches(['\n', '\r']))
+    {
+      return Some(Action::AttachNotification(Notification::error(f!("{err}")), 4));
+    }
+    let notification = Notification::info(f!("Set macro '{label}'"));
+    self.save_macros().or(Some(Action::AttachNotification(notification, 2)))
+  }
+}
@@ -0,0 +1,161 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  path::{Path, PathBuf},
+};
+
+use teddy_core::buffer::Edit;
+
+/// How many jumps are remembered, older ones are dropped.
+const MAX_JUMPS: usize = 100;
+
+/// A char index in a file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Location {
+  pub path: PathBuf,
+  pub char_idx: usize,
+}
+
+impl Location {
+  pub fn new(path: PathBuf, char_idx: usize) -> Self {
+    Self { path, char_idx }
+  }
+}
+
+/// Marks set with `m`, `a` to `z` for each file and `A` to `Z` across files, along with the ones
+/// set by the editor: `.` the last edit, `^` where insert mode was left, `<` and `>` the last
+/// visual selection and `'` where the last jump started.
+#[derive(Default)]
+pub struct Marks {
+  local: HashMap<PathBuf, BTreeMap<char, usize>>,
+  global: BTreeMap<char, Location>,
+}
+
+impl Marks {
+  /// Whether `mark` can be set with `m`.
+  pub fn is_settable(mark: char) -> bool {
+    mark.is_ascii_alphabetic()
+  }
+
+  pub fn set(&mut self, mark: char, location: Location) {
+    if mark.is_ascii_uppercase() {
+      self.global.insert(mark, location);
+    } else {
+      self.local.entry(location.path).or_default().insert(mark, location.char_idx);
+    }
+  }
+
+  /// Where `mark` is, looking at the marks of `path` for the lowercase and automatic ones.
+  pub fn get(&self, path: &Path, mark: char) -> Option<Location> {
+    if mark.is_ascii_uppercase() {
+      return self.global.get(&mark).cloned();
+    }
+    let char_idx = *self.local.get(path)?.get(&mark)?;
+    Some(Location::new(path.to_path_buf(), char_idx))
+  }
+
+  /// The marks of `path` followed by the global ones, each sorted by name.
+  pub fn iter<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = (char, Location)> + 'a {
+    let local = self.local.get(path).into_iter().flatten();
+    let local = local.map(|(mark, char_idx)| (*mark, Location::new(path.to_path_buf(), *char_idx)));
+    local.chain(self.global.iter().map(|(mark, location)| (*mark, location.clone())))
+  }
+
+  /// The marks `A` to `Z`, sorted by name.
+  pub fn global(&self) -> impl Iterator<Item = (char, &Location)> {
+    self.global.iter().map(|(mark, location)| (*mark, location))
+  }
+
+  /// Shifts the marks in `path` so they keep pointing at the same text.
+  pub fn apply_edit(&mut self, path: &Path, edit: &Edit) {
+    for char_idx in self.local.get_mut(path).into_iter().flat_map(BTreeMap::values_mut) {
+      *char_idx = edit.shift(*char_idx);
+    }
+    for location in self.global.values_mut().filter(|location| location.path == path) {
+      location.char_idx = edit.shift(location.char_idx);
+    }
+  }
+}
+
+/// Where the cursor was before each jump, walked with `Ctrl-o` and `Ctrl-i`.
+#[derive(Default)]
+pub struct JumpList {
+  jumps: Vec<Location>,
+  /// The jump the cursor is at, `jumps.len()` when it isn't at any.
+  current: usize,
+}
+
+impl JumpList {
+  /// Remembers `location` as the start of a jump, moving it to the end if it's already there.
+  pub fn push(&mut self, location: Location) {
+    self.jumps.retain(|jump| *jump != location);
+    self.jumps.push(location);
+    if self.jumps.len() > MAX_JUMPS {
+      self.jumps.remove(0);
+    }
+    self.current = self.jumps.len();
+  }
+
+  /// The jump before the current one. `here` is remembered first when at the end of the list, so
+  /// `Ctrl-i` can come back to it.
+  pub fn older(&mut self, here: Location) -> Option<Location> {
+    if self.current == self.jumps.len() {
+      self.push(here);
+      self.current -= 1;
+    }
+    self.current = self.current.checked_sub(1)?;
+    Some(self.jumps[self.current].clone())
+  }
+
+  pub fn newer(&mut self) -> Option<Location> {
+    let location = self.jumps.get(self.current + 1)?.clone();
+    self.current += 1;
+    Some(location)
+  }
+
+  /// Every jump, oldest first, with its index.
+  pub fn iter(&self) -> impl Iterator<Item = (usize, &Location)> {
+    self.jumps.iter().enumerate()
+  }
+
+  pub fn current(&self) -> usize {
+    self.current
+  }
+
+  pub fn apply_edit(&mut self, path: &Path, edit: &Edit) {
+    for jump in self.jumps.iter_mut().filter(|jump| jump.path == path) {
+      jump.char_idx = edit.shift(jump.char_idx);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_marks_and_jumps() {
+    let a = PathBuf::from("/a.rs");
+    let b = PathBuf::from("/b.rs");
+    let mut marks = Marks::default();
+    marks.set('x', Location::new(a.clone(), 10));
+    marks.set('X', Location::new(b.clone(), 4));
+    assert_eq!(marks.get(&b, 'x'), None);
+    assert_eq!(marks.get(&a, 'X'), Some(Location::new(b.clone(), 4)));
+
+    marks.apply_edit(&a, &Edit::insert(2, "abc"));
+    marks.apply_edit(&b, &Edit::remove(0, 2));
+    assert_eq!(marks.get(&a, 'x').unwrap().char_idx, 13);
+    assert_eq!(marks.get(&a, 'X').unwrap().char_idx, 2);
+    assert_eq!(marks.iter(&a).map(|(mark, _)| mark).collect::<String>(), "xX");
+
+    let mut jumps = JumpList::default();
+    jumps.push(Location::new(a.clone(), 1));
+    jumps.push(Location::new(b.clone(), 2));
+    assert_eq!(jumps.older(Location::new(a.clone(), 3)), Some(Location::new(b.clone(), 2)));
+    assert_eq!(jumps.older(Location::new(b.clone(), 2)), Some(Location::new(a.clone(), 1)));
+    assert_eq!(jumps.older(Location::new(a.clone(), 1)), None);
+    assert_eq!(jumps.newer(), Some(Location::new(b.clone(), 2)));
+    assert_eq!(jumps.newer(), Some(Location::new(a.clone(), 3)));
+    assert_eq!(jumps.newer(), None);
+  }
+}
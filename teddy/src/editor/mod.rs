@@ -1,2 +1,23 @@
+pub mod completion;
+pub mod diagnostics;
+mod diff_mode;
+mod disk;
 mod editor;
+mod folds;
+mod formatting;
+mod jumps;
+mod language_servers;
+mod macros;
+pub mod marks;
+mod multi_cursor;
+mod operators;
+pub mod register;
+mod sessions;
+mod shell;
+mod snippets;
+mod swap_files;
+mod tasks;
+mod terminals;
+mod undo;
+mod version_control;
 pub use editor::*;
@@ -0,0 +1,147 @@
+//! Multiple cursors, added on the next match or by splitting a selection.
+
+use teddy_core::{
+  action::{Action, Notification},
+  buffer::Edit,
+  text_object::TextObject,
+};
+
+use super::Editor;
+use crate::{
+  frame::cursors::{self, CursorManager},
+  prelude::{f, Result},
+  text_objects,
+};
+
+impl Editor {
+  /// Runs `f` for every cursor of the active frame, from the first in the buffer to the last, so
+  /// each sees the text as the ones before it left it. Cursors `f` fails for are skipped, the
+  /// error is only returned when it failed for all of them.
+  pub(super) fn for_each_cursor(
+    &mut self,
+    mut f: impl FnMut(&mut Self, usize) -> Result<()>,
+  ) -> Result<()> {
+    let active_frame = self.frames.active_frame().ok_or("No buffer")?;
+    let order = active_frame.cursor.in_order(active_frame.rope());
+
+    let (mut done, mut error) = (false, None);
+    for cursor in order {
+      match f(self, cursor) {
+        Ok(()) => done = true,
+        Err(err) => error = Some(err),
+      }
+    }
+    if let Some(active_frame) = self.frames.active_frame_mut() {
+      active_frame.cursor.merge(active_frame.buffer.rope());
+    }
+    match (done, error) {
+      (false, Some(err)) => Err(err),
+      _ => Ok(()),
+    }
+  }
+
+  /// Applies `edit` made by cursor `cursor` to the active buffer, see [`Frame::apply_edit_at`].
+  pub(super) fn apply_edit_at(&mut self, edit: &Edit, cursor: usize) -> Result<()> {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return Ok(());
+    };
+    active_frame.apply_edit_at(edit, cursor)?;
+    self.buffer_changed(edit);
+    Ok(())
+  }
+
+  /// Adds a cursor on the next match of the primary cursor's selection, or of the word under it
+  /// outside visual mode, wrapping around the end of the buffer.
+  pub fn add_cursor_at_next_match(&mut self) -> Option<Action> {
+    let selection = self.selection();
+    let active_frame = self.frames.active_frame()?;
+    let rope = active_frame.rope();
+    let primary = active_frame.cursor_char_idx();
+    let word = match selection.clone() {
+      Some(selection) => selection,
+      None => text_objects::select(rope, primary, TextObject::Word, true, None)?,
+    };
+
+    let text = rope.to_string();
+    let needle = rope.slice(word.clone()).to_string();
+    let is_word = |char: char| char.is_alphanumeric() || char == '_';
+    let whole_word = |byte: usize| {
+      let before = text[..byte].chars().next_back();
+      let after = text[byte + needle.len()..].chars().next();
+      selection.is_some() || !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    };
+    let occupied: Vec<usize> =
+      active_frame.cursor.positions(rope).iter().map(|(idx, _)| *idx).collect();
+    let start = rope.char_to_byte(word.end);
+    let matches = text[start..].match_indices(&needle).map(|(byte, _)| start + byte);
+    let matches = matches.chain(text[..start].match_indices(&needle).map(|(byte, _)| byte));
+    let found = matches.filter(|byte| whole_word(*byte)).find_map(|byte| {
+      let from = rope.byte_to_char(byte);
+      let range = from..from + word.len();
+      (!occupied.iter().any(|idx| range.contains(idx))).then_some(range)
+    });
+
+    let Some(found) = found else {
+      let notification = Notification::info(f!("No other match for '{needle}'"));
+      return Some(Action::AttachNotification(notification, 2));
+    };
+    let (anchor, head) = match selection {
+      Some(_) => (found.start, found.end - 1),
+      None => (found.start, found.start + (primary - word.start)),
+    };
+    let active_frame = self.frames.active_frame_mut()?;
+    active_frame.cursor.add(active_frame.buffer.rope(), anchor, head);
+    let mode = self.macro_key_resolver.input_manager.editor_mode().clone();
+    self.sync_selection(&mode);
+    Some(Action::Render)
+  }
+
+  /// Replaces every visual selection with one selection, and cursor, for each of its lines.
+  pub fn split_selection(&mut self) -> Option<Action> {
+    let selections = self.selections();
+    let active_frame = self.frames.active_frame_mut()?;
+    let rope = active_frame.buffer.rope();
+    let primary = active_frame.cursor.primary_idx();
+
+    let mut lines = Vec::new();
+    let mut primary_line = 0;
+    for (cursor, selection) in selections.iter().enumerate() {
+      if cursor == primary {
+        primary_line = lines.len();
+      }
+      let first = rope.char_to_line(selection.start);
+      let last = rope.char_to_line(selection.end.saturating_sub(1).max(selection.start));
+      for line in first..=last {
+        let start = rope.line_to_char(line);
+        let len = rope.line(line).chars().take_while(|char| *char != '\n' && *char != '\r').count();
+        let from = selection.start.max(start);
+        let to = selection.end.min(start + len).max(from + 1);
+        lines.push((from, to - 1));
+      }
+    }
+    if lines.len() <= selections.len() {
+      return None;
+    }
+
+    let mut cursors = CursorManager::default();
+    let (anchor, head) = lines[0];
+    cursors.get_mut(0).anchor = anchor;
+    cursors::goto_char(rope, &mut cursors.get_mut(0).cursor, head);
+    for (anchor, head) in &lines[1..] {
+      cursors.add(rope, *anchor, *head);
+    }
+    cursors.set_primary(primary_line);
+    active_frame.cursor = cursors;
+
+    let mode = self.macro_key_resolver.input_manager.editor_mode().clone();
+    self.sync_selection(&mode);
+    Some(Action::Render)
+  }
+
+  /// Drops every cursor but the primary one.
+  pub fn keep_primary_cursor(&mut self) {
+    if let Some(active_frame) = self.frames.active_frame_mut() {
+      active_frame.cursor.keep_primary();
+    }
+  }
+}
@@ -0,0 +1,237 @@
+//! Operators on motions, text objects and visual selections, like `d3w`, `diw`, `!ip` or `d` in
+//! visual mode.
+
+use std::ops::Range;
+
+use teddy_core::{
+  action::{Action, Notification},
+  buffer::Edit,
+  input_mode::{CommandModeData, InputMode, VisualSelection},
+  text_object::{Operator, TextObject},
+};
+
+use super::{register, Editor};
+use crate::{
+  frame::cursors,
+  inputresolver::CursorMovement,
+  motions,
+  prelude::{f, Result},
+  syntax::Language,
+  text_objects,
+};
+
+impl Editor {
+  /// Keeps the visual selection in step with the primary cursor. Entering visual mode starts a
+  /// selection at every cursor.
+  pub(super) fn sync_selection(&mut self, previous_mode: &InputMode) {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return;
+    };
+    let InputMode::Visual(selection) = self.macro_key_resolver.input_manager.editor_mode_mut()
+    else {
+      return;
+    };
+    let rope = active_frame.buffer.rope();
+    if !matches!(previous_mode, InputMode::Visual(_)) {
+      for cursor in active_frame.cursor.iter_mut() {
+        cursor.anchor = cursors::char_idx(rope, &cursor.cursor);
+      }
+    }
+    let primary = active_frame.cursor.get(active_frame.cursor.primary_idx());
+    *selection = VisualSelection::new(primary.anchor, cursors::char_idx(rope, &primary.cursor));
+  }
+
+  /// The chars between the anchor of cursor `cursor` and the cursor, both included.
+  fn selection_of(&self, cursor: usize) -> Option<Range<usize>> {
+    let active_frame = self.frames.active_frame()?;
+    let anchor = active_frame.cursor.get(cursor).anchor;
+    let (from, to) = VisualSelection::new(anchor, active_frame.char_idx_of(cursor)).range();
+    Some(from..(to + 1).min(active_frame.rope().len_chars()))
+  }
+
+  /// The visual selection of every cursor, as ranges of chars.
+  pub fn selections(&self) -> Vec<Range<usize>> {
+    let (InputMode::Visual(_), Some(active_frame)) =
+      (self.macro_key_resolver.input_manager.editor_mode(), self.frames.active_frame())
+    else {
+      return Vec::new();
+    };
+    (0..active_frame.cursor.count()).filter_map(|cursor| self.selection_of(cursor)).collect()
+  }
+
+  /// The visual selection of the primary cursor.
+  pub fn selection(&self) -> Option<Range<usize>> {
+    let primary = self.frames.active_frame()?.cursor.primary_idx();
+    self.selections().get(primary).cloned()
+  }
+
+  fn text_object(&self, cursor: usize, object: TextObject, inner: bool) -> Result<Range<usize>> {
+    let active_frame = self.frames.active_frame().ok_or("No buffer")?;
+    let language = active_frame.path().and_then(Language::from_path);
+    if object.is_syntax() && language.is_none() {
+      return Err("No parse tree for this file type".into());
+    }
+    let (rope, char_idx) = (active_frame.rope(), active_frame.char_idx_of(cursor));
+    let range = text_objects::select(rope, char_idx, object, inner, language);
+    range.filter(|range| !range.is_empty()).ok_or_else(|| "Nothing to select here".into())
+  }
+
+  /// Runs an operator on a text object around every cursor, like `diw`.
+  pub fn operate(&mut self, operator: Operator, object: TextObject, inner: bool) -> Option<Action> {
+    if !matches!(operator, Operator::Fold | Operator::Filter) {
+      self.register.start(false);
+    }
+    let result = self.for_each_cursor(|editor, cursor| {
+      let range = editor.text_object(cursor, object, inner)?;
+      editor.apply_operator(operator, cursor, range)
+    });
+    self.operator_result(result)
+  }
+
+  /// Runs an operator from every cursor to where `movement` goes, `count` times, like `d3w`. Motions
+  /// between lines work on whole lines.
+  pub fn operate_motion(
+    &mut self,
+    operator: Operator,
+    movement: CursorMovement,
+    count: usize,
+  ) -> Option<Action> {
+    if !matches!(operator, Operator::Fold | Operator::Filter) {
+      self.register.start(motions::is_linewise(movement));
+    }
+    let result = self.for_each_cursor(|editor, cursor| {
+      let active_frame = editor.frames.active_frame().ok_or("No buffer")?;
+      let (rope, char_idx) = (active_frame.rope(), active_frame.char_idx_of(cursor));
+      let range = motions::operator_range(rope, char_idx, movement, count, operator);
+      match range.filter(|range| !range.is_empty()) {
+        Some(range) => editor.apply_operator(operator, cursor, range),
+        None => Ok(()),
+      }
+    });
+    self.operator_result(result)
+  }
+
+  /// Selects a text object around every cursor in visual mode.
+  pub fn select_object(&mut self, object: TextObject, inner: bool) -> Option<Action> {
+    let result = self.for_each_cursor(|editor, cursor| {
+      let range = editor.text_object(cursor, object, inner)?;
+      let active_frame = editor.frames.active_frame_mut().ok_or("No buffer")?;
+      active_frame.cursor.get_mut(cursor).anchor = range.start;
+      active_frame.set_char_idx_of(cursor, range.end - 1);
+      Ok(())
+    });
+    let mode = self.macro_key_resolver.input_manager.editor_mode().clone();
+    self.sync_selection(&mode);
+    self.operator_result(result)
+  }
+
+  /// Runs an operator on the visual selections and leaves visual mode.
+  pub fn operate_selection(&mut self, operator: Operator) -> Option<Action> {
+    // The keys may have left visual mode already, `!` for one switches to command mode, so the
+    // marks are set from the selection itself.
+    let primary = self.frames.active_frame()?.cursor.primary_idx();
+    if let Some(range) = self.selection_of(primary) {
+      self.set_selection_marks(range.start, range.end.saturating_sub(1).max(range.start));
+    }
+    let mode = self.macro_key_resolver.input_manager.editor_mode_mut();
+    if let InputMode::Visual(_) = mode {
+      *mode = InputMode::Normal;
+    }
+
+    if !matches!(operator, Operator::Fold | Operator::Filter) {
+      self.register.start(false);
+    }
+    let result = self.for_each_cursor(|editor, cursor| {
+      let range = editor.selection_of(cursor).ok_or("No buffer")?;
+      editor.apply_operator(operator, cursor, range)
+    });
+    self.operator_result(result)
+  }
+
+  /// Deletes, changes or yanks `range` for cursor `cursor`, adding its text to the register, or
+  /// folds its lines.
+  fn apply_operator(
+    &mut self,
+    operator: Operator,
+    cursor: usize,
+    range: Range<usize>,
+  ) -> Result<()> {
+    if operator == Operator::Filter {
+      return self.filter_command(cursor, range);
+    }
+    if operator == Operator::Fold {
+      let active_frame = self.frames.active_frame_mut().ok_or("No buffer")?;
+      let rope = active_frame.rope();
+      let last = range.end.saturating_sub(1).max(range.start);
+      return active_frame.fold(rope.char_to_line(range.start), rope.char_to_line(last));
+    }
+    let active_frame = self.frames.active_frame().ok_or("No buffer")?;
+    self.register.push(active_frame.rope().slice(range.clone()).to_string());
+    if operator != Operator::Yank {
+      self.apply_edit_at(&Edit::remove(range.start, range.end), cursor)?;
+    }
+    if operator == Operator::Change {
+      let mode = InputMode::Insert { left_insert: true };
+      *self.macro_key_resolver.input_manager.editor_mode_mut() = mode;
+    }
+    if let Some(active_frame) = self.frames.active_frame_mut() {
+      active_frame.set_char_idx_of(cursor, range.start);
+    }
+    Ok(())
+  }
+
+  /// Starts the command line for `!{motion}` with the lines of `range` in it, `:{lines}!`. Only the
+  /// primary cursor's lines are filtered.
+  fn filter_command(&mut self, cursor: usize, range: Range<usize>) -> Result<()> {
+    let active_frame = self.frames.active_frame().ok_or("No buffer")?;
+    if cursor != active_frame.cursor.primary_idx() {
+      return Ok(());
+    }
+    let rope = active_frame.rope();
+    let first = rope.char_to_line(range.start);
+    let last = rope.char_to_line(range.end.saturating_sub(1).max(range.start));
+    let lines = match first == last {
+      true => ".".to_string(),
+      false => f!("{},{}", first + 1, last + 1),
+    };
+    // Anything typed after the keys already went into the command line.
+    let input_manager = &mut self.macro_key_resolver.input_manager;
+    let typed = match input_manager.editor_mode() {
+      InputMode::Command(data) => data.value().to_string(),
+      _ => String::new(),
+    };
+    let data = CommandModeData::with_text(&f!("{lines}!{typed}"));
+    *input_manager.editor_mode_mut() = InputMode::Command(data);
+    Ok(())
+  }
+
+  pub(super) fn operator_result(&self, result: Result<()>) -> Option<Action> {
+    let notification = match result {
+      Ok(()) => return Some(Action::Render),
+      Err(err) => Notification::error(f!("{err}")),
+    };
+    Some(Action::AttachNotification(notification, 2))
+  }
+
+  /// Puts the register after every cursor, leaving each on the last char it put. Whole lines go
+  /// below the cursor's line, with the cursor on the first of them. With as many cursors as the
+  /// register has texts, each cursor gets its own.
+  pub fn put(&mut self) -> Option<Action> {
+    if self.register.is_empty() {
+      return None;
+    }
+    let texts = self.register.texts_for(self.frames.active_frame()?.cursor.count());
+    let linewise = self.register.linewise;
+    let result = self.for_each_cursor(|editor, cursor| {
+      let active_frame = editor.frames.active_frame().ok_or("No buffer")?;
+      let char_idx = active_frame.char_idx_of(cursor);
+      let (edit, char_idx) =
+        register::put_edit(active_frame.rope(), char_idx, &texts[cursor], linewise);
+
+      editor.apply_edit_at(&edit, cursor)?;
+      editor.frames.active_frame_mut().ok_or("No buffer")?.set_char_idx_of(cursor, char_idx);
+      Ok(())
+    });
+    self.operator_result(result)
+  }
+}
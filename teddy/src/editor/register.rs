@@ -0,0 +1,120 @@
+//! The register `d`, `c` and `y` fill and `p` puts back.
+
+use ropey::Rope;
+use teddy_core::buffer::Edit;
+
+use crate::prelude::f;
+
+/// The texts of the last delete or yank, one for each cursor.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Register {
+  pub texts: Vec<String>,
+  /// Whether the texts are whole lines, like after `dd` or `yj`, which are put below the cursor's
+  /// line instead of after the cursor.
+  pub linewise: bool,
+}
+
+impl Register {
+  /// Empties the register for the texts of an operator, whole lines when `linewise`.
+  pub fn start(&mut self, linewise: bool) {
+    self.texts.clear();
+    self.linewise = linewise;
+  }
+
+  /// Adds the text an operator took. Whole lines are kept ending in a line break, whether the
+  /// operator took the one after them or, on the last line, the one before.
+  pub fn push(&mut self, text: String) {
+    if !self.linewise {
+      self.texts.push(text);
+      return;
+    }
+    let text = match text.strip_prefix('\n') {
+      Some(lines) if !text.ends_with('\n') => lines.to_string(),
+      _ => text,
+    };
+    self.texts.push(match text.ends_with('\n') {
+      true => text,
+      false => text + "\n",
+    });
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.texts.is_empty()
+  }
+
+  /// The text each of `count` cursors puts: its own with as many texts as cursors, all of them
+  /// otherwise.
+  pub fn texts_for(&self, count: usize) -> Vec<String> {
+    match (self.texts.len() == count, self.linewise) {
+      (true, _) => self.texts.clone(),
+      (false, true) => vec![self.texts.concat(); count],
+      (false, false) => vec![self.texts.join("\n"); count],
+    }
+  }
+}
+
+/// The edit putting `text` of a register for a cursor at `char_idx`, and the char the cursor goes
+/// to: the last one put, or the start of the lines put when `linewise`.
+pub fn put_edit(rope: &Rope, char_idx: usize, text: &str, linewise: bool) -> (Edit, usize) {
+  if !linewise {
+    let at_line_end = matches!(rope.get_char(char_idx), None | Some('\n' | '\r'));
+    let at = if at_line_end { char_idx } else { char_idx + 1 };
+    let last = at + text.chars().count().saturating_sub(1);
+    return (Edit::insert(at, text), last);
+  }
+  let line = rope.char_to_line(char_idx.min(rope.len_chars()));
+  match line + 1 < rope.len_lines() {
+    true => {
+      let at = rope.line_to_char(line + 1);
+      (Edit::insert(at, text), at)
+    }
+    // The last line has no line break to put the lines after.
+    false => {
+      let at = rope.len_chars();
+      (Edit::insert(at, f!("\n{}", text.strip_suffix('\n').unwrap_or(text))), at + 1)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use teddy_core::text_object::Operator;
+
+  use super::*;
+  use crate::{inputresolver::CursorMovement, motions};
+
+  /// Runs `operator` over the doubled keys like `dd`, then `p`, returning the text and cursor.
+  fn doubled_then_put(text: &str, char_idx: usize, operator: Operator) -> (String, usize) {
+    let mut rope = Rope::from_str(text);
+    let range = motions::operator_range(&rope, char_idx, CursorMovement::Down, 0, operator);
+    let range = range.unwrap();
+    let mut register = Register::default();
+    register.start(motions::is_linewise(CursorMovement::Down));
+    register.push(rope.slice(range.clone()).to_string());
+    let mut cursor = char_idx;
+    if operator == Operator::Delete {
+      rope.remove(range.clone());
+      cursor = range.start;
+    }
+
+    let (edit, cursor) = put_edit(&rope, cursor, &register.texts_for(1)[0], register.linewise);
+    rope.insert(edit.from, &edit.text);
+    (rope.to_string(), cursor)
+  }
+
+  #[test]
+  fn test_linewise_put() {
+    let text = "one\ntwo\nthree\n";
+    assert_eq!(doubled_then_put(text, 5, Operator::Delete), ("one\nthree\ntwo\n".into(), 10));
+    assert_eq!(doubled_then_put(text, 0, Operator::Yank), ("one\none\ntwo\nthree\n".into(), 4));
+    // The last line without a line break after it.
+    assert_eq!(doubled_then_put("a\nb", 2, Operator::Yank), ("a\nb\nb".into(), 4));
+    assert_eq!(doubled_then_put("a\nb\nc", 4, Operator::Delete), ("a\nb\nc".into(), 4));
+
+    let mut register = Register::default();
+    register.start(false);
+    register.push("wo".to_string());
+    let (edit, cursor) = put_edit(&Rope::from_str("two"), 0, &register.texts[0], false);
+    assert_eq!((edit, cursor), (Edit::insert(1, "wo"), 2));
+  }
+}
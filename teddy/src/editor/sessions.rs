@@ -0,0 +1,123 @@
+//! Saving and restoring the open files, and what else a session keeps, with `:mksession` and
+//! `:source`.
+
+use std::path::{Path, PathBuf};
+
+use teddy_core::action::{Action, Notification};
+
+use super::Editor;
+use crate::{
+  diff::DiffView,
+  frame::Frame,
+  prelude::f,
+  session::{Session, SessionBuffer},
+};
+
+impl Editor {
+  /// The open files with their cursors, the diff split, the register, the global marks and the
+  /// command line history.
+  pub fn session(&self) -> Session {
+    let mut frames: Vec<(&u16, &Frame)> =
+      self.frames.frames.iter().filter(|(_, frame)| frame.path().is_some()).collect();
+    frames.sort_by_key(|(_, frame)| frame.path());
+
+    let active = self.frames.active_frame().and_then(Frame::path);
+    let buffers = frames.iter().map(|(_, frame)| {
+      let (column, line) = frame.cursor.primary().get();
+      let path = frame.path().unwrap_or(Path::new("")).to_path_buf();
+      let folds = frame.folds.iter().copied().collect();
+      SessionBuffer { path, line, column, scroll: frame.scroll, folds }
+    });
+    let path = |id: u16| self.frames.frames.get(&id).and_then(Frame::path).map(Path::to_path_buf);
+    let diff = self.diff.as_ref().and_then(|diff| path(diff.left).zip(path(diff.right)));
+    let marks = self.marks.global().map(|(mark, location)| (mark, location.clone())).collect();
+    let commands = self.macro_key_resolver.input_manager.command_manager.history.clone();
+    Session {
+      active: frames.iter().position(|(_, frame)| frame.path() == active),
+      buffers: buffers.collect(),
+      diff,
+      register: self.register.clone(),
+      marks,
+      commands,
+    }
+  }
+
+  /// Opens the files of `session` and puts their cursors back, along with the rest of it. Files
+  /// that can't be opened are skipped, with a notification.
+  pub fn restore_session(&mut self, session: &Session) -> Vec<Action> {
+    let mut actions = Vec::new();
+    if !session.register.is_empty() {
+      self.register = session.register.clone();
+    }
+    for (mark, location) in &session.marks {
+      self.marks.set(*mark, location.clone());
+    }
+    if !session.commands.is_empty() {
+      let history = &mut self.macro_key_resolver.input_manager.command_manager.history;
+      *history = session.commands.clone();
+    }
+
+    for buffer in &session.buffers {
+      match self.open_file(&buffer.path) {
+        Ok(opened) => actions.extend(opened),
+        Err(err) => {
+          let notification = Notification::error(f!("{}: {err}", buffer.path.display()));
+          actions.push(Action::AttachNotification(notification, 6));
+          continue;
+        }
+      }
+      let Some(frame) = self.frames.active_frame_mut() else {
+        continue;
+      };
+      let rope = frame.rope();
+      let len_lines = rope.len_lines();
+      let line = buffer.line.min(len_lines - 1);
+      let line_len = rope.line(line).chars().count();
+      let char_idx = rope.line_to_char(line) + buffer.column.min(line_len);
+      frame.set_cursor_char_idx(char_idx);
+      frame.scroll = buffer.scroll;
+      frame.folds.restore(buffer.folds.iter().copied().filter(|fold| fold.end < len_lines));
+    }
+
+    let (left, right) = match &session.diff {
+      Some((left, right)) => {
+        (self.frames.frame_id_by_path(left), self.frames.frame_id_by_path(right))
+      }
+      None => (None, None),
+    };
+    if let Some((left, right)) = left.zip(right).filter(|(left, right)| left != right) {
+      self.diff = Some(DiffView::new(left, right));
+    }
+
+    let active = session.active.and_then(|active| session.buffers.get(active));
+    if let Some(id) = active.and_then(|buffer| self.frames.frame_id_by_path(&buffer.path)) {
+      self.frames.focus(id);
+    }
+    actions.push(Action::Render);
+    actions
+  }
+
+  /// Runs `:mksession`, saving to `path` or the working directory's session.
+  pub fn make_session(&mut self, path: Option<PathBuf>) -> Option<Action> {
+    let notification = match path.or_else(Session::default_path) {
+      Some(path) => match self.session().save(&path) {
+        Ok(()) => Notification::info(f!("Saved session to {}", path.display())),
+        Err(err) => Notification::error(f!("Failed to save session: {err}")),
+      },
+      None => Notification::error("No state directory to save the session in".to_string()),
+    };
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  /// Runs `:source`, restoring `path` or the working directory's session.
+  pub fn source_session(&mut self, path: Option<PathBuf>) -> Vec<Action> {
+    let path = path.or_else(Session::default_path).unwrap_or_default();
+    match Session::load(&path) {
+      Ok(session) => self.restore_session(&session),
+      Err(err) => {
+        let notification = Notification::error(f!("Failed to load {}: {err}", path.display()));
+        Vec::from_iter([Action::AttachNotification(notification, 6)])
+      }
+    }
+  }
+}
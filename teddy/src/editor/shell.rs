@@ -0,0 +1,159 @@
+//! Shell commands from the command line: `:!`, `:r !` and `:{range}!`.
+
+use chrono::Utc;
+use teddy_core::{
+  action::{Action, Notification, ShellOutput},
+  buffer::Edit,
+};
+use teddy_events::ShellEvent;
+
+use super::Editor;
+use crate::{
+  frame::{notification_manager::NotificationMessage, Frame, FrameBuffer},
+  prelude::{f, Result},
+  shell::{self, Target},
+};
+
+impl Editor {
+  /// Starts `command` for `:!`, `:r !` or `:{range}!`, with a spinner until it's done.
+  pub fn shell(&mut self, command: String, output: ShellOutput) -> Option<Action> {
+    let (input, target) = match self.shell_target(output) {
+      Ok(target) => target,
+      Err(err) => return Some(Action::AttachNotification(Notification::error(f!("{err}")), 4)),
+    };
+    let id = self.shell.start(&command, input, target, &self.events);
+    let notification = Notification::info(f!("{command} (Ctrl-c cancels)"));
+    // It stays up until the command is done and replaces it.
+    let lasts_to = Utc::now().timestamp() + 24 * 60 * 60;
+    let message = NotificationMessage::new(notification, lasts_to).with_id(f!("shell {id}"));
+    self.frames.notification_manager.replace(message.with_spinner());
+    None
+  }
+
+  /// Where the output of a command goes, and the lines it reads when it filters them.
+  fn shell_target(&self, output: ShellOutput) -> Result<(Option<String>, Target)> {
+    let Some(active_frame) = self.frames.active_frame().zip(self.frames.active_id()) else {
+      return match output {
+        ShellOutput::Show => Ok((None, Target::Show)),
+        _ => Err("No buffer".into()),
+      };
+    };
+    let (active_frame, frame) = active_frame;
+    let (rope, revision) = (active_frame.rope(), active_frame.buffer.revision());
+    let line = active_frame.cursor.primary().get().1;
+    match output {
+      ShellOutput::Show => Ok((None, Target::Show)),
+      ShellOutput::Read => Ok((None, Target::Read { frame, line, revision })),
+      ShellOutput::Filter(range) => {
+        let lines = shell::parse_range(&range, line, rope.len_lines().saturating_sub(1))?;
+        let chars = rope.line_to_char(lines.start)..rope.line_to_char(lines.end);
+        let input = rope.slice(chars).to_string();
+        Ok((Some(input), Target::Filter { frame, lines, revision }))
+      }
+    }
+  }
+
+  pub fn shell_event(&mut self, event: ShellEvent) -> Option<Vec<Action>> {
+    let id = match event {
+      ShellEvent::Finished { id, .. } | ShellEvent::Failed { id, .. } => id,
+    };
+    let job = self.shell.finish(id)?;
+    let result = match event {
+      ShellEvent::Finished { success: false, stderr, .. } if job.target != Target::Show => {
+        let error = stderr.lines().next().unwrap_or("exited with an error").to_string();
+        Err(error.into())
+      }
+      ShellEvent::Finished { success, stdout, stderr, .. } => {
+        self.shell_output(&job.command, &job.target, success, stdout + &stderr)
+      }
+      ShellEvent::Failed { error, .. } => Err(error.into()),
+    };
+    let notification = match result {
+      Ok(Some(notification)) => notification,
+      Ok(None) => Notification::success(f!("{} finished", job.command)),
+      Err(err) => Notification::error(f!("{}: {err}", job.command)),
+    };
+    let lasts_to = Utc::now().timestamp() + 4;
+    let message = NotificationMessage::new(notification, lasts_to).with_id(f!("shell {id}"));
+    self.frames.notification_manager.replace(message);
+    Some(Vec::from_iter([Action::Render]))
+  }
+
+  /// Puts `output` where `target` says. Output that's shown comes back as a notification when it's
+  /// a single line, longer output gets a scratch buffer.
+  fn shell_output(
+    &mut self,
+    command: &str,
+    target: &Target,
+    success: bool,
+    output: String,
+  ) -> Result<Option<Notification>> {
+    let (frame, revision) = match target {
+      Target::Show if output.trim_end().lines().count() <= 1 => {
+        let line = output.trim_end().to_string();
+        return Ok(match success {
+          true => (!line.is_empty()).then(|| Notification::info(line)),
+          false => Some(Notification::error(f!("{command}: {line}"))),
+        });
+      }
+      Target::Show => {
+        let mut frame = Frame::with_buffer(FrameBuffer::scratch(&f!("!{command}")));
+        frame.options = self.options.window.clone();
+        frame.buffer.append(&output)?;
+        frame.buffer.mark_saved();
+        self.frames.add_frame(frame)?;
+        return Ok(None);
+      }
+      Target::Read { frame, revision, .. } | Target::Filter { frame, revision, .. } => {
+        (*frame, *revision)
+      }
+    };
+    let buffer = &self.frames.frames.get(&frame).ok_or("The buffer was closed")?.buffer;
+    if buffer.revision() != revision {
+      return Err("The buffer changed while it ran".into());
+    }
+    let rope = buffer.rope();
+    let edit = match target {
+      Target::Read { line, .. } => {
+        let at = rope.line_to_char((line + 1).min(rope.len_lines()));
+        // The last line has no line break to insert after.
+        let text = match at == rope.len_chars() && rope.chars().last() != Some('\n') {
+          true => f!("\n{}", output.trim_end_matches('\n')),
+          false => output,
+        };
+        Edit::insert(at, &text)
+      }
+      Target::Filter { lines, .. } => {
+        let (from, to) = (rope.line_to_char(lines.start), rope.line_to_char(lines.end));
+        // Keep the line break after the lines if they had one.
+        let had_break = rope.slice(from..to).chars().last() == Some('\n');
+        let text = match had_break && !output.is_empty() && !output.ends_with('\n') {
+          true => output + "\n",
+          false => output,
+        };
+        Edit { from, to, text }
+      }
+      Target::Show => unreachable!(),
+    };
+
+    let active = self.frames.active_id();
+    self.frames.focus(frame);
+    let result = self.apply_edit(&edit);
+    if let Some(id) = active {
+      self.frames.focus(id);
+    }
+    result.map(|()| None)
+  }
+
+  /// Stops the running commands and tasks, Ctrl-c in normal mode.
+  pub fn cancel_shell(&mut self) {
+    let jobs = self.shell.cancel_all().into_iter().map(|(id, job)| (f!("shell {id}"), job.command));
+    let runs = self.tasks.cancel_all().into_iter().map(|(id, run)| (f!("task {id}"), run.name));
+    for (id, name) in jobs.chain(runs).collect::<Vec<_>>() {
+      let notification = Notification::warn(f!("{name} cancelled"));
+      let lasts_to = Utc::now().timestamp() + 2;
+      let message = NotificationMessage::new(notification, lasts_to).with_id(id);
+      self.frames.notification_manager.replace(message);
+    }
+  }
+}
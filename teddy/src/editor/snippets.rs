@@ -0,0 +1,122 @@
+//! Expanding snippets and jumping between their tabstops.
+
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use teddy_config::Config;
+use teddy_core::{
+  action::{Action, Notification},
+  buffer::Edit,
+};
+
+use super::Editor;
+use crate::{
+  prelude::Result,
+  snippets::{parser::parse, session::SnippetSession, SnippetSource, SnippetVariables, Snippets},
+};
+
+impl Editor {
+  /// Loads the user snippets and offers them as completions. Returns notifications about files
+  /// that couldn't be loaded.
+  pub fn load_snippets(&mut self, config: &Config) -> Vec<Action> {
+    let Some(dir) = Config::config_dir() else {
+      return Vec::new();
+    };
+    let (mut snippets, errors) = Snippets::load(&dir.join("snippets"));
+    for server in &config.language_servers {
+      for extension in &server.file_extensions {
+        snippets.alias(extension, &server.language_id);
+      }
+    }
+    self.completion.register(SnippetSource(snippets));
+
+    let notifications = errors.into_iter().map(Notification::error);
+    notifications.map(|notification| Action::AttachNotification(notification, 6)).collect()
+  }
+
+  /// Replaces `from..to` of the active buffer with the expanded `body` and moves to its first
+  /// tabstop.
+  pub fn expand_snippet(&mut self, from: usize, to: usize, body: &str) -> Result<()> {
+    let active_frame = self.frames.active_frame_mut().ok_or("No active buffer")?;
+    let rope = active_frame.rope();
+    let path = active_frame.path().map(Path::to_path_buf);
+
+    let line_idx = rope.char_to_line(from);
+    let line = rope.line(line_idx).to_string();
+    let line = line.trim_end_matches(['\n', '\r']);
+    let indent: String = line.chars().take_while(|char| *char == ' ' || *char == '\t').collect();
+    let word = rope.slice(from..to).to_string();
+
+    let variables = SnippetVariables { path: path.as_deref(), line, line_idx, word: &word };
+    let snippet = parse(body, |name| variables.resolve(name), &indent);
+
+    self.snippet = None;
+    self.apply_edit(&Edit { from, to, text: snippet.text.clone() })?;
+
+    self.snippet = SnippetSession::new(&snippet, from);
+    self.jump_tabstop(0);
+    Ok(())
+  }
+
+  pub(super) fn jump_tabstop(&mut self, offset: isize) -> Option<Action> {
+    let session = self.snippet.as_mut()?;
+    let Some(char_idx) = session.jump(offset) else {
+      // Shift-Tab on the first stop stays put, Tab past the last one ends the session.
+      if offset > 0 {
+        self.snippet = None;
+      }
+      return None;
+    };
+    if session.is_last() {
+      self.snippet = None;
+    }
+    self.frames.active_frame_mut()?.set_cursor_char_idx(char_idx);
+    None
+  }
+
+  /// Removes a selected placeholder before `key` is typed over it. Returns whether that was all
+  /// `key` should do.
+  pub(super) fn type_over_placeholder(&mut self, key: KeyEvent) -> bool {
+    let Some(edit) = self.snippet.as_mut().and_then(SnippetSession::take_selection) else {
+      return false;
+    };
+    if !matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete) {
+      return false;
+    }
+    if self.apply_edit(&edit).is_err() {
+      return false;
+    }
+    !matches!(key.code, KeyCode::Char(_))
+  }
+
+  /// Updates the snippet session after `edit`, copying the edited tabstop to its mirrors. Edits
+  /// outside of the current tabstop end the session.
+  pub(super) fn sync_snippet(&mut self, edit: &Edit) {
+    let Some(session) = self.snippet.as_mut() else {
+      return;
+    };
+    let Some(source) = session.apply_edit(edit) else {
+      self.snippet = None;
+      return;
+    };
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return;
+    };
+    let path = active_frame.path().map(Path::to_path_buf);
+
+    let mut cursor = active_frame.cursor_char_idx();
+    while let Some(mirror) = session.mirror_edit(active_frame.rope(), source) {
+      if active_frame.apply_edit(&mirror).is_err() {
+        break;
+      }
+      session.apply_edit(&mirror);
+      cursor = mirror.shift(cursor);
+
+      if let Some(path) = &path {
+        self.diagnostics.apply_edit(path, &mirror);
+        self.lsp.did_change(path, &mirror, active_frame.rope());
+      }
+    }
+    active_frame.set_cursor_char_idx(cursor);
+  }
+}
@@ -0,0 +1,62 @@
+//! Swap files of changed buffers, and getting them back with `:recover`.
+
+use std::{
+  path::Path,
+  time::{Duration, Instant},
+};
+
+use teddy_core::{
+  action::{Action, Notification},
+  buffer::Edit,
+};
+
+use super::Editor;
+use crate::{prelude::f, swap};
+
+impl Editor {
+  /// Writes the swap files of changed buffers, at most once per `updatetime`.
+  pub fn snapshot_swaps(&mut self) {
+    let interval = Duration::from_millis(self.options.global.updatetime as u64);
+    if self.last_swap.elapsed() >= interval {
+      swap::flush();
+      self.last_swap = Instant::now();
+    }
+  }
+
+  /// Tells about a swap file left for `path` by a crash, or by another teddy still editing it.
+  pub(super) fn swap_notice(&self, path: &Path) -> Option<Action> {
+    let swap = swap::find(path)?;
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    let notification = match swap.is_stale() {
+      true => Notification::warn(f!("Found unsaved changes to {name}, use :recover or :recover!")),
+      false if swap.pid == std::process::id() => return None,
+      false => Notification::warn(f!("{name} is being edited by teddy (pid {})", swap.pid)),
+    };
+    Some(Action::AttachNotification(notification, 10))
+  }
+
+  /// Replaces the active buffer with its swap file, or deletes the swap file when `discard`.
+  pub fn recover(&mut self, discard: bool) -> Option<Action> {
+    let active_frame = self.frames.active_frame_mut()?;
+    let path = active_frame.path()?.to_path_buf();
+    let Some(swap) = swap::find(&path) else {
+      let notification = Notification::error("No swap file for this buffer".to_string());
+      return Some(Action::AttachNotification(notification, 4));
+    };
+    if discard {
+      swap::forget(&path);
+      return Some(Action::AttachNotification(Notification::info("Deleted swap file".into()), 2));
+    }
+
+    let cursor = active_frame.cursor_char_idx();
+    let edit = Edit { from: 0, to: active_frame.rope().len_chars(), text: swap.text };
+    let notification = match self.apply_edit(&edit) {
+      Ok(()) => Notification::info("Recovered, write the buffer to keep the changes".into()),
+      Err(err) => Notification::error(f!("Failed to recover: {err}")),
+    };
+    if let Some(active_frame) = self.frames.active_frame_mut() {
+      active_frame.set_cursor_char_idx(cursor);
+    }
+    Some(Action::AttachNotification(notification, 6))
+  }
+}
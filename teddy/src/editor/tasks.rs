@@ -0,0 +1,169 @@
+//! Tasks run by `:make` and `:task`, and the quickfix list of their errors.
+
+use std::ops::Range;
+
+use chrono::Utc;
+use ratatui::style::Style;
+use teddy_core::action::{Action, Diagnostic, DiagnosticLevel, DiagnosticSource, Notification};
+use teddy_events::TaskEvent;
+
+use super::diagnostics::level_color;
+use super::Editor;
+use crate::{
+  components::list::{ListComponent, ListEntry},
+  frame::{notification_manager::NotificationMessage, Frame, FrameBuffer},
+  prelude::f,
+  task::{Quickfix, QuickfixEntry},
+};
+
+impl Editor {
+  /// Runs `:task {name}`, or `:make` without a name. The output goes to a log buffer, reused by
+  /// later runs of the task, and the errors of the last run are replaced as new ones come in.
+  pub fn run_task(&mut self, name: Option<String>) -> Option<Action> {
+    let task = match &name {
+      Some(name) => self.task_configs.iter().find(|task| &task.name == name),
+      None => self.task_configs.first(),
+    };
+    let Some(task) = task.cloned() else {
+      let names: Vec<&str> = self.task_configs.iter().map(|task| task.name.as_str()).collect();
+      let notification = match name {
+        Some(name) => Notification::error(f!("No task {name}, there's {}", names.join(", "))),
+        None => Notification::error("No tasks configured".to_string()),
+      };
+      return Some(Action::AttachNotification(notification, 4));
+    };
+
+    let log_name = f!("task {}", task.name);
+    let log = self.frames.frames.iter().find(|(_, frame)| frame.buffer.context.name == log_name);
+    let log = match log.map(|(id, _)| *id) {
+      Some(id) => {
+        let frame = self.frames.frames.get_mut(&id)?;
+        frame.buffer = FrameBuffer::scratch(&log_name);
+        frame.set_cursor_char_idx(0);
+        self.frames.focus(id);
+        id
+      }
+      None => {
+        let mut frame = Frame::with_buffer(FrameBuffer::scratch(&log_name));
+        frame.options = self.options.window.clone();
+        self.frames.add_frame(frame).ok()?
+      }
+    };
+    let id = match self.tasks.start(&task, log, &self.events) {
+      Ok(id) => id,
+      Err(err) => {
+        let notification = Notification::error(f!("{}: {err}", task.name));
+        return Some(Action::AttachNotification(notification, 4));
+      }
+    };
+
+    // The diagnostics of the task's last run are out of date.
+    self.diagnostics.clear_source(&DiagnosticSource::Task(task.name.clone()));
+
+    let notification = Notification::info(f!("{} (Ctrl-c cancels)", task.command));
+    // It stays up until the task is done and replaces it.
+    let lasts_to = Utc::now().timestamp() + 24 * 60 * 60;
+    let message = NotificationMessage::new(notification, lasts_to).with_id(f!("task {id}"));
+    self.frames.notification_manager.replace(message.with_spinner());
+    Some(Action::Render)
+  }
+
+  pub fn task_event(&mut self, event: TaskEvent) -> Option<Vec<Action>> {
+    let (id, result) = match event {
+      TaskEvent::Line { id, line } => {
+        self.task_line(id, &line);
+        return None;
+      }
+      TaskEvent::Finished { id, success } => (id, Ok(success)),
+      TaskEvent::Failed { id, error } => (id, Err(error)),
+    };
+    let run = self.tasks.finish(id)?;
+    let errors = run.entries.iter().filter(|entry| entry.level == DiagnosticLevel::Error).count();
+    let warnings = run.entries.iter().filter(|entry| entry.level == DiagnosticLevel::Warn).count();
+    let notification = match result {
+      Err(error) => Notification::error(f!("{}: {error}", run.name)),
+      Ok(true) if run.entries.is_empty() => Notification::success(f!("{} succeeded", run.name)),
+      Ok(success) => {
+        let summary = f!("{}: {errors} errors, {warnings} warnings", run.name);
+        match success && errors == 0 {
+          true => Notification::warn(summary),
+          false => Notification::error(summary),
+        }
+      }
+    };
+    self.quickfix = Quickfix::new(run.entries);
+
+    let lasts_to = Utc::now().timestamp() + 4;
+    let message = NotificationMessage::new(notification, lasts_to).with_id(f!("task {id}"));
+    self.frames.notification_manager.replace(message);
+    Some(Vec::from_iter([Action::Render]))
+  }
+
+  /// Logs a line of task `id`'s output, and adds the error in it as a diagnostic.
+  fn task_line(&mut self, id: u64, line: &str) {
+    let Some(run) = self.tasks.get_mut(id) else {
+      return;
+    };
+    let (text, entry) = run.parse(line);
+    let (log, name) = (run.log, run.name.clone());
+    if let Some(entry) = entry {
+      run.entries.push(entry.clone());
+      let span = self.quickfix_span(&entry);
+      let message = f!("{name}: {}", entry.message);
+      let diagnostic = Diagnostic::new(entry.level, message, entry.path, span.start, span.end);
+      self.diagnostics.insert(diagnostic.with_source(DiagnosticSource::Task(name)));
+    }
+    if let (Some(text), Some(frame)) = (text, self.frames.frames.get_mut(&log)) {
+      // It's written to nowhere, there's nothing to save.
+      if frame.buffer.append(&f!("{text}\n")).is_ok() {
+        frame.buffer.mark_saved();
+      }
+    }
+  }
+
+  /// The char the line and column of `entry` point at in its file, which is empty at the end of
+  /// the file.
+  fn quickfix_span(&mut self, entry: &QuickfixEntry) -> Range<usize> {
+    let rope = self.rope_for(&entry.path).unwrap_or_default();
+    let line = entry.line.min(rope.len_lines() - 1);
+    let column = entry.column.min(rope.line(line).len_chars().saturating_sub(1));
+    let from = rope.line_to_char(line) + column;
+    from..(from + 1).min(rope.len_chars())
+  }
+
+  /// Runs `:cnext`, or `:cprev` when not `forward`.
+  pub fn goto_quickfix(&mut self, forward: bool) -> Vec<Action> {
+    let len = self.quickfix.entries.len();
+    let Some((idx, entry)) = self.quickfix.step(forward).map(|(idx, entry)| (idx, entry.clone()))
+    else {
+      let notification = Notification::info("The quickfix list is empty".to_string());
+      return Vec::from_iter([Action::AttachNotification(notification, 2)]);
+    };
+    let char_idx = self.quickfix_span(&entry).start;
+    let notification = Notification::info(f!("({}/{len}) {}", idx + 1, entry.message));
+    Vec::from_iter([
+      Action::JumpTo(entry.path, char_idx),
+      Action::AttachNotification(notification, 4),
+    ])
+  }
+
+  /// Runs `:copen`.
+  pub fn show_quickfix(&mut self) {
+    let root = std::env::current_dir().unwrap_or_default();
+    let entries: Vec<QuickfixEntry> = self.quickfix.entries.clone();
+
+    let entries = entries.into_iter().map(|entry| {
+      let char_idx = self.quickfix_span(&entry).start;
+      let file = entry.path.strip_prefix(&root).unwrap_or(&entry.path);
+      ListEntry {
+        label: entry.message.lines().next().unwrap_or_default().to_string(),
+        detail: f!("{}:{}:{}", file.display(), entry.line + 1, entry.column + 1),
+        style: Style::default().fg(level_color(&self.theme, entry.level)),
+        location: Some((entry.path.clone(), char_idx)),
+      }
+    });
+    let entries = entries.collect::<Vec<_>>();
+
+    self.popup = Some(Box::new(ListComponent::new("Quickfix", entries)));
+  }
+}
@@ -0,0 +1,93 @@
+//! Terminal frames opened with `:terminal`.
+
+use crossterm::event::KeyEvent;
+use ropey::Rope;
+use teddy_core::{
+  action::{Action, Notification},
+  input_mode::InputMode,
+};
+use teddy_events::TerminalEvent;
+
+use super::Editor;
+use crate::{
+  buffers::scrollback::ScrollbackBuffer,
+  frame::{cursors::CursorManager, Frame, FrameBuffer},
+  prelude::{f, Result},
+  terminal::Terminal,
+};
+
+impl Editor {
+  /// Runs `:terminal`, opening a frame with the user's shell in it, in terminal mode.
+  pub fn open_terminal(&mut self) -> Result<()> {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    let buffer = ScrollbackBuffer::new(Rope::new(), 0);
+    let mut frame = Frame::with_buffer(FrameBuffer::read_only("[Terminal]", Box::new(buffer)));
+    frame.options = self.options.window.clone();
+    let id = self.frames.add_frame(frame)?;
+    // The renderer sizes it to its frame.
+    let terminal = Terminal::spawn(id, &shell, 24, 80, &self.events)?;
+    self.frames.frames.get_mut(&id).ok_or("No buffer")?.terminal = Some(terminal);
+    *self.macro_key_resolver.input_manager.editor_mode_mut() = InputMode::Terminal;
+    Ok(())
+  }
+
+  pub fn terminal_event(&mut self, event: TerminalEvent) -> Option<Vec<Action>> {
+    let id = match event {
+      TerminalEvent::Output { id, .. } | TerminalEvent::Closed { id } => id,
+    };
+    let terminal = self.frames.frames.get_mut(&id)?.terminal.as_mut()?;
+    match event {
+      TerminalEvent::Output { bytes, .. } => {
+        if let Err(err) = terminal.output(&bytes) {
+          let notification = Notification::error(f!("Terminal: {err}"));
+          return Some(Vec::from_iter([Action::AttachNotification(notification, 4)]));
+        }
+        Some(Vec::from_iter([Action::Render]))
+      }
+      TerminalEvent::Closed { .. } => {
+        terminal.running = false;
+        let input_manager = &mut self.macro_key_resolver.input_manager;
+        if self.frames.active_id() == Some(id)
+          && *input_manager.editor_mode() == InputMode::Terminal
+        {
+          *input_manager.editor_mode_mut() = InputMode::Normal;
+        }
+        let active = self.frames.active_id();
+        self.frames.focus(id);
+        self.snapshot_terminal();
+        if let Some(active) = active {
+          self.frames.focus(active);
+        }
+        let notification = Notification::info("Terminal closed".to_string());
+        Some(Vec::from_iter([Action::AttachNotification(notification, 2), Action::Render]))
+      }
+    }
+  }
+
+  pub(super) fn terminal_key(&mut self, key: KeyEvent) -> Option<Action> {
+    let terminal = self.frames.active_frame_mut()?.terminal.as_mut()?;
+    let notification = match terminal.key(key) {
+      Ok(()) => return None,
+      Err(err) => Notification::error(f!("Terminal: {err}")),
+    };
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  /// Makes the active terminal's scrollback its buffer, to be read and yanked from in normal mode,
+  /// with the cursor where the terminal's is.
+  pub(super) fn snapshot_terminal(&mut self) {
+    let Some(active_frame) = self.frames.active_frame_mut() else {
+      return;
+    };
+    let Some(terminal) = active_frame.terminal.as_mut() else {
+      return;
+    };
+    let (buffer, line, column) = terminal.snapshot();
+    active_frame.buffer = FrameBuffer::read_only("[Terminal]", Box::new(buffer));
+    active_frame.cursor = CursorManager::default();
+    let rope = active_frame.rope();
+    let line = line.min(rope.len_lines().saturating_sub(1));
+    let char_idx = rope.line_to_char(line) + column.min(rope.line(line).len_chars());
+    active_frame.set_cursor_char_idx(char_idx);
+  }
+}
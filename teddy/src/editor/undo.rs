@@ -0,0 +1,46 @@
+//! Undo and redo, a change at a time.
+
+use teddy_core::action::{Action, Notification};
+
+use super::Editor;
+use crate::{frame::history::Step, prelude::f};
+
+impl Editor {
+  /// Undoes the last change of the active buffer, or redoes the last undone one when `redo`. The
+  /// cursor goes to where the change starts.
+  pub fn undo(&mut self, redo: bool) -> Option<Action> {
+    let active_frame = self.frames.active_frame_mut()?;
+    let Some(step) = active_frame.history.take(redo) else {
+      let message = if redo { "Already at newest change" } else { "Already at oldest change" };
+      return Some(Action::AttachNotification(Notification::info(message.to_string()), 2));
+    };
+
+    // Undone from the last edit of the change to the first, each as the text was after it.
+    let mut reverted = Step::default();
+    let mut result = Ok(());
+    for edit in step.edits.iter().rev() {
+      let Some(active_frame) = self.frames.active_frame_mut() else {
+        break;
+      };
+      match active_frame.revert(edit) {
+        Ok(undo) => reverted.edits.push(undo),
+        Err(err) => {
+          result = Err(err);
+          break;
+        }
+      }
+      self.buffer_changed(edit);
+    }
+
+    let active_frame = self.frames.active_frame_mut()?;
+    if let Some(start) = reverted.edits.iter().map(|edit| edit.from).min() {
+      active_frame.set_cursor_char_idx(start);
+    }
+    active_frame.history.reverted(reverted, redo);
+    active_frame.unhide_cursors();
+    match result {
+      Ok(()) => Some(Action::Render),
+      Err(err) => Some(Action::AttachNotification(Notification::error(f!("{err}")), 2)),
+    }
+  }
+}
@@ -0,0 +1,88 @@
+//! Git signs, hunk staging and inline blame.
+
+use teddy_core::{
+  action::{Action, Notification},
+  buffer::Edit,
+};
+use teddy_events::VcsEvent;
+
+use super::Editor;
+use crate::prelude::f;
+
+impl Editor {
+  /// Moves the cursor to the next (or previous) git hunk of the active buffer.
+  pub fn goto_hunk(&mut self, forward: bool) -> Option<Action> {
+    self.vcs.update();
+    let active_frame = self.frames.active_frame()?;
+    let line = active_frame.cursor.primary().get().1;
+    let hunks = self.vcs.hunks(active_frame.path()?);
+    let hunk = match forward {
+      true => hunks.iter().find(|hunk| hunk.sign_line() > line),
+      false => hunks.iter().rev().find(|hunk| hunk.sign_line() < line),
+    };
+
+    let Some(hunk) = hunk else {
+      let notification = Notification::info("No more hunks".to_string());
+      return Some(Action::AttachNotification(notification, 2));
+    };
+    let char_idx = active_frame.rope().line_to_char(hunk.sign_line());
+    self.record_jump();
+    self.frames.active_frame_mut()?.set_cursor_char_idx(char_idx);
+    None
+  }
+
+  pub fn stage_hunk(&mut self) -> Option<Action> {
+    self.vcs.update();
+    let active_frame = self.frames.active_frame()?;
+    let line = active_frame.cursor.primary().get().1;
+    let notification = match self.vcs.stage(active_frame.path()?, active_frame.rope(), line) {
+      Ok(()) => return Some(Action::Render),
+      Err(err) => Notification::error(f!("{err}")),
+    };
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  pub fn reset_hunk(&mut self) -> Option<Action> {
+    self.vcs.update();
+    let active_frame = self.frames.active_frame()?;
+    let line = active_frame.cursor.primary().get().1;
+    let result = self
+      .vcs
+      .reset(active_frame.path()?, active_frame.rope(), line)
+      .and_then(|(chars, text)| self.apply_edit(&Edit { from: chars.start, to: chars.end, text }));
+    let notification = match result {
+      Ok(()) => return Some(Action::Render),
+      Err(err) => Notification::error(f!("{err}")),
+    };
+    Some(Action::AttachNotification(notification, 4))
+  }
+
+  /// Blames the cursor line of the active buffer in the background, for the status bar.
+  pub fn request_blame(&mut self) {
+    let Some(active_frame) = self.frames.active_frame() else {
+      return;
+    };
+    let Some(path) = active_frame.path() else {
+      return;
+    };
+    let line = active_frame.cursor.primary().get().1;
+    let revision = active_frame.buffer.revision();
+    self.vcs.request_blame(path, line, active_frame.rope(), revision, &self.events);
+  }
+
+  pub fn vcs_event(&mut self, event: VcsEvent) -> Option<Vec<Action>> {
+    match event {
+      VcsEvent::Blame { path, line, revision, blame } => {
+        self.vcs.set_blame(path, line, revision, blame)
+      }
+      VcsEvent::Index { path, root, relative, branch, index } => {
+        let frame = self.frames.frames.get_mut(&self.frames.frame_id_by_path(&path)?)?;
+        let listener = self.vcs.set_index(path, root, relative, branch, index, frame.rope());
+        if let Some(listener) = listener {
+          frame.buffer.on_change(listener);
+        }
+      }
+    }
+    Some(Vec::from_iter([Action::Render]))
+  }
+}
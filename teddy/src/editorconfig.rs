@@ -0,0 +1,292 @@
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+
+use crate::options::buffer::{BufferOptions, FileEncoding, LineEnding};
+
+/// A parsed `.editorconfig` file.
+#[derive(Debug, Default, PartialEq)]
+pub struct EditorConfig {
+  pub root: bool,
+  /// Section globs with their properties, in file order. Names and values are lowercase.
+  pub sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl EditorConfig {
+  pub fn parse(text: &str) -> Self {
+    let mut config = Self::default();
+    for line in text.lines() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with(['#', ';']) {
+        continue;
+      }
+
+      if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+        config.sections.push((glob.to_string(), Vec::new()));
+        continue;
+      }
+      let Some((key, value)) = line.split_once(['=', ':']) else {
+        continue;
+      };
+      let key = key.trim().to_lowercase();
+      let value = value.trim().to_lowercase();
+      match config.sections.last_mut() {
+        Some((_, properties)) => properties.push((key, value)),
+        None if key == "root" => config.root = value == "true",
+        None => {}
+      }
+    }
+    config
+  }
+
+  /// Whether the section `glob` of a file in `dir` applies to `path`.
+  fn matches(glob: &str, dir: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(dir) else {
+      return false;
+    };
+    let relative: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+    let relative = relative.join("/");
+
+    let glob = match glob.strip_prefix('/') {
+      Some(glob) => glob.to_string(),
+      None if glob.contains('/') => glob.to_string(),
+      None => format!("**/{glob}"),
+    };
+    let text: Vec<char> = relative.chars().collect();
+    let glob: Vec<char> = glob.chars().collect();
+    // A leading `**/` also matches files in `dir` itself.
+    glob_match(&glob, &text)
+      || (glob.starts_with(&['*', '*', '/']) && glob_match(&glob[3..], &text))
+  }
+}
+
+/// The `.editorconfig` properties that apply to `path`. Files closer to it win, and the search
+/// stops at a file with `root = true`.
+pub fn properties(path: &Path) -> HashMap<String, String> {
+  let mut configs: Vec<(PathBuf, EditorConfig)> = Vec::new();
+  for dir in path.ancestors().skip(1) {
+    let Ok(text) = std::fs::read_to_string(dir.join(".editorconfig")) else {
+      continue;
+    };
+    let config = EditorConfig::parse(&text);
+    let root = config.root;
+    configs.push((dir.to_path_buf(), config));
+    if root {
+      break;
+    }
+  }
+
+  let mut properties = HashMap::new();
+  for (dir, config) in configs.iter().rev() {
+    for (glob, section) in &config.sections {
+      if EditorConfig::matches(glob, dir, path) {
+        properties.extend(section.iter().cloned());
+      }
+    }
+  }
+  properties.retain(|_, value| value != "unset");
+  properties
+}
+
+/// Overrides `options` with the `.editorconfig` properties, ignoring unknown values.
+pub fn apply(properties: &HashMap<String, String>, options: &mut BufferOptions) {
+  let get = |key: &str| properties.get(key).map(String::as_str);
+  let number =
+    |key: &str| get(key).and_then(|value| value.parse::<usize>().ok()).filter(|n| *n > 0);
+  let boolean = |key: &str| match get(key) {
+    Some("true") => Some(true),
+    Some("false") => Some(false),
+    _ => None,
+  };
+
+  match get("indent_style") {
+    Some("space") => options.indent.expand_tab = true,
+    Some("tab") => options.indent.expand_tab = false,
+    _ => {}
+  }
+  // `tab_width` defaults to `indent_size`, and `indent_size = tab` to `tab_width`.
+  let tab_width = number("tab_width").or_else(|| number("indent_size"));
+  if let Some(width) = tab_width {
+    options.indent.tab_width = width;
+  }
+  let indent_size = match get("indent_size") {
+    Some("tab") => tab_width,
+    _ => number("indent_size"),
+  };
+  if let Some(width) = indent_size {
+    options.indent.shift_width = width;
+  }
+
+  match get("end_of_line") {
+    Some("lf") => options.line_ending = Some(LineEnding::Lf),
+    Some("crlf") => options.line_ending = Some(LineEnding::CrLf),
+    Some("cr") => options.line_ending = Some(LineEnding::Cr),
+    _ => {}
+  }
+  if let Some(encoding) = get("charset").and_then(FileEncoding::from_name) {
+    options.encoding = encoding;
+  }
+  if let Some(trim) = boolean("trim_trailing_whitespace") {
+    options.trim_trailing_whitespace = trim;
+  }
+  if let Some(insert) = boolean("insert_final_newline") {
+    options.insert_final_newline = insert;
+  }
+  match get("max_line_length") {
+    Some("off") => options.max_line_length = None,
+    _ => options.max_line_length = number("max_line_length").or(options.max_line_length),
+  }
+}
+
+/// Matches `text` against an EditorConfig glob: `*` and `?` stay within a directory, `**` doesn't,
+/// and `[a-z]`, `[!abc]`, `{a,b}` and `{1..10}` work like in a shell.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+  let Some((&first, rest)) = glob.split_first() else {
+    return text.is_empty();
+  };
+
+  match first {
+    '\\' if !rest.is_empty() => {
+      text.first() == Some(&rest[0]) && glob_match(&rest[1..], &text[1..])
+    }
+    '*' if rest.first() == Some(&'*') => {
+      let rest = &rest[1..];
+      (0..=text.len()).any(|skip| glob_match(rest, &text[skip..]))
+    }
+    '*' => {
+      let dir_len = text.iter().position(|char| *char == '/').unwrap_or(text.len());
+      (0..=dir_len).any(|skip| glob_match(rest, &text[skip..]))
+    }
+    '?' => text.first().is_some_and(|char| *char != '/') && glob_match(rest, &text[1..]),
+    '[' => match (rest.iter().position(|char| *char == ']'), text.first()) {
+      (Some(end), Some(char)) if end > 0 => {
+        class_match(&rest[..end], *char) && glob_match(&rest[end + 1..], &text[1..])
+      }
+      _ => literal(first, rest, text),
+    },
+    '{' => match closing_brace(rest) {
+      Some(end) => braces_match(&rest[..end], &rest[end + 1..], text),
+      None => literal(first, rest, text),
+    },
+    _ => literal(first, rest, text),
+  }
+}
+
+fn literal(char: char, glob: &[char], text: &[char]) -> bool {
+  text.first() == Some(&char) && glob_match(glob, &text[1..])
+}
+
+fn class_match(class: &[char], char: char) -> bool {
+  let (negated, class) = match class.first() {
+    Some('!' | '^') => (true, &class[1..]),
+    _ => (false, class),
+  };
+  let mut idx = 0;
+  let mut found = false;
+  while idx < class.len() {
+    if idx + 2 < class.len() && class[idx + 1] == '-' {
+      found |= (class[idx]..=class[idx + 2]).contains(&char);
+      idx += 3;
+    } else {
+      found |= class[idx] == char;
+      idx += 1;
+    }
+  }
+  found != negated && char != '/'
+}
+
+/// The index of the `}` closing a brace that was just opened.
+fn closing_brace(glob: &[char]) -> Option<usize> {
+  let mut depth = 0;
+  for (idx, char) in glob.iter().enumerate() {
+    match char {
+      '{' => depth += 1,
+      '}' if depth == 0 => return Some(idx),
+      '}' => depth -= 1,
+      _ => {}
+    }
+  }
+  None
+}
+
+fn braces_match(inner: &[char], rest: &[char], text: &[char]) -> bool {
+  let inner_str: String = inner.iter().collect();
+  if let Some((start, end)) = inner_str.split_once("..") {
+    if let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) {
+      let digits = text.iter().take_while(|char| char.is_ascii_digit() || **char == '-').count();
+      return (1..=digits).any(|len| {
+        let number: String = text[..len].iter().collect();
+        number
+          .parse::<i64>()
+          .is_ok_and(|number| (start.min(end)..=start.max(end)).contains(&number))
+          && glob_match(rest, &text[len..])
+      });
+    }
+  }
+
+  // Split on the top level commas.
+  let mut alternatives = Vec::new();
+  let mut depth = 0;
+  let mut start = 0;
+  for (idx, char) in inner.iter().enumerate() {
+    match char {
+      '{' => depth += 1,
+      '}' => depth -= 1,
+      ',' if depth == 0 => {
+        alternatives.push(&inner[start..idx]);
+        start = idx + 1;
+      }
+      _ => {}
+    }
+  }
+  alternatives.push(&inner[start..]);
+
+  if alternatives.len() == 1 {
+    // `{single}` is taken literally.
+    let literal: Vec<char> = ['{'].iter().chain(inner).chain(['}'].iter()).copied().collect();
+    return text.starts_with(&literal) && glob_match(rest, &text[literal.len()..]);
+  }
+  alternatives.into_iter().any(|alternative| glob_match(&[alternative, rest].concat(), text))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_editorconfig() {
+    let config = EditorConfig::parse(
+      "root = true\n\n[*]\nindent_style = space\nindent_size = 4\n\n[*.{rs,toml}]\n\
+       indent_size = 2\n\n[Makefile]\nindent_style = tab\n\n[lib/**.js]\nend_of_line = CRLF\n",
+    );
+    assert!(config.root);
+    assert_eq!(config.sections.len(), 4);
+    assert_eq!(config.sections[3].1, [("end_of_line".to_string(), "crlf".to_string())]);
+
+    let dir = Path::new("/repo");
+    let matches = |glob, path: &str| EditorConfig::matches(glob, dir, Path::new(path));
+    assert!(matches("*.{rs,toml}", "/repo/src/main.rs"));
+    assert!(!matches("*.{rs,toml}", "/repo/src/main.js"));
+    assert!(matches("Makefile", "/repo/Makefile"));
+    assert!(matches("lib/**.js", "/repo/lib/a/b.js"));
+    assert!(!matches("lib/**.js", "/repo/src/lib/b.js"));
+    assert!(matches("file[0-9].{1..12}", "/repo/file3.10"));
+    assert!(!matches("file[!0-9].txt", "/repo/file3.txt"));
+
+    let mut options = BufferOptions::default();
+    let properties = HashMap::from_iter(
+      [
+        ("indent_style", "tab"),
+        ("indent_size", "tab"),
+        ("tab_width", "8"),
+        ("end_of_line", "crlf"),
+      ]
+      .map(|(key, value)| (key.to_string(), value.to_string())),
+    );
+    apply(&properties, &mut options);
+    assert!(!options.indent.expand_tab);
+    assert_eq!((options.indent.shift_width, options.indent.tab_width), (8, 8));
+    assert_eq!(options.line_ending, Some(LineEnding::CrLf));
+  }
+}
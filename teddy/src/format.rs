@@ -0,0 +1,229 @@
+//! Formatting with external programs like rustfmt, which read a file's text on stdin and write it
+//! formatted to stdout. They run in the background and their results come back as
+//! [`FormatEvent`]s, which are applied as the smallest edits that turn the buffer into them, so
+//! cursors, marks and diagnostics outside the changes stay where they were.
+
+use std::{
+  collections::BTreeMap,
+  io,
+  path::{Path, PathBuf},
+  process::Stdio,
+  time::{Duration, Instant},
+};
+
+use regex::Regex;
+use ropey::Rope;
+use similar::{DiffTag, TextDiff};
+use teddy_core::buffer::Edit;
+use teddy_events::{Event, FormatEvent};
+use tokio::{io::AsyncWriteExt, process::Command, sync::mpsc::UnboundedSender, task::AbortHandle};
+
+use crate::prelude::f;
+
+/// How long a formatter may take before it's killed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The buffer a format was started for. A `textDocument/formatting` answer doesn't say what it was
+/// for, so there's one of these for the language server's request too.
+pub struct FormatTarget {
+  pub path: PathBuf,
+  /// The buffer's revision when it was read, the result doesn't apply to later ones.
+  pub revision: u64,
+  /// Whether to write the buffer once it's formatted.
+  pub write: bool,
+}
+
+pub struct FormatJob {
+  pub command: String,
+  pub target: FormatTarget,
+  handle: AbortHandle,
+}
+
+/// The external formatters still running.
+#[derive(Default)]
+pub struct FormatJobs {
+  next: u64,
+  running: BTreeMap<u64, FormatJob>,
+}
+
+impl FormatJobs {
+  /// Runs `command` on `text`, the buffer of `target`, and returns the id its [`FormatEvent`] will
+  /// have. An earlier run for the same file is cancelled, its result would be out of date.
+  pub fn start(
+    &mut self,
+    command: &str,
+    args: &[String],
+    text: String,
+    target: FormatTarget,
+    events: &UnboundedSender<Event>,
+  ) -> u64 {
+    self.running.retain(|_, job| {
+      let earlier = job.target.path == target.path;
+      if earlier {
+        job.handle.abort();
+      }
+      !earlier
+    });
+
+    self.next += 1;
+    let id = self.next;
+    let (program, args, path) = (command.to_string(), args.to_vec(), target.path.clone());
+    let events = events.clone();
+    let handle = tokio::spawn(async move {
+      let event = match run(&program, &args, &path, text).await {
+        Ok(text) => FormatEvent::Formatted { id, text },
+        Err(FormatError::Rejected { message, position }) => {
+          FormatEvent::Rejected { id, message, position }
+        }
+        Err(FormatError::Spawn(err)) => {
+          FormatEvent::Failed { id, error: f!("couldn't run: {err}") }
+        }
+        Err(FormatError::TimedOut) => {
+          let error = f!("didn't finish within {}s", TIMEOUT.as_secs());
+          FormatEvent::Failed { id, error }
+        }
+      };
+      let _ = events.send(Event::Format(event));
+    });
+    let job = FormatJob { command: command.to_string(), target, handle: handle.abort_handle() };
+    self.running.insert(id, job);
+    id
+  }
+
+  /// Takes the job of a finished formatter, unless it was cancelled since.
+  pub fn finish(&mut self, id: u64) -> Option<FormatJob> {
+    self.running.remove(&id)
+  }
+}
+
+#[derive(Debug)]
+enum FormatError {
+  /// The formatter couldn't be started, most likely it isn't installed.
+  Spawn(io::Error),
+  /// The formatter refused the text, usually over a syntax error. `position` is the line and
+  /// column it pointed at, both from zero.
+  Rejected {
+    message: String,
+    position: Option<(usize, usize)>,
+  },
+  TimedOut,
+}
+
+/// Runs `command` on `text`, the contents of `path`, and returns the formatted text.
+async fn run(
+  command: &str,
+  args: &[String],
+  path: &Path,
+  text: String,
+) -> Result<String, FormatError> {
+  let path_arg = path.to_string_lossy();
+  let args = args.iter().map(|arg| arg.replace("{path}", &path_arg));
+  let mut command = Command::new(command);
+  command.args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+  // Dropped when it times out or the job is aborted, which kills it.
+  command.kill_on_drop(true);
+  // Run next to the file so the formatter picks up its config, like rustfmt.toml.
+  if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+    command.current_dir(dir);
+  }
+  let mut child = command.spawn().map_err(FormatError::Spawn)?;
+
+  // Written alongside reading the output, so a full pipe can't block either side.
+  let empty = text.is_empty();
+  if let Some(mut stdin) = child.stdin.take() {
+    tokio::spawn(async move { stdin.write_all(text.as_bytes()).await });
+  }
+  let output = match tokio::time::timeout(TIMEOUT, child.wait_with_output()).await {
+    Ok(output) => output.map_err(FormatError::Spawn)?,
+    Err(_) => return Err(FormatError::TimedOut),
+  };
+
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  if !output.status.success() {
+    let message = stderr.lines().map(str::trim).find(|line| !line.is_empty());
+    let message = message.unwrap_or("exited with an error");
+    let message = message.trim_start_matches("error: ").trim_start_matches("[error] ").to_string();
+    return Err(FormatError::Rejected { message, position: error_position(&stderr) });
+  }
+  // Taking no output as formatted would empty the buffer.
+  if stdout.is_empty() && !empty {
+    let message = "produced no output".to_string();
+    return Err(FormatError::Rejected { message, position: None });
+  }
+  Ok(stdout)
+}
+
+/// The first `line:column` in a formatter's error output, like rustfmt's `--> <stdin>:3:5`.
+fn error_position(stderr: &str) -> Option<(usize, usize)> {
+  let regex = Regex::new(r"(\d+):(\d+)").ok()?;
+  let captures = regex.captures(stderr)?;
+  let line = captures[1].parse::<usize>().ok()?;
+  let column = captures[2].parse::<usize>().ok()?;
+  Some((line.saturating_sub(1), column.saturating_sub(1)))
+}
+
+/// The edits turning `old` into `new`, the last one first so each can be applied without shifting
+/// the others. Changed lines are narrowed down to the chars that differ.
+pub fn edits(old: &Rope, new: &str) -> Vec<Edit> {
+  let old_text = old.to_string();
+  let diff = TextDiff::from_lines(old_text.as_str(), new);
+  let (old_lines, new_lines) = (diff.old_slices(), diff.new_slices());
+  // The char each old line starts at, and where the text ends.
+  let starts = old_lines.iter().fold(Vec::from_iter([0]), |mut starts, line| {
+    starts.push(starts[starts.len() - 1] + line.chars().count());
+    starts
+  });
+
+  let mut edits = Vec::new();
+  for op in diff.ops() {
+    let (tag, old_range, new_range) = op.as_tag_tuple();
+    if tag == DiffTag::Equal {
+      continue;
+    }
+    let from = starts[old_range.start];
+    let removed = old_lines[old_range].concat();
+    let inserted = new_lines[new_range].concat();
+    if tag != DiffTag::Replace {
+      edits.push(Edit { from, to: from + removed.chars().count(), text: inserted });
+      continue;
+    }
+
+    let chars = TextDiff::configure()
+      .deadline(Instant::now() + Duration::from_millis(50))
+      .diff_chars(removed.as_str(), inserted.as_str());
+    let inserted_chars: Vec<&str> = chars.new_slices().to_vec();
+    for op in chars.ops() {
+      let (tag, old_range, new_range) = op.as_tag_tuple();
+      if tag != DiffTag::Equal {
+        let text = inserted_chars[new_range].concat();
+        edits.push(Edit { from: from + old_range.start, to: from + old_range.end, text });
+      }
+    }
+  }
+  edits.reverse();
+  edits
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_edits() {
+    let old = Rope::from_str("fn main(){\nlet x=1;\n  x\n}\n// end\n");
+    let new = "fn main() {\n  let x = 1;\n  x\n}\n// end\n";
+    let edits = edits(&old, new);
+
+    let mut rope = old.clone();
+    for edit in &edits {
+      rope.remove(edit.from..edit.to);
+      rope.insert(edit.from, &edit.text);
+    }
+    assert_eq!(rope.to_string(), new);
+    // Only the missing spaces are inserted, the rest is left alone.
+    assert!(edits.iter().all(|edit| edit.from == edit.to && edit.text.trim().is_empty()));
+    assert!(edits.windows(2).all(|pair| pair[0].from >= pair[1].from));
+    assert_eq!(error_position("error: expected `;`\n --> <stdin>:3:5"), Some((2, 4)));
+  }
+}
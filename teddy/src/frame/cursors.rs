@@ -0,0 +1,168 @@
+use ropey::Rope;
+use teddy_core::buffer::Edit;
+use teddy_cursor::Cursor;
+
+/// A cursor, and where its selection starts in visual mode with the cursor as the other end.
+#[derive(Default, Debug)]
+pub struct CursorSelection {
+  pub cursor: Cursor,
+  pub anchor: usize,
+}
+
+/// The cursors of a frame. There's always at least one, the primary cursor, which the view follows
+/// and which completion, snippets and the like work with.
+#[derive(Debug)]
+pub struct CursorManager {
+  cursors: Vec<CursorSelection>,
+  primary: usize,
+}
+
+impl Default for CursorManager {
+  fn default() -> Self {
+    Self { cursors: Vec::from_iter([CursorSelection::default()]), primary: 0 }
+  }
+}
+
+/// The char index `cursor` is at in `rope`.
+pub fn char_idx(rope: &Rope, cursor: &Cursor) -> usize {
+  let (x, y) = cursor.get();
+  (rope.line_to_char(y.min(rope.len_lines() - 1)) + x).min(rope.len_chars())
+}
+
+/// Moves `cursor` to `char_idx` in `rope`.
+pub fn goto_char(rope: &Rope, cursor: &mut Cursor, char_idx: usize) {
+  let char_idx = char_idx.min(rope.len_chars());
+  let y = rope.char_to_line(char_idx);
+  cursor.goto(char_idx - rope.line_to_char(y), y);
+}
+
+impl CursorManager {
+  pub fn primary(&self) -> &Cursor {
+    &self.cursors[self.primary].cursor
+  }
+
+  pub fn primary_mut(&mut self) -> &mut Cursor {
+    &mut self.cursors[self.primary].cursor
+  }
+
+  /// The index of the primary cursor, for [`CursorManager::get`].
+  pub fn primary_idx(&self) -> usize {
+    self.primary
+  }
+
+  pub fn set_primary(&mut self, idx: usize) {
+    self.primary = idx.min(self.cursors.len() - 1);
+  }
+
+  pub fn count(&self) -> usize {
+    self.cursors.len()
+  }
+
+  pub fn get(&self, idx: usize) -> &CursorSelection {
+    &self.cursors[idx]
+  }
+
+  pub fn get_mut(&mut self, idx: usize) -> &mut CursorSelection {
+    &mut self.cursors[idx]
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &CursorSelection> {
+    self.cursors.iter()
+  }
+
+  pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut CursorSelection> {
+    self.cursors.iter_mut()
+  }
+
+  /// The indices of the cursors, from the first in the buffer to the last.
+  pub fn in_order(&self, rope: &Rope) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..self.cursors.len()).collect();
+    order.sort_by_key(|idx| char_idx(rope, &self.cursors[*idx].cursor));
+    order
+  }
+
+  /// Adds a cursor at `char_idx`, selecting from `anchor`, and makes it the primary one.
+  pub fn add(&mut self, rope: &Rope, anchor: usize, char_idx: usize) {
+    let mut cursor = Cursor::default();
+    goto_char(rope, &mut cursor, char_idx);
+    self.cursors.push(CursorSelection { cursor, anchor });
+    self.primary = self.cursors.len() - 1;
+  }
+
+  /// Drops every cursor but the primary one.
+  pub fn keep_primary(&mut self) {
+    let primary = self.cursors.swap_remove(self.primary);
+    self.cursors = Vec::from_iter([primary]);
+    self.primary = 0;
+  }
+
+  /// Where each cursor and anchor is, to hand to [`CursorManager::shift`] after an edit.
+  pub fn positions(&self, rope: &Rope) -> Vec<(usize, usize)> {
+    self
+      .cursors
+      .iter()
+      .map(|selection| (char_idx(rope, &selection.cursor), selection.anchor))
+      .collect()
+  }
+
+  /// Keeps the cursors on the same text after `edit`, taken from `positions` to `rope`. Cursor
+  /// `moved` made the edit and goes to the end of its text instead.
+  pub fn shift(&mut self, rope: &Rope, positions: Vec<(usize, usize)>, edit: &Edit, moved: usize) {
+    let edited = edit.from + edit.text.chars().count();
+    for (idx, (selection, (position, anchor))) in self.cursors.iter_mut().zip(positions).enumerate()
+    {
+      let position = if idx == moved { edited } else { edit.shift(position) };
+      goto_char(rope, &mut selection.cursor, position);
+      selection.anchor = edit.shift(anchor);
+    }
+  }
+
+  /// Drops cursors that ended up on the same char as another, keeping the primary one.
+  pub fn merge(&mut self, rope: &Rope) {
+    let primary = char_idx(rope, self.primary());
+    let mut seen = Vec::from_iter([primary]);
+    let mut idx = 0;
+    self.cursors.retain(|selection| {
+      let position = char_idx(rope, &selection.cursor);
+      let keep = idx == self.primary || !seen.contains(&position);
+      seen.push(position);
+      idx += 1;
+      keep
+    });
+    self.primary = self
+      .cursors
+      .iter()
+      .position(|selection| char_idx(rope, &selection.cursor) == primary)
+      .unwrap_or(0);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_cursors() {
+    let rope = Rope::from_str("one two\nthree\n");
+    let mut cursors = CursorManager::default();
+    cursors.add(&rope, 8, 8);
+    cursors.add(&rope, 4, 4);
+    assert_eq!(cursors.count(), 3);
+    assert_eq!(cursors.in_order(&rope), Vec::from_iter([0, 2, 1]));
+
+    let positions = cursors.positions(&rope);
+    let edit = Edit::insert(0, "xx");
+    let rope = Rope::from_str("xxone two\nthree\n");
+    cursors.shift(&rope, positions, &edit, 0);
+    let positions: Vec<usize> = cursors.positions(&rope).iter().map(|(idx, _)| *idx).collect();
+    assert_eq!(positions, Vec::from_iter([2, 10, 6]));
+    assert_eq!(cursors.get(2).anchor, 6);
+
+    goto_char(&rope, &mut cursors.get_mut(1).cursor, 6);
+    cursors.merge(&rope);
+    assert_eq!(cursors.count(), 2);
+    assert_eq!(char_idx(&rope, cursors.primary()), 6);
+    cursors.keep_primary();
+    assert_eq!(cursors.count(), 1);
+  }
+}
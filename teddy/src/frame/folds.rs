@@ -0,0 +1,167 @@
+use ropey::Rope;
+
+/// Lines `start` to `end`, both included, shown as one summary line while `closed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+  pub start: usize,
+  pub end: usize,
+  pub closed: bool,
+}
+
+/// The folds of a frame. They can nest, a closed fold hides the folds inside it.
+#[derive(Default, Debug)]
+pub struct Folds {
+  folds: Vec<Fold>,
+  /// The buffer revision the folds were last found at, for the automatic fold methods.
+  pub revision: Option<u64>,
+}
+
+impl Folds {
+  pub fn iter(&self) -> impl Iterator<Item = &Fold> {
+    self.folds.iter()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.folds.is_empty()
+  }
+
+  /// Adds a closed fold over lines `start` to `end`, like `zf`.
+  pub fn add(&mut self, start: usize, end: usize) {
+    let (start, end) = (start.min(end), start.max(end));
+    if start == end {
+      return;
+    }
+    self.folds.retain(|fold| (fold.start, fold.end) != (start, end));
+    self.folds.push(Fold { start, end, closed: true });
+    self.folds.sort_by_key(|fold| (fold.start, usize::MAX - fold.end));
+  }
+
+  /// Replaces the folds with `found`, keeping closed the ones starting where a closed fold did.
+  pub fn replace(&mut self, found: Vec<(usize, usize)>, revision: u64) {
+    let closed: Vec<usize> =
+      self.folds.iter().filter(|fold| fold.closed).map(|fold| fold.start).collect();
+    self.folds = found
+      .into_iter()
+      .map(|(start, end)| Fold { start, end, closed: closed.contains(&start) })
+      .collect();
+    self.folds.sort_by_key(|fold| (fold.start, usize::MAX - fold.end));
+    self.revision = Some(revision);
+  }
+
+  /// The outermost closed fold with `line` in it, the one shown in its place.
+  pub fn closed_at(&self, line: usize) -> Option<&Fold> {
+    self.folds.iter().find(|fold| fold.closed && fold.start <= line && line <= fold.end)
+  }
+
+  /// Whether `line` is inside a closed fold, other than as the line its summary is shown on.
+  pub fn is_hidden(&self, line: usize) -> bool {
+    self.closed_at(line).is_some_and(|fold| fold.start != line)
+  }
+
+  /// Opens the closed fold shown at `line`. Returns false when there's none.
+  pub fn open(&mut self, line: usize) -> bool {
+    let fold =
+      self.folds.iter_mut().find(|fold| fold.closed && fold.start <= line && line <= fold.end);
+    fold.map(|fold| fold.closed = false).is_some()
+  }
+
+  /// Closes the innermost open fold with `line` in it. Returns false when there's none.
+  pub fn close(&mut self, line: usize) -> bool {
+    let mut folds = self.folds.iter_mut().rev();
+    let fold = folds.find(|fold| !fold.closed && fold.start <= line && line <= fold.end);
+    fold.map(|fold| fold.closed = true).is_some()
+  }
+
+  /// Puts back folds saved earlier, those of the automatic fold methods are found again on the next
+  /// update and keep whether they were closed.
+  pub fn restore(&mut self, folds: impl IntoIterator<Item = Fold>) {
+    self.folds = folds.into_iter().filter(|fold| fold.start < fold.end).collect();
+    self.folds.sort_by_key(|fold| (fold.start, usize::MAX - fold.end));
+    self.revision = None;
+  }
+
+  pub fn toggle(&mut self, line: usize) -> bool {
+    self.open(line) || self.close(line)
+  }
+
+  pub fn set_all(&mut self, closed: bool) {
+    for fold in &mut self.folds {
+      fold.closed = closed;
+    }
+  }
+
+  /// Keeps the folds on the same lines after `removed` line breaks after `line` were replaced by
+  /// `inserted` ones. Folds left with a single line are dropped.
+  pub fn shift(&mut self, line: usize, removed: usize, inserted: usize) {
+    let shift = |at: usize| match at {
+      at if at <= line => at,
+      at if at <= line + removed => line + inserted,
+      at => at - removed + inserted,
+    };
+    for fold in &mut self.folds {
+      fold.start = shift(fold.start);
+      fold.end = shift(fold.end);
+    }
+    self.folds.retain(|fold| fold.start < fold.end);
+  }
+}
+
+/// Folds over the lines indented more than the line before them, starting at that line. Blank
+/// lines go with the lines around them.
+pub fn indent_folds(rope: &Rope, tab_width: usize) -> Vec<(usize, usize)> {
+  let indent = |line: usize| {
+    let text = rope.line(line);
+    let blank = text.chars().all(char::is_whitespace);
+    let width = text
+      .chars()
+      .take_while(|char| *char == ' ' || *char == '\t')
+      .fold(0, |width, char| width + if char == '\t' { tab_width } else { 1 });
+    (!blank).then_some(width)
+  };
+
+  let mut folds = Vec::new();
+  let mut open: Vec<(usize, usize)> = Vec::new();
+  let mut last = 0;
+  for (line, width) in (0..rope.len_lines()).filter_map(|line| Some((line, indent(line)?))) {
+    while let Some(&(start, start_width)) = open.last() {
+      if width > start_width {
+        break;
+      }
+      open.pop();
+      if last > start {
+        folds.push((start, last));
+      }
+    }
+    open.push((line, width));
+    last = line;
+  }
+  folds.extend(open.into_iter().filter(|(start, _)| last > *start).map(|(start, _)| (start, last)));
+  folds
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_folds() {
+    let rope = Rope::from_str("fn a() {\n  if x {\n    y\n  }\n\n  z\n}\nfn b() {}\n");
+    let found = indent_folds(&rope, 4);
+    assert_eq!(found, Vec::from_iter([(1, 2), (0, 5)]));
+
+    let mut folds = Folds::default();
+    folds.replace(found, 1);
+    assert!(folds.close(2));
+    assert!(folds.close(2));
+    assert!(!folds.close(2));
+    assert!(folds.is_hidden(4) && folds.is_hidden(1) && !folds.is_hidden(0));
+    assert!(folds.open(3));
+    assert_eq!(folds.closed_at(2), Some(&Fold { start: 1, end: 2, closed: true }));
+
+    folds.shift(0, 0, 2);
+    assert_eq!(folds.closed_at(3).map(|fold| (fold.start, fold.end)), Some((3, 4)));
+    folds.add(9, 8);
+    folds.shift(7, 2, 0);
+    assert_eq!(folds.iter().count(), 2);
+  }
+}
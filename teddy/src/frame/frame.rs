@@ -1,16 +1,32 @@
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::KeyEvent;
 use ropey::Rope;
 use teddy_core::action::Action;
-use teddy_core::buffer::{Buffer, WritableBuffer};
-use teddy_cursor::Cursor;
+use teddy_core::buffer::{Buffer, ChangeListener, Edit, WritableBuffer};
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::buffers::placeholder::PlaceholderBuffer;
+use crate::buffers::{file::FileBuffer, placeholder::PlaceholderBuffer};
 use crate::prelude::*;
+use crate::swap;
 
-use super::keybinding::Selection;
+use super::{
+  cursors::{self, CursorManager},
+  folds::{self, Folds},
+  history::{self, History},
+  indent,
+  line_cache::LineCache,
+};
+use crate::options::{
+  buffer::{BufferOptions, LineEnding},
+  window::{FoldMethod, WindowOptions},
+};
+use crate::syntax::{self, Language};
+use crate::terminal::Terminal;
 
 impl Debug for Frame {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -22,18 +38,6 @@ impl Debug for Frame {
   }
 }
 
-#[derive(Default, Debug)]
-pub struct CursorManager {
-  pub cursor: Cursor,
-  select: Option<Selection>,
-}
-
-impl CursorManager {
-  pub fn with_buffer_len(_len: usize) -> Self {
-    Self { cursor: Cursor::default(), select: None }
-  }
-}
-
 pub struct FrameBuffer {
   component: Component,
   pub context: BufferContext,
@@ -45,23 +49,154 @@ pub enum Component {
 }
 
 impl Component {
-  pub fn buffer_len(&mut self) -> usize {
+  pub fn buffer_len(&self) -> usize {
+    self.rope().len_chars()
+  }
+
+  pub fn rope(&self) -> &Rope {
+    match self {
+      Component::Read(buf) => buf.rope(),
+      Component::Write(buf) => buf.rope(),
+    }
+  }
+
+  pub fn revision(&self) -> u64 {
     match self {
-      Component::Read(buf) => buf.buff().len_chars(),
-      Component::Write(buf) => buf.buff_mut().len_chars(),
+      Component::Read(buf) => buf.revision(),
+      Component::Write(buf) => buf.revision(),
     }
   }
 }
 
 pub struct BufferContext {
   pub name: String,
+  /// The file backing the buffer, if any.
+  pub path: Option<PathBuf>,
+  pub options: BufferOptions,
+  /// Opened in large-file mode, without language servers, completion or save-time edits.
+  pub large: bool,
+  /// Still being read in the background, edits and writes are refused until it's done.
+  pub loading: bool,
+  /// The revision last written to or read from the file.
+  pub saved_revision: u64,
+  /// When the file was last modified, as of the last read or write.
+  pub mtime: Option<SystemTime>,
+  /// The modification time of an outside change that was already reported.
+  pub changed_on_disk: Option<SystemTime>,
 }
 
 impl Default for FrameBuffer {
   fn default() -> Self {
     Self {
       component: Component::Write(Box::new(PlaceholderBuffer::default())),
-      context: BufferContext { name: "[No Name]".to_string() },
+      context: BufferContext {
+        name: "[No Name]".to_string(),
+        path: None,
+        options: BufferOptions::default(),
+        large: false,
+        loading: false,
+        saved_revision: 0,
+        mtime: None,
+        changed_on_disk: None,
+      },
+    }
+  }
+}
+
+impl FrameBuffer {
+  /// Opens `path` with `options`, keeping the line endings and encoding the file already uses.
+  pub fn file(path: &Path, mut options: BufferOptions) -> Result<Self> {
+    let (buffer, encoding) = FileBuffer::open(path)?;
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string());
+    options.encoding = encoding;
+    options.line_ending = LineEnding::detect(buffer.rope().chars());
+
+    Ok(Self {
+      component: Component::Write(Box::new(buffer)),
+      context: BufferContext {
+        name: name.unwrap_or_else(|| path.display().to_string()),
+        path: Some(path.to_path_buf()),
+        options,
+        large: false,
+        loading: false,
+        saved_revision: 0,
+        mtime: modified_time(path),
+        changed_on_disk: None,
+      },
+    })
+  }
+
+  /// An empty buffer named `name`, without a file.
+  pub fn scratch(name: &str) -> Self {
+    let mut frame_buffer =
+      Self { component: Component::Write(Box::new(FileBuffer::default())), ..Self::default() };
+    frame_buffer.context.name = name.to_string();
+    frame_buffer
+  }
+
+  /// A buffer named `name` that can't be edited or written.
+  pub fn read_only(name: &str, buffer: Box<dyn Buffer>) -> Self {
+    let mut frame_buffer = Self { component: Component::Read(buffer), ..Self::default() };
+    frame_buffer.context.name = name.to_string();
+    frame_buffer
+  }
+
+  /// An empty buffer for `path` in large-file mode, filled by [`FrameBuffer::append`] as
+  /// [`load_in_background`](crate::buffers::loader::load_in_background) reads it.
+  pub fn loading(path: &Path, options: BufferOptions) -> Self {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string());
+    Self {
+      component: Component::Write(Box::new(FileBuffer::default())),
+      context: BufferContext {
+        name: name.unwrap_or_else(|| path.display().to_string()),
+        path: Some(path.to_path_buf()),
+        options,
+        large: true,
+        loading: true,
+        saved_revision: 0,
+        mtime: modified_time(path),
+        changed_on_disk: None,
+      },
+    }
+  }
+
+  /// Adds a chunk read by the background loader to the end of the buffer.
+  pub fn append(&mut self, text: &str) -> Result<()> {
+    let Component::Write(ref mut buf) = self.component else {
+      return Err("Buffer is read only".into());
+    };
+    buf.apply(&Edit::insert(buf.rope().len_chars(), text))?;
+    Ok(())
+  }
+
+  pub fn rope(&self) -> &Rope {
+    self.component.rope()
+  }
+
+  /// Goes up with every change, see [`Buffer::revision`].
+  pub fn revision(&self) -> u64 {
+    self.component.revision()
+  }
+
+  /// Whether there are changes that weren't written to the file.
+  pub fn is_modified(&self) -> bool {
+    self.revision() != self.context.saved_revision
+  }
+
+  /// Records that the buffer matches its file as it is on disk now.
+  pub fn mark_saved(&mut self) {
+    self.context.saved_revision = self.revision();
+    self.context.mtime = self.context.path.as_deref().and_then(modified_time);
+    self.context.changed_on_disk = None;
+    if let Some(path) = &self.context.path {
+      swap::forget(path);
+    }
+  }
+
+  /// Calls `listener` after every change, see [`WritableBuffer::on_change`].
+  pub fn on_change(&mut self, listener: ChangeListener) {
+    if let Component::Write(ref mut buf) = self.component {
+      buf.on_change(listener);
     }
   }
 }
@@ -69,19 +204,21 @@ impl Default for FrameBuffer {
 pub struct Frame {
   pub cursor: CursorManager,
   pub buffer: FrameBuffer,
+  pub options: WindowOptions,
+  /// The first line on screen.
+  pub scroll: usize,
+  /// Lines of the buffer as last rendered.
+  pub lines: LineCache,
+  pub folds: Folds,
+  pub history: History,
+  /// The program of a terminal frame, whose scrollback is the buffer.
+  pub terminal: Option<Terminal>,
   action_sender: Option<UnboundedSender<Action>>,
 }
 
 impl Default for Frame {
   fn default() -> Self {
-    let mut frame_buffer = FrameBuffer::default();
-    let buffer_len = frame_buffer.component.buffer_len();
-
-    Frame {
-      action_sender: None,
-      cursor: CursorManager::with_buffer_len(buffer_len),
-      buffer: frame_buffer,
-    }
+    Self::with_buffer(FrameBuffer::default())
   }
 }
 
@@ -92,39 +229,196 @@ impl Default for Frame {
 //}
 
 impl Frame {
-  pub fn buff(&mut self) -> Rope {
-    match &mut self.buffer.component {
-      Component::Read(k) => k.buff(),
-      Component::Write(ref mut k) => k.buff_mut().clone(),
+  pub fn with_buffer(frame_buffer: FrameBuffer) -> Self {
+    Frame {
+      action_sender: None,
+      cursor: CursorManager::default(),
+      buffer: frame_buffer,
+      options: WindowOptions::default(),
+      scroll: 0,
+      lines: LineCache::default(),
+      folds: Folds::default(),
+      history: History::default(),
+      terminal: None,
     }
   }
 
+  pub fn rope(&self) -> &Rope {
+    self.buffer.rope()
+  }
+
   pub fn get_context(&self) -> &BufferContext {
     &self.buffer.context
   }
-  pub fn insert(&mut self, _keyevent: KeyEvent) -> Result<()> {
-    match self.buffer.component {
-      Component::Write(ref mut buf) => {
-        let buff = buf.buff_mut();
 
-        let (x, y) = self.cursor.cursor.get();
+  pub fn path(&self) -> Option<&Path> {
+    self.buffer.context.path.as_deref()
+  }
 
-        let idx = buff.line_to_char(y) + x;
+  /// The primary cursor's position as a char index into the buffer.
+  pub fn cursor_char_idx(&self) -> usize {
+    cursors::char_idx(self.rope(), self.cursor.primary())
+  }
 
-        if let KeyCode::Char(char) = _keyevent.code {
-          buff.insert_char(idx, char);
-        } else if let KeyCode::Backspace = _keyevent.code {
-          if x != 0 {
-            buff.remove(idx - 1..idx);
-          }
-        };
-      }
-      Component::Read(_) => panic!("The fuck"),
+  pub fn set_cursor_char_idx(&mut self, char_idx: usize) {
+    cursors::goto_char(self.buffer.rope(), self.cursor.primary_mut(), char_idx);
+  }
+
+  /// The position of cursor `cursor` as a char index, see [`CursorManager::get`].
+  pub fn char_idx_of(&self, cursor: usize) -> usize {
+    cursors::char_idx(self.rope(), &self.cursor.get(cursor).cursor)
+  }
+
+  pub fn set_char_idx_of(&mut self, cursor: usize, char_idx: usize) {
+    cursors::goto_char(self.buffer.rope(), &mut self.cursor.get_mut(cursor).cursor, char_idx);
+  }
+
+  /// Streams the buffer to its file, with the line endings and encoding of its options.
+  pub fn write(&mut self) -> Result<()> {
+    let context = &self.buffer.context;
+    let Some(path) = context.path.as_deref() else {
+      return Err("Buffer has no file name".into());
+    };
+    if context.loading {
+      return Err("File is still loading".into());
+    }
+    let rope = self.rope();
+    replace_file(path, |writer| context.options.write(rope, writer))?;
+    self.buffer.mark_saved();
+    Ok(())
+  }
+
+  /// Replaces `edit.from..edit.to` with `edit.text` and puts the primary cursor after the new
+  /// text.
+  pub fn apply_edit(&mut self, edit: &Edit) -> Result<()> {
+    self.apply_edit_at(edit, self.cursor.primary_idx())?;
+    self.cursor.merge(self.buffer.rope());
+    Ok(())
+  }
+
+  /// Applies `edit` made by cursor `cursor`, which goes after the new text. The other cursors
+  /// shift to stay on the same text.
+  pub fn apply_edit_at(&mut self, edit: &Edit, cursor: usize) -> Result<()> {
+    let undo = self.edit_at(edit, cursor)?;
+    self.history.record(undo);
+    Ok(())
+  }
+
+  /// Applies `edit` of an undo or redo, which isn't recorded, and returns the edit reverting it.
+  pub fn revert(&mut self, edit: &Edit) -> Result<Edit> {
+    let undo = self.edit_at(edit, self.cursor.primary_idx())?;
+    self.cursor.merge(self.buffer.rope());
+    Ok(undo)
+  }
+
+  /// Applies `edit` for [`Frame::apply_edit_at`] and returns the edit undoing it.
+  fn edit_at(&mut self, edit: &Edit, cursor: usize) -> Result<Edit> {
+    let Component::Write(ref mut buf) = self.buffer.component else {
+      return Err("Buffer is read only".into());
+    };
+    if self.buffer.context.loading {
+      return Err("File is still loading".into());
     }
+    let positions = self.cursor.positions(buf.rope());
+    let rope = buf.rope();
+    let line = rope.char_to_line(edit.from.min(rope.len_chars()));
+    let removed = rope.get_slice(edit.from..edit.to).map(String::from).unwrap_or_default();
+    let removed_lines = removed.matches('\n').count();
+    buf.apply(edit)?;
+    self.folds.shift(line, removed_lines, edit.text.matches('\n').count());
+
+    self.cursor.shift(buf.rope(), positions, edit, cursor);
+    Ok(history::inverse(edit, removed))
+  }
+
+  /// Makes a closed fold over lines `start` to `end`, which only stays with the manual fold method.
+  pub fn fold(&mut self, start: usize, end: usize) -> Result<()> {
+    if self.options.foldmethod != FoldMethod::Manual {
+      return Err("Folds can only be made with foldmethod=manual".into());
+    }
+    if start == end {
+      return Err("A fold needs more than one line".into());
+    }
+    self.folds.add(start, end);
+    self.unhide_cursors();
     Ok(())
   }
 
+  /// Moves the cursors hidden by closed folds to the line shown in their place.
+  pub fn unhide_cursors(&mut self) {
+    for selection in self.cursor.iter_mut() {
+      let line = selection.cursor.get().1;
+      if let Some(fold) = self.folds.closed_at(line).filter(|fold| fold.start != line) {
+        selection.cursor.goto(0, fold.start);
+      }
+    }
+  }
+
+  /// Finds the folds again when the buffer changed since, unless they're made by hand.
+  pub fn update_folds(&mut self) {
+    let revision = self.buffer.revision();
+    if self.options.foldmethod == FoldMethod::Manual || self.folds.revision == Some(revision) {
+      return;
+    }
+    let rope = self.buffer.rope();
+    let found = match self.options.foldmethod {
+      FoldMethod::Indent => folds::indent_folds(rope, self.buffer.context.options.indent.tab_width),
+      _ => match self.path().and_then(Language::from_path) {
+        Some(language) => syntax::folds(rope, language),
+        None => Vec::new(),
+      },
+    };
+    self.folds.replace(found, revision);
+  }
+
+  /// Applies an insert mode key at cursor `cursor` and returns the resulting edit, if any.
+  pub fn insert_at(&mut self, key_event: KeyEvent, cursor: usize) -> Result<Option<Edit>> {
+    let rope = self.rope().clone();
+    let char_idx = self.char_idx_of(cursor);
+    let language = self.path().and_then(Language::from_path);
+    let Some((edit, char_idx)) =
+      indent::key_edit(&rope, char_idx, key_event, &self.buffer.context.options.indent, language)
+    else {
+      return Ok(None);
+    };
+
+    self.apply_edit_at(&edit, cursor)?;
+    self.set_char_idx_of(cursor, char_idx);
+    Ok(Some(edit))
+  }
+
   //pub fn render(&self, f: &mut ratatui::buffer::Buffer, area: ratatui::prelude::Rect) {
   //  self.buffer.draw(f, area).expect("Didn't work :(")
   //}
 }
+
+/// When the file at `path` was last modified, if it exists.
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+  std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Replaces the file at `path` with what `write` writes. It's written next to it and renamed, so a
+/// failed or interrupted write, like one hitting a char the encoding can't hold, keeps what was
+/// there. The file keeps its permissions, and through a symlink the file it points at is replaced.
+fn replace_file(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> Result<()>) -> Result<()> {
+  let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+  let name = path.file_name().ok_or("Not a file name")?.to_string_lossy();
+  let partial = path.with_file_name(f!(".{name}.teddy-tmp"));
+  let permissions = std::fs::metadata(&path).ok().map(|metadata| metadata.permissions());
+
+  let result = (|| -> Result<()> {
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()?;
+    if let Some(permissions) = permissions {
+      file.set_permissions(permissions)?;
+    }
+    std::fs::rename(&partial, &path)?;
+    Ok(())
+  })();
+  if result.is_err() {
+    let _ = std::fs::remove_file(&partial);
+  }
+  result
+}
@@ -0,0 +1,105 @@
+use teddy_core::buffer::Edit;
+
+/// How many changes `u` can go back.
+const MAX_STEPS: usize = 1000;
+
+/// The edits of one change, each as the edit undoing it, in the order they were made.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Step {
+  pub edits: Vec<Edit>,
+}
+
+/// The undo and redo history of a frame's buffer. Edits go into an open step until it's closed,
+/// which the editor does whenever it's idle in normal mode, so a change like `ciwfoo<Esc>` or a
+/// repeat with `.` is undone as a whole.
+#[derive(Debug, Default)]
+pub struct History {
+  undo: Vec<Step>,
+  redo: Vec<Step>,
+  open: Step,
+}
+
+impl History {
+  /// Records `undo`, the edit undoing one just made. Anything undone before can't be redone anymore.
+  pub fn record(&mut self, undo: Edit) {
+    self.open.edits.push(undo);
+    self.redo.clear();
+  }
+
+  /// Ends the step the edits since the last close go into.
+  pub fn close(&mut self) {
+    if self.open.edits.is_empty() {
+      return;
+    }
+    self.undo.push(std::mem::take(&mut self.open));
+    if self.undo.len() > MAX_STEPS {
+      self.undo.remove(0);
+    }
+  }
+
+  /// Takes the step to undo, or to redo when `redo`.
+  pub fn take(&mut self, redo: bool) -> Option<Step> {
+    self.close();
+    match redo {
+      true => self.redo.pop(),
+      false => self.undo.pop(),
+    }
+  }
+
+  /// Keeps `step`, which reverts the one just undone, or redone when `redo`, to go back again.
+  pub fn reverted(&mut self, step: Step, redo: bool) {
+    match redo {
+      true => self.undo.push(step),
+      false => self.redo.push(step),
+    }
+  }
+}
+
+/// The edit undoing `edit` once it's made, `removed` being the text it replaces.
+pub fn inverse(edit: &Edit, removed: String) -> Edit {
+  let to = edit.from + edit.text.chars().count();
+  Edit { from: edit.from, to, text: removed }
+}
+
+#[cfg(test)]
+mod tests {
+  use ropey::Rope;
+  use teddy_core::buffer::{Buffer, TextBuffer, WritableBuffer};
+
+  use super::*;
+
+  fn apply(buffer: &mut TextBuffer, edit: &Edit) -> Edit {
+    let removed = buffer.slice(edit.from..edit.to).to_string();
+    buffer.apply(edit).unwrap();
+    inverse(edit, removed)
+  }
+
+  fn revert(buffer: &mut TextBuffer, history: &mut History, redo: bool) -> String {
+    let step = history.take(redo).unwrap();
+    let edits = step.edits.iter().rev().map(|edit| apply(buffer, edit)).collect();
+    history.reverted(Step { edits }, redo);
+    buffer.rope().to_string()
+  }
+
+  #[test]
+  fn test_history() {
+    let mut buffer = TextBuffer::new(Rope::from_str("hello"));
+    let mut history = History::default();
+    history.record(apply(&mut buffer, &Edit::insert(5, " world")));
+    history.record(apply(&mut buffer, &Edit::remove(0, 1)));
+    history.close();
+    history.record(apply(&mut buffer, &Edit { from: 0, to: 1, text: "J".to_string() }));
+
+    assert_eq!(revert(&mut buffer, &mut history, false), "ello world");
+    assert_eq!(revert(&mut buffer, &mut history, false), "hello");
+    assert_eq!(history.take(false), None);
+    assert_eq!(revert(&mut buffer, &mut history, true), "ello world");
+    assert_eq!(revert(&mut buffer, &mut history, true), "Jllo world");
+    assert_eq!(history.take(true), None);
+
+    // A new change drops what was undone.
+    revert(&mut buffer, &mut history, false);
+    history.record(apply(&mut buffer, &Edit::insert(0, "y")));
+    assert_eq!(history.take(true), None);
+  }
+}
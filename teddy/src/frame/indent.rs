@@ -0,0 +1,212 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ropey::Rope;
+use teddy_config::IndentConfig;
+use teddy_core::buffer::Edit;
+
+use crate::{
+  prelude::f,
+  syntax::{self, Indent, Language},
+};
+
+const OPENERS: [char; 3] = ['{', '[', '('];
+const CLOSERS: [char; 3] = ['}', ']', ')'];
+
+/// The edit an Insert mode key makes at `cursor`, and where the cursor ends up after it. `language`
+/// is the one of the file, whose parse tree decides the indentation of new lines when it has one.
+pub fn key_edit(
+  rope: &Rope,
+  cursor: usize,
+  key: KeyEvent,
+  config: &IndentConfig,
+  language: Option<Language>,
+) -> Option<(Edit, usize)> {
+  let line_start = rope.line_to_char(rope.char_to_line(cursor));
+  let before = rope.slice(line_start..cursor).to_string();
+  let only_whitespace = before.chars().all(char::is_whitespace);
+
+  match key.code {
+    KeyCode::Char(char) if CLOSERS.contains(&char) && !before.is_empty() && only_whitespace => {
+      // Closing a scope on its own line dedents it.
+      let width = indent_width(&before, config).saturating_sub(config.shift_width);
+      let text = f!("{}{char}", make_indent(width, config));
+      let to = line_start + text.chars().count();
+      Some((Edit { from: line_start, to: cursor, text }, to))
+    }
+    KeyCode::Char(char) => Some((Edit::insert(cursor, char), cursor + 1)),
+    KeyCode::Enter => Some(newline(rope, cursor, &before, config, language)),
+    KeyCode::Tab => {
+      let text = match config.expand_tab {
+        true => " ".repeat(config.shift_width - indent_width(&before, config) % config.shift_width),
+        false => "\t".to_string(),
+      };
+      let to = cursor + text.chars().count();
+      Some((Edit::insert(cursor, text), to))
+    }
+    KeyCode::BackTab => {
+      let line = rope.char_to_line(cursor);
+      let edit = shift_line(rope, line, false, config)?;
+      let cursor = edit.shift(cursor);
+      Some((edit, cursor))
+    }
+    KeyCode::Backspace if cursor == 0 => None,
+    KeyCode::Backspace if !before.is_empty() && only_whitespace && config.expand_tab => {
+      // Remove back to the previous indent level.
+      let width = indent_width(&before, config);
+      let target = (width - 1) / config.shift_width * config.shift_width;
+      let spaces = before.chars().rev().take_while(|char| *char == ' ').count();
+      let from = cursor - spaces.min(width - target).max(1);
+      Some((Edit::remove(from, cursor), from))
+    }
+    KeyCode::Backspace => {
+      // Removes "\r\n" as a whole.
+      let from = match cursor >= 2 && rope.slice(cursor - 2..cursor) == "\r\n" {
+        true => cursor - 2,
+        false => cursor - 1,
+      };
+      Some((Edit::remove(from, cursor), from))
+    }
+    KeyCode::Delete if cursor < rope.len_chars() => {
+      let to = match rope.get_slice(cursor..cursor + 2).is_some_and(|slice| slice == "\r\n") {
+        true => cursor + 2,
+        false => cursor + 1,
+      };
+      Some((Edit::remove(cursor, to), cursor))
+    }
+    _ => None,
+  }
+}
+
+/// Breaks the line at `cursor`, keeping its indentation. The parse tree of `language` can indent
+/// the new line once more, like after a Python `if x:`, or once less, like after a `return`.
+/// Otherwise it's indented once more after an opening bracket. A closing bracket right after the
+/// cursor goes on a line of its own.
+fn newline(
+  rope: &Rope,
+  cursor: usize,
+  before: &str,
+  config: &IndentConfig,
+  language: Option<Language>,
+) -> (Edit, usize) {
+  let indent: String = before.chars().take_while(|char| *char == ' ' || *char == '\t').collect();
+  let code = before.trim_end();
+  let opener = code.chars().last().and_then(|char| OPENERS.iter().position(|o| *o == char));
+
+  let code_end = cursor - (before.chars().count() - code.chars().count());
+  let syntax_indent = language
+    .filter(|_| !code.trim_start().is_empty())
+    .and_then(|language| syntax::indent_after(rope, code_end, language));
+  let mut text = match syntax_indent {
+    Some(Indent::Less) => {
+      let width = indent_width(&indent, config).saturating_sub(config.shift_width);
+      f!("\n{}", make_indent(width, config))
+    }
+    _ => f!("\n{indent}"),
+  };
+  if syntax_indent == Some(Indent::More) || (syntax_indent.is_none() && opener.is_some()) {
+    text.push_str(&make_indent(config.shift_width, config));
+  }
+  let new_cursor = cursor + text.chars().count();
+
+  let closer = rope.get_char(cursor).and_then(|char| CLOSERS.iter().position(|c| *c == char));
+  if opener.is_some() && opener == closer {
+    text.push('\n');
+    text.push_str(&indent);
+  }
+  (Edit::insert(cursor, text), new_cursor)
+}
+
+/// Indents (or dedents) `line` by one level. Returns `None` for blank lines and lines that can't
+/// be dedented any further.
+pub fn shift_line(rope: &Rope, line: usize, indent: bool, config: &IndentConfig) -> Option<Edit> {
+  let line_start = rope.line_to_char(line);
+  let text = rope.line(line).to_string();
+  if text.trim().is_empty() {
+    return None;
+  }
+
+  let leading: String = text.chars().take_while(|char| *char == ' ' || *char == '\t').collect();
+  let width = indent_width(&leading, config);
+  let new_width = match indent {
+    true => width + config.shift_width,
+    false if width == 0 => return None,
+    false => width.saturating_sub(config.shift_width),
+  };
+
+  let to = line_start + leading.chars().count();
+  Some(Edit { from: line_start, to, text: make_indent(new_width, config) })
+}
+
+/// How many columns `whitespace` takes up.
+fn indent_width(whitespace: &str, config: &IndentConfig) -> usize {
+  whitespace.chars().fold(0, |width, char| match char {
+    '\t' => (width / config.tab_width + 1) * config.tab_width,
+    _ => width + 1,
+  })
+}
+
+fn make_indent(width: usize, config: &IndentConfig) -> String {
+  match config.expand_tab {
+    true => " ".repeat(width),
+    false => "\t".repeat(width / config.tab_width) + &" ".repeat(width % config.tab_width),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crossterm::event::KeyModifiers;
+
+  use super::*;
+
+  fn apply_in(
+    language: Option<Language>,
+    text: &str,
+    cursor: usize,
+    code: KeyCode,
+  ) -> (String, usize) {
+    let mut rope = Rope::from_str(text);
+    let config = IndentConfig { expand_tab: true, shift_width: 2, tab_width: 2 };
+    let key = KeyEvent::new(code, KeyModifiers::NONE);
+    let (edit, cursor) = key_edit(&rope, cursor, key, &config, language).unwrap();
+    rope.remove(edit.from..edit.to);
+    rope.insert(edit.from, &edit.text);
+    (rope.to_string(), cursor)
+  }
+
+  fn apply(text: &str, cursor: usize, code: KeyCode) -> (String, usize) {
+    apply_in(None, text, cursor, code)
+  }
+
+  #[test]
+  fn test_indentation() {
+    assert_eq!(apply("  if x {}", 8, KeyCode::Enter), ("  if x {\n    \n  }".to_string(), 13));
+    assert_eq!(apply("  foo", 5, KeyCode::Enter), ("  foo\n  ".to_string(), 8));
+    assert_eq!(apply("    ", 4, KeyCode::Char('}')), ("  }".to_string(), 3));
+    assert_eq!(apply("     ", 5, KeyCode::Backspace), ("    ".to_string(), 4));
+    assert_eq!(apply("    x", 4, KeyCode::Backspace), ("  x".to_string(), 2));
+    assert_eq!(apply("a\nb", 2, KeyCode::Backspace), ("ab".to_string(), 1));
+    assert_eq!(apply(" x", 1, KeyCode::Tab), ("  x".to_string(), 2));
+    assert_eq!(apply("    x", 5, KeyCode::BackTab), ("  x".to_string(), 3));
+  }
+
+  #[test]
+  fn test_python_indentation() {
+    let enter = |text: &str| apply_in(Some(Language::Python), text, text.len(), KeyCode::Enter);
+    assert_eq!(enter("def f(x):"), ("def f(x):\n  ".to_string(), 12));
+    assert_eq!(enter("def f(x):\n  if x:  "), ("def f(x):\n  if x:  \n    ".to_string(), 24));
+    assert_eq!(enter("def f(x):\n  return x"), ("def f(x):\n  return x\n".to_string(), 21));
+    // A dict's `:` opens no block, its `{` still indents.
+    assert_eq!(enter("y = {x:"), ("y = {x:\n".to_string(), 8));
+    assert_eq!(enter("y = {"), ("y = {\n  ".to_string(), 8));
+  }
+
+  #[test]
+  fn test_match_arm_indentation() {
+    let text = "fn f() {\n  match x {\n    Some(y) =>\n  }\n}\n";
+    let cursor = text.find("=>").unwrap() + 2;
+    let (text, cursor) = apply_in(Some(Language::Rust), text, cursor, KeyCode::Enter);
+    assert_eq!(&text[..cursor], "fn f() {\n  match x {\n    Some(y) =>\n      ");
+    // Without a parse tree only brackets indent.
+    let (text, _) = apply("    Some(y) =>", 14, KeyCode::Enter);
+    assert_eq!(text, "    Some(y) =>\n    ");
+  }
+}
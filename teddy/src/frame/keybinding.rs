@@ -8,19 +8,6 @@
 //
 //use super::Frame;
 //
-#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
-pub struct Selection {
-  from: usize,
-  to: usize,
-
-  buffer_size: usize,
-}
-
-impl Selection {
-  pub fn with_buffer_len(buffer_size: usize) -> Self {
-    Self { from: 0, to: 0, buffer_size }
-  }
-}
 //
 //  pub fn select(&mut self, from: usize, to: usize) {
 //    if from > self.file_size {
@@ -40,16 +27,6 @@ impl Selection {
 //  }
 //}
 
-//#[derive(PartialEq, Eq, Hash, Clone)]
-//enum BetweenChars {
-//  Parantheses,
-//
-//  /// '[' and ']'
-//  Brackets,
-//  /// '{' and '}'
-//  Brackets2,
-//}
-
 //pub trait BindAction: Debug {
 //  fn act(&self, frame: &mut Frame) -> Result<Option<Action>>;
 //}
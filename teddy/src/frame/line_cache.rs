@@ -0,0 +1,50 @@
+use std::{
+  cell::{Cell, RefCell},
+  collections::HashMap,
+  rc::Rc,
+};
+
+use ropey::Rope;
+
+/// How many lines are kept before starting over, so scrolling through a large file doesn't keep
+/// all of it twice.
+const MAX_LINES: usize = 4096;
+
+/// The lines of a buffer without their line breaks, built the first time the renderer asks for
+/// them. Everything is dropped once the buffer's revision changes.
+#[derive(Debug, Default)]
+pub struct LineCache {
+  revision: Cell<Option<u64>>,
+  lines: RefCell<HashMap<usize, Rc<str>>>,
+}
+
+impl LineCache {
+  /// Line `idx` of `rope`, which is at `revision`.
+  pub fn line(&self, rope: &Rope, revision: u64, idx: usize) -> Rc<str> {
+    let mut lines = self.lines.borrow_mut();
+    if self.revision.replace(Some(revision)) != Some(revision) || lines.len() >= MAX_LINES {
+      lines.clear();
+    }
+
+    let line = lines.entry(idx).or_insert_with(|| {
+      let line = rope.line(idx).to_string();
+      Rc::from(line.trim_end_matches(['\n', '\r']))
+    });
+    line.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_line_cache() {
+    let cache = LineCache::default();
+    let rope = Rope::from_str("one\r\ntwo\n");
+    assert_eq!(&*cache.line(&rope, 0, 0), "one");
+    assert_eq!(&*cache.line(&Rope::from_str("changed\n"), 0, 0), "one");
+    assert_eq!(&*cache.line(&Rope::from_str("changed\n"), 1, 0), "changed");
+    assert_eq!(&*cache.line(&rope, 1, 2), "");
+  }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 use super::{notification_manager::NotificationManager, Frame};
 
@@ -25,14 +25,43 @@ impl FrameManager {
   pub fn active_frame_mut(&mut self) -> Option<&mut Frame> {
     self.frames.get_mut(&self.active_frame_id?)
   }
+
+  pub fn active_id(&self) -> Option<u16> {
+    self.active_frame_id
+  }
   pub fn add_window(&mut self) -> crate::prelude::Result<u16> {
+    self.add_frame(Frame::default())
+  }
+
+  /// Adds `frame` and focuses it.
+  pub fn add_frame(&mut self, frame: Frame) -> crate::prelude::Result<u16> {
     let id = rand::random();
 
-    self.frames.insert(id, Frame::default());
+    self.frames.insert(id, frame);
     self.active_frame_id = Some(id);
 
     Ok(id)
   }
+
+  pub fn focus(&mut self, id: u16) {
+    if self.frames.contains_key(&id) {
+      self.active_frame_id = Some(id);
+    }
+  }
+
+  /// Removes the frame `id`, focusing another one when it was the active frame.
+  pub fn remove_frame(&mut self, id: u16) -> Option<Frame> {
+    let frame = self.frames.remove(&id)?;
+    if self.active_frame_id == Some(id) {
+      self.active_frame_id = self.frames.keys().next().copied();
+    }
+    Some(frame)
+  }
+
+  /// Finds the frame showing the file at `path`.
+  pub fn frame_id_by_path(&self, path: &Path) -> Option<u16> {
+    self.frames.iter().find(|(_, frame)| frame.path() == Some(path)).map(|(id, _)| *id)
+  }
   //
   //pub fn window(&self, index: u16) -> Option<&Frame> {
   //  self.frames.get(&index)
@@ -1,4 +1,9 @@
+pub mod cursors;
+pub mod folds;
 mod frame;
+pub mod history;
+pub mod indent;
+pub mod line_cache;
 pub mod notification_manager;
 pub use frame::*;
 
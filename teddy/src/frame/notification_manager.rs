@@ -1,5 +1,5 @@
 use chrono::Utc;
-use teddy_core::action::Notification;
+use teddy_core::action::{Notification, Spinner};
 
 #[derive(Default, Debug, Clone)]
 pub struct NotificationManager {
@@ -10,6 +10,22 @@ impl NotificationManager {
   pub fn append(&mut self, notification: NotificationMessage) {
     self.vec.push(notification)
   }
+
+  /// Replaces the notification with the same id as `notification`, or appends it. Used for
+  /// progress that updates in place.
+  pub fn replace(&mut self, notification: NotificationMessage) {
+    let existing =
+      self.vec.iter_mut().find(|message| message.id.is_some() && message.id == notification.id);
+    match existing {
+      Some(existing) => *existing = notification,
+      None => self.append(notification),
+    }
+  }
+
+  /// Moves the spinners of notifications for work still going on to their next frame.
+  pub fn tick(&mut self) {
+    self.vec.iter_mut().filter_map(|message| message.spinner.as_mut()).for_each(Spinner::next);
+  }
   //pub fn testing<F, Fut>(&'static mut self, initial_not: Notification, fun: F)
   //where
   //  F: Fn(Arc<Mutex<NotificationMessage>>) -> Fut + Send + 'static,
@@ -33,13 +49,25 @@ pub struct NotificationMessage {
   pub payload: Notification,
   pub created_at: i64,
   pub lasts_to: i64,
+  /// Identifies a notification for [`NotificationManager::replace`].
+  pub id: Option<String>,
+  /// Shown before the message while the work it's about goes on.
+  pub spinner: Option<Spinner>,
 }
 
 impl NotificationMessage {
   pub fn new(payload: Notification, lasting: i64) -> Self {
-    Self { payload, lasts_to: lasting, created_at: Utc::now().timestamp() }
+    Self { payload, lasts_to: lasting, created_at: Utc::now().timestamp(), id: None, spinner: None }
   }
   pub fn as_is(payload: Notification, lasting: i64) -> Self {
-    Self { payload, lasts_to: lasting, created_at: Utc::now().timestamp() }
+    Self { payload, lasts_to: lasting, created_at: Utc::now().timestamp(), id: None, spinner: None }
+  }
+  pub fn with_id(mut self, id: String) -> Self {
+    self.id = Some(id);
+    self
+  }
+  pub fn with_spinner(mut self) -> Self {
+    self.spinner = Some(Spinner::default());
+    self
   }
 }
@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:diffsplit {file}`, diffing the active buffer against `file`.
+pub struct DiffSplitCommand;
+
+impl Command for DiffSplitCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    let path = query.split_whitespace().nth(1).ok_or("Usage: :diffsplit {file}")?;
+    Ok(Some(Vec::from_iter([Action::DiffSplit(PathBuf::from(path))])))
+  }
+}
+
+pub struct DiffOffCommand;
+
+impl Command for DiffOffCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::DiffOff])))
+  }
+}
@@ -0,0 +1,12 @@
+use teddy_core::action::{Action, DiskChoice};
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:reload`, `:keep` and `:diffdisk`, the answers to a file changing on disk.
+pub struct DiskCommand(pub DiskChoice);
+
+impl Command for DiskCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::ResolveDiskChange(self.0)])))
+  }
+}
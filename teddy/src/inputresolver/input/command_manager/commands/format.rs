@@ -0,0 +1,12 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:format`, formatting the buffer without writing it.
+pub struct FormatCommand;
+
+impl Command for FormatCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::Format])))
+  }
+}
@@ -0,0 +1,19 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+pub struct StageHunkCommand;
+
+impl Command for StageHunkCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::StageHunk])))
+  }
+}
+
+pub struct ResetHunkCommand;
+
+impl Command for ResetHunkCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::ResetHunk])))
+  }
+}
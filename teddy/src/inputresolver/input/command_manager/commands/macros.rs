@@ -0,0 +1,48 @@
+use std::error::Error;
+
+use teddy_core::action::Action;
+
+use crate::{inputresolver::input::command_manager::Command, prelude::f};
+
+/// `:macros` lists the recorded macros.
+pub struct MacrosCommand;
+
+impl Command for MacrosCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn Error>> {
+    Ok(Some(Vec::from_iter([Action::ShowMacros])))
+  }
+}
+
+/// `:editmacro {label}` puts a macro in the register as text.
+pub struct EditMacroCommand;
+
+impl Command for EditMacroCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn Error>> {
+    let (label, _) = arguments(query)?;
+    Ok(Some(Vec::from_iter([Action::EditMacro(label)])))
+  }
+}
+
+/// `:setmacro {label} [keys]` sets a macro from keys in vim key notation, or from the register.
+pub struct SetMacroCommand;
+
+impl Command for SetMacroCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn Error>> {
+    let (label, keys) = arguments(query)?;
+    let keys = Some(keys.to_string()).filter(|keys| !keys.is_empty());
+    Ok(Some(Vec::from_iter([Action::SetMacro { label, keys }])))
+  }
+}
+
+/// The label after the command name, and whatever follows it.
+fn arguments(query: &str) -> Result<(char, &str), Box<dyn Error>> {
+  let rest = query.trim_start().split_once(' ').map(|(_, rest)| rest.trim_start());
+  let (label, keys) =
+    rest.map(|rest| rest.split_once(' ').unwrap_or((rest, ""))).unwrap_or_default();
+  let mut chars = label.chars();
+  match (chars.next(), chars.next()) {
+    (Some(label), None) => Ok((label, keys)),
+    (None, _) => Err("Expected a macro label".into()),
+    _ => Err(f!("Invalid macro label '{label}'").into()),
+  }
+}
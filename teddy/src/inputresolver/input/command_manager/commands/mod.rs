@@ -1,4 +1,19 @@
+pub mod diagnostics;
+pub mod diff;
+pub mod disk;
 pub mod echo;
+pub mod format;
+pub mod hunks;
+pub mod jumps;
+pub mod macros;
+pub mod marks;
+pub mod quickfix;
 pub mod quit;
+pub mod recover;
+pub mod session;
+pub mod set;
+pub mod shell;
+pub mod task;
+pub mod terminal;
 pub mod write;
 pub mod write_and_quit;
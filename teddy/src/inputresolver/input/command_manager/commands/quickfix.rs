@@ -0,0 +1,28 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:copen`, listing the errors of the last task.
+pub struct CopenCommand;
+
+impl Command for CopenCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::ShowQuickfix])))
+  }
+}
+
+pub struct CnextCommand;
+
+impl Command for CnextCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::NextQuickfix])))
+  }
+}
+
+pub struct CprevCommand;
+
+impl Command for CprevCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::PreviousQuickfix])))
+  }
+}
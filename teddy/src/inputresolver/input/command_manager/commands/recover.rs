@@ -0,0 +1,14 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:recover` restores the active buffer from its swap file, `:recover!` deletes the swap file.
+pub struct RecoverCommand {
+  pub discard: bool,
+}
+
+impl Command for RecoverCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::Recover { discard: self.discard }])))
+  }
+}
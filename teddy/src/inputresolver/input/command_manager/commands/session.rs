@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:mksession [file]` when `save`, `:source [file]` otherwise. Without a file, the session of the
+/// working directory is used.
+pub struct SessionCommand {
+  pub save: bool,
+}
+
+impl Command for SessionCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    let path = query.split_whitespace().nth(1).map(PathBuf::from);
+    let action = match self.save {
+      true => Action::MakeSession(path),
+      false => Action::SourceSession(path),
+    };
+    Ok(Some(Vec::from_iter([action])))
+  }
+}
@@ -0,0 +1,25 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:set` and `:setlocal`, taking arguments like `number`, `nowrap`, `tabstop=2` or `list?`.
+pub struct SetCommand {
+  /// Leaves the global values alone.
+  pub local: bool,
+}
+
+impl Command for SetCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    let arguments: Vec<&str> = query.split_whitespace().skip(1).collect();
+    if arguments.is_empty() {
+      return Err("Expected an option".into());
+    }
+
+    let local = self.local;
+    let actions = arguments
+      .into_iter()
+      .map(|argument| Action::SetOption { argument: argument.to_string(), local })
+      .collect();
+    Ok(Some(actions))
+  }
+}
@@ -0,0 +1,37 @@
+use teddy_core::action::{Action, ShellOutput};
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:!{command}` runs a command and shows its output, `:{range}!{command}` filters the lines of
+/// the range through it.
+pub struct ShellCommand;
+
+impl Command for ShellCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    let (range, command) = query.trim().split_once('!').ok_or("Usage: :[range]!{command}")?;
+    let command = command.trim();
+    if command.is_empty() {
+      return Err("Usage: :[range]!{command}".into());
+    }
+    let output = match range.trim() {
+      "" => ShellOutput::Show,
+      range => ShellOutput::Filter(range.to_string()),
+    };
+    Ok(Some(Vec::from_iter([Action::Shell { command: command.to_string(), output }])))
+  }
+}
+
+/// `:r !{command}`, inserting the output of a command below the cursor.
+pub struct ReadCommand;
+
+impl Command for ReadCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    let argument = query.trim().strip_prefix('r').unwrap_or_default().trim_start();
+    let command = argument.strip_prefix('!').map(str::trim).unwrap_or_default();
+    if command.is_empty() {
+      return Err("Usage: :r !{command}".into());
+    }
+    let output = ShellOutput::Read;
+    Ok(Some(Vec::from_iter([Action::Shell { command: command.to_string(), output }])))
+  }
+}
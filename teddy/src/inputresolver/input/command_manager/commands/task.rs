@@ -0,0 +1,22 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:make`, running the first configured task.
+pub struct MakeCommand;
+
+impl Command for MakeCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::RunTask(None)])))
+  }
+}
+
+/// `:task {name}`, running a configured task by name.
+pub struct TaskCommand;
+
+impl Command for TaskCommand {
+  fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    let name = query.split_whitespace().nth(1).ok_or("Usage: :task {name}")?;
+    Ok(Some(Vec::from_iter([Action::RunTask(Some(name.to_string()))])))
+  }
+}
@@ -0,0 +1,12 @@
+use teddy_core::action::Action;
+
+use crate::inputresolver::input::command_manager::Command;
+
+/// `:terminal`, opening a shell in a new frame.
+pub struct TerminalCommand;
+
+impl Command for TerminalCommand {
+  fn act(&mut self, _query: &str) -> Result<Option<Vec<Action>>, Box<dyn std::error::Error>> {
+    Ok(Some(Vec::from_iter([Action::OpenTerminal])))
+  }
+}
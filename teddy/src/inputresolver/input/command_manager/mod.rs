@@ -2,17 +2,37 @@ use std::{collections::HashMap, error::Error};
 
 mod commands;
 
+use commands::diagnostics::DiagnosticsCommand;
+use commands::diff::{DiffOffCommand, DiffSplitCommand};
+use commands::disk::DiskCommand;
 use commands::echo::EchoCommand;
+use commands::format::FormatCommand;
+use commands::hunks::{ResetHunkCommand, StageHunkCommand};
+use commands::jumps::JumpsCommand;
+use commands::macros::{EditMacroCommand, MacrosCommand, SetMacroCommand};
+use commands::marks::MarksCommand;
+use commands::quickfix::{CnextCommand, CopenCommand, CprevCommand};
 use commands::quit::QuitCommand;
+use commands::recover::RecoverCommand;
+use commands::session::SessionCommand;
+use commands::set::SetCommand;
+use commands::shell::{ReadCommand, ShellCommand};
+use commands::task::{MakeCommand, TaskCommand};
+use commands::terminal::TerminalCommand;
 use commands::write::WriteCommand;
 use commands::write_and_quit::WriteAndQuitCommand;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use teddy_core::action::{Action, Notification};
+use teddy_core::action::{Action, DiskChoice, Notification};
+
+use crate::prelude::f;
 use teddy_core::input_mode::{CommandModeData, InputMode};
 
 use super::input_manager::InputResult;
 
+/// How many command lines are remembered.
+const MAX_HISTORY: usize = 100;
+
 pub trait Command {
   fn act(&mut self, query: &str) -> Result<Option<Vec<Action>>, Box<dyn Error>>;
 }
@@ -28,6 +48,10 @@ struct CommandEntry {
 pub struct CommandManager {
   // The cmd query (:[query]) isn't stored here because it's closely coupled with the enum EditorMode.
   registry: HashMap<String, CommandEntry>,
+  /// The command lines run, the oldest first, walked with Up and Down.
+  pub history: Vec<String>,
+  /// The entry of `history` on the command line while walking it.
+  browsing: Option<usize>,
 }
 
 impl CommandManager {
@@ -55,17 +79,240 @@ impl CommandManager {
         cmd: Box::new(WriteAndQuitCommand),
       },
     );
+
+    self.registry.insert(
+      "diagnostics".to_string(),
+      CommandEntry {
+        description: Some("Lists the diagnostics of every open file".to_string()),
+        cmd: Box::new(DiagnosticsCommand),
+      },
+    );
+    self.registry.insert(
+      "stagehunk".to_string(),
+      CommandEntry {
+        description: Some("Stages the git hunk at the cursor".to_string()),
+        cmd: Box::new(StageHunkCommand),
+      },
+    );
+    self.registry.insert(
+      "resethunk".to_string(),
+      CommandEntry {
+        description: Some("Puts the git hunk at the cursor back the way it's staged".to_string()),
+        cmd: Box::new(ResetHunkCommand),
+      },
+    );
+    self.registry.insert(
+      "diffsplit".to_string(),
+      CommandEntry {
+        description: Some("Diffs the current buffer against a file, side by side".to_string()),
+        cmd: Box::new(DiffSplitCommand),
+      },
+    );
+    self.registry.insert(
+      "diffoff".to_string(),
+      CommandEntry {
+        description: Some("Leaves diff mode".to_string()),
+        cmd: Box::new(DiffOffCommand),
+      },
+    );
+    self.registry.insert(
+      "!".to_string(),
+      CommandEntry {
+        description: Some(
+          "Runs a shell command, or filters the lines of a range through it".to_string(),
+        ),
+        cmd: Box::new(ShellCommand),
+      },
+    );
+    self.registry.insert(
+      "r".to_string(),
+      CommandEntry {
+        description: Some("Inserts the output of a shell command below the cursor".to_string()),
+        cmd: Box::new(ReadCommand),
+      },
+    );
+    self.registry.insert(
+      "format".to_string(),
+      CommandEntry {
+        description: Some("Formats the buffer with its language's formatter".to_string()),
+        cmd: Box::new(FormatCommand),
+      },
+    );
+    self.registry.insert(
+      "make".to_string(),
+      CommandEntry {
+        description: Some("Runs the first task, filling the quickfix list".to_string()),
+        cmd: Box::new(MakeCommand),
+      },
+    );
+    self.registry.insert(
+      "task".to_string(),
+      CommandEntry {
+        description: Some("Runs a task by name, filling the quickfix list".to_string()),
+        cmd: Box::new(TaskCommand),
+      },
+    );
+    self.registry.insert(
+      "copen".to_string(),
+      CommandEntry {
+        description: Some("Lists the errors of the last task".to_string()),
+        cmd: Box::new(CopenCommand),
+      },
+    );
+    self.registry.insert(
+      "cnext".to_string(),
+      CommandEntry {
+        description: Some("Opens the next error of the last task".to_string()),
+        cmd: Box::new(CnextCommand),
+      },
+    );
+    self.registry.insert(
+      "cprev".to_string(),
+      CommandEntry {
+        description: Some("Opens the previous error of the last task".to_string()),
+        cmd: Box::new(CprevCommand),
+      },
+    );
+    self.registry.insert(
+      "terminal".to_string(),
+      CommandEntry {
+        description: Some("Opens a shell in a new frame".to_string()),
+        cmd: Box::new(TerminalCommand),
+      },
+    );
+    self.registry.insert(
+      "marks".to_string(),
+      CommandEntry {
+        description: Some("Lists the marks of the buffer and the global ones".to_string()),
+        cmd: Box::new(MarksCommand),
+      },
+    );
+    self.registry.insert(
+      "jumps".to_string(),
+      CommandEntry {
+        description: Some("Lists the jumps Ctrl-o and Ctrl-i go through".to_string()),
+        cmd: Box::new(JumpsCommand),
+      },
+    );
+    self.registry.insert(
+      "macros".to_string(),
+      CommandEntry {
+        description: Some("Lists the recorded macros".to_string()),
+        cmd: Box::new(MacrosCommand),
+      },
+    );
+    self.registry.insert(
+      "editmacro".to_string(),
+      CommandEntry {
+        description: Some("Puts a macro in the register as text, to put and edit it".to_string()),
+        cmd: Box::new(EditMacroCommand),
+      },
+    );
+    self.registry.insert(
+      "setmacro".to_string(),
+      CommandEntry {
+        description: Some("Sets a macro from keys like ihi<Esc>, or from the register".to_string()),
+        cmd: Box::new(SetMacroCommand),
+      },
+    );
+
+    self.registry.insert(
+      "set".to_string(),
+      CommandEntry {
+        description: Some("Sets an option, like number, nowrap or tabstop=2".to_string()),
+        cmd: Box::new(SetCommand { local: false }),
+      },
+    );
+    self.registry.insert(
+      "setlocal".to_string(),
+      CommandEntry {
+        description: Some("Sets an option of the current window or buffer only".to_string()),
+        cmd: Box::new(SetCommand { local: true }),
+      },
+    );
+    self.registry.insert(
+      "reload".to_string(),
+      CommandEntry {
+        description: Some("Reloads the file from disk, dropping unsaved changes".to_string()),
+        cmd: Box::new(DiskCommand(DiskChoice::Reload)),
+      },
+    );
+    self.registry.insert(
+      "keep".to_string(),
+      CommandEntry {
+        description: Some("Keeps the buffer after its file changed on disk".to_string()),
+        cmd: Box::new(DiskCommand(DiskChoice::Keep)),
+      },
+    );
+    self.registry.insert(
+      "diffdisk".to_string(),
+      CommandEntry {
+        description: Some("Lists how the buffer differs from its file on disk".to_string()),
+        cmd: Box::new(DiskCommand(DiskChoice::Diff)),
+      },
+    );
+    self.registry.insert(
+      "recover".to_string(),
+      CommandEntry {
+        description: Some("Restores unsaved changes from the swap file".to_string()),
+        cmd: Box::new(RecoverCommand { discard: false }),
+      },
+    );
+    self.registry.insert(
+      "recover!".to_string(),
+      CommandEntry {
+        description: Some("Deletes the swap file of the buffer".to_string()),
+        cmd: Box::new(RecoverCommand { discard: true }),
+      },
+    );
+    self.registry.insert(
+      "mksession".to_string(),
+      CommandEntry {
+        description: Some("Saves the open files to a session".to_string()),
+        cmd: Box::new(SessionCommand { save: true }),
+      },
+    );
+    self.registry.insert(
+      "source".to_string(),
+      CommandEntry {
+        description: Some("Opens the files of a session".to_string()),
+        cmd: Box::new(SessionCommand { save: false }),
+      },
+    );
   }
 
   pub fn input(&mut self, cmd_data: &mut CommandModeData, keycode: KeyEvent) -> Vec<InputResult> {
     match (keycode.modifiers, keycode.code) {
       (KeyModifiers::CONTROL, KeyCode::Char('c')) | (KeyModifiers::NONE, KeyCode::Esc) => {
+        self.browsing = None;
         Vec::from_iter([InputResult::ChangeInputMode(InputMode::Normal)])
       }
-      (KeyModifiers::NONE, KeyCode::Char(char)) => {
+      (_, KeyCode::Up) => {
+        self.browse(cmd_data, false);
+        vec![]
+      }
+      (_, KeyCode::Down) => {
+        self.browse(cmd_data, true);
+        vec![]
+      }
+      (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(char)) => {
         cmd_data.insert(char);
         vec![]
       }
+      (_, KeyCode::Backspace) => {
+        if cmd_data.backspace() {
+          vec![]
+        } else {
+          Vec::from_iter([InputResult::ChangeInputMode(InputMode::Normal)])
+        }
+      }
+      (_, KeyCode::Enter) => {
+        let query = cmd_data.value().to_string();
+        self.remember(&query);
+        let mut results = Vec::from_iter([InputResult::ChangeInputMode(InputMode::Normal)]);
+        results.extend(self.execute(&query).into_iter().map(InputResult::CausedAction));
+        results
+      }
       (_, _) => {
         let notification = Notification::error("Invalid input".into());
         let action = Action::AttachNotification(notification, 10);
@@ -74,14 +321,62 @@ impl CommandManager {
     }
   }
 
+  /// Puts the previous command line of the history on the command line, or the next one when
+  /// `newer`. Past the newest one the command line is empty again.
+  fn browse(&mut self, cmd_data: &mut CommandModeData, newer: bool) {
+    let idx = match (self.browsing, newer) {
+      (None, false) => self.history.len().checked_sub(1),
+      (None, true) => return,
+      (Some(idx), false) => Some(idx.saturating_sub(1)),
+      (Some(idx), true) => Some(idx + 1).filter(|idx| *idx < self.history.len()),
+    };
+    self.browsing = idx;
+    let text = idx.map_or("", |idx| self.history[idx].as_str());
+    *cmd_data = CommandModeData::with_text(text);
+  }
+
+  /// Adds `query` to the history, moving it to the end if it's there already.
+  fn remember(&mut self, query: &str) {
+    self.browsing = None;
+    if query.trim().is_empty() {
+      return;
+    }
+    self.history.retain(|entry| entry != query);
+    self.history.push(query.to_string());
+    if self.history.len() > MAX_HISTORY {
+      self.history.remove(0);
+    }
+  }
+
   pub fn query(&mut self, query: String) -> Option<&mut Box<dyn Command>> {
-    let entries: Vec<(&String, &CommandEntry)> =
-      self.registry.iter().filter(|v| v.0.starts_with(&query)).collect();
-    let first = query.split("\n").next().expect("Is empty");
+    let first = query.split_whitespace().next()?;
+    // `:!cmd`, `:{range}!cmd` and `:r!cmd` don't need a space after the command.
+    let range = first.trim_start_matches(|char: char| "0123456789.$%,+-".contains(char));
+    let first = match first {
+      _ if range.starts_with('!') => "!",
+      _ if first.starts_with("r!") => "r",
+      first => first,
+    };
 
     self.registry.get_mut(first).map(|v| &mut v.cmd)
   }
 
+  /// Runs the command line `query`, errors are turned into notifications.
+  pub fn execute(&mut self, query: &str) -> Vec<Action> {
+    let Some(cmd) = self.query(query.to_string()) else {
+      let notification = Notification::fail(f!("Command '{}' doesn't exist", query.trim()));
+      return Vec::from_iter([Action::AttachNotification(notification, 2)]);
+    };
+
+    match cmd.act(query) {
+      Ok(actions) => actions.unwrap_or_default(),
+      Err(err) => {
+        let notification = Notification::error(f!("Error: {err}"));
+        Vec::from_iter([Action::AttachNotification(notification, 8)])
+      }
+    }
+  }
+
   pub fn search(&self, query: String) -> Vec<(String, Option<String>)> {
     let entries = self
       .registry
@@ -1,15 +1,48 @@
-use std::ops::Range;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crossterm::event::KeyEvent;
-
-use teddy_core::{action::Action, input_mode::InputMode};
+use teddy_core::{action::Action, input_mode::InputMode, text_object::Operator};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum InputResult {
   Insert(KeyEvent),
   CausedAction(Action),
   CursorIntent(CursorMovement),
+  /// An operator typed before a motion, like `d3w`, or doubled for lines, like `dd`.
+  Operate {
+    operator: Operator,
+    movement: CursorMovement,
+    count: usize,
+  },
   ChangeInputMode(InputMode),
+  Completion(CompletionIntent),
+  NextTabstop,
+  PreviousTabstop,
+  /// A key for the program in the terminal frame.
+  TerminalKey(KeyEvent),
+}
+
+impl InputResult {
+  /// Whether the result changes the buffer, see [`Action::is_change`].
+  pub fn is_change(&self) -> bool {
+    match self {
+      InputResult::Insert(_) => true,
+      InputResult::CausedAction(action) => action.is_change(),
+      InputResult::Operate { operator, .. } => {
+        matches!(operator, Operator::Delete | Operator::Change)
+      }
+      _ => false,
+    }
+  }
+}
+
+/// What to do with the insert mode completion menu.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompletionIntent {
+  Trigger,
+  Next,
+  Previous,
+  Accept,
+  Cancel,
 }
 use crate::inputresolver::CursorMovement;
 
@@ -17,11 +50,34 @@ use super::command_manager::CommandManager;
 
 use super::keybind_manager::KeybindManager;
 
-#[derive(Default)]
 pub struct InnerInputManager {
   input_mode: InputMode,
   pub command_manager: CommandManager,
   pub keybind_manager: KeybindManager,
+  /// Set by the editor while the completion menu is showing, so it gets keys first.
+  completion_open: bool,
+  /// Set by the editor while a snippet has tabstops left to jump to.
+  snippet_active: bool,
+  /// Ctrl-\ was pressed in terminal mode, Ctrl-n next goes back to normal mode.
+  terminal_escape: bool,
+}
+
+impl Default for InnerInputManager {
+  fn default() -> Self {
+    let mut command_manager = CommandManager::default();
+    command_manager.setup();
+    let mut keybind_manager = KeybindManager::default();
+    keybind_manager.setup();
+
+    Self {
+      input_mode: InputMode::default(),
+      command_manager,
+      keybind_manager,
+      completion_open: false,
+      snippet_active: false,
+      terminal_escape: false,
+    }
+  }
 }
 
 impl InnerInputManager {
@@ -31,21 +87,116 @@ impl InnerInputManager {
   pub fn editor_mode_mut(&mut self) -> &mut InputMode {
     &mut self.input_mode
   }
+  pub fn set_completion_open(&mut self, open: bool) {
+    self.completion_open = open;
+  }
+  pub fn set_snippet_active(&mut self, active: bool) {
+    self.snippet_active = active;
+  }
 
-  pub fn input(&mut self, key_event: KeyEvent) -> Option<Vec<InputResult>> {
-    match &mut self.input_mode {
+  /// Resolves a key into what should happen, `count` being the count typed before it in normal
+  /// mode. Mode changes are applied here and are not part of the returned results.
+  pub fn input(&mut self, key_event: KeyEvent, count: Option<usize>) -> Option<Vec<InputResult>> {
+    let results = match &mut self.input_mode {
       InputMode::Command(cmd_data) => Some(self.command_manager.input(cmd_data, key_event)),
 
-      InputMode::Normal => self.keybind_manager.on_keyinput(key_event, None),
-      InputMode::Visual(selection) => self.keybind_manager.on_keyinput(key_event, Some(*selection)),
+      InputMode::Normal => self.keybind_manager.on_keyinput(key_event, None, count),
+      InputMode::Visual(selection) => {
+        self.keybind_manager.on_keyinput(key_event, Some(*selection), count)
+      }
+
+      InputMode::Insert { left_insert: _ } if self.completion_open => {
+        Some(completion_menu_input(key_event))
+      }
+      InputMode::Insert { left_insert: _ } if self.snippet_active => Some(snippet_input(key_event)),
+      InputMode::Insert { left_insert: _ } => Some(insert_mode_input(key_event)),
+      InputMode::Terminal => Some(self.terminal_input(key_event)),
+    }?;
+
+    let results: Vec<InputResult> = results
+      .into_iter()
+      .filter_map(|result| match result {
+        InputResult::ChangeInputMode(mode) => {
+          self.input_mode = mode;
+          None
+        }
+        result => Some(result),
+      })
+      .collect();
 
-      InputMode::Insert { left_insert: _ } => {
-        Some(Vec::from_iter([InputResult::Insert(key_event)]))
+    (!results.is_empty()).then_some(results)
+  }
+
+  /// Everything goes to the program but Ctrl-\ Ctrl-n, which leaves terminal mode.
+  fn terminal_input(&mut self, key_event: KeyEvent) -> Vec<InputResult> {
+    let escape = std::mem::take(&mut self.terminal_escape);
+    match (key_event.modifiers, key_event.code) {
+      (KeyModifiers::CONTROL, KeyCode::Char('\\')) if !escape => {
+        self.terminal_escape = true;
+        Vec::new()
+      }
+      (KeyModifiers::CONTROL, KeyCode::Char('n')) if escape => {
+        Vec::from_iter([InputResult::ChangeInputMode(InputMode::Normal)])
       }
+      // The held back Ctrl-\ wasn't the start of Ctrl-\ Ctrl-n, the program gets both keys.
+      _ if escape => {
+        let ctrl_backslash = KeyEvent::new(KeyCode::Char('\\'), KeyModifiers::CONTROL);
+        Vec::from_iter([
+          InputResult::TerminalKey(ctrl_backslash),
+          InputResult::TerminalKey(key_event),
+        ])
+      }
+      _ => Vec::from_iter([InputResult::TerminalKey(key_event)]),
     }
   }
 }
 
+/// Keys the completion menu takes while open, anything else is typed as usual.
+fn completion_menu_input(key_event: KeyEvent) -> Vec<InputResult> {
+  use CompletionIntent::*;
+
+  let intent = match (key_event.modifiers, key_event.code) {
+    (KeyModifiers::CONTROL, KeyCode::Char('n')) | (_, KeyCode::Down) | (_, KeyCode::Tab) => Next,
+    (KeyModifiers::CONTROL, KeyCode::Char('p')) | (_, KeyCode::Up) | (_, KeyCode::BackTab) => {
+      Previous
+    }
+    (KeyModifiers::CONTROL, KeyCode::Char('y')) | (_, KeyCode::Enter) => Accept,
+    (KeyModifiers::CONTROL, KeyCode::Char('e')) => Cancel,
+    (_, KeyCode::Esc) => {
+      return Vec::from_iter([
+        InputResult::Completion(Cancel),
+        InputResult::ChangeInputMode(InputMode::Normal),
+      ])
+    }
+    _ => return insert_mode_input(key_event),
+  };
+  Vec::from_iter([InputResult::Completion(intent)])
+}
+
+/// Tab and Shift-Tab jump between the tabstops of an expanded snippet.
+fn snippet_input(key_event: KeyEvent) -> Vec<InputResult> {
+  match key_event.code {
+    KeyCode::Tab => Vec::from_iter([InputResult::NextTabstop]),
+    KeyCode::BackTab => Vec::from_iter([InputResult::PreviousTabstop]),
+    _ => insert_mode_input(key_event),
+  }
+}
+
+fn insert_mode_input(key_event: KeyEvent) -> Vec<InputResult> {
+  let result = match (key_event.modifiers, key_event.code) {
+    (_, KeyCode::Esc) => InputResult::ChangeInputMode(InputMode::Normal),
+    (KeyModifiers::CONTROL, KeyCode::Char(' ')) | (KeyModifiers::CONTROL, KeyCode::Char('n')) => {
+      InputResult::Completion(CompletionIntent::Trigger)
+    }
+    (_, KeyCode::Left) => InputResult::CursorIntent(CursorMovement::Left),
+    (_, KeyCode::Right) => InputResult::CursorIntent(CursorMovement::Right),
+    (_, KeyCode::Up) => InputResult::CursorIntent(CursorMovement::Up),
+    (_, KeyCode::Down) => InputResult::CursorIntent(CursorMovement::Down),
+    _ => InputResult::Insert(key_event),
+  };
+  Vec::from_iter([result])
+}
+
 //pub struct InputManager {
 //  pub input_mode: InputMode,
 //  pub keybind_manager: KeybindManager,
@@ -1,14 +1,346 @@
-use crossterm::event::KeyEvent;
-use teddy_core::input_mode::VisualSelection;
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use teddy_core::{
+  action::{Action, FoldCommand},
+  input_mode::{CommandModeData, InputMode, VisualSelection},
+  text_object::{BetweenChars, Operator, TextObject},
+};
+
+use super::input_manager::InputResult;
+use crate::{
+  inputresolver::{utils::parse_keys, CursorMovement},
+  prelude::f,
+};
+
+/// Cursor moves repeated for a count are capped, a count typed by accident shouldn't hang.
+const MAX_REPEAT: usize = 10_000;
+
+/// Keybindings for normal and visual mode.
+///
+/// Keys are buffered until they either match a binding or can't be the start of one, that's how
+/// multi key bindings like `gd` work. An operator like `d` then waits for a motion, a text object
+/// or its own keys again for whole lines, with a count before or after it.
 #[derive(Default)]
-pub struct KeybindManager;
+pub struct KeybindManager {
+  normal: HashMap<Vec<KeyEvent>, Vec<InputResult>>,
+  visual: HashMap<Vec<KeyEvent>, Vec<InputResult>>,
+  /// Motions, in both modes and after an operator.
+  motions: HashMap<Vec<KeyEvent>, CursorMovement>,
+  /// The operators of normal mode that wait for a motion.
+  operators: HashMap<Vec<KeyEvent>, Operator>,
+  pending: Vec<KeyEvent>,
+  /// The count typed before the pending keys.
+  count: Option<usize>,
+  operator: Option<PendingOperator>,
+}
+
+/// An operator waiting for what it works on.
+struct PendingOperator {
+  operator: Operator,
+  keys: Vec<KeyEvent>,
+  /// The count typed before the operator.
+  count: Option<usize>,
+  /// The count typed after it, like the 3 in `d3w`.
+  motion_count: Option<usize>,
+  /// The keys typed after it so far, without the count.
+  typed: Vec<KeyEvent>,
+}
 
 impl KeybindManager {
+  pub fn setup(&mut self) {
+    use CursorMovement::*;
+    use InputResult::*;
+
+    for (keys, movement) in [
+      ("h", Left),
+      ("j", Down),
+      ("k", Up),
+      ("l", Right),
+      ("<Left>", Left),
+      ("<Down>", Down),
+      ("<Up>", Up),
+      ("<Right>", Right),
+      ("w", WordForward { big: false }),
+      ("W", WordForward { big: true }),
+      ("b", WordBackward { big: false }),
+      ("B", WordBackward { big: true }),
+      ("e", WordEnd { big: false }),
+      ("E", WordEnd { big: true }),
+      ("0", LineStart),
+      ("^", FirstNonBlank),
+      ("$", LineEnd),
+      ("}", ParagraphForward),
+      ("{", ParagraphBackward),
+      ("gg", FileStart),
+      ("G", FileEnd),
+    ] {
+      self.register_motion(keys, movement);
+    }
+
+    self.register("i", [ChangeInputMode(InputMode::Insert { left_insert: true })]);
+    self.register(
+      "a",
+      [ChangeInputMode(InputMode::Insert { left_insert: false }), CursorIntent(Right)],
+    );
+    self.register(":", [ChangeInputMode(InputMode::Command(CommandModeData::default()))]);
+
+    self.register(">>", [CausedAction(Action::IndentLine)]);
+    self.register("<lt><lt>", [CausedAction(Action::DedentLine)]);
+
+    self.register("gd", [CausedAction(Action::GotoDefinition)]);
+    self.register("K", [CausedAction(Action::Hover)]);
+    self.register("]d", [CausedAction(Action::NextDiagnostic)]);
+    self.register("[d", [CausedAction(Action::PreviousDiagnostic)]);
+    self.register("]q", [CausedAction(Action::NextQuickfix)]);
+    self.register("[q", [CausedAction(Action::PreviousQuickfix)]);
+    self.register("]h", [CausedAction(Action::NextHunk)]);
+    self.register("[h", [CausedAction(Action::PreviousHunk)]);
+    self.register("]c", [CausedAction(Action::NextChange)]);
+    self.register("[c", [CausedAction(Action::PreviousChange)]);
+    self.register("do", [CausedAction(Action::DiffGet)]);
+    self.register("dp", [CausedAction(Action::DiffPut)]);
+    self.register("<C-w>w", [CausedAction(Action::SwitchDiffSide)]);
+    self.register("<C-w><C-w>", [CausedAction(Action::SwitchDiffSide)]);
+
+    for mark in ('a'..='z').chain('A'..='Z') {
+      self.register(&f!("m{mark}"), [CausedAction(Action::SetMark(mark))]);
+    }
+    for mark in ('a'..='z').chain('A'..='Z').chain(['.', '^', '<', '>', '\'']) {
+      let notation = if mark == '<' { "<lt>".to_string() } else { mark.to_string() };
+      let goto = |linewise| CausedAction(Action::GotoMark { mark, linewise });
+      self.register(&f!("'{notation}"), [goto(true)]);
+      self.register(&f!("`{notation}"), [goto(false)]);
+    }
+    self.register("<C-o>", [CausedAction(Action::Jump { newer: false })]);
+    // Terminals send Tab for Ctrl-i.
+    self.register("<C-i>", [CausedAction(Action::Jump { newer: true })]);
+    self.register("<Tab>", [CausedAction(Action::Jump { newer: true })]);
+
+    self.register("v", [ChangeInputMode(InputMode::Visual(VisualSelection::new(0, 0)))]);
+    self.register("p", [CausedAction(Action::Put)]);
+    self.register("u", [CausedAction(Action::Undo { redo: false })]);
+    self.register("<C-r>", [CausedAction(Action::Undo { redo: true })]);
+    self.register_visual("<Esc>", [ChangeInputMode(InputMode::Normal)]);
+    self.register_visual("v", [ChangeInputMode(InputMode::Normal)]);
+
+    let operators = [
+      ("d", Operator::Delete),
+      ("c", Operator::Change),
+      ("y", Operator::Yank),
+      ("zf", Operator::Fold),
+      ("!", Operator::Filter),
+    ];
+    for (keys, operator) in operators {
+      self.register_operator(keys, operator);
+    }
+    for (keys, operator) in operators.into_iter().chain([("x", Operator::Delete)]) {
+      let operate = CausedAction(Action::OperateSelection(operator));
+      self.register_visual(keys, [operate, ChangeInputMode(mode_after(operator))]);
+    }
+    for (kind, inner) in [("i", true), ("a", false)] {
+      for (keys, object) in text_objects() {
+        let select = Action::SelectObject { object, inner };
+        self.register_visual(&f!("{kind}{keys}"), [CausedAction(select)]);
+        for (operator_keys, operator) in operators {
+          let operate = CausedAction(Action::Operate { operator, object, inner });
+          let keys = f!("{operator_keys}{kind}{keys}");
+          self.register(&keys, [operate, ChangeInputMode(mode_after(operator))]);
+        }
+      }
+    }
+
+    for (keys, command) in [
+      ("zo", FoldCommand::Open),
+      ("zc", FoldCommand::Close),
+      ("za", FoldCommand::Toggle),
+      ("zR", FoldCommand::OpenAll),
+      ("zM", FoldCommand::CloseAll),
+    ] {
+      self.register(keys, [CausedAction(Action::Fold(command))]);
+    }
+    self.register("<C-c>", [CausedAction(Action::CancelShell)]);
+
+    self.register("<C-n>", [CausedAction(Action::AddCursorAtNextMatch)]);
+    self.register_visual("<C-n>", [CausedAction(Action::AddCursorAtNextMatch)]);
+    self.register_visual("<A-s>", [CausedAction(Action::SplitSelection)]);
+    self.register(",", [CausedAction(Action::KeepPrimaryCursor)]);
+  }
+
+  /// Binds `keys`, written in vim key notation, in normal mode.
+  pub fn register(&mut self, keys: &str, results: impl IntoIterator<Item = InputResult>) {
+    let keys = parse_keys(keys).unwrap_or_else(|| panic!("Invalid keybinding '{keys}'"));
+    self.normal.insert(keys, Vec::from_iter(results));
+  }
+
+  /// Binds `keys` in visual mode.
+  pub fn register_visual(&mut self, keys: &str, results: impl IntoIterator<Item = InputResult>) {
+    let keys = parse_keys(keys).unwrap_or_else(|| panic!("Invalid keybinding '{keys}'"));
+    self.visual.insert(keys, Vec::from_iter(results));
+  }
+
+  /// Binds `keys` to a motion, in normal and visual mode and after operators.
+  pub fn register_motion(&mut self, keys: &str, movement: CursorMovement) {
+    let keys = parse_keys(keys).unwrap_or_else(|| panic!("Invalid keybinding '{keys}'"));
+    self.motions.insert(keys, movement);
+  }
+
+  /// Binds `keys` to an operator waiting for a motion in normal mode.
+  pub fn register_operator(&mut self, keys: &str, operator: Operator) {
+    let keys = parse_keys(keys).unwrap_or_else(|| panic!("Invalid keybinding '{keys}'"));
+    self.operators.insert(keys, operator);
+  }
+
+  /// Whether keys were typed that only start a binding so far, or an operator waits for a motion.
+  pub fn is_pending(&self) -> bool {
+    !self.pending.is_empty() || self.operator.is_some()
+  }
+
+  /// Takes a key, with the count typed before it in normal mode.
   pub fn on_keyinput(
     &mut self,
     keymode: KeyEvent,
     selection: Option<VisualSelection>,
-  ) -> Option<Vec<super::input_manager::InputResult>> {
-    todo!()
+    count: Option<usize>,
+  ) -> Option<Vec<InputResult>> {
+    if let Some(operator) = self.operator.take() {
+      return self.operator_key(operator, keymode);
+    }
+    if self.pending.is_empty() {
+      self.count = count;
+    }
+    self.pending.push(keymode);
+
+    if let Some(operator) = self.operators.get(&self.pending).filter(|_| selection.is_none()) {
+      self.operator = Some(PendingOperator {
+        operator: *operator,
+        keys: std::mem::take(&mut self.pending),
+        count: self.count.take(),
+        motion_count: None,
+        typed: Vec::new(),
+      });
+      return None;
+    }
+    if let Some(movement) = self.motions.get(&self.pending).copied() {
+      self.pending.clear();
+      let (movement, count) = counted(movement, self.count.take());
+      return Some(vec![InputResult::CursorIntent(movement); count.min(MAX_REPEAT)]);
+    }
+    let bindings = match selection {
+      Some(_) => &self.visual,
+      None => &self.normal,
+    };
+    if let Some(results) = bindings.get(&self.pending) {
+      self.pending.clear();
+      return Some(results.clone());
+    }
+
+    let starts = |keys: &&Vec<KeyEvent>| keys.starts_with(&self.pending);
+    let operators = self.operators.keys().filter(|_| selection.is_none());
+    if !bindings.keys().chain(self.motions.keys()).chain(operators).any(|keys| starts(&keys)) {
+      self.pending.clear();
+    }
+    None
+  }
+
+  /// Takes a key typed after an operator: a digit of its count, the keys of a motion or of a
+  /// binding starting with the operator like `diw`, or the operator again for whole lines.
+  fn operator_key(
+    &mut self,
+    mut pending: PendingOperator,
+    key: KeyEvent,
+  ) -> Option<Vec<InputResult>> {
+    if key.code == KeyCode::Esc {
+      return None;
+    }
+    if let (KeyModifiers::NONE, KeyCode::Char(digit @ '0'..='9')) = (key.modifiers, key.code) {
+      if pending.typed.is_empty() && (digit != '0' || pending.motion_count.is_some()) {
+        let digit = digit.to_digit(10).unwrap_or_default() as usize;
+        let count = pending.motion_count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+        pending.motion_count = Some(count);
+        self.operator = Some(pending);
+        return None;
+      }
+    }
+    pending.typed.push(key);
+
+    let operator = pending.operator;
+    let count = match (pending.count, pending.motion_count) {
+      (None, None) => None,
+      (before, after) => Some(before.unwrap_or(1).saturating_mul(after.unwrap_or(1))),
+    };
+    let keys = [pending.keys.as_slice(), pending.typed.as_slice()].concat();
+    if let Some(results) = self.normal.get(&keys) {
+      return Some(results.clone());
+    }
+    let target = match self.motions.get(&pending.typed) {
+      // `dd` is the line and the ones below it, as many as the count says.
+      _ if pending.typed == pending.keys => Some((CursorMovement::Down, count.unwrap_or(1) - 1)),
+      Some(movement) => Some(counted(*movement, count)),
+      None => None,
+    };
+    if let Some((movement, count)) = target {
+      let operate = InputResult::Operate { operator, movement, count };
+      return Some(Vec::from_iter([operate, InputResult::ChangeInputMode(mode_after(operator))]));
+    }
+
+    let waiting = self.normal.keys().any(|bound| bound.starts_with(&keys))
+      || self.motions.keys().any(|bound| bound.starts_with(&pending.typed))
+      || pending.keys.starts_with(&pending.typed);
+    if waiting {
+      self.operator = Some(pending);
+    }
+    None
+  }
+}
+
+/// A motion and how many times it's made for `count`. A count before `G` or `gg` is the line to
+/// go to instead.
+fn counted(movement: CursorMovement, count: Option<usize>) -> (CursorMovement, usize) {
+  match (movement, count) {
+    (CursorMovement::FileStart | CursorMovement::FileEnd, Some(line)) => {
+      (CursorMovement::Line(line.saturating_sub(1)), 1)
+    }
+    (movement, count) => (movement, count.unwrap_or(1)),
   }
 }
+
+/// The mode an operator leaves the editor in. It's switched to as soon as the keys are typed, so
+/// the keys after them in a macro or repeat are taken in that mode.
+fn mode_after(operator: Operator) -> InputMode {
+  match operator {
+    Operator::Change => InputMode::Insert { left_insert: true },
+    Operator::Delete | Operator::Yank | Operator::Fold => InputMode::Normal,
+    Operator::Filter => InputMode::Command(CommandModeData::default()),
+  }
+}
+
+/// The keys of each text object, typed after `i` or `a`.
+fn text_objects() -> Vec<(&'static str, TextObject)> {
+  use BetweenChars::*;
+  use TextObject::*;
+
+  Vec::from_iter([
+    ("w", Word),
+    ("W", BigWord),
+    ("\"", Quote('"')),
+    ("'", Quote('\'')),
+    ("`", Quote('`')),
+    ("(", Between(Parentheses)),
+    (")", Between(Parentheses)),
+    ("b", Between(Parentheses)),
+    ("[", Between(Brackets)),
+    ("]", Between(Brackets)),
+    ("{", Between(Braces)),
+    ("}", Between(Braces)),
+    ("B", Between(Braces)),
+    ("<lt>", Between(AngleBrackets)),
+    (">", Between(AngleBrackets)),
+    ("s", Sentence),
+    ("p", Paragraph),
+    ("f", Function),
+    ("c", Class),
+    ("a", Argument),
+    ("/", Comment),
+  ])
+}
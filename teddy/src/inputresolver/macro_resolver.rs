@@ -1,115 +1,303 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, ops::Range};
 
 use super::{
   input::input_manager::{InnerInputManager, InputResult},
   utils::{self, KeyEventExt as _},
 };
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use teddy_core::input_mode::InputMode;
+use teddy_core::{
+  action::{Action, Notification},
+  input_mode::InputMode,
+};
 
-enum MacroCheckReturn {
-  Continue,
-  Ignore,
-  Some(Vec<InputResult>),
-}
+use crate::prelude::{f, Result};
+
+/// How deep macros and `.` may replay each other, or themselves, before replay is given up.
+const MAX_REPLAY_DEPTH: usize = 100;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum CursorMovement {
   Up,
   Down,
   Left,
   Right,
+  /// `w`, or `W` when `big`.
+  WordForward {
+    big: bool,
+  },
+  /// `b`, or `B` when `big`.
+  WordBackward {
+    big: bool,
+  },
+  /// `e`, or `E` when `big`.
+  WordEnd {
+    big: bool,
+  },
+  /// `0`.
+  LineStart,
+  /// `^`.
+  FirstNonBlank,
+  /// `$`.
+  LineEnd,
+  /// `}`.
+  ParagraphForward,
+  /// `{`.
+  ParagraphBackward,
+  /// `gg`.
+  FileStart,
+  /// `G`.
+  FileEnd,
+  /// A line, from zero, like `5G`.
+  Line(usize),
 
   Readjust,
   Custom(usize),
 }
 
-enum StoreTrackerState {
-  Recording { registry: Option<char> },
-  Replaying,
+/// What the key after `q` or `@` is taken as.
+enum PendingLabel {
+  Record,
+  Replay,
+}
+
+/// A macro being recorded, into `label` from the key after it until the closing `q`.
+struct Recording {
+  label: char,
+  keys: Vec<KeyEvent>,
 }
 
 #[derive(Default)]
 pub struct MacroResolver {
-  macro_stores: HashMap<char, (usize, Option<usize>)>,
-  store_tracker_state: Option<StoreTrackerState>,
+  macro_stores: BTreeMap<char, Vec<KeyEvent>>,
+  pending_label: Option<PendingLabel>,
+  recording: Option<Recording>,
+  /// Set when a recording finished, until [`MacroResolver::take_recorded`].
+  recorded: bool,
+  /// The count typed before `@`, like the 5 in `5@a`.
+  count: Option<usize>,
+  /// The macro `@@` replays.
+  last_replayed: Option<char>,
+  replay_depth: usize,
   master_buffer: Vec<KeyEvent>,
-  latest_index: Option<usize>,
+  /// Where the keys typed since the editor was last idle start in `master_buffer`, and whether
+  /// they changed the buffer so far.
+  change: Option<(usize, bool)>,
+  /// The keys in `master_buffer` of the last change, repeated by `.`.
+  last_change: Option<Range<usize>>,
 
   pub input_manager: InnerInputManager,
 }
 
 impl MacroResolver {
-  fn check_macro_insertion(&mut self, key: KeyEvent) -> MacroCheckReturn {
-    if *self.input_manager.editor_mode() != InputMode::Normal {
-      return MacroCheckReturn::Continue;
-    }
-    let Some(macro_state) = self.store_tracker_state.as_mut() else {
-      return match key.code {
-        KeyCode::Char('q') => {
-          self.store_tracker_state = Some(StoreTrackerState::Recording { registry: None });
-          MacroCheckReturn::Ignore
+  pub fn input(&mut self, key: KeyEvent) -> Option<Vec<InputResult>> {
+    tracing::trace!("event: {:#?}", &key);
+    // Bindings and recorded macros hold keys the way their notation reads them.
+    let key = utils::normalized(key);
+    // A change ends once the editor is back in normal mode with no keys pending, so an insert
+    // session started by an operator is part of it.
+    if self.is_idle() {
+      if let Some((start, true)) = self.change {
+        self.last_change = Some(start..self.master_buffer.len());
+      }
+      self.change = Some((self.master_buffer.len(), false));
+    }
+    self.master_buffer.push(key);
+
+    let recording = self.recording.is_some();
+    let mut results = Vec::new();
+    if let Err(err) = self.resolve(key, &mut results) {
+      self.count = None;
+      let notification = Notification::error(f!("{err}"));
+      results.push(InputResult::CausedAction(Action::AttachNotification(notification, 3)));
+    }
+    // Only typed keys are recorded, so a replay inside a recording is kept as `@a`.
+    if let Some(current) = self.recording.as_mut().filter(|_| recording) {
+      current.keys.push(key);
+    }
+    if let Some((_, changed)) = self.change.as_mut() {
+      *changed |= results.iter().any(InputResult::is_change);
+    }
+
+    (!results.is_empty()).then_some(results)
+  }
+
+  /// Whether the next key starts something new: the editor is in normal mode and no keys are
+  /// waiting for the rest of a binding, a macro label or count.
+  pub fn is_idle(&self) -> bool {
+    *self.input_manager.editor_mode() == InputMode::Normal
+      && !self.input_manager.keybind_manager.is_pending()
+      && self.pending_label.is_none()
+      && self.count.is_none()
+  }
+
+  /// Resolves `key`, typed or replayed, into `results`.
+  fn resolve(&mut self, key: KeyEvent, results: &mut Vec<InputResult>) -> Result<()> {
+    if *self.input_manager.editor_mode() != InputMode::Normal
+      || self.input_manager.keybind_manager.is_pending()
+    {
+      results.extend(self.input_manager.input(key, None).unwrap_or_default());
+      return Ok(());
+    }
+
+    if let Some(pending) = self.pending_label.take() {
+      if key.code == KeyCode::Esc {
+        self.count = None;
+        return Ok(());
+      }
+      if matches!(pending, PendingLabel::Replay) && key.code == KeyCode::Char('@') {
+        let label = self.last_replayed.ok_or("No macro has been replayed yet")?;
+        return self.replay(label, results);
+      }
+      let Some(label) = utils::validate_macro_label(key) else {
+        let label = utils::key_notation(&[key]);
+        return Err(f!("Invalid macro label '{label}', use a-z, A-Z or 0-9").into());
+      };
+      match pending {
+        PendingLabel::Record => self.record(label),
+        PendingLabel::Replay => return self.replay(label, results),
+      }
+      return Ok(());
+    }
+
+    match (key.modifiers, key.code) {
+      _ if key.initiated_recording() => match self.recording.take() {
+        Some(recording) => {
+          self.macro_stores.insert(recording.label, recording.keys);
+          self.recorded = true;
         }
-        KeyCode::Char('@') => {
-          self.store_tracker_state = Some(StoreTrackerState::Replaying);
-          MacroCheckReturn::Ignore
+        None => self.pending_label = Some(PendingLabel::Record),
+      },
+      (_, KeyCode::Char('@')) => self.pending_label = Some(PendingLabel::Replay),
+      (KeyModifiers::NONE, KeyCode::Char('.')) => {
+        // Repeating isn't a change of its own, the last change stays the one to repeat.
+        self.change = None;
+        let range = self.last_change.clone().ok_or("No change to repeat yet")?;
+        let mut keys = self.master_buffer[range].to_vec();
+        // A count before `.` replaces the one the change was typed with, `3dw` then `2.` is `2dw`,
+        // and the next `.` repeats it with the new count too.
+        if let Some(count) = self.count.take() {
+          let typed = keys.iter().take_while(|key| is_digit(key)).count();
+          let count = count.to_string();
+          keys.splice(..typed, count.chars().map(|digit| KeyEvent::from(KeyCode::Char(digit))));
+          let start = self.master_buffer.len();
+          self.master_buffer.extend_from_slice(&keys);
+          self.last_change = Some(start..self.master_buffer.len());
         }
-        _ => MacroCheckReturn::Continue,
-      };
+        return self.replay_keys(&keys, results);
+      }
+      (KeyModifiers::NONE, KeyCode::Char(digit @ '0'..='9'))
+        if digit != '0' || self.count.is_some() =>
+      {
+        let digit = digit.to_digit(10).unwrap_or_default() as usize;
+        self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+      }
+      _ => {
+        let count = self.count.take();
+        results.extend(self.input_manager.input(key, count).unwrap_or_default());
+      }
+    }
+    Ok(())
+  }
+
+  /// Starts recording into `label`, `A` to `Z` append to the lowercase macro.
+  fn record(&mut self, label: char) {
+    self.count = None;
+    let keys = match label.is_ascii_uppercase() {
+      true => self.macro_stores.get(&label.to_ascii_lowercase()).cloned().unwrap_or_default(),
+      false => Vec::new(),
     };
+    self.recording = Some(Recording { label: label.to_ascii_lowercase(), keys });
+  }
 
-    match macro_state {
-      StoreTrackerState::Recording { registry } if registry.is_none() => {
-        let macro_label = utils::validate_macro_label(key).expect("Invalid Macro Label");
-        *registry = Some(macro_label);
-        self.macro_stores.insert(macro_label, (self.latest_index.unwrap() + 1, None));
-        MacroCheckReturn::Ignore
-      }
-      StoreTrackerState::Recording { registry } if key.initiated_recording() => {
-        let _macro = self.macro_stores.get_mut(&registry.unwrap());
-        _macro.unwrap().1 = Some(self.latest_index.unwrap());
+  /// Replays macro `label` as many times as the count before `@` says.
+  fn replay(&mut self, label: char, results: &mut Vec<InputResult>) -> Result<()> {
+    let label = label.to_ascii_lowercase();
+    if self.recording.as_ref().is_some_and(|recording| recording.label == label) {
+      return Err(f!("Macro '{label}' is still being recorded, finish it with q").into());
+    }
+    let keys =
+      self.macro_stores.get(&label).cloned().ok_or_else(|| f!("Macro '{label}' is empty"))?;
+    self.last_replayed = Some(label);
+    self.replay_keys(&keys, results)
+  }
 
-        self.store_tracker_state = None;
-        MacroCheckReturn::Ignore
-      }
-      // Här kommer den ju att aktivt recorda genom att vänta på när den är klar.
-      StoreTrackerState::Recording { registry: _ } => MacroCheckReturn::Continue,
-      StoreTrackerState::Replaying => {
-        let macro_label = utils::validate_macro_label(key).expect("Invalid Macro Label");
-        let _macro = self.macro_stores.get(&macro_label);
-        self.store_tracker_state = None;
-
-        let (start_index, end_index) = _macro.expect("Macro has not been finished recording");
-        let end_index = end_index.expect("Macro has not been finished recording");
-
-        let store_slice = self.master_buffer[*start_index..end_index].to_vec();
-        let input_results: Vec<InputResult> =
-          store_slice.iter().filter_map(|v| self.input_manager.input(*v)).flatten().collect();
-
-        if input_results.is_empty() {
-          MacroCheckReturn::Ignore
-        } else {
-          MacroCheckReturn::Some(input_results)
-        }
+  /// Resolves `keys` as many times as the count typed before says.
+  fn replay_keys(&mut self, keys: &[KeyEvent], results: &mut Vec<InputResult>) -> Result<()> {
+    if self.replay_depth >= MAX_REPLAY_DEPTH {
+      return Err("Macros replay each other too deeply".into());
+    }
+    let count = self.count.take().unwrap_or(1);
+
+    self.replay_depth += 1;
+    let mut result = Ok(());
+    for key in (0..count).flat_map(|_| keys.iter()) {
+      result = self.resolve(*key, results);
+      if result.is_err() {
+        break;
       }
     }
+    self.replay_depth -= 1;
+    result
   }
 
-  pub fn input(&mut self, key: KeyEvent) -> Option<Vec<InputResult>> {
-    tracing::trace!("event: {:#?}", &key);
-    match self.check_macro_insertion(key) {
-      MacroCheckReturn::Continue => self.input_manager.input(key),
-      MacroCheckReturn::Some(v) => Some(v),
-      MacroCheckReturn::Ignore => None,
+  /// Whether a recording finished since the last call, so the macros can be saved.
+  pub fn take_recorded(&mut self) -> bool {
+    std::mem::take(&mut self.recorded)
+  }
+
+  /// The keys of macro `label`, in vim key notation.
+  pub fn macro_notation(&self, label: char) -> Option<String> {
+    self.macro_stores.get(&label.to_ascii_lowercase()).map(|keys| utils::key_notation(keys))
+  }
+
+  /// Replaces macro `label` with the keys in `notation`.
+  pub fn set_macro(&mut self, label: char, notation: &str) -> Result<()> {
+    if !label.is_ascii_alphanumeric() {
+      return Err(f!("Invalid macro label '{label}', use a-z, A-Z or 0-9").into());
+    }
+    let keys = utils::parse_keys(notation).ok_or_else(|| f!("Invalid keys '{notation}'"))?;
+    self.macro_stores.insert(label.to_ascii_lowercase(), keys);
+    Ok(())
+  }
+
+  /// Every macro's label and keys, in vim key notation.
+  pub fn macros(&self) -> impl Iterator<Item = (char, String)> + '_ {
+    self.macro_stores.iter().map(|(label, keys)| (*label, utils::key_notation(keys)))
+  }
+
+  /// Every macro on a line of its own, its label followed by a space and its keys.
+  pub fn macros_text(&self) -> String {
+    self.macros().map(|(label, keys)| f!("{label} {keys}\n")).collect()
+  }
+
+  /// Loads macros written by [`MacroResolver::macros_text`]. Returns the lines that couldn't be
+  /// read.
+  pub fn load_macros(&mut self, text: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (idx, line) in text.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+      let mut chars = line.chars();
+      let (Some(label), Some(' ')) = (chars.next(), chars.next()) else {
+        errors.push(f!("Macros line {}: expected a label and a space", idx + 1));
+        continue;
+      };
+      if let Err(err) = self.set_macro(label, chars.as_str()) {
+        errors.push(f!("Macros line {}: {err}", idx + 1));
+      }
     }
+    errors
   }
 }
 
+fn is_digit(key: &KeyEvent) -> bool {
+  key.modifiers == KeyModifiers::NONE && matches!(key.code, KeyCode::Char('0'..='9'))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use crossterm::event::KeyModifiers;
+  use teddy_core::text_object::{Operator, TextObject};
 
   #[test]
   fn test_mode_switching_under_record() {
@@ -247,4 +435,135 @@ mod tests {
       }
     }
   }
+
+  #[test]
+  fn test_counted_replay_and_notation() {
+    let mut input_resolver = MacroResolver::default();
+    let mut type_keys = |notation: &str| -> Vec<InputResult> {
+      let keys = utils::parse_keys(notation).unwrap();
+      keys.into_iter().flat_map(|key| input_resolver.input(key).unwrap_or_default()).collect()
+    };
+    let insert = |char| InputResult::Insert(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+
+    assert_eq!(type_keys("qaix<Esc>qqAiy<Esc>q"), Vec::from_iter([insert('x'), insert('y')]));
+    assert_eq!(
+      type_keys("2@a"),
+      Vec::from_iter([insert('x'), insert('y'), insert('x'), insert('y')])
+    );
+    assert_eq!(type_keys("@@"), Vec::from_iter([insert('x'), insert('y')]));
+    assert!(matches!(
+      type_keys("q!").as_slice(),
+      [InputResult::CausedAction(Action::AttachNotification(..))]
+    ));
+    assert!(matches!(
+      type_keys("@b").as_slice(),
+      [InputResult::CausedAction(Action::AttachNotification(..))]
+    ));
+
+    assert_eq!(input_resolver.macros_text(), "a ix<Esc>iy<Esc>\n");
+    let mut loaded = MacroResolver::default();
+    assert!(loaded.load_macros("a ix<Esc>iy<Esc>\nb <C-o><lt>\n").is_empty());
+    assert_eq!(loaded.macro_notation('b').as_deref(), Some("<C-o><lt>"));
+    assert_eq!(loaded.load_macros("?x").len(), 1);
+  }
+
+  #[test]
+  fn test_operator_pending() {
+    let mut input_resolver = MacroResolver::default();
+    let mut type_keys = |notation: &str| -> Vec<InputResult> {
+      let keys = utils::parse_keys(notation).unwrap();
+      keys.into_iter().flat_map(|key| input_resolver.input(key).unwrap_or_default()).collect()
+    };
+    let operate = |operator, movement, count| InputResult::Operate { operator, movement, count };
+    let word = CursorMovement::WordForward { big: false };
+
+    assert_eq!(type_keys("3j"), vec![InputResult::CursorIntent(CursorMovement::Down); 3]);
+    assert_eq!(
+      type_keys("5G"),
+      Vec::from_iter([InputResult::CursorIntent(CursorMovement::Line(4))])
+    );
+    assert_eq!(type_keys("dw"), Vec::from_iter([operate(Operator::Delete, word, 1)]));
+    assert_eq!(type_keys("2d3w"), Vec::from_iter([operate(Operator::Delete, word, 6)]));
+    assert_eq!(
+      type_keys("d$"),
+      Vec::from_iter([operate(Operator::Delete, CursorMovement::LineEnd, 1)])
+    );
+    assert_eq!(
+      type_keys("3dd"),
+      Vec::from_iter([operate(Operator::Delete, CursorMovement::Down, 2)])
+    );
+    assert_eq!(type_keys("yy"), Vec::from_iter([operate(Operator::Yank, CursorMovement::Down, 0)]));
+    assert_eq!(
+      type_keys("zf3j"),
+      Vec::from_iter([operate(Operator::Fold, CursorMovement::Down, 3)])
+    );
+    assert_eq!(
+      type_keys("!3j"),
+      Vec::from_iter([operate(Operator::Filter, CursorMovement::Down, 3)])
+    );
+    type_keys("<Esc>");
+    assert_eq!(
+      type_keys("!!"),
+      Vec::from_iter([operate(Operator::Filter, CursorMovement::Down, 0)])
+    );
+    type_keys("<Esc>");
+    assert_eq!(
+      type_keys("dgg"),
+      Vec::from_iter([operate(Operator::Delete, CursorMovement::FileStart, 1)])
+    );
+    // Keys that aren't a motion cancel the operator, Escape too.
+    assert!(type_keys("dxd<Esc>").is_empty());
+    assert_eq!(type_keys("cw"), Vec::from_iter([operate(Operator::Change, word, 1)]));
+  }
+
+  #[test]
+  fn test_dot_repeat() {
+    let mut input_resolver = MacroResolver::default();
+    let mut type_keys = |notation: &str| -> Vec<InputResult> {
+      let keys = utils::parse_keys(notation).unwrap();
+      keys.into_iter().flat_map(|key| input_resolver.input(key).unwrap_or_default()).collect()
+    };
+    let insert = |char| InputResult::Insert(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+    let change_word = InputResult::CausedAction(Action::Operate {
+      operator: Operator::Change,
+      object: TextObject::Word,
+      inner: true,
+    });
+
+    assert!(matches!(
+      type_keys(".").as_slice(),
+      [InputResult::CausedAction(Action::AttachNotification(..))]
+    ));
+    assert_eq!(
+      type_keys("ciwab<Esc>"),
+      Vec::from_iter([change_word.clone(), insert('a'), insert('b')])
+    );
+    // Moving and yanking aren't changes, `.` still repeats the last one.
+    type_keys("lyiw");
+    assert_eq!(type_keys("."), Vec::from_iter([change_word.clone(), insert('a'), insert('b')]));
+    // Text objects take no count, `2.` is `2ciw`.
+    assert_eq!(type_keys("2.").len(), 3);
+    assert_eq!(
+      type_keys("p."),
+      Vec::from_iter([
+        InputResult::CausedAction(Action::Put),
+        InputResult::CausedAction(Action::Put)
+      ])
+    );
+
+    // The count is part of the change, and a new one replaces it.
+    let delete_words = |count| InputResult::Operate {
+      operator: Operator::Delete,
+      movement: CursorMovement::WordForward { big: false },
+      count,
+    };
+    assert_eq!(type_keys("3dw"), Vec::from_iter([delete_words(3)]));
+    assert_eq!(type_keys("."), Vec::from_iter([delete_words(3)]));
+    assert_eq!(type_keys("12."), Vec::from_iter([delete_words(12)]));
+    assert_eq!(type_keys("."), Vec::from_iter([delete_words(12)]));
+    assert_eq!(
+      type_keys("u."),
+      Vec::from_iter([InputResult::CausedAction(Action::Undo { redo: false }), delete_words(12)])
+    );
+  }
 }
@@ -1,4 +1,6 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MediaKeyCode, ModifierKeyCode};
+
+use crate::prelude::f;
 
 pub(super) trait KeyEventExt {
   fn initiated_recording(&self) -> bool;
@@ -10,12 +12,224 @@ impl KeyEventExt for KeyEvent {
   }
 }
 
+/// Macros are kept in `a` to `z` and `0` to `9`, `A` to `Z` append to the lowercase ones.
 pub(super) fn validate_macro_label(key: KeyEvent) -> Option<char> {
   match (key.modifiers, key.code) {
-    (KeyModifiers::NONE, KeyCode::Char(char)) => Some(char),
-    _ => {
-      panic!("{:?}", key);
-      //None
+    (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(char))
+      if char.is_ascii_alphanumeric() =>
+    {
+      Some(char)
+    }
+    _ => None,
+  }
+}
+
+/// The modifiers written in key notation, `<C-S-a>` is Control and Shift with `a`.
+const MODIFIERS: [(KeyModifiers, &str); 6] = [
+  (KeyModifiers::CONTROL, "C"),
+  (KeyModifiers::SHIFT, "S"),
+  (KeyModifiers::ALT, "A"),
+  (KeyModifiers::SUPER, "D"),
+  (KeyModifiers::HYPER, "H"),
+  (KeyModifiers::META, "T"),
+];
+
+const MEDIA_KEYS: [MediaKeyCode; 13] = [
+  MediaKeyCode::Play,
+  MediaKeyCode::Pause,
+  MediaKeyCode::PlayPause,
+  MediaKeyCode::Reverse,
+  MediaKeyCode::Stop,
+  MediaKeyCode::FastForward,
+  MediaKeyCode::Rewind,
+  MediaKeyCode::TrackNext,
+  MediaKeyCode::TrackPrevious,
+  MediaKeyCode::Record,
+  MediaKeyCode::LowerVolume,
+  MediaKeyCode::RaiseVolume,
+  MediaKeyCode::MuteVolume,
+];
+
+const MODIFIER_KEYS: [ModifierKeyCode; 14] = [
+  ModifierKeyCode::LeftShift,
+  ModifierKeyCode::LeftControl,
+  ModifierKeyCode::LeftAlt,
+  ModifierKeyCode::LeftSuper,
+  ModifierKeyCode::LeftHyper,
+  ModifierKeyCode::LeftMeta,
+  ModifierKeyCode::RightShift,
+  ModifierKeyCode::RightControl,
+  ModifierKeyCode::RightAlt,
+  ModifierKeyCode::RightSuper,
+  ModifierKeyCode::RightHyper,
+  ModifierKeyCode::RightMeta,
+  ModifierKeyCode::IsoLevel3Shift,
+  ModifierKeyCode::IsoLevel5Shift,
+];
+
+/// Drops SHIFT from the chars it's already part of, like `G` or `!`, and adds it to `<S-Tab>`, so
+/// a typed key is the same as the one its notation is read as. Terminals differ in what they send.
+pub(crate) fn normalized(mut key: KeyEvent) -> KeyEvent {
+  match key.code {
+    KeyCode::Char(char) if char.is_uppercase() || !(char.is_alphanumeric() || char == ' ') => {
+      key.modifiers.remove(KeyModifiers::SHIFT)
+    }
+    KeyCode::BackTab => key.modifiers.insert(KeyModifiers::SHIFT),
+    _ => {}
+  }
+  key
+}
+
+fn parse_special_key(name: &str) -> Option<KeyEvent> {
+  let (mut modifiers, mut name) = (KeyModifiers::NONE, name);
+  while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+    modifiers |= MODIFIERS.iter().find(|(_, prefix)| *prefix == modifier)?.0;
+    name = rest;
+  }
+
+  let code = match name {
+    "Esc" => KeyCode::Esc,
+    "CR" | "Enter" => KeyCode::Enter,
+    "Tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+    "Tab" => KeyCode::Tab,
+    "BS" => KeyCode::Backspace,
+    "Del" => KeyCode::Delete,
+    "Space" => KeyCode::Char(' '),
+    "lt" => KeyCode::Char('<'),
+    "gt" => KeyCode::Char('>'),
+    "Up" => KeyCode::Up,
+    "Down" => KeyCode::Down,
+    "Left" => KeyCode::Left,
+    "Right" => KeyCode::Right,
+    "Home" => KeyCode::Home,
+    "End" => KeyCode::End,
+    "PageUp" => KeyCode::PageUp,
+    "PageDown" => KeyCode::PageDown,
+    "Insert" => KeyCode::Insert,
+    "Nul" => KeyCode::Null,
+    "CapsLock" => KeyCode::CapsLock,
+    "ScrollLock" => KeyCode::ScrollLock,
+    "NumLock" => KeyCode::NumLock,
+    "PrintScreen" => KeyCode::PrintScreen,
+    "Pause" => KeyCode::Pause,
+    "Menu" => KeyCode::Menu,
+    "KeypadBegin" => KeyCode::KeypadBegin,
+    name if name.chars().count() == 1 => KeyCode::Char(name.chars().next()?),
+    name => match name.strip_prefix('F').and_then(|number| number.parse().ok()) {
+      Some(number) => KeyCode::F(number),
+      None => media_or_modifier_key(name)?,
+    },
+  };
+
+  Some(KeyEvent::new(code, modifiers))
+}
+
+/// Parses vim style key notation, `gd`, `<C-s>` or `<Esc>`, into key events.
+pub(crate) fn parse_keys(notation: &str) -> Option<Vec<KeyEvent>> {
+  let mut keys = Vec::new();
+  let mut chars = notation.chars();
+
+  while let Some(char) = chars.next() {
+    if char != '<' {
+      keys.push(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+      continue;
     }
+
+    let name: String = chars.by_ref().take_while(|char| *char != '>').collect();
+    keys.push(parse_special_key(&name)?);
+  }
+
+  Some(keys)
+}
+
+/// The media key `MediaPlay` or modifier key `LeftShift`, named after their variants.
+fn media_or_modifier_key(name: &str) -> Option<KeyCode> {
+  let media = MEDIA_KEYS.into_iter().find(|media| f!("Media{media:?}") == name);
+  let modifier = || MODIFIER_KEYS.into_iter().find(|modifier| f!("{modifier:?}") == name);
+  media.map(KeyCode::Media).or_else(|| modifier().map(KeyCode::Modifier))
+}
+
+/// Writes `keys` in the notation [`parse_keys`] reads, which gives back the [`normalized`] keys.
+pub(crate) fn key_notation(keys: &[KeyEvent]) -> String {
+  keys.iter().map(|key| key_name(normalized(*key))).collect()
+}
+
+fn key_name(key: KeyEvent) -> String {
+  let name = match key.code {
+    KeyCode::Char('<') if key.modifiers.is_empty() => return "<lt>".to_string(),
+    KeyCode::Char(char) if key.modifiers.is_empty() => return char.to_string(),
+    KeyCode::Char(' ') => "Space".to_string(),
+    KeyCode::Char('<') => "lt".to_string(),
+    KeyCode::Char('>') => "gt".to_string(),
+    KeyCode::Char(char) => char.to_string(),
+    KeyCode::Esc => "Esc".to_string(),
+    KeyCode::Enter => "CR".to_string(),
+    KeyCode::Tab => "Tab".to_string(),
+    KeyCode::BackTab => "Tab".to_string(),
+    KeyCode::Backspace => "BS".to_string(),
+    KeyCode::Delete => "Del".to_string(),
+    KeyCode::Up => "Up".to_string(),
+    KeyCode::Down => "Down".to_string(),
+    KeyCode::Left => "Left".to_string(),
+    KeyCode::Right => "Right".to_string(),
+    KeyCode::Home => "Home".to_string(),
+    KeyCode::End => "End".to_string(),
+    KeyCode::PageUp => "PageUp".to_string(),
+    KeyCode::PageDown => "PageDown".to_string(),
+    KeyCode::Insert => "Insert".to_string(),
+    KeyCode::F(number) => f!("F{number}"),
+    KeyCode::Null => "Nul".to_string(),
+    KeyCode::CapsLock => "CapsLock".to_string(),
+    KeyCode::ScrollLock => "ScrollLock".to_string(),
+    KeyCode::NumLock => "NumLock".to_string(),
+    KeyCode::PrintScreen => "PrintScreen".to_string(),
+    KeyCode::Pause => "Pause".to_string(),
+    KeyCode::Menu => "Menu".to_string(),
+    KeyCode::KeypadBegin => "KeypadBegin".to_string(),
+    KeyCode::Media(media) => f!("Media{media:?}"),
+    KeyCode::Modifier(modifier) => f!("{modifier:?}"),
+  };
+
+  let mut notation = String::from("<");
+  for (modifier, prefix) in MODIFIERS {
+    if key.modifiers.contains(modifier) {
+      notation.push_str(prefix);
+      notation.push('-');
+    }
+  }
+  f!("{notation}{name}>")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_notation_round_trip() {
+    let key = KeyEvent::new;
+    let typed = [
+      key(KeyCode::Char('G'), KeyModifiers::SHIFT),
+      key(KeyCode::Char('!'), KeyModifiers::SHIFT),
+      key(KeyCode::Char('<'), KeyModifiers::SHIFT),
+      key(KeyCode::Char('a'), KeyModifiers::SHIFT),
+      key(KeyCode::Char('A'), KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+      key(KeyCode::Char(' '), KeyModifiers::SHIFT),
+      key(KeyCode::BackTab, KeyModifiers::NONE),
+      key(KeyCode::BackTab, KeyModifiers::CONTROL),
+      key(KeyCode::Up, KeyModifiers::SHIFT),
+      key(KeyCode::Char('x'), KeyModifiers::SUPER),
+      key(KeyCode::CapsLock, KeyModifiers::NONE),
+      key(KeyCode::Media(MediaKeyCode::PlayPause), KeyModifiers::NONE),
+      key(KeyCode::Modifier(ModifierKeyCode::LeftShift), KeyModifiers::SHIFT),
+      key(KeyCode::F(5), KeyModifiers::NONE),
+    ];
+
+    let notation = key_notation(&typed);
+    assert_eq!(
+      notation,
+      "G!<lt><S-a><C-A><S-Space><S-Tab><C-S-Tab><S-Up><D-x><CapsLock><MediaPlayPause><S-LeftShift><F5>"
+    );
+    let keys = Vec::from_iter(typed.map(normalized));
+    assert_eq!(parse_keys(&notation), Some(keys));
   }
 }
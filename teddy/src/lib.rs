@@ -1,11 +1,26 @@
+#![allow(clippy::module_inception)]
 mod app;
 pub use app::*;
 
 pub mod buffers;
 pub mod components;
+pub mod diff;
 pub mod editor;
+pub mod editorconfig;
+pub mod format;
 pub mod frame;
+pub mod motions;
+pub mod options;
 pub mod prelude;
+pub mod session;
+pub mod shell;
+pub mod snippets;
+pub mod swap;
+pub mod syntax;
+pub mod task;
+pub mod terminal;
+pub mod text_objects;
 pub mod ui;
+pub mod vcs;
 
 pub mod inputresolver;
@@ -0,0 +1,278 @@
+//! Where the motions beyond a step in one direction go, like `w` or `}`, and what an operator
+//! typed before a motion works on, like `d3w` or `yj`.
+
+use std::ops::Range;
+
+use ropey::Rope;
+use teddy_core::text_object::Operator;
+
+use crate::inputresolver::CursorMovement;
+
+/// The char `movement` goes to from `char_idx`. `None` for the steps up, down, left and right,
+/// which keep to a column and are moved by the cursor itself.
+pub fn target(rope: &Rope, char_idx: usize, movement: CursorMovement) -> Option<usize> {
+  use CursorMovement::*;
+
+  let last = rope.len_chars().saturating_sub(1);
+  let line = rope.char_to_line(char_idx.min(rope.len_chars()));
+  let target = match movement {
+    WordForward { big } => word_forward(rope, char_idx, big),
+    WordBackward { big } => word_backward(rope, char_idx, big),
+    WordEnd { big } => word_end(rope, char_idx, big),
+    LineStart => rope.line_to_char(line),
+    FirstNonBlank => first_non_blank(rope, line),
+    // The last char of the line, not its line break.
+    LineEnd => line_end(rope, line).saturating_sub(1).max(rope.line_to_char(line)),
+    ParagraphForward => paragraph_forward(rope, line),
+    ParagraphBackward => paragraph_backward(rope, line),
+    FileStart => first_non_blank(rope, 0),
+    FileEnd => first_non_blank(rope, last_line(rope)),
+    Line(line) => first_non_blank(rope, line.min(last_line(rope))),
+    Up | Down | Left | Right | Readjust | Custom(_) => return None,
+  };
+  Some(target.min(last))
+}
+
+/// Whether an operator typed before `movement` works on whole lines, like `dj` or `dd`.
+pub fn is_linewise(movement: CursorMovement) -> bool {
+  use CursorMovement::*;
+  matches!(movement, Up | Down | FileStart | FileEnd | Line(_))
+}
+
+/// The chars an operator works on when typed before `movement` from `char_idx`, with the count of
+/// the motion. Motions between lines take whole lines, the doubled operator keys like `dd` are
+/// `count - 1` lines down.
+pub fn operator_range(
+  rope: &Rope,
+  char_idx: usize,
+  movement: CursorMovement,
+  count: usize,
+  operator: Operator,
+) -> Option<Range<usize>> {
+  use CursorMovement::*;
+
+  let char_idx = char_idx.min(rope.len_chars());
+  let line = rope.char_to_line(char_idx);
+  let repeat = |step: &dyn Fn(usize) -> usize| (0..count).fold(char_idx, |idx, _| step(idx));
+  let range = match movement {
+    Up => return Some(lines(rope, line.saturating_sub(count), line, operator)),
+    Down => return Some(lines(rope, line, (line + count).min(last_line(rope)), operator)),
+    FileStart => return Some(lines(rope, 0, line, operator)),
+    FileEnd => return Some(lines(rope, line, last_line(rope), operator)),
+    Line(target) => {
+      let target = target.min(last_line(rope));
+      return Some(lines(rope, line.min(target), line.max(target), operator));
+    }
+    Left => char_idx.saturating_sub(count).max(rope.line_to_char(line))..char_idx,
+    Right => char_idx..(char_idx + count).min(line_end(rope, line)),
+    // Like vim, `cw` on a word changes to its end and leaves the blanks after it.
+    WordForward { big } if operator == Operator::Change && !is_blank_at(rope, char_idx) => {
+      char_idx..(repeat(&|idx| word_end(rope, idx, big)) + 1).min(rope.len_chars())
+    }
+    WordForward { big } => {
+      // The last word of a line doesn't take the line break and the indent after it.
+      let end = repeat(&|idx| word_forward(rope, idx, big));
+      let last = rope.char_to_line(end.min(rope.len_chars()));
+      match last > line && (count == 1 || end == rope.len_chars()) {
+        true => char_idx..line_end(rope, line).max(char_idx),
+        false => char_idx..end,
+      }
+    }
+    WordBackward { big } => repeat(&|idx| word_backward(rope, idx, big))..char_idx,
+    WordEnd { big } => char_idx..(repeat(&|idx| word_end(rope, idx, big)) + 1),
+    LineStart => rope.line_to_char(line)..char_idx,
+    FirstNonBlank => {
+      let first = first_non_blank(rope, line);
+      first.min(char_idx)..first.max(char_idx)
+    }
+    LineEnd => char_idx..line_end(rope, (line + count.max(1) - 1).min(last_line(rope))),
+    ParagraphForward => {
+      char_idx..repeat(&|idx| paragraph_forward(rope, rope.char_to_line(idx.min(rope.len_chars()))))
+    }
+    ParagraphBackward => repeat(&|idx| paragraph_backward(rope, rope.char_to_line(idx)))..char_idx,
+    Readjust | Custom(_) => return None,
+  };
+  Some(range.start..range.end.min(rope.len_chars()))
+}
+
+/// Lines `first` to `last` with their line breaks. The last line of the text takes the line break
+/// before it instead, and `c` leaves the last one so the line stays.
+fn lines(rope: &Rope, first: usize, last: usize, operator: Operator) -> Range<usize> {
+  let from = rope.line_to_char(first);
+  if operator == Operator::Change {
+    return from..line_end(rope, last);
+  }
+  match last + 1 < rope.len_lines() {
+    true => from..rope.line_to_char(last + 1),
+    false if first > 0 => line_end(rope, first - 1)..rope.len_chars(),
+    false => from..rope.len_chars(),
+  }
+}
+
+fn last_line(rope: &Rope) -> usize {
+  // A line break ending the text doesn't start another line to be on.
+  let lines = rope.len_lines();
+  match lines > 1 && rope.line(lines - 1).len_chars() == 0 {
+    true => lines - 2,
+    false => lines - 1,
+  }
+}
+
+/// The char after the last one of `line`, line break excluded.
+fn line_end(rope: &Rope, line: usize) -> usize {
+  let start = rope.line_to_char(line);
+  start + rope.line(line).chars().take_while(|char| *char != '\n' && *char != '\r').count()
+}
+
+fn first_non_blank(rope: &Rope, line: usize) -> usize {
+  let start = rope.line_to_char(line);
+  start + rope.line(line).chars().take_while(|char| *char == ' ' || *char == '\t').count()
+}
+
+fn is_blank_at(rope: &Rope, char_idx: usize) -> bool {
+  rope.get_char(char_idx).is_none_or(char::is_whitespace)
+}
+
+/// Words are runs of letters, digits and underscores, or of other non-blank chars. Big words are
+/// runs of any non-blank chars.
+fn class(char: char, big: bool) -> u8 {
+  match char {
+    char if char.is_whitespace() => 0,
+    _ if big => 1,
+    char if char.is_alphanumeric() || char == '_' => 2,
+    _ => 1,
+  }
+}
+
+/// The start of the next word, or the end of the text. An empty line counts as a word.
+fn word_forward(rope: &Rope, char_idx: usize, big: bool) -> usize {
+  let len = rope.len_chars();
+  let mut idx = char_idx;
+  let Some(start) = rope.get_char(idx).map(|char| class(char, big)) else {
+    return len;
+  };
+  while idx < len && start != 0 && class(rope.char(idx), big) == start {
+    idx += 1;
+  }
+  while idx < len && rope.char(idx).is_whitespace() {
+    if idx > char_idx && rope.char(idx) == '\n' && rope.char(idx - 1) == '\n' {
+      break;
+    }
+    idx += 1;
+  }
+  idx
+}
+
+/// The start of the word before `char_idx`, or of the one it's in.
+fn word_backward(rope: &Rope, char_idx: usize, big: bool) -> usize {
+  let mut idx = char_idx.min(rope.len_chars());
+  while idx > 0 && rope.char(idx - 1).is_whitespace() {
+    idx -= 1;
+  }
+  let Some(class_before) = idx.checked_sub(1).map(|before| class(rope.char(before), big)) else {
+    return 0;
+  };
+  while idx > 0 && class(rope.char(idx - 1), big) == class_before {
+    idx -= 1;
+  }
+  idx
+}
+
+/// The last char of the word after `char_idx`, or of the one it's in.
+fn word_end(rope: &Rope, char_idx: usize, big: bool) -> usize {
+  let len = rope.len_chars();
+  let mut idx = char_idx + 1;
+  while idx < len && rope.char(idx).is_whitespace() {
+    idx += 1;
+  }
+  let Some(start) = rope.get_char(idx).map(|char| class(char, big)) else {
+    return len.saturating_sub(1);
+  };
+  while idx + 1 < len && class(rope.char(idx + 1), big) == start {
+    idx += 1;
+  }
+  idx
+}
+
+fn is_blank_line(rope: &Rope, line: usize) -> bool {
+  rope.line(line).chars().all(char::is_whitespace)
+}
+
+/// The start of the first blank line after the paragraph at `line`, or the end of the text.
+fn paragraph_forward(rope: &Rope, line: usize) -> usize {
+  let last = last_line(rope);
+  let mut line = line;
+  while line < last && is_blank_line(rope, line) {
+    line += 1;
+  }
+  while line < last && !is_blank_line(rope, line) {
+    line += 1;
+  }
+  match is_blank_line(rope, line) {
+    true => rope.line_to_char(line),
+    false => rope.len_chars(),
+  }
+}
+
+/// The start of the first blank line before the paragraph at `line`, or of the text.
+fn paragraph_backward(rope: &Rope, line: usize) -> usize {
+  let mut line = line;
+  while line > 0 && is_blank_line(rope, line) {
+    line -= 1;
+  }
+  while line > 0 && !is_blank_line(rope, line) {
+    line -= 1;
+  }
+  rope.line_to_char(line)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn operated(
+    text: &str,
+    idx: usize,
+    movement: CursorMovement,
+    count: usize,
+    op: Operator,
+  ) -> String {
+    let rope = Rope::from_str(text);
+    let range = operator_range(&rope, idx, movement, count, op).unwrap();
+    rope.slice(range).to_string()
+  }
+
+  #[test]
+  fn test_motions() {
+    use CursorMovement::*;
+    let rope = Rope::from_str("let x = foo(a);\n  bar\n\nbaz\n");
+    let word = WordForward { big: false };
+    assert_eq!(target(&rope, 0, word), Some(4));
+    assert_eq!(target(&rope, 8, word), Some(11));
+    assert_eq!(target(&rope, 8, WordForward { big: true }), Some(18));
+    assert_eq!(target(&rope, 18, WordBackward { big: false }), Some(13));
+    assert_eq!(target(&rope, 8, WordEnd { big: false }), Some(10));
+    assert_eq!(target(&rope, 17, FirstNonBlank), Some(18));
+    assert_eq!(target(&rope, 0, LineEnd), Some(14));
+    assert_eq!(target(&rope, 0, ParagraphForward), Some(22));
+    assert_eq!(target(&rope, 23, ParagraphBackward), Some(22));
+    assert_eq!(target(&rope, 0, FileEnd), Some(23));
+    assert_eq!(target(&rope, 0, Line(1)), Some(18));
+    assert_eq!(target(&rope, 0, Down), None);
+
+    let (delete, change) = (Operator::Delete, Operator::Change);
+    let text = "let x = foo(a);\n  bar\n\nbaz\n";
+    assert_eq!(operated(text, 0, word, 1, delete), "let ");
+    assert_eq!(operated(text, 0, word, 3, delete), "let x = ");
+    assert_eq!(operated(text, 0, word, 1, change), "let");
+    assert_eq!(operated(text, 14, word, 1, delete), ";");
+    assert_eq!(operated(text, 4, LineEnd, 1, delete), "x = foo(a);");
+    assert_eq!(operated(text, 18, Down, 0, delete), "  bar\n");
+    assert_eq!(operated(text, 18, Down, 0, change), "  bar");
+    assert_eq!(operated(text, 0, Down, 1, delete), "let x = foo(a);\n  bar\n");
+    assert_eq!(operated(text, 23, Up, 1, delete), "\nbaz\n");
+    assert_eq!(operated(text, 0, ParagraphForward, 1, delete), "let x = foo(a);\n  bar\n");
+    assert_eq!(operated(text, 6, Left, 9, delete), "let x ");
+    assert_eq!(operated("a\nb", 2, Down, 0, delete), "\nb");
+  }
+}
@@ -0,0 +1,338 @@
+use std::{borrow::Cow, io::Write};
+
+use encoding_rs::{DecoderResult, Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use ropey::Rope;
+use teddy_config::{Config, IndentConfig};
+use teddy_core::buffer::Edit;
+
+use super::{OptionStore, OptionValue};
+use crate::prelude::{f, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+  Lf,
+  CrLf,
+  Cr,
+}
+
+impl LineEnding {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      LineEnding::Lf => "\n",
+      LineEnding::CrLf => "\r\n",
+      LineEnding::Cr => "\r",
+    }
+  }
+
+  /// The name used by `:set fileformat`.
+  pub fn name(&self) -> &'static str {
+    match self {
+      LineEnding::Lf => "unix",
+      LineEnding::CrLf => "dos",
+      LineEnding::Cr => "mac",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "unix" => Some(LineEnding::Lf),
+      "dos" => Some(LineEnding::CrLf),
+      "mac" => Some(LineEnding::Cr),
+      _ => None,
+    }
+  }
+
+  /// The line ending used throughout `chars`, `None` if it mixes several. Text without line
+  /// breaks is taken as [`LineEnding::Lf`].
+  pub fn detect(chars: impl Iterator<Item = char>) -> Option<Self> {
+    let mut found = None;
+    let mut chars = chars.peekable();
+    while let Some(char) = chars.next() {
+      let line_ending = match char {
+        '\r' if chars.next_if_eq(&'\n').is_some() => LineEnding::CrLf,
+        '\r' => LineEnding::Cr,
+        '\n' => LineEnding::Lf,
+        _ => continue,
+      };
+      match found {
+        Some(found) if found != line_ending => return None,
+        _ => found = Some(line_ending),
+      }
+    }
+    Some(found.unwrap_or(LineEnding::Lf))
+  }
+}
+
+/// How a file is encoded on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileEncoding {
+  pub encoding: &'static Encoding,
+  /// Whether the file starts with a byte order mark.
+  pub bom: bool,
+}
+
+impl Default for FileEncoding {
+  fn default() -> Self {
+    Self { encoding: UTF_8, bom: false }
+  }
+}
+
+impl FileEncoding {
+  /// Looks up an encoding by the names `:set fileencoding` and `.editorconfig` use, like
+  /// `utf-8-bom`, `latin1` or `shift_jis`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    let name = name.to_lowercase();
+    let (name, bom) = match name.strip_suffix("-bom") {
+      Some(name) => (name, true),
+      None => (name.as_str(), false),
+    };
+    let encoding = Encoding::for_label(name.as_bytes())?;
+    // UTF-16 files are always written with a byte order mark.
+    let bom = bom || encoding == UTF_16LE || encoding == UTF_16BE;
+    Some(Self { encoding, bom })
+  }
+
+  pub fn name(&self) -> String {
+    let name = match self.encoding == WINDOWS_1252 {
+      true => "latin1".to_string(),
+      false => self.encoding.name().to_lowercase(),
+    };
+    match self.bom && self.encoding == UTF_8 {
+      true => f!("{name}-bom"),
+      false => name,
+    }
+  }
+
+  /// Guesses the encoding of a file starting with `bytes`. Without a byte order mark, text that
+  /// isn't UTF-8 is taken as Shift-JIS if it reads as Japanese, and as Latin-1 otherwise. Unless
+  /// `last`, more of the file follows, so `bytes` may end in the middle of a char.
+  pub fn sniff(bytes: &[u8], last: bool) -> Self {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+      return Self { encoding, bom: true };
+    }
+    match std::str::from_utf8(bytes) {
+      Ok(_) => return Self::default(),
+      Err(err) if !last && err.error_len().is_none() => return Self::default(),
+      Err(_) => {}
+    }
+
+    let mut decoder = SHIFT_JIS.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(0));
+    let (result, _) = decoder.decode_to_string_without_replacement(bytes, &mut text, last);
+    let japanese = text.chars().any(|char| ('\u{3040}'..='\u{30ff}').contains(&char));
+    match (result, japanese) {
+      (DecoderResult::InputEmpty, true) => Self { encoding: SHIFT_JIS, bom: false },
+      _ => Self { encoding: WINDOWS_1252, bom: false },
+    }
+  }
+
+  /// Decodes a whole file, see [`FileEncoding::sniff`].
+  pub fn decode(bytes: &[u8]) -> (String, Self) {
+    let encoding = Self::sniff(bytes, true);
+    let (text, _) = encoding.encoding.decode_with_bom_removal(bytes);
+    (text.into_owned(), encoding)
+  }
+
+  pub fn bom(&self) -> &'static [u8] {
+    match (self.bom, self.encoding) {
+      (false, _) => &[],
+      (true, encoding) if encoding == UTF_16LE => &[0xFF, 0xFE],
+      (true, encoding) if encoding == UTF_16BE => &[0xFE, 0xFF],
+      (true, _) => &[0xEF, 0xBB, 0xBF],
+    }
+  }
+
+  /// Encodes a piece of text, without the byte order mark.
+  pub fn encode<'a>(&self, text: &'a str) -> Result<Cow<'a, [u8]>> {
+    // encoding_rs only decodes UTF-16.
+    if self.encoding == UTF_16LE {
+      return Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect());
+    } else if self.encoding == UTF_16BE {
+      return Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect());
+    }
+
+    let (encoded, _, unmappable) = self.encoding.encode(text);
+    if unmappable {
+      return Err(f!("Some characters can't be written as {}", self.name()).into());
+    }
+    Ok(encoded)
+  }
+}
+
+/// Settings of a single buffer. They start out from the [`Config`] and what the file was detected
+/// to use, and can be overridden per file, e.g. by `.editorconfig`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BufferOptions {
+  pub indent: IndentConfig,
+  /// Line endings written on save, the buffer's own are kept if `None`.
+  pub line_ending: Option<LineEnding>,
+  pub encoding: FileEncoding,
+  pub trim_trailing_whitespace: bool,
+  pub insert_final_newline: bool,
+  /// Where a ruler is drawn.
+  pub max_line_length: Option<usize>,
+}
+
+impl Default for BufferOptions {
+  fn default() -> Self {
+    Self {
+      indent: IndentConfig::default(),
+      line_ending: Some(LineEnding::Lf),
+      encoding: FileEncoding::default(),
+      trim_trailing_whitespace: false,
+      insert_final_newline: false,
+      max_line_length: None,
+    }
+  }
+}
+
+impl BufferOptions {
+  pub fn new(config: &Config) -> Self {
+    Self { indent: config.indent, ..Default::default() }
+  }
+
+  /// The edits to make before saving `rope`, last one first so they can be applied in order.
+  pub fn save_edits(&self, rope: &Rope) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    if self.insert_final_newline && rope.len_chars() > 0 {
+      let last = rope.char(rope.len_chars() - 1);
+      if last != '\n' && last != '\r' {
+        let newline = self.line_ending.unwrap_or(LineEnding::Lf).as_str();
+        edits.push(Edit::insert(rope.len_chars(), newline));
+      }
+    }
+
+    if self.trim_trailing_whitespace {
+      for line_idx in (0..rope.len_lines()).rev() {
+        let line = rope.line(line_idx).to_string();
+        let content = line.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim_end_matches([' ', '\t']);
+        if trimmed.len() < content.len() {
+          let from = rope.line_to_char(line_idx) + trimmed.chars().count();
+          edits.push(Edit::remove(from, from + content[trimmed.len()..].chars().count()));
+        }
+      }
+    }
+    edits
+  }
+
+  /// Streams `rope` to `writer` the way it's stored on disk, converting line endings and encoding
+  /// one chunk at a time.
+  pub fn write(&self, rope: &Rope, writer: &mut impl Write) -> Result<()> {
+    writer.write_all(self.encoding.bom())?;
+
+    let mut piece = String::new();
+    // A "\r" ending the previous chunk, whose "\n" was already written.
+    let mut after_cr = false;
+    for chunk in rope.chunks() {
+      let Some(line_ending) = self.line_ending else {
+        writer.write_all(&self.encoding.encode(chunk)?)?;
+        continue;
+      };
+
+      piece.clear();
+      for char in chunk.chars() {
+        if std::mem::take(&mut after_cr) && char == '\n' {
+          continue;
+        }
+        match char {
+          '\r' => {
+            piece.push_str(line_ending.as_str());
+            after_cr = true;
+          }
+          '\n' => piece.push_str(line_ending.as_str()),
+          char => piece.push(char),
+        }
+      }
+      writer.write_all(&self.encoding.encode(&piece)?)?;
+    }
+    Ok(())
+  }
+}
+
+impl OptionStore for BufferOptions {
+  fn get(&self, name: &str) -> Option<OptionValue> {
+    let value = match name {
+      "tabstop" => OptionValue::Number(self.indent.tab_width),
+      "shiftwidth" => OptionValue::Number(self.indent.shift_width),
+      "expandtab" => OptionValue::Bool(self.indent.expand_tab),
+      "textwidth" => OptionValue::Number(self.max_line_length.unwrap_or(0)),
+      "fileformat" => {
+        let name = self.line_ending.map(|line_ending| line_ending.name()).unwrap_or("mixed");
+        OptionValue::String(name.to_string())
+      }
+      "fileencoding" => OptionValue::String(self.encoding.name()),
+      "fixendofline" => OptionValue::Bool(self.insert_final_newline),
+      "trimtrailingwhitespace" => OptionValue::Bool(self.trim_trailing_whitespace),
+      _ => return None,
+    };
+    Some(value)
+  }
+
+  fn set(&mut self, name: &str, value: OptionValue) -> Result<()> {
+    let at_least_one = |value: usize| match value {
+      0 => Err(f!("{name} must be at least 1")),
+      value => Ok(value),
+    };
+
+    match (name, value) {
+      ("tabstop", OptionValue::Number(value)) => self.indent.tab_width = at_least_one(value)?,
+      ("shiftwidth", OptionValue::Number(value)) => self.indent.shift_width = at_least_one(value)?,
+      ("expandtab", OptionValue::Bool(value)) => self.indent.expand_tab = value,
+      // Like vim, 0 turns it off.
+      ("textwidth", OptionValue::Number(value)) => {
+        self.max_line_length = (value > 0).then_some(value)
+      }
+      ("fileformat", OptionValue::String(value)) => {
+        let line_ending = LineEnding::from_name(&value)
+          .ok_or_else(|| f!("fileformat must be unix, dos or mac, got '{value}'"))?;
+        self.line_ending = Some(line_ending);
+      }
+      ("fileencoding", OptionValue::String(value)) => {
+        self.encoding =
+          FileEncoding::from_name(&value).ok_or_else(|| f!("Unknown encoding: {value}"))?;
+      }
+      ("fixendofline", OptionValue::Bool(value)) => self.insert_final_newline = value,
+      ("trimtrailingwhitespace", OptionValue::Bool(value)) => self.trim_trailing_whitespace = value,
+      (name, value) => return Err(f!("Invalid value for {name}: {value}").into()),
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_file_format() {
+    let options = BufferOptions {
+      line_ending: Some(LineEnding::CrLf),
+      encoding: FileEncoding::from_name("latin1").unwrap(),
+      trim_trailing_whitespace: true,
+      insert_final_newline: true,
+      ..Default::default()
+    };
+    let mut rope = Rope::from_str("a  \r\nb\t\nc é ");
+    for edit in options.save_edits(&rope) {
+      rope.remove(edit.from..edit.to);
+      rope.insert(edit.from, &edit.text);
+    }
+    assert_eq!(rope.to_string(), "a\r\nb\nc é\r\n");
+    let mut bytes = Vec::new();
+    options.write(&rope, &mut bytes).unwrap();
+    assert_eq!(bytes, b"a\r\nb\r\nc \xE9\r\n");
+    assert_eq!(LineEnding::detect(rope.chars()), None);
+    assert_eq!(LineEnding::detect("a\r\nb\r\n".chars()), Some(LineEnding::CrLf));
+
+    let decoded = |bytes: &[u8]| {
+      let (text, encoding) = FileEncoding::decode(bytes);
+      (text, encoding.name())
+    };
+    assert_eq!(decoded(b"\xFF\xFEa\x00"), ("a".to_string(), "utf-16le".to_string()));
+    assert_eq!(decoded(b"\x82\xA0"), ("\u{3042}".to_string(), "shift_jis".to_string()));
+    assert_eq!(decoded(b"caf\xE9"), ("caf\u{e9}".to_string(), "latin1".to_string()));
+    // A chunk of a large file may end inside a char.
+    assert_eq!(FileEncoding::sniff(&"\u{e9}".as_bytes()[..1], false), FileEncoding::default());
+  }
+}
@@ -0,0 +1,327 @@
+//! Settings that can be changed while running with `:set`. Every option has a scope: global
+//! options live on the [`Editor`](crate::editor::Editor), window options on each frame and buffer
+//! options on each buffer. Local options also have a global value that new frames and buffers
+//! start out with.
+
+pub mod buffer;
+pub mod window;
+
+use std::{fmt::Display, path::Path};
+
+use teddy_config::Config;
+
+use buffer::BufferOptions;
+use window::WindowOptions;
+
+use crate::prelude::{f, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionScope {
+  Global,
+  Window,
+  Buffer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+  Bool,
+  Number,
+  String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionValue {
+  Bool(bool),
+  Number(usize),
+  String(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OptionDef {
+  pub name: &'static str,
+  pub short: Option<&'static str>,
+  pub scope: OptionScope,
+  pub kind: OptionKind,
+}
+
+const fn option(
+  name: &'static str,
+  short: Option<&'static str>,
+  scope: OptionScope,
+  kind: OptionKind,
+) -> OptionDef {
+  OptionDef { name, short, scope, kind }
+}
+
+pub const OPTIONS: &[OptionDef] = {
+  use OptionKind::*;
+  use OptionScope::*;
+  &[
+    option("scrolloff", Some("so"), Global, Number),
+    option("autocomplete", Some("ac"), Global, Bool),
+    option("largefile", Some("lf"), Global, Number),
+    option("updatetime", Some("ut"), Global, Number),
+    option("number", Some("nu"), Window, Bool),
+    option("relativenumber", Some("rnu"), Window, Bool),
+    option("wrap", None, Window, Bool),
+    option("cursorline", Some("cul"), Window, Bool),
+    option("list", None, Window, Bool),
+    option("signcolumn", Some("scl"), Window, String),
+    option("foldmethod", Some("fdm"), Window, String),
+    option("tabstop", Some("ts"), Buffer, Number),
+    option("shiftwidth", Some("sw"), Buffer, Number),
+    option("expandtab", Some("et"), Buffer, Bool),
+    option("textwidth", Some("tw"), Buffer, Number),
+    option("fileformat", Some("ff"), Buffer, String),
+    option("fileencoding", Some("fenc"), Buffer, String),
+    option("fixendofline", Some("fixeol"), Buffer, Bool),
+    option("trimtrailingwhitespace", Some("trim"), Buffer, Bool),
+  ]
+};
+
+impl OptionDef {
+  /// Finds an option by its full or short name.
+  pub fn lookup(name: &str) -> Option<&'static OptionDef> {
+    OPTIONS.iter().find(|option| option.name == name || option.short == Some(name))
+  }
+}
+
+impl Display for OptionValue {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      OptionValue::Bool(value) => write!(f, "{value}"),
+      OptionValue::Number(value) => write!(f, "{value}"),
+      OptionValue::String(value) => write!(f, "{value}"),
+    }
+  }
+}
+
+/// Options that can be read and written by name.
+pub trait OptionStore {
+  /// `None` if the option isn't stored here.
+  fn get(&self, name: &str) -> Option<OptionValue>;
+  /// Sets an option to a value of its kind, failing for values it doesn't accept.
+  fn set(&mut self, name: &str, value: OptionValue) -> Result<()>;
+}
+
+/// One argument of `:set`.
+#[derive(Debug, PartialEq)]
+pub enum SetArgument {
+  /// `name?`, or `name` for options that aren't booleans.
+  Show(&'static OptionDef),
+  /// `name`, `noname` and `name=value`.
+  Set(&'static OptionDef, OptionValue),
+  /// `name!` and `invname`.
+  Toggle(&'static OptionDef),
+}
+
+impl SetArgument {
+  pub fn parse(argument: &str) -> Result<Self> {
+    let lookup = |name: &str| OptionDef::lookup(name).ok_or_else(|| f!("Unknown option: {name}"));
+
+    if let Some((name, value)) = argument.split_once(['=', ':']) {
+      let option = lookup(name)?;
+      let value = match option.kind {
+        OptionKind::Bool => return Err(f!("{} doesn't take a value", option.name).into()),
+        OptionKind::Number => OptionValue::Number(
+          value.parse().map_err(|_| f!("{} expects a number, got '{value}'", option.name))?,
+        ),
+        OptionKind::String => OptionValue::String(value.to_string()),
+      };
+      return Ok(SetArgument::Set(option, value));
+    }
+    if let Some(name) = argument.strip_suffix('?') {
+      return Ok(SetArgument::Show(lookup(name)?));
+    }
+    if let Some(option) = OptionDef::lookup(argument) {
+      return Ok(match option.kind {
+        OptionKind::Bool => SetArgument::Set(option, OptionValue::Bool(true)),
+        _ => SetArgument::Show(option),
+      });
+    }
+
+    let boolean = |name: &str| -> Result<&'static OptionDef> {
+      let option = lookup(name)?;
+      match option.kind {
+        OptionKind::Bool => Ok(option),
+        _ => Err(f!("{} isn't a boolean option", option.name).into()),
+      }
+    };
+    if let Some(name) = argument.strip_suffix('!').or_else(|| argument.strip_prefix("inv")) {
+      return Ok(SetArgument::Toggle(boolean(name)?));
+    }
+    match argument.strip_prefix("no") {
+      Some(name) => Ok(SetArgument::Set(boolean(name)?, OptionValue::Bool(false))),
+      None => Err(f!("Unknown option: {argument}").into()),
+    }
+  }
+
+  pub fn option(&self) -> &'static OptionDef {
+    match self {
+      SetArgument::Show(option) | SetArgument::Set(option, _) | SetArgument::Toggle(option) => {
+        option
+      }
+    }
+  }
+}
+
+/// Options without a local value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlobalOptions {
+  /// Lines kept visible above and below the cursor.
+  pub scrolloff: usize,
+  /// Open the completion menu while typing.
+  pub autocomplete: bool,
+  /// Files of at least this many MiB load in the background, without language servers and
+  /// completion.
+  pub largefile: usize,
+  /// Milliseconds between swap file snapshots.
+  pub updatetime: usize,
+}
+
+impl Default for GlobalOptions {
+  fn default() -> Self {
+    Self { scrolloff: 3, autocomplete: true, largefile: 50, updatetime: 4000 }
+  }
+}
+
+impl OptionStore for GlobalOptions {
+  fn get(&self, name: &str) -> Option<OptionValue> {
+    let value = match name {
+      "scrolloff" => OptionValue::Number(self.scrolloff),
+      "autocomplete" => OptionValue::Bool(self.autocomplete),
+      "largefile" => OptionValue::Number(self.largefile),
+      "updatetime" => OptionValue::Number(self.updatetime),
+      _ => return None,
+    };
+    Some(value)
+  }
+
+  fn set(&mut self, name: &str, value: OptionValue) -> Result<()> {
+    match (name, value) {
+      ("scrolloff", OptionValue::Number(value)) => self.scrolloff = value,
+      ("autocomplete", OptionValue::Bool(value)) => self.autocomplete = value,
+      ("largefile", OptionValue::Number(value)) => self.largefile = value,
+      ("updatetime", OptionValue::Number(value)) => self.updatetime = value,
+      (name, value) => return Err(f!("Invalid value for {name}: {value}").into()),
+    }
+    Ok(())
+  }
+}
+
+/// The global value of every option.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+  pub global: GlobalOptions,
+  /// Given to new frames.
+  pub window: WindowOptions,
+  /// Given to new buffers.
+  pub buffer: BufferOptions,
+}
+
+impl Options {
+  pub fn new(config: &Config) -> Self {
+    Self { buffer: BufferOptions::new(config), ..Default::default() }
+  }
+
+  /// Runs one `:set` argument. `window` and `buffer` are the local options of the active frame,
+  /// the global values are left alone when `local` (`:setlocal`). Returns a message to show.
+  pub fn set(
+    &mut self,
+    argument: &str,
+    local: bool,
+    window: Option<&mut WindowOptions>,
+    buffer: Option<&mut BufferOptions>,
+  ) -> Result<Option<String>> {
+    let argument = SetArgument::parse(argument)?;
+    let option = argument.option();
+    let (local_store, global_store): (Option<&mut dyn OptionStore>, &mut dyn OptionStore) =
+      match option.scope {
+        OptionScope::Global => (None, &mut self.global),
+        OptionScope::Window => (window.map(|w| w as &mut dyn OptionStore), &mut self.window),
+        OptionScope::Buffer => (buffer.map(|b| b as &mut dyn OptionStore), &mut self.buffer),
+      };
+    if local && option.scope != OptionScope::Global && local_store.is_none() {
+      return Err("There's no buffer to set a local option for".into());
+    }
+
+    let current = match &local_store {
+      Some(store) => store.get(option.name),
+      None => global_store.get(option.name),
+    };
+    let current = current.ok_or_else(|| f!("{} isn't stored anywhere", option.name))?;
+    let value = match argument {
+      SetArgument::Show(option) => {
+        return Ok(Some(match current {
+          OptionValue::Bool(true) => option.name.to_string(),
+          OptionValue::Bool(false) => f!("no{}", option.name),
+          value => f!("{}={value}", option.name),
+        }));
+      }
+      SetArgument::Set(_, value) => value,
+      SetArgument::Toggle(_) => OptionValue::Bool(current != OptionValue::Bool(true)),
+    };
+
+    if let Some(store) = local_store {
+      store.set(option.name, value.clone())?;
+    }
+    if !local || option.scope == OptionScope::Global {
+      global_store.set(option.name, value)?;
+    }
+    Ok(None)
+  }
+
+  /// Sets the global values from a file of `:set` lines, like `set number tabstop=4`. Returns
+  /// what went wrong, line by line.
+  pub fn load(&mut self, path: &Path) -> Vec<String> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+      return Vec::new();
+    };
+
+    let mut errors = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with(['#', '"']) {
+        continue;
+      }
+      let arguments = line.strip_prefix("set ").unwrap_or(line);
+      for argument in arguments.split_whitespace() {
+        if let Err(err) = self.set(argument, false, None, None) {
+          errors.push(f!("{}:{}: {err}", path.display(), idx + 1));
+        }
+      }
+    }
+    errors
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_set() {
+    let mut options = Options::default();
+    let mut window = WindowOptions::default();
+    let mut buffer = BufferOptions::default();
+    let mut set = |argument, local| {
+      options.set(argument, local, Some(&mut window), Some(&mut buffer)).map_err(|e| e.to_string())
+    };
+
+    assert_eq!(set("nonu", false), Ok(None));
+    assert_eq!(set("nu?", false), Ok(Some("nonumber".to_string())));
+    assert_eq!(set("ts=2", true), Ok(None));
+    assert_eq!(set("ts", false), Ok(Some("tabstop=2".to_string())));
+    assert_eq!(set("list!", false), Ok(None));
+    assert_eq!(set("ts=x", false), Err("tabstop expects a number, got 'x'".to_string()));
+    assert_eq!(set("sw=0", false), Err("shiftwidth must be at least 1".to_string()));
+    assert_eq!(set("ff=dos", false), Ok(None));
+    assert!(set("noff", false).is_err());
+    assert!(set("bogus", false).is_err());
+
+    assert!(!window.number && window.list);
+    assert_eq!((buffer.indent.tab_width, options.buffer.indent.tab_width), (2, 4));
+    assert!(!options.window.number && options.window.list);
+    assert_eq!(options.buffer.line_ending, buffer.line_ending);
+  }
+}
@@ -0,0 +1,114 @@
+use super::{OptionStore, OptionValue};
+use crate::prelude::{f, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignColumn {
+  Yes,
+  No,
+  /// Only shown while the buffer has diagnostics.
+  Auto,
+}
+
+impl SignColumn {
+  pub fn name(&self) -> &'static str {
+    match self {
+      SignColumn::Yes => "yes",
+      SignColumn::No => "no",
+      SignColumn::Auto => "auto",
+    }
+  }
+}
+
+/// Where folds come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldMethod {
+  /// Only folds made with `zf`.
+  Manual,
+  /// Lines indented more than the line above them.
+  Indent,
+  /// Functions, types and other items of the parse tree.
+  Syntax,
+}
+
+impl FoldMethod {
+  pub fn name(&self) -> &'static str {
+    match self {
+      FoldMethod::Manual => "manual",
+      FoldMethod::Indent => "indent",
+      FoldMethod::Syntax => "syntax",
+    }
+  }
+}
+
+/// How a frame shows its buffer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowOptions {
+  pub number: bool,
+  /// Line numbers relative to the cursor line.
+  pub relativenumber: bool,
+  /// Wrap long lines instead of cutting them off.
+  pub wrap: bool,
+  pub cursorline: bool,
+  /// Show tabs, trailing spaces and non-breaking spaces.
+  pub list: bool,
+  pub signcolumn: SignColumn,
+  pub foldmethod: FoldMethod,
+}
+
+impl Default for WindowOptions {
+  fn default() -> Self {
+    Self {
+      number: true,
+      relativenumber: false,
+      wrap: false,
+      cursorline: false,
+      list: false,
+      signcolumn: SignColumn::Yes,
+      foldmethod: FoldMethod::Manual,
+    }
+  }
+}
+
+impl OptionStore for WindowOptions {
+  fn get(&self, name: &str) -> Option<OptionValue> {
+    let value = match name {
+      "number" => OptionValue::Bool(self.number),
+      "relativenumber" => OptionValue::Bool(self.relativenumber),
+      "wrap" => OptionValue::Bool(self.wrap),
+      "cursorline" => OptionValue::Bool(self.cursorline),
+      "list" => OptionValue::Bool(self.list),
+      "signcolumn" => OptionValue::String(self.signcolumn.name().to_string()),
+      "foldmethod" => OptionValue::String(self.foldmethod.name().to_string()),
+      _ => return None,
+    };
+    Some(value)
+  }
+
+  fn set(&mut self, name: &str, value: OptionValue) -> Result<()> {
+    match (name, value) {
+      ("number", OptionValue::Bool(value)) => self.number = value,
+      ("relativenumber", OptionValue::Bool(value)) => self.relativenumber = value,
+      ("wrap", OptionValue::Bool(value)) => self.wrap = value,
+      ("cursorline", OptionValue::Bool(value)) => self.cursorline = value,
+      ("list", OptionValue::Bool(value)) => self.list = value,
+      ("signcolumn", OptionValue::String(value)) => {
+        self.signcolumn = match value.as_str() {
+          "yes" => SignColumn::Yes,
+          "no" => SignColumn::No,
+          "auto" => SignColumn::Auto,
+          _ => return Err(f!("signcolumn must be yes, no or auto, got '{value}'").into()),
+        }
+      }
+      ("foldmethod", OptionValue::String(value)) => {
+        self.foldmethod = match value.as_str() {
+          "manual" => FoldMethod::Manual,
+          "indent" => FoldMethod::Indent,
+          "syntax" => FoldMethod::Syntax,
+          _ => return Err(f!("foldmethod must be manual, indent or syntax, got '{value}'").into()),
+        }
+      }
+      (name, value) => return Err(f!("Invalid value for {name}: {value}").into()),
+    }
+    Ok(())
+  }
+}
@@ -6,6 +6,7 @@ pub fn install() -> Result<(), Box<dyn Error>> {
   color_eyre::install()?;
   let default_hook = panic::take_hook();
   panic::set_hook(Box::new(move |info| {
+    teddy::swap::flush();
     if let Err(e) = tui::restore() {
       eprintln!("Failed to restore terminal settings: {:?}", e);
     }
@@ -0,0 +1,193 @@
+//! Sessions: the open files and where the cursor was in each, the diff split, the register, the
+//! global marks and the command line history, saved with `:mksession` and restored with `:source`
+//! or on startup.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use teddy_config::Config;
+
+use crate::editor::{marks::Location, register::Register};
+use crate::frame::folds::Fold;
+use crate::prelude::{f, Result};
+
+/// Bumped whenever the format changes in a way older versions can't read.
+pub const VERSION: u64 = 2;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Session {
+  pub buffers: Vec<SessionBuffer>,
+  /// Index into `buffers` of the focused one.
+  pub active: Option<usize>,
+  /// The files side by side in diff mode, the left one first.
+  pub diff: Option<(PathBuf, PathBuf)>,
+  pub register: Register,
+  /// The marks `A` to `Z`.
+  pub marks: Vec<(char, Location)>,
+  /// The command lines run, the oldest first.
+  pub commands: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SessionBuffer {
+  pub path: PathBuf,
+  pub line: usize,
+  pub column: usize,
+  /// The first line on screen.
+  pub scroll: usize,
+  pub folds: Vec<Fold>,
+}
+
+impl Session {
+  /// The session of the working directory, kept in the state directory.
+  pub fn default_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    let name = cwd.to_string_lossy().replace(['/', '\\'], "%");
+    Some(Config::state_dir()?.join("sessions").join(f!("{name}.json")))
+  }
+
+  pub fn load(path: &Path) -> Result<Self> {
+    Self::parse(&std::fs::read_to_string(path)?)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+      std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&self.to_json())?)?;
+    Ok(())
+  }
+
+  fn to_json(&self) -> Value {
+    let buffers: Vec<Value> = self
+      .buffers
+      .iter()
+      .map(|buffer| {
+        json!({
+          "path": buffer.path,
+          "line": buffer.line,
+          "column": buffer.column,
+          "scroll": buffer.scroll,
+          "folds": buffer.folds.iter().map(|fold| [fold.start, fold.end, fold.closed as usize]).collect::<Vec<_>>(),
+        })
+      })
+      .collect();
+    let marks: Vec<Value> = self
+      .marks
+      .iter()
+      .map(|(mark, location)| json!([mark, location.path, location.char_idx]))
+      .collect();
+    json!({
+      "version": VERSION,
+      "buffers": buffers,
+      "active": self.active,
+      "diff": self.diff,
+      "register": { "texts": self.register.texts, "linewise": self.register.linewise },
+      "marks": marks,
+      "commands": self.commands,
+    })
+  }
+
+  pub fn parse(text: &str) -> Result<Self> {
+    let value: Value = serde_json::from_str(text)?;
+    match value["version"].as_u64() {
+      // The first version only has the buffers, the rest is left empty.
+      Some(1 | VERSION) => {}
+      Some(version) => return Err(f!("Session version {version} isn't supported").into()),
+      None => return Err("Not a session file".into()),
+    }
+
+    let number = |value: &Value, key: &str| value[key].as_u64().unwrap_or(0) as usize;
+    let fold = |fold: &Value| {
+      let [start, end, closed] = [0, 1, 2].map(|idx| fold[idx].as_u64());
+      Some(Fold { start: start? as usize, end: end? as usize, closed: closed? != 0 })
+    };
+    let buffers = value["buffers"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let buffers = buffers
+      .iter()
+      .filter_map(|buffer| {
+        Some(SessionBuffer {
+          path: PathBuf::from(buffer["path"].as_str()?),
+          line: number(buffer, "line"),
+          column: number(buffer, "column"),
+          scroll: number(buffer, "scroll"),
+          folds: buffer["folds"]
+            .as_array()
+            .map(|folds| folds.iter().filter_map(fold).collect())
+            .unwrap_or_default(),
+        })
+      })
+      .collect();
+    let active = value["active"].as_u64().map(|active| active as usize);
+    let path = |value: &Value| value.as_str().map(PathBuf::from);
+    let diff = path(&value["diff"][0]).zip(path(&value["diff"][1]));
+    let strings = |value: &Value| -> Vec<String> {
+      let values = value.as_array().map(Vec::as_slice).unwrap_or_default();
+      values.iter().filter_map(|value| value.as_str().map(String::from)).collect()
+    };
+    let marks = value["marks"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let marks = marks
+      .iter()
+      .filter_map(|mark| {
+        let name = mark[0].as_str()?.chars().next()?;
+        Some((name, Location::new(path(&mark[1])?, mark[2].as_u64()? as usize)))
+      })
+      .collect();
+    Ok(Self {
+      buffers,
+      active,
+      diff,
+      register: Register {
+        texts: strings(&value["register"]["texts"]),
+        linewise: value["register"]["linewise"].as_bool().unwrap_or(false),
+      },
+      marks,
+      commands: strings(&value["commands"]),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_session() {
+    let session = Session {
+      buffers: Vec::from_iter([
+        SessionBuffer {
+          path: PathBuf::from("/a.rs"),
+          line: 3,
+          column: 1,
+          scroll: 0,
+          folds: Vec::new(),
+        },
+        SessionBuffer {
+          path: PathBuf::from("/b.rs"),
+          line: 40,
+          column: 0,
+          scroll: 20,
+          folds: Vec::from_iter([
+            Fold { start: 2, end: 9, closed: true },
+            Fold { start: 4, end: 6, closed: false },
+          ]),
+        },
+      ]),
+      active: Some(1),
+      diff: Some((PathBuf::from("/a.rs"), PathBuf::from("/b.rs"))),
+      register: Register {
+        texts: Vec::from_iter(["foo\n".to_string(), "bar\n".to_string()]),
+        linewise: true,
+      },
+      marks: Vec::from_iter([('A', Location::new(PathBuf::from("/b.rs"), 120))]),
+      commands: Vec::from_iter(["w".to_string(), "set nowrap".to_string()]),
+    };
+    let text = serde_json::to_string(&session.to_json()).unwrap();
+    assert_eq!(Session::parse(&text).unwrap(), session);
+
+    let first = Session::parse(r#"{ "version": 1, "buffers": [{ "path": "/a.rs" }] }"#).unwrap();
+    assert_eq!((first.buffers.len(), first.diff, first.commands.len()), (1, None, 0));
+    let error = Session::parse(r#"{ "version": 99 }"#).unwrap_err();
+    assert_eq!(error.to_string(), "Session version 99 isn't supported");
+  }
+}
@@ -0,0 +1,160 @@
+//! Shell commands run from the command line: `:!cmd`, `:r !cmd` and `:{range}!cmd`. They run in
+//! the background on tokio and report back as [`ShellEvent`]s, until they're cancelled.
+
+use std::{collections::BTreeMap, ops::Range, process::Stdio};
+
+use teddy_events::{Event, ShellEvent};
+use tokio::{io::AsyncWriteExt, process::Command, sync::mpsc::UnboundedSender, task::AbortHandle};
+
+use crate::prelude::{f, Result};
+
+/// What to do with the output of a command once it's done.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+  Show,
+  /// Insert it below `line` of frame `frame`, if the buffer is still at `revision`.
+  Read {
+    frame: u16,
+    line: usize,
+    revision: u64,
+  },
+  /// Replace `lines` of frame `frame` with it, if the buffer is still at `revision`.
+  Filter {
+    frame: u16,
+    lines: Range<usize>,
+    revision: u64,
+  },
+}
+
+pub struct Job {
+  pub command: String,
+  pub target: Target,
+  handle: AbortHandle,
+}
+
+/// The shell commands still running.
+#[derive(Default)]
+pub struct ShellJobs {
+  next: u64,
+  jobs: BTreeMap<u64, Job>,
+}
+
+impl ShellJobs {
+  /// Starts `command` with `input` on its stdin, and returns the id its [`ShellEvent`] will have.
+  pub fn start(
+    &mut self,
+    command: &str,
+    input: Option<String>,
+    target: Target,
+    events: &UnboundedSender<Event>,
+  ) -> u64 {
+    self.next += 1;
+    let id = self.next;
+    let (shell_command, events) = (command.to_string(), events.clone());
+    let task = tokio::spawn(async move {
+      let event = match run(&shell_command, input).await {
+        Ok((success, stdout, stderr)) => ShellEvent::Finished { id, success, stdout, stderr },
+        Err(err) => ShellEvent::Failed { id, error: f!("{err}") },
+      };
+      let _ = events.send(Event::Shell(event));
+    });
+    let job = Job { command: command.to_string(), target, handle: task.abort_handle() };
+    self.jobs.insert(id, job);
+    id
+  }
+
+  /// Takes the job of a [`ShellEvent`], unless it was cancelled since.
+  pub fn finish(&mut self, id: u64) -> Option<Job> {
+    self.jobs.remove(&id)
+  }
+
+  /// Stops every running command, killing its process.
+  pub fn cancel_all(&mut self) -> Vec<(u64, Job)> {
+    let jobs = std::mem::take(&mut self.jobs);
+    jobs.values().for_each(|job| job.handle.abort());
+    jobs.into_iter().collect()
+  }
+}
+
+/// Runs `command` with `sh -c`, and returns whether it succeeded and what it wrote.
+async fn run(command: &str, input: Option<String>) -> Result<(bool, String, String)> {
+  let mut child = Command::new("sh")
+    .arg("-c")
+    .arg(command)
+    .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    // Dropped when the task is aborted, which kills it.
+    .kill_on_drop(true)
+    .spawn()?;
+  if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+    // Written alongside reading the output, so a full pipe can't block either side.
+    tokio::spawn(async move {
+      let _ = stdin.write_all(input.as_bytes()).await;
+    });
+  }
+
+  let output = child.wait_with_output().await?;
+  let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+  let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+  Ok((output.status.success(), stdout, stderr))
+}
+
+/// The lines of a command line range like `%`, `5`, `.,$` or `.,.+3`, with `line` as `.` and
+/// `last` as `$`, all counted from zero.
+pub fn parse_range(range: &str, line: usize, last: usize) -> Result<Range<usize>> {
+  let range = range.trim();
+  if range == "%" {
+    return Ok(0..last + 1);
+  }
+  let (start, end) = range.split_once(',').unwrap_or((range, range));
+  let (start, end) = (address(start, line, last)?, address(end, line, last)?);
+  if start.max(end) > last {
+    return Err(f!("Invalid range: {range}").into());
+  }
+  Ok(start.min(end)..start.max(end) + 1)
+}
+
+/// One side of a range: a line number, `.` or `$`, then any number of `+n` and `-n` offsets.
+fn address(address: &str, line: usize, last: usize) -> Result<usize> {
+  let invalid = || f!("Invalid address: {address}");
+  let address = address.trim();
+  let offsets_at = address.find(['+', '-']).unwrap_or(address.len());
+  let (base, mut offsets) = address.split_at(offsets_at);
+  let mut result = match base {
+    "" | "." => line as isize,
+    "$" => last as isize,
+    number => number.parse::<isize>().map_err(|_| invalid())? - 1,
+  };
+  while let Some(sign) = offsets.chars().next() {
+    let digits = offsets[1..].find(['+', '-']).map_or(offsets.len(), |idx| idx + 1);
+    let amount = match &offsets[1..digits] {
+      "" => 1,
+      number => number.parse::<isize>().map_err(|_| invalid())?,
+    };
+    result += if sign == '+' { amount } else { -amount };
+    offsets = &offsets[digits..];
+  }
+  usize::try_from(result).map_err(|_| invalid().into())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_range() {
+    assert_eq!(parse_range("%", 3, 9).unwrap(), 0..10);
+    assert_eq!(parse_range("5", 3, 9).unwrap(), 4..5);
+    assert_eq!(parse_range(".", 3, 9).unwrap(), 3..4);
+    assert_eq!(parse_range(".,$", 3, 9).unwrap(), 3..10);
+    assert_eq!(parse_range(".,.+2", 3, 9).unwrap(), 3..6);
+    assert_eq!(parse_range(".-1,.", 3, 9).unwrap(), 2..4);
+    assert_eq!(parse_range("$-2,$", 3, 9).unwrap(), 7..10);
+    assert_eq!(parse_range("8,2", 3, 9).unwrap(), 1..8);
+    assert_eq!(parse_range("+,++", 3, 9).unwrap(), 4..6);
+    assert!(parse_range("3,20", 3, 9).is_err());
+    assert!(parse_range("0", 3, 9).is_err());
+    assert!(parse_range("x", 3, 9).is_err());
+  }
+}
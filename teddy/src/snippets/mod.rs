@@ -0,0 +1,254 @@
+pub mod parser;
+pub mod session;
+
+use std::{collections::HashMap, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+  editor::completion::{CompletionContext, CompletionItem, CompletionKind, CompletionSource},
+  prelude::{f, Result},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+  pub name: String,
+  pub prefixes: Vec<String>,
+  pub body: String,
+  pub description: Option<String>,
+  /// Languages the snippet is for, every language if empty.
+  pub scope: Vec<String>,
+}
+
+/// User snippets, loaded from `<config dir>/snippets`. A `<language>.json` file holds the snippets
+/// of one language, and `*.code-snippets` files can name the languages of each snippet with a
+/// `scope` field, just like VS Code.
+#[derive(Default)]
+pub struct Snippets {
+  snippets: Vec<Snippet>,
+  /// Maps file extensions to languages, for the file names that aren't a language id.
+  languages: HashMap<String, String>,
+}
+
+impl Snippets {
+  /// Loads every snippet file in `dir`. Files that fail to parse are skipped and reported.
+  pub fn load(dir: &Path) -> (Self, Vec<String>) {
+    let mut snippets = Self::default();
+    let mut errors = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+      return (snippets, errors);
+    };
+
+    let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+      let language = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => path.file_stem().and_then(|stem| stem.to_str()),
+        Some("code-snippets") => None,
+        _ => continue,
+      };
+
+      let parsed = std::fs::read_to_string(&path)
+        .map_err(Into::into)
+        .and_then(|json| parse_file(&json, language));
+      match parsed {
+        Ok(parsed) => snippets.snippets.extend(parsed),
+        Err(err) => errors.push(f!("Failed to load snippets from {}: {err}", path.display())),
+      }
+    }
+    (snippets, errors)
+  }
+
+  /// Lets `language` snippets be used in files with `extension`.
+  pub fn alias(&mut self, extension: &str, language: &str) {
+    self.languages.insert(extension.to_string(), language.to_string());
+  }
+
+  /// The snippets usable in `path`.
+  pub fn for_path<'a>(&'a self, path: Option<&Path>) -> impl Iterator<Item = &'a Snippet> {
+    let extension = path.and_then(Path::extension).and_then(|ext| ext.to_str()).unwrap_or_default();
+    let language = self.languages.get(extension).map(String::as_str).unwrap_or(extension);
+    let extension = extension.to_string();
+    let language = language.to_string();
+
+    self.snippets.iter().filter(move |snippet| {
+      snippet.scope.is_empty() || snippet.scope.iter().any(|s| *s == language || *s == extension)
+    })
+  }
+}
+
+/// Parses a snippet file. `language` is the scope of snippets without one.
+fn parse_file(json: &str, language: Option<&str>) -> Result<Vec<Snippet>> {
+  let value: Value = serde_json::from_str(&strip_comments(json))?;
+  let Value::Object(entries) = value else {
+    return Err("Expected an object of snippets".into());
+  };
+
+  let strings = |value: &Value| -> Vec<String> {
+    match value {
+      Value::String(string) => Vec::from_iter([string.clone()]),
+      Value::Array(items) => {
+        items.iter().filter_map(|item| item.as_str().map(Into::into)).collect()
+      }
+      _ => Vec::new(),
+    }
+  };
+
+  let snippets = entries.into_iter().filter_map(|(name, snippet)| {
+    let prefixes = strings(&snippet["prefix"]);
+    let body = strings(&snippet["body"]).join("\n");
+    if prefixes.is_empty() || body.is_empty() {
+      return None;
+    }
+    let scope = match snippet["scope"].as_str() {
+      Some(scope) => scope.split(',').map(|s| s.trim().to_string()).collect(),
+      None => language.map(|language| Vec::from_iter([language.to_string()])).unwrap_or_default(),
+    };
+    let description = snippet["description"].as_str().map(Into::into);
+    Some(Snippet { name, prefixes, body, description, scope })
+  });
+  Ok(snippets.collect())
+}
+
+/// Snippet files are JSON with comments.
+fn strip_comments(json: &str) -> String {
+  let mut out = String::with_capacity(json.len());
+  let mut chars = json.chars().peekable();
+  let mut in_string = false;
+
+  while let Some(char) = chars.next() {
+    if in_string {
+      out.push(char);
+      match char {
+        '\\' => out.extend(chars.next()),
+        '"' => in_string = false,
+        _ => {}
+      }
+      continue;
+    }
+
+    match (char, chars.peek()) {
+      ('"', _) => {
+        in_string = true;
+        out.push(char);
+      }
+      ('/', Some('/')) => while chars.next_if(|next| *next != '\n').is_some() {},
+      ('/', Some('*')) => {
+        chars.next();
+        let mut previous = ' ';
+        for next in chars.by_ref() {
+          if previous == '*' && next == '/' {
+            break;
+          }
+          previous = next;
+        }
+      }
+      _ => out.push(char),
+    }
+  }
+  out
+}
+
+/// What snippet variables like `$TM_FILENAME` expand to.
+pub struct SnippetVariables<'a> {
+  pub path: Option<&'a Path>,
+  /// The line the snippet is expanded on, without the newline.
+  pub line: &'a str,
+  /// Zero based.
+  pub line_idx: usize,
+  pub word: &'a str,
+}
+
+impl SnippetVariables<'_> {
+  pub fn resolve(&self, name: &str) -> Option<String> {
+    let now = chrono::Local::now();
+    let file_name = || self.path?.file_name().map(|name| name.to_string_lossy().to_string());
+    let cwd = std::env::current_dir().ok();
+
+    let value = match name {
+      "TM_FILENAME" => file_name()?,
+      "TM_FILENAME_BASE" => self.path?.file_stem()?.to_string_lossy().to_string(),
+      "TM_FILEPATH" => self.path?.display().to_string(),
+      "TM_DIRECTORY" => self.path?.parent()?.display().to_string(),
+      "RELATIVE_FILEPATH" => {
+        let path = self.path?;
+        cwd
+          .as_deref()
+          .and_then(|cwd| path.strip_prefix(cwd).ok())
+          .unwrap_or(path)
+          .display()
+          .to_string()
+      }
+      "WORKSPACE_NAME" => cwd?.file_name()?.to_string_lossy().to_string(),
+      "TM_CURRENT_LINE" => self.line.to_string(),
+      "TM_CURRENT_WORD" => self.word.to_string(),
+      "TM_LINE_INDEX" => self.line_idx.to_string(),
+      "TM_LINE_NUMBER" => (self.line_idx + 1).to_string(),
+      "TM_SELECTED_TEXT" | "CLIPBOARD" => String::new(),
+      "CURRENT_YEAR" => now.format("%Y").to_string(),
+      "CURRENT_YEAR_SHORT" => now.format("%y").to_string(),
+      "CURRENT_MONTH" => now.format("%m").to_string(),
+      "CURRENT_MONTH_NAME" => now.format("%B").to_string(),
+      "CURRENT_MONTH_NAME_SHORT" => now.format("%b").to_string(),
+      "CURRENT_DATE" => now.format("%d").to_string(),
+      "CURRENT_DAY_NAME" => now.format("%A").to_string(),
+      "CURRENT_DAY_NAME_SHORT" => now.format("%a").to_string(),
+      "CURRENT_HOUR" => now.format("%H").to_string(),
+      "CURRENT_MINUTE" => now.format("%M").to_string(),
+      "CURRENT_SECOND" => now.format("%S").to_string(),
+      "CURRENT_SECONDS_UNIX" => now.timestamp().to_string(),
+      _ => return None,
+    };
+    Some(value)
+  }
+}
+
+/// Offers snippets whose prefix matches the word being typed.
+pub struct SnippetSource(pub Snippets);
+
+impl CompletionSource for SnippetSource {
+  fn complete(&mut self, context: &CompletionContext) -> Vec<CompletionItem> {
+    let start = context.word_start();
+    let word = context.word();
+
+    let mut items = Vec::new();
+    for snippet in self.0.for_path(context.path) {
+      for prefix in snippet.prefixes.iter().filter(|prefix| prefix.starts_with(&word)) {
+        let mut item = CompletionItem::new(CompletionKind::Snippet, prefix, start);
+        item.detail = Some(snippet.description.clone().unwrap_or_else(|| snippet.name.clone()));
+        item.documentation = Some(snippet.body.clone());
+        item.insert_text = Some(snippet.body.clone());
+        items.push(item);
+      }
+    }
+    items
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::*;
+
+  #[test]
+  fn test_parse_file() {
+    let json = r#"{
+      // Comments are allowed
+      "Main": { "prefix": ["main", "fn_main"], "body": ["fn main() {", "\t$0", "}"] },
+      "Log": { "prefix": "log", "body": "println!(\"// $1\");", "scope": "rust,python" }
+    }"#;
+    let snippets = parse_file(json, Some("rust")).unwrap();
+    let mut snippets = Snippets { snippets, ..Default::default() };
+    snippets.alias("rs", "rust");
+    snippets.alias("py", "python");
+
+    let names: Vec<&str> = snippets
+      .for_path(Some(&PathBuf::from("main.rs")))
+      .map(|snippet| snippet.name.as_str())
+      .collect();
+    assert_eq!(names, ["Log", "Main"]);
+    assert_eq!(snippets.snippets[0].body, "println!(\"// $1\");");
+    assert_eq!(snippets.for_path(Some(&PathBuf::from("main.py"))).count(), 1);
+  }
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, ops::Range};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+  Text(String),
+  Tabstop { number: usize, children: Vec<Node> },
+  Variable { name: String, default: Vec<Node> },
+}
+
+/// A tabstop and every place it shows up, as char ranges into [`ParsedSnippet::text`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tabstop {
+  pub number: usize,
+  pub ranges: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedSnippet {
+  pub text: String,
+  /// Ordered by number, with `$0` last.
+  pub tabstops: Vec<Tabstop>,
+}
+
+/// Expands a snippet body. `variables` resolves names like `TM_FILENAME`, and every line after
+/// the first is prefixed with `indent`.
+pub fn parse(
+  body: &str,
+  variables: impl Fn(&str) -> Option<String>,
+  indent: &str,
+) -> ParsedSnippet {
+  let chars: Vec<char> = body.chars().collect();
+  let mut parser = Parser { chars: &chars, pos: 0 };
+  let nodes = parser.nodes(false);
+
+  let mut defaults = HashMap::new();
+  collect_defaults(&nodes, &variables, &mut defaults);
+
+  let mut renderer = Renderer {
+    variables: &variables,
+    defaults: &defaults,
+    indent,
+    text: String::new(),
+    len: 0,
+    stops: Vec::new(),
+  };
+  renderer.render(&nodes);
+
+  let mut tabstops: Vec<Tabstop> = Vec::new();
+  for (number, range) in renderer.stops {
+    match tabstops.iter_mut().find(|stop| stop.number == number) {
+      Some(stop) => stop.ranges.push(range),
+      None => tabstops.push(Tabstop { number, ranges: Vec::from_iter([range]) }),
+    }
+  }
+  tabstops.sort_by_key(|stop| if stop.number == 0 { usize::MAX } else { stop.number });
+
+  ParsedSnippet { text: renderer.text, tabstops }
+}
+
+struct Parser<'a> {
+  chars: &'a [char],
+  pos: usize,
+}
+
+impl Parser<'_> {
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).copied()
+  }
+
+  /// Parses until the end, or until an unescaped `}` when `nested`.
+  fn nodes(&mut self, nested: bool) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+
+    while let Some(char) = self.peek() {
+      match char {
+        '\\' => {
+          self.pos += 1;
+          match self.peek() {
+            Some(escaped @ ('$' | '}' | '\\')) => {
+              text.push(escaped);
+              self.pos += 1;
+            }
+            _ => text.push('\\'),
+          }
+        }
+        '}' if nested => break,
+        '$' => match self.dollar() {
+          Some(node) => {
+            if !text.is_empty() {
+              nodes.push(Node::Text(std::mem::take(&mut text)));
+            }
+            nodes.push(node);
+          }
+          None => text.push('$'),
+        },
+        char => {
+          text.push(char);
+          self.pos += 1;
+        }
+      }
+    }
+
+    if !text.is_empty() {
+      nodes.push(Node::Text(text));
+    }
+    nodes
+  }
+
+  fn number(&mut self) -> Option<usize> {
+    let start = self.pos;
+    while self.peek().is_some_and(|char| char.is_ascii_digit()) {
+      self.pos += 1;
+    }
+    self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+  }
+
+  fn name(&mut self) -> Option<String> {
+    let start = self.pos;
+    if !self.peek().is_some_and(|char| char.is_ascii_alphabetic() || char == '_') {
+      return None;
+    }
+    while self.peek().is_some_and(|char| char.is_ascii_alphanumeric() || char == '_') {
+      self.pos += 1;
+    }
+    Some(self.chars[start..self.pos].iter().collect())
+  }
+
+  /// Parses what follows a `$`. Leaves the position untouched and returns `None` if it isn't a
+  /// tabstop or variable, so the `$` is taken literally.
+  fn dollar(&mut self) -> Option<Node> {
+    let start = self.pos;
+    self.pos += 1;
+
+    let node = match self.peek() {
+      Some('{') => {
+        self.pos += 1;
+        self.braced()
+      }
+      _ => match self.number() {
+        Some(number) => Some(Node::Tabstop { number, children: Vec::new() }),
+        None => self.name().map(|name| Node::Variable { name, default: Vec::new() }),
+      },
+    };
+
+    if node.is_none() {
+      self.pos = start;
+    }
+    node
+  }
+
+  /// Parses the inside of `${...}`, including the closing brace.
+  fn braced(&mut self) -> Option<Node> {
+    let node = if let Some(number) = self.number() {
+      match self.peek()? {
+        '}' => Node::Tabstop { number, children: Vec::new() },
+        ':' => {
+          self.pos += 1;
+          Node::Tabstop { number, children: self.nodes(true) }
+        }
+        '|' => {
+          // Choices aren't offered, the first one is used as the placeholder.
+          self.pos += 1;
+          let mut choice = String::new();
+          while !matches!(self.peek()?, ',' | '|') {
+            choice.push(self.peek()?);
+            self.pos += 1;
+          }
+          while self.peek()? != '}' {
+            self.pos += 1;
+          }
+          Node::Tabstop { number, children: Vec::from_iter([Node::Text(choice)]) }
+        }
+        _ => return None,
+      }
+    } else {
+      let name = self.name()?;
+      match self.peek()? {
+        '}' => Node::Variable { name, default: Vec::new() },
+        ':' => {
+          self.pos += 1;
+          Node::Variable { name, default: self.nodes(true) }
+        }
+        // Transforms aren't supported, the variable is used as is.
+        '/' => {
+          while self.peek()? != '}' {
+            self.pos += if self.peek()? == '\\' { 2 } else { 1 };
+          }
+          Node::Variable { name, default: Vec::new() }
+        }
+        _ => return None,
+      }
+    };
+
+    (self.peek()? == '}').then(|| {
+      self.pos += 1;
+      node
+    })
+  }
+}
+
+/// The text of the first placeholder of each tabstop, used to fill in its mirrors.
+fn collect_defaults(
+  nodes: &[Node],
+  variables: &impl Fn(&str) -> Option<String>,
+  defaults: &mut HashMap<usize, String>,
+) {
+  for node in nodes {
+    match node {
+      Node::Tabstop { number, children } => {
+        if !children.is_empty() && !defaults.contains_key(number) {
+          let mut renderer = Renderer {
+            variables,
+            defaults: &HashMap::new(),
+            indent: "",
+            text: String::new(),
+            len: 0,
+            stops: Vec::new(),
+          };
+          renderer.render(children);
+          defaults.insert(*number, renderer.text);
+        }
+        collect_defaults(children, variables, defaults);
+      }
+      Node::Variable { default, .. } => collect_defaults(default, variables, defaults),
+      Node::Text(_) => {}
+    }
+  }
+}
+
+struct Renderer<'a, F> {
+  variables: &'a F,
+  defaults: &'a HashMap<usize, String>,
+  indent: &'a str,
+  text: String,
+  /// `text` in chars.
+  len: usize,
+  stops: Vec<(usize, Range<usize>)>,
+}
+
+impl<F: Fn(&str) -> Option<String>> Renderer<'_, F> {
+  fn push(&mut self, text: &str) {
+    for char in text.chars() {
+      self.text.push(char);
+      self.len += 1;
+      if char == '\n' {
+        self.text.push_str(self.indent);
+        self.len += self.indent.chars().count();
+      }
+    }
+  }
+
+  fn render(&mut self, nodes: &[Node]) {
+    for node in nodes {
+      match node {
+        Node::Text(text) => self.push(text),
+        Node::Tabstop { number, children } => {
+          let start = self.len;
+          match children.is_empty() {
+            true => self.push(self.defaults.get(number).map(String::as_str).unwrap_or_default()),
+            false => self.render(children),
+          }
+          self.stops.push((*number, start..self.len));
+        }
+        Node::Variable { name, default } => match (self.variables)(name) {
+          Some(value) => self.push(&value),
+          None if !default.is_empty() => self.render(default),
+          None => self.push(name),
+        },
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    let variables = |name: &str| (name == "TM_FILENAME").then(|| "main.rs".to_string());
+    let body = "fn ${1:name}() {\n\t$0\n}\n// $1 in $TM_FILENAME ${UNKNOWN:x} \\$1";
+    let parsed = parse(body, variables, "  ");
+
+    assert_eq!(parsed.text, "fn name() {\n  \t\n  }\n  // name in main.rs x $1");
+    assert_eq!(parsed.tabstops[0], Tabstop { number: 1, ranges: Vec::from_iter([3..7, 25..29]) });
+    assert_eq!(
+      parsed.tabstops[1],
+      Tabstop { number: 0, ranges: std::iter::once(15..15).collect() }
+    );
+  }
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use ropey::Rope;
+use teddy_core::buffer::Edit;
+
+use super::parser::ParsedSnippet;
+
+/// An expanded snippet whose tabstops can still be jumped between.
+#[derive(Debug)]
+pub struct SnippetSession {
+  /// Ranges of each tabstop in jump order, as char indexes into the buffer.
+  stops: Vec<Vec<Range<usize>>>,
+  current: usize,
+  /// The placeholder of the current stop is replaced by the next typed char.
+  selected: bool,
+}
+
+impl SnippetSession {
+  /// Starts a session for `snippet` inserted at `at`. Returns `None` if it has nowhere to jump.
+  pub fn new(snippet: &ParsedSnippet, at: usize) -> Option<Self> {
+    let mut stops: Vec<Vec<Range<usize>>> = snippet
+      .tabstops
+      .iter()
+      .map(|stop| stop.ranges.iter().map(|range| at + range.start..at + range.end).collect())
+      .collect();
+
+    let has_final_stop = snippet.tabstops.last().is_some_and(|stop| stop.number == 0);
+    if !has_final_stop {
+      let end = at + snippet.text.chars().count();
+      stops.push(std::iter::once(end..end).collect());
+    }
+
+    (stops.len() > 1).then_some(Self { stops, current: 0, selected: false })
+  }
+
+  pub fn current(&self) -> &[Range<usize>] {
+    &self.stops[self.current]
+  }
+
+  /// Moves to the stop `offset` away and returns where the cursor goes, or `None` once the
+  /// session is over.
+  pub fn jump(&mut self, offset: isize) -> Option<usize> {
+    let next = self.current.checked_add_signed(offset)?;
+    let range = self.stops.get(next)?.first()?.clone();
+
+    self.current = next;
+    self.selected = !range.is_empty();
+    Some(range.start)
+  }
+
+  pub fn is_last(&self) -> bool {
+    self.current == self.stops.len() - 1
+  }
+
+  /// The placeholder to remove before typing over it, if it's still selected.
+  pub fn take_selection(&mut self) -> Option<Edit> {
+    let range = self.current().first()?.clone();
+    std::mem::take(&mut self.selected).then(|| Edit::remove(range.start, range.end))
+  }
+
+  pub fn deselect(&mut self) {
+    self.selected = false;
+  }
+
+  /// Moves the stops along with `edit`. Edits inside a range of the current stop grow or shrink
+  /// it, and the index of that range is returned. `None` means the edit happened elsewhere.
+  pub fn apply_edit(&mut self, edit: &Edit) -> Option<usize> {
+    let inside = self.stops[self.current]
+      .iter()
+      .position(|range| range.start <= edit.from && edit.to <= range.end);
+
+    for (idx, stop) in self.stops.iter_mut().enumerate() {
+      for (range_idx, range) in stop.iter_mut().enumerate() {
+        if idx == self.current && Some(range_idx) == inside {
+          range.end = range.end - (edit.to - edit.from) + edit.text.chars().count();
+        } else {
+          *range = edit.shift(range.start)..edit.shift(range.end).max(edit.shift(range.start));
+        }
+      }
+    }
+    inside
+  }
+
+  /// The next edit copying the range at `source` of the current stop to one of its mirrors. Pass
+  /// it to [`SnippetSession::apply_edit`] before asking for another.
+  pub fn mirror_edit(&self, rope: &Rope, source: usize) -> Option<Edit> {
+    let ranges = self.current();
+    let text = rope.get_slice(ranges.get(source)?.clone())?.to_string();
+
+    ranges.iter().enumerate().find_map(|(idx, range)| {
+      let mirror = rope.get_slice(range.clone())?;
+      (idx != source && mirror != text.as_str()).then(|| Edit {
+        from: range.start,
+        to: range.end,
+        text: text.clone(),
+      })
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::snippets::parser::parse;
+
+  #[test]
+  fn test_mirrors() {
+    let snippet = parse("let ${1:x} = $1;$0", |_| None, "");
+    let mut rope = Rope::from_str(&snippet.text);
+    let mut session = SnippetSession::new(&snippet, 0).unwrap();
+    assert_eq!(session.jump(0), Some(4));
+
+    let edit = session.take_selection().unwrap();
+    rope.remove(edit.from..edit.to);
+    assert_eq!(session.apply_edit(&edit), Some(0));
+    let edit = Edit::insert(4, "abc");
+    rope.insert(4, &edit.text);
+    assert_eq!(session.apply_edit(&edit), Some(0));
+
+    while let Some(edit) = session.mirror_edit(&rope, 0) {
+      rope.remove(edit.from..edit.to);
+      rope.insert(edit.from, &edit.text);
+      session.apply_edit(&edit);
+    }
+    assert_eq!(rope.to_string(), "let abc = abc;");
+    assert_eq!(session.jump(1), Some(14));
+    assert!(session.is_last());
+  }
+}
@@ -0,0 +1,181 @@
+//! Swap files: snapshots of buffers with unsaved changes, written every few seconds and from the
+//! panic hook, so the changes can be recovered with `:recover` after a crash.
+//!
+//! A swap file starts with a header line, then the id of the process that wrote it and the path of
+//! the file, each on their own line, then the text of the buffer.
+
+use std::{
+  collections::BTreeMap,
+  io,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use ropey::Rope;
+use teddy_config::Config;
+use teddy_core::buffer::ChangeListener;
+
+use crate::prelude::{f, Result};
+
+const HEADER: &str = "teddy swap 1";
+
+/// The latest text of every buffer with unsaved changes. Shared with the panic hook, which has
+/// nothing else to reach the editor with.
+static UNSAVED: Mutex<BTreeMap<PathBuf, Unsaved>> = Mutex::new(BTreeMap::new());
+
+struct Unsaved {
+  rope: Rope,
+  revision: u64,
+  /// The revision in the swap file.
+  swapped: Option<u64>,
+}
+
+/// A swap file found for a file being opened.
+#[derive(Debug, PartialEq)]
+pub struct Swap {
+  pub pid: u32,
+  pub text: String,
+}
+
+impl Swap {
+  /// Whether the process that wrote the swap file is gone, leaving the swap file behind.
+  pub fn is_stale(&self) -> bool {
+    self.pid != std::process::id() && !is_running(self.pid)
+  }
+}
+
+/// Whether a process with id `pid` exists. Signal 0 checks without sending anything, and fails
+/// with EPERM for a process of another user, which is still running.
+fn is_running(pid: u32) -> bool {
+  // 0 and negative ids would stand for process groups.
+  let Ok(pid) = libc::pid_t::try_from(pid) else {
+    return false;
+  };
+  if pid <= 0 {
+    return false;
+  }
+  // SAFETY: `kill` with signal 0 only checks the process, it has no other effect.
+  let result = unsafe { libc::kill(pid, 0) };
+  result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+fn swap_dir() -> Option<PathBuf> {
+  Some(Config::state_dir()?.join("swap"))
+}
+
+/// Where the swap file of `path` goes, vim style: `/a/b.rs` is `%a%b.rs.swp`.
+fn swap_path(dir: &Path, path: &Path) -> PathBuf {
+  let name = path.to_string_lossy().replace(['/', '\\'], "%");
+  dir.join(f!("{name}.swp"))
+}
+
+/// Keeps the latest text of the buffer at `path` for the next snapshot.
+pub fn tracker(path: &Path) -> ChangeListener {
+  let path = path.to_path_buf();
+  Box::new(move |change, rope| {
+    let Ok(mut unsaved) = UNSAVED.lock() else {
+      return;
+    };
+    let swapped = unsaved.get(&path).and_then(|buffer| buffer.swapped);
+    let buffer = Unsaved { rope: rope.clone(), revision: change.revision, swapped };
+    unsaved.insert(path.clone(), buffer);
+  })
+}
+
+/// Writes the swap files that are behind their buffer.
+pub fn flush() {
+  if let Ok(mut unsaved) = UNSAVED.try_lock() {
+    write_swaps(&mut unsaved);
+  }
+}
+
+fn write_swaps(unsaved: &mut BTreeMap<PathBuf, Unsaved>) {
+  let Some(dir) = swap_dir() else {
+    return;
+  };
+  if std::fs::create_dir_all(&dir).is_err() {
+    return;
+  }
+  for (path, buffer) in
+    unsaved.iter_mut().filter(|(_, buffer)| buffer.swapped != Some(buffer.revision))
+  {
+    match write(&swap_path(&dir, path), path, &buffer.rope) {
+      Ok(()) => buffer.swapped = Some(buffer.revision),
+      Err(err) => tracing::warn!("Failed to write the swap file of {}: {err}", path.display()),
+    }
+  }
+}
+
+fn write(swap: &Path, path: &Path, rope: &Rope) -> Result<()> {
+  let mut text = f!("{HEADER}\n{}\n{}\n", std::process::id(), path.display());
+  text.reserve(rope.len_bytes());
+  rope.chunks().for_each(|chunk| text.push_str(chunk));
+  // Write next to it and rename, so a crash while writing keeps the previous snapshot.
+  let partial = swap.with_extension("swp.tmp");
+  std::fs::write(&partial, text)?;
+  std::fs::rename(partial, swap)?;
+  Ok(())
+}
+
+/// Drops the snapshot of `path` once its changes are written or thrown away.
+pub fn forget(path: &Path) {
+  if let Ok(mut unsaved) = UNSAVED.lock() {
+    unsaved.remove(path);
+  }
+  if let Some(dir) = swap_dir() {
+    let _ = std::fs::remove_file(swap_path(&dir, path));
+  }
+}
+
+/// Drops every snapshot, when quitting normally.
+pub fn forget_all() {
+  let paths: Vec<PathBuf> = match UNSAVED.lock() {
+    Ok(unsaved) => unsaved.keys().cloned().collect(),
+    Err(_) => return,
+  };
+  paths.iter().for_each(|path| forget(path));
+}
+
+/// The swap file left for `path`, if there is one.
+pub fn find(path: &Path) -> Option<Swap> {
+  let text = std::fs::read_to_string(swap_path(&swap_dir()?, path)).ok()?;
+  parse(&text)
+}
+
+fn parse(text: &str) -> Option<Swap> {
+  let mut parts = text.splitn(4, '\n');
+  if parts.next()? != HEADER {
+    return None;
+  }
+  let pid = parts.next()?.parse().ok()?;
+  let _path = parts.next()?;
+  Some(Swap { pid, text: parts.next()?.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_swap_file() {
+    let dir = std::env::temp_dir().join(f!("teddy-swap-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = Path::new("/home/user/notes.txt");
+    let swap = swap_path(&dir, path);
+    assert_eq!(swap.file_name().unwrap(), "%home%user%notes.txt.swp");
+
+    write(&swap, path, &Rope::from_str("first\nsecond\n")).unwrap();
+    let found = parse(&std::fs::read_to_string(&swap).unwrap()).unwrap();
+    assert_eq!(found, Swap { pid: std::process::id(), text: "first\nsecond\n".to_string() });
+    assert!(!found.is_stale());
+    assert_eq!(parse("not a swap file"), None);
+
+    assert!(is_running(std::process::id()));
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+    assert!(!is_running(exited.id()));
+    assert!(!is_running(0));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -0,0 +1,362 @@
+//! Parse trees from tree-sitter, for the text objects that follow the structure of the code:
+//! functions, classes, arguments and comments, for folds and for indentation.
+
+use std::{ops::Range, path::Path};
+
+use ropey::Rope;
+use teddy_core::text_object::TextObject;
+use tree_sitter::{Node, Parser, Tree};
+
+use crate::text_objects::inside;
+
+/// The languages with a grammar.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Language {
+  Rust,
+  Python,
+}
+
+impl Language {
+  pub fn from_path(path: &Path) -> Option<Self> {
+    match path.extension()?.to_str()? {
+      "rs" => Some(Self::Rust),
+      "py" | "pyi" => Some(Self::Python),
+      _ => None,
+    }
+  }
+
+  fn grammar(&self) -> tree_sitter::Language {
+    match self {
+      Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+      Self::Python => tree_sitter_python::LANGUAGE.into(),
+    }
+  }
+
+  /// The kinds of node `object` can be. For arguments, the kinds of list they're in.
+  fn kinds(&self, object: TextObject) -> &'static [&'static str] {
+    match (self, object) {
+      (Self::Rust, TextObject::Function) => {
+        &["function_item", "function_signature_item", "closure_expression"]
+      }
+      (Self::Rust, TextObject::Class) => {
+        &["struct_item", "enum_item", "union_item", "trait_item", "impl_item"]
+      }
+      (Self::Rust, TextObject::Argument) => {
+        &["parameters", "arguments", "type_parameters", "type_arguments", "closure_parameters"]
+      }
+      (Self::Rust, TextObject::Comment) => &["line_comment", "block_comment"],
+      (Self::Python, TextObject::Function) => &["function_definition", "lambda"],
+      (Self::Python, TextObject::Class) => &["class_definition"],
+      (Self::Python, TextObject::Argument) => &["parameters", "argument_list", "lambda_parameters"],
+      _ => &[],
+    }
+  }
+
+  /// The kinds of node that fold with the `syntax` fold method.
+  fn fold_kinds(&self) -> &'static [&'static str] {
+    match self {
+      Self::Rust => &[
+        "function_item",
+        "impl_item",
+        "trait_item",
+        "struct_item",
+        "enum_item",
+        "union_item",
+        "mod_item",
+        "macro_definition",
+        "match_expression",
+        "use_declaration",
+        "block_comment",
+      ],
+      Self::Python => &[
+        "function_definition",
+        "class_definition",
+        "if_statement",
+        "for_statement",
+        "while_statement",
+        "try_statement",
+        "with_statement",
+        "match_statement",
+      ],
+    }
+  }
+
+  /// The tokens a new line is indented once more after, with the kind of node they're in. Brackets
+  /// are left to [`crate::frame::indent`], which handles them for every language.
+  fn indent_tokens(&self) -> &'static [(&'static str, &'static str)] {
+    match self {
+      Self::Rust => &[("=>", "match_arm")],
+      Self::Python => &[
+        (":", "function_definition"),
+        (":", "class_definition"),
+        (":", "if_statement"),
+        (":", "elif_clause"),
+        (":", "else_clause"),
+        (":", "for_statement"),
+        (":", "while_statement"),
+        (":", "try_statement"),
+        (":", "except_clause"),
+        (":", "finally_clause"),
+        (":", "with_statement"),
+        (":", "match_statement"),
+        (":", "case_clause"),
+      ],
+    }
+  }
+
+  /// The statements that end a block, a new line after them is indented once less.
+  fn dedent_kinds(&self) -> &'static [&'static str] {
+    match self {
+      Self::Rust => &[],
+      Self::Python => &[
+        "return_statement",
+        "pass_statement",
+        "break_statement",
+        "continue_statement",
+        "raise_statement",
+      ],
+    }
+  }
+}
+
+/// How a line started after some code is indented compared to the code's line.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Indent {
+  More,
+  Less,
+}
+
+pub fn parse(rope: &Rope, language: Language) -> Option<Tree> {
+  let mut parser = Parser::new();
+  parser.set_language(&language.grammar()).ok()?;
+  let mut read = |byte: usize, _| -> &[u8] {
+    if byte >= rope.len_bytes() {
+      return &[];
+    }
+    let (chunk, start, _, _) = rope.chunk_at_byte(byte);
+    &chunk.as_bytes()[byte - start..]
+  };
+  parser.parse_with(&mut read, None)
+}
+
+/// The chars of the syntax object `object` around `char_idx`, see [`crate::text_objects::select`].
+pub fn select(
+  rope: &Rope,
+  char_idx: usize,
+  object: TextObject,
+  inner: bool,
+  language: Language,
+) -> Option<Range<usize>> {
+  let tree = parse(rope, language)?;
+  let byte = rope.char_to_byte(char_idx);
+  let node = tree.root_node().descendant_for_byte_range(byte, byte)?;
+  let kinds = language.kinds(object);
+
+  if object == TextObject::Argument {
+    let in_list = |node: &Node| node.parent().is_some_and(|list| kinds.contains(&list.kind()));
+    let argument = ancestors(node).find(|node| node.is_named() && in_list(node))?;
+    return Some(argument_range(rope, argument, inner));
+  }
+
+  let node = ancestors(node).find(|node| kinds.contains(&node.kind()))?;
+  let range = match object {
+    TextObject::Comment => comment_range(rope, node, inner),
+    _ => body_range(rope, node, inner),
+  };
+  Some(match inner {
+    true => range,
+    false => whole_lines(rope, range),
+  })
+}
+
+/// How a new line is indented after the code ending at `char_idx`, like once more after the `:`
+/// of a Python `if` or the `=>` of a match arm. `None` when the parse tree has nothing to say.
+pub fn indent_after(rope: &Rope, char_idx: usize, language: Language) -> Option<Indent> {
+  let tree = parse(rope, language)?;
+  let byte = rope.char_to_byte(char_idx);
+  let token = tree.root_node().descendant_for_byte_range(byte.checked_sub(1)?, byte)?;
+  let parent = token.parent()?;
+  if language.indent_tokens().contains(&(token.kind(), parent.kind())) {
+    return Some(Indent::More);
+  }
+  let ends_block =
+    |node: Node| node.end_byte() == byte && language.dedent_kinds().contains(&node.kind());
+  if ancestors(token).any(ends_block) {
+    return Some(Indent::Less);
+  }
+  None
+}
+
+/// The first and last line of every node that folds and spans more than one line.
+pub fn folds(rope: &Rope, language: Language) -> Vec<(usize, usize)> {
+  let Some(tree) = parse(rope, language) else {
+    return Vec::new();
+  };
+  let kinds = language.fold_kinds();
+  let mut folds = Vec::new();
+  let mut cursor = tree.walk();
+  'walk: loop {
+    let node = cursor.node();
+    let (start, end) = (node.start_position().row, node.end_position().row);
+    if kinds.contains(&node.kind()) && start < end {
+      folds.push((start, end));
+    }
+    if cursor.goto_first_child() {
+      continue;
+    }
+    while !cursor.goto_next_sibling() {
+      if !cursor.goto_parent() {
+        break 'walk;
+      }
+    }
+  }
+  folds
+}
+
+fn ancestors(node: Node) -> impl Iterator<Item = Node> {
+  std::iter::successors(Some(node), |node| node.parent())
+}
+
+fn chars(rope: &Rope, node: Node) -> Range<usize> {
+  rope.byte_to_char(node.start_byte())..rope.byte_to_char(node.end_byte())
+}
+
+/// Grows `range` to whole lines when nothing but blanks shares its first and last line.
+fn whole_lines(rope: &Rope, range: Range<usize>) -> Range<usize> {
+  let first = rope.char_to_line(range.start);
+  let last = rope.char_to_line(range.end.saturating_sub(1).max(range.start));
+  let line_end = rope.line_to_char(last) + rope.line(last).len_chars();
+  let before = rope.slice(rope.line_to_char(first)..range.start);
+  let after = rope.slice(range.end.min(line_end)..line_end);
+  match before.chars().chain(after.chars()).all(char::is_whitespace) {
+    true => rope.line_to_char(first)..rope.line_to_char((last + 1).min(rope.len_lines())),
+    false => range,
+  }
+}
+
+/// A function or class, or the inside of its body when `inner`.
+fn body_range(rope: &Rope, node: Node, inner: bool) -> Range<usize> {
+  let Some(body) = node.child_by_field_name("body").filter(|_| inner) else {
+    return chars(rope, node);
+  };
+  let range = chars(rope, body);
+  let delimited = match (rope.get_char(range.start), range.end.checked_sub(1)) {
+    (Some('{' | '(' | '['), Some(last)) => matches!(rope.char(last), '}' | ')' | ']'),
+    _ => false,
+  };
+  match delimited {
+    true => inside(rope, range.start, range.end - 1),
+    false => range,
+  }
+}
+
+/// An argument, with the comma and whitespace up to the next one when not `inner`, or from the
+/// previous one for the last argument.
+fn argument_range(rope: &Rope, argument: Node, inner: bool) -> Range<usize> {
+  let range = chars(rope, argument);
+  if inner {
+    return range;
+  }
+  fn comma(node: Option<Node>) -> Option<Node> {
+    node.filter(|node| node.kind() == ",")
+  }
+  if let Some(comma) = comma(argument.next_sibling()) {
+    let end = match comma.next_named_sibling() {
+      Some(next) => chars(rope, next).start,
+      None => chars(rope, comma).end,
+    };
+    return range.start..end;
+  }
+  if let Some(comma) = comma(argument.prev_sibling()) {
+    let start = match comma.prev_named_sibling() {
+      Some(previous) => chars(rope, previous).end,
+      None => chars(rope, comma).start,
+    };
+    return start..range.end;
+  }
+  range
+}
+
+/// A comment, along with the line comments right above and below it. `inner` leaves out the
+/// comment markers and the whitespace around the text.
+fn comment_range(rope: &Rope, node: Node, inner: bool) -> Range<usize> {
+  let adjacent = |next: Node, to: Node| {
+    next.kind() == "line_comment"
+      && to.kind() == "line_comment"
+      && next.start_position().row.abs_diff(to.start_position().row) == 1
+  };
+  let (mut first, mut last) = (node, node);
+  while let Some(previous) = first.prev_sibling().filter(|previous| adjacent(*previous, first)) {
+    first = previous;
+  }
+  while let Some(next) = last.next_sibling().filter(|next| adjacent(*next, last)) {
+    last = next;
+  }
+
+  let range = chars(rope, first).start..chars(rope, last).end;
+  if !inner {
+    return range;
+  }
+  let text: Vec<char> = rope.slice(range.clone()).chars().collect();
+  let mut from = text.iter().take_while(|char| matches!(char, '/' | '*' | '!')).count();
+  from += text[from..].iter().take_while(|char| char.is_whitespace()).count();
+  let mut to = text.len();
+  to -= text[from..to].iter().rev().take_while(|char| char.is_whitespace()).count();
+  if node.kind() == "block_comment" && text[from..to].ends_with(&['*', '/']) {
+    to -= 2;
+    to -= text[from..to].iter().rev().take_while(|char| char.is_whitespace()).count();
+  }
+  range.start + from..range.start + to
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_syntax_objects() {
+    let rope = Rope::from_str(
+      "struct Point {\n  x: i32,\n}\n\n/// Adds them.\nfn add(a: i32, b: i32) -> i32 {\n  a + b\n}\n",
+    );
+    let text = |object, needle: &str, inner| {
+      let char_idx = rope.to_string().find(needle).unwrap();
+      let range = select(&rope, char_idx, object, inner, Language::Rust).unwrap();
+      rope.slice(range).to_string()
+    };
+
+    assert_eq!(text(TextObject::Class, "x:", true), "  x: i32,\n");
+    assert_eq!(text(TextObject::Class, "x:", false), "struct Point {\n  x: i32,\n}\n");
+    assert_eq!(text(TextObject::Function, "a + b", true), "  a + b\n");
+    assert_eq!(
+      text(TextObject::Function, "a + b", false),
+      "fn add(a: i32, b: i32) -> i32 {\n  a + b\n}\n"
+    );
+    assert_eq!(text(TextObject::Argument, "a: i32", true), "a: i32");
+    assert_eq!(text(TextObject::Argument, "a: i32", false), "a: i32, ");
+    assert_eq!(text(TextObject::Argument, "b: i32", false), ", b: i32");
+    assert_eq!(text(TextObject::Comment, "Adds", true), "Adds them.");
+    assert_eq!(Language::from_path(Path::new("notes.txt")), None);
+  }
+
+  #[test]
+  fn test_indent_after() {
+    let indent = |text: &str, after: &str, language| {
+      let rope = Rope::from_str(text);
+      let char_idx = text.find(after).unwrap() + after.len();
+      indent_after(&rope, char_idx, language)
+    };
+
+    let python = "class A:\n  def f(self, x):\n    y = {x: 1}\n    if x:\n      return y\n";
+    assert_eq!(indent(python, "class A:", Language::Python), Some(Indent::More));
+    assert_eq!(indent(python, "(self, x):", Language::Python), Some(Indent::More));
+    assert_eq!(indent(python, "if x:", Language::Python), Some(Indent::More));
+    assert_eq!(indent(python, "return y", Language::Python), Some(Indent::Less));
+    // A dict's `:` opens no block, nor does the middle of a statement end one.
+    assert_eq!(indent(python, "{x:", Language::Python), None);
+    assert_eq!(indent(python, "return", Language::Python), None);
+
+    let rust = "fn f(x: Option<u8>) {\n  match x {\n    Some(y) =>\n    None => 0,\n  }\n}\n";
+    assert_eq!(indent(rust, "Some(y) =>", Language::Rust), Some(Indent::More));
+    assert_eq!(indent(rust, "None => 0,", Language::Rust), None);
+  }
+}
@@ -0,0 +1,265 @@
+//! Tasks run by `:make` and `:task <name>`, like `cargo check`. Their output streams back line by
+//! line as [`TaskEvent`]s for the log buffer, and the errors picked out of it make up the quickfix
+//! list.
+
+use std::{collections::BTreeMap, path::PathBuf, process::Stdio};
+
+use regex::Regex;
+use serde_json::Value;
+use teddy_config::{ErrorFormat, TaskConfig};
+use teddy_core::action::DiagnosticLevel;
+use teddy_events::{Event, TaskEvent};
+use tokio::{
+  io::{AsyncBufReadExt, BufReader},
+  process::Command,
+  sync::mpsc::UnboundedSender,
+  task::AbortHandle,
+};
+
+use crate::prelude::{f, Result};
+
+/// An error in a task's output. `line` and `column` count from zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickfixEntry {
+  pub path: PathBuf,
+  pub line: usize,
+  pub column: usize,
+  pub level: DiagnosticLevel,
+  pub message: String,
+}
+
+/// The errors of the last task that finished, walked with `:cnext` and `:cprev`.
+#[derive(Debug, Default)]
+pub struct Quickfix {
+  pub entries: Vec<QuickfixEntry>,
+  current: Option<usize>,
+}
+
+impl Quickfix {
+  pub fn new(entries: Vec<QuickfixEntry>) -> Self {
+    Self { entries, current: None }
+  }
+
+  /// Moves to the next entry, or the previous one when not `forward`, wrapping around. Returns it
+  /// with its index.
+  pub fn step(&mut self, forward: bool) -> Option<(usize, &QuickfixEntry)> {
+    let len = self.entries.len();
+    let idx = match (self.current, forward) {
+      _ if len == 0 => return None,
+      (None, true) => 0,
+      (None, false) => len - 1,
+      (Some(current), true) => (current + 1) % len,
+      (Some(current), false) => (current + len - 1) % len,
+    };
+    self.current = Some(idx);
+    Some((idx, &self.entries[idx]))
+  }
+}
+
+enum Parser {
+  Regex(Regex),
+  CargoJson,
+}
+
+pub struct Run {
+  pub name: String,
+  /// The frame its output goes to.
+  pub log: u16,
+  /// The errors found so far.
+  pub entries: Vec<QuickfixEntry>,
+  parsers: Vec<Parser>,
+  handle: AbortHandle,
+}
+
+impl Run {
+  /// Reads a line of output, returning what it shows in the log, if anything, and the error in it.
+  pub fn parse(&self, line: &str) -> (Option<String>, Option<QuickfixEntry>) {
+    for parser in &self.parsers {
+      let parsed = match parser {
+        Parser::CargoJson => cargo_message(line),
+        Parser::Regex(regex) => {
+          regex_entry(regex, line).map(|entry| (Some(line.into()), Some(entry)))
+        }
+      };
+      if let Some((log, mut entry)) = parsed {
+        // Paths are relative to where the task ran.
+        if let Some(entry) = &mut entry {
+          entry.path = std::path::absolute(&entry.path).unwrap_or(entry.path.clone());
+        }
+        return (log, entry);
+      }
+    }
+    (Some(line.to_string()), None)
+  }
+}
+
+/// The tasks still running.
+#[derive(Default)]
+pub struct Tasks {
+  next: u64,
+  running: BTreeMap<u64, Run>,
+}
+
+impl Tasks {
+  /// Starts `task`, logging to frame `log`, and returns the id its [`TaskEvent`]s will have.
+  pub fn start(
+    &mut self,
+    task: &TaskConfig,
+    log: u16,
+    events: &UnboundedSender<Event>,
+  ) -> Result<u64> {
+    let parsers = task.error_formats.iter().map(|format| match format {
+      ErrorFormat::Regex(regex) => Regex::new(regex).map(Parser::Regex),
+      ErrorFormat::CargoJson => Ok(Parser::CargoJson),
+    });
+    let parsers = parsers.collect::<std::result::Result<Vec<_>, _>>()?;
+
+    self.next += 1;
+    let id = self.next;
+    let (command, events) = (task.command.clone(), events.clone());
+    let handle = tokio::spawn(async move {
+      let event = match run(id, &command, &events).await {
+        Ok(success) => TaskEvent::Finished { id, success },
+        Err(err) => TaskEvent::Failed { id, error: f!("{err}") },
+      };
+      let _ = events.send(Event::Task(event));
+    });
+    let run = Run {
+      name: task.name.clone(),
+      log,
+      entries: Vec::new(),
+      parsers,
+      handle: handle.abort_handle(),
+    };
+    self.running.insert(id, run);
+    Ok(id)
+  }
+
+  pub fn get_mut(&mut self, id: u64) -> Option<&mut Run> {
+    self.running.get_mut(&id)
+  }
+
+  /// Takes the run of a finished task, unless it was cancelled since.
+  pub fn finish(&mut self, id: u64) -> Option<Run> {
+    self.running.remove(&id)
+  }
+
+  /// Stops every running task, killing its process.
+  pub fn cancel_all(&mut self) -> Vec<(u64, Run)> {
+    let running = std::mem::take(&mut self.running);
+    running.values().for_each(|run| run.handle.abort());
+    running.into_iter().collect()
+  }
+}
+
+/// Runs `command` with `sh -c`, sending each line it writes as it comes, and returns whether it
+/// succeeded.
+async fn run(id: u64, command: &str, events: &UnboundedSender<Event>) -> Result<bool> {
+  let mut child = Command::new("sh")
+    .arg("-c")
+    .arg(command)
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    // Dropped when the task is aborted, which kills it.
+    .kill_on_drop(true)
+    .spawn()?;
+  let mut stdout = BufReader::new(child.stdout.take().ok_or("No stdout")?).split(b'\n');
+  let mut stderr = BufReader::new(child.stderr.take().ok_or("No stderr")?).split(b'\n');
+
+  let send = |line: Vec<u8>| {
+    let line = String::from_utf8_lossy(&line).trim_end_matches('\r').to_string();
+    let _ = events.send(Event::Task(TaskEvent::Line { id, line }));
+  };
+  let (mut stdout_open, mut stderr_open) = (true, true);
+  while stdout_open || stderr_open {
+    tokio::select! {
+      line = stdout.next_segment(), if stdout_open => match line? {
+        Some(line) => send(line),
+        None => stdout_open = false,
+      },
+      line = stderr.next_segment(), if stderr_open => match line? {
+        Some(line) => send(line),
+        None => stderr_open = false,
+      },
+    }
+  }
+  Ok(child.wait().await?.success())
+}
+
+/// Matches a line against an `errorformat`-like regex, see [`ErrorFormat::Regex`].
+fn regex_entry(regex: &Regex, line: &str) -> Option<QuickfixEntry> {
+  let captures = regex.captures(line)?;
+  let number =
+    |name: &str| captures.name(name).and_then(|number| number.as_str().parse::<usize>().ok());
+  let level = match captures.name("level").map(|level| level.as_str().to_lowercase()) {
+    None => DiagnosticLevel::Error,
+    Some(level) if level.starts_with("err") => DiagnosticLevel::Error,
+    Some(level) if level.starts_with("warn") => DiagnosticLevel::Warn,
+    Some(_) => DiagnosticLevel::Info,
+  };
+  let message = captures.name("message").map_or(line.trim(), |message| message.as_str());
+  Some(QuickfixEntry {
+    path: PathBuf::from(captures.name("file")?.as_str()),
+    line: number("line")?.saturating_sub(1),
+    column: number("column").unwrap_or(1).saturating_sub(1),
+    level,
+    message: message.to_string(),
+  })
+}
+
+/// Reads a line of cargo's `--message-format=json`. Compiler messages are logged as rustc renders
+/// them, other JSON like built artifacts isn't logged at all. Lines that aren't JSON are left to
+/// the other parsers.
+fn cargo_message(line: &str) -> Option<(Option<String>, Option<QuickfixEntry>)> {
+  let value: Value = serde_json::from_str(line).ok()?;
+  if value["reason"] != "compiler-message" {
+    return Some((None, None));
+  }
+  let message = &value["message"];
+  let rendered = message["rendered"].as_str().map(|rendered| rendered.trim_end().to_string());
+  let level = match message["level"].as_str().unwrap_or_default() {
+    level if level.starts_with("error") => DiagnosticLevel::Error,
+    "warning" => DiagnosticLevel::Warn,
+    _ => DiagnosticLevel::Info,
+  };
+  let spans = message["spans"].as_array().map(Vec::as_slice).unwrap_or_default();
+  // Summaries like "aborting due to 2 previous errors" point nowhere.
+  let entry = spans.iter().find(|span| span["is_primary"] == true).and_then(|span| {
+    Some(QuickfixEntry {
+      path: PathBuf::from(span["file_name"].as_str()?),
+      line: (span["line_start"].as_u64()? as usize).saturating_sub(1),
+      column: (span["column_start"].as_u64()? as usize).saturating_sub(1),
+      level,
+      message: message["message"].as_str()?.to_string(),
+    })
+  });
+  Some((rendered, entry))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    let regex = Regex::new(TaskConfig::RUSTC_SHORT).unwrap();
+    let entry = regex_entry(&regex, "src/main.rs:3:9: warning: unused variable: `x`").unwrap();
+    assert_eq!((entry.path, entry.line, entry.column), (PathBuf::from("src/main.rs"), 2, 8));
+    assert_eq!(
+      (entry.level, entry.message.as_str()),
+      (DiagnosticLevel::Warn, "unused variable: `x`")
+    );
+    assert!(regex_entry(&regex, "    Checking teddy v0.1.0").is_none());
+
+    let json = r#"{"reason":"compiler-message","message":{"message":"mismatched types","level":"error",
+      "rendered":"error[E0308]: mismatched types\n","spans":[{"file_name":"src/lib.rs",
+      "line_start":10,"column_start":5,"is_primary":true}]}}"#;
+    let (log, entry) = cargo_message(&json.replace('\n', "")).unwrap();
+    assert_eq!(log.as_deref(), Some("error[E0308]: mismatched types"));
+    let entry = entry.unwrap();
+    assert_eq!((entry.line, entry.column, entry.level), (9, 4, DiagnosticLevel::Error));
+    assert_eq!(cargo_message(r#"{"reason":"compiler-artifact"}"#), Some((None, None)));
+    assert_eq!(cargo_message("error: could not compile"), None);
+  }
+}
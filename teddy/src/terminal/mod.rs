@@ -0,0 +1,122 @@
+//! Terminal frames: a shell on a pseudo terminal, drawn from its [`Screen`](screen::Screen) while
+//! in terminal mode and read as a plain buffer of its scrollback otherwise.
+
+pub mod pty;
+pub mod screen;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ropey::Rope;
+use teddy_events::{read_pty, Event};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{buffers::scrollback::ScrollbackBuffer, prelude::Result};
+
+use self::{pty::Pty, screen::Screen};
+
+pub struct Terminal {
+  pub id: u16,
+  pty: Pty,
+  pub screen: Screen,
+  /// Whether the program is still running, it's closed the terminal otherwise.
+  pub running: bool,
+  /// How many snapshots were taken, for their revisions.
+  snapshots: u64,
+}
+
+impl Terminal {
+  /// Starts `shell` in a terminal of `rows` by `columns`, its output coming back as
+  /// [`TerminalEvent`](teddy_events::TerminalEvent)s for `id`.
+  pub fn spawn(
+    id: u16,
+    shell: &str,
+    rows: u16,
+    columns: u16,
+    events: &UnboundedSender<Event>,
+  ) -> Result<Self> {
+    let pty = Pty::spawn(shell, rows, columns)?;
+    read_pty(id, pty.reader()?, events.clone());
+    let screen = Screen::new(rows as usize, columns as usize);
+    Ok(Self { id, pty, screen, running: true, snapshots: 0 })
+  }
+
+  /// Takes output of the program, answering the queries in it.
+  pub fn output(&mut self, bytes: &[u8]) -> Result<()> {
+    self.screen.feed(bytes);
+    let replies = self.screen.take_replies();
+    if !replies.is_empty() {
+      self.pty.write(&replies)?;
+    }
+    Ok(())
+  }
+
+  /// Sends a key to the program.
+  pub fn key(&mut self, key: KeyEvent) -> Result<()> {
+    match key_bytes(key, self.screen.application_cursor) {
+      Some(bytes) if self.running => Ok(self.pty.write(&bytes)?),
+      _ => Ok(()),
+    }
+  }
+
+  pub fn resize(&mut self, rows: u16, columns: u16) -> Result<()> {
+    if self.screen.size() == (rows as usize, columns as usize) {
+      return Ok(());
+    }
+    self.screen.resize(rows as usize, columns as usize);
+    Ok(self.pty.resize(rows, columns)?)
+  }
+
+  /// The scrollback and screen as a read only buffer, and the line the cursor is on in it.
+  pub fn snapshot(&mut self) -> (ScrollbackBuffer, usize, usize) {
+    self.snapshots += 1;
+    let (text, line) = self.screen.text();
+    let buffer = ScrollbackBuffer::new(Rope::from_str(&text), self.snapshots);
+    (buffer, line, self.screen.cursor().1)
+  }
+}
+
+/// What a terminal sends for `key`, the way xterm does.
+fn key_bytes(key: KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+  let arrow = |char: char| match application_cursor {
+    true => format!("\x1bO{char}").into_bytes(),
+    false => format!("\x1b[{char}").into_bytes(),
+  };
+  let mut bytes = match key.code {
+    KeyCode::Char(char) if key.modifiers.contains(KeyModifiers::CONTROL) => match char {
+      'a'..='z' => vec![char as u8 - b'a' + 1],
+      '@' | ' ' => vec![0],
+      '[' => vec![0x1b],
+      '\\' => vec![0x1c],
+      ']' => vec![0x1d],
+      '^' => vec![0x1e],
+      '_' => vec![0x1f],
+      _ => return None,
+    },
+    KeyCode::Char(char) => char.to_string().into_bytes(),
+    KeyCode::Enter => vec![b'\r'],
+    KeyCode::Backspace => vec![0x7f],
+    KeyCode::Tab => vec![b'\t'],
+    KeyCode::BackTab => b"\x1b[Z".to_vec(),
+    KeyCode::Esc => vec![0x1b],
+    KeyCode::Up => arrow('A'),
+    KeyCode::Down => arrow('B'),
+    KeyCode::Right => arrow('C'),
+    KeyCode::Left => arrow('D'),
+    KeyCode::Home => arrow('H'),
+    KeyCode::End => arrow('F'),
+    KeyCode::Insert => b"\x1b[2~".to_vec(),
+    KeyCode::Delete => b"\x1b[3~".to_vec(),
+    KeyCode::PageUp => b"\x1b[5~".to_vec(),
+    KeyCode::PageDown => b"\x1b[6~".to_vec(),
+    KeyCode::F(number @ 1..=4) => format!("\x1bO{}", (b'P' + number - 1) as char).into_bytes(),
+    KeyCode::F(number @ 5..=12) => {
+      let code = [15, 17, 18, 19, 20, 21, 23, 24][number as usize - 5];
+      format!("\x1b[{code}~").into_bytes()
+    }
+    _ => return None,
+  };
+  // Alt sends an escape first.
+  if key.modifiers.contains(KeyModifiers::ALT) {
+    bytes.insert(0, 0x1b);
+  }
+  Some(bytes)
+}
@@ -0,0 +1,125 @@
+//! Pseudo terminals, through libc. Only Unix systems have them.
+
+use std::{
+  fs::File,
+  io::{self, Write},
+  process::{Child, Command},
+};
+
+#[cfg(unix)]
+use std::{
+  ffi::{CStr, OsStr},
+  fs::OpenOptions,
+  os::unix::{
+    ffi::OsStrExt,
+    io::{AsRawFd, FromRawFd},
+    process::CommandExt,
+  },
+  path::PathBuf,
+};
+
+/// A program running on a pseudo terminal. It's killed when dropped.
+pub struct Pty {
+  master: File,
+  child: Child,
+}
+
+impl Pty {
+  /// Starts `program` as the session leader of a new pseudo terminal of `rows` by `columns`.
+  #[cfg(unix)]
+  pub fn spawn(program: &str, rows: u16, columns: u16) -> io::Result<Self> {
+    let master = open_master()?;
+    set_size(&master, rows, columns)?;
+    let slave = OpenOptions::new().read(true).write(true).open(slave_path(&master)?)?;
+
+    let mut command = Command::new(program);
+    command
+      .stdin(slave.try_clone()?)
+      .stdout(slave.try_clone()?)
+      .stderr(slave)
+      .env("TERM", "xterm-256color");
+    // SAFETY: only async-signal-safe calls are made between fork and exec.
+    unsafe {
+      command.pre_exec(|| {
+        if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+          return Err(io::Error::last_os_error());
+        }
+        Ok(())
+      });
+    }
+    let child = command.spawn()?;
+    Ok(Self { master, child })
+  }
+
+  #[cfg(not(unix))]
+  pub fn spawn(_program: &str, _rows: u16, _columns: u16) -> io::Result<Self> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Terminals need a Unix system"))
+  }
+
+  /// Another handle on the master side, for reading the program's output.
+  pub fn reader(&self) -> io::Result<File> {
+    self.master.try_clone()
+  }
+
+  pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+    self.master.write_all(bytes)
+  }
+
+  #[cfg(unix)]
+  pub fn resize(&self, rows: u16, columns: u16) -> io::Result<()> {
+    set_size(&self.master, rows, columns)
+  }
+
+  #[cfg(not(unix))]
+  pub fn resize(&self, _rows: u16, _columns: u16) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl Drop for Pty {
+  fn drop(&mut self) {
+    let _ = self.child.kill();
+    let _ = self.child.wait();
+  }
+}
+
+#[cfg(unix)]
+fn open_master() -> io::Result<File> {
+  // SAFETY: plain libc calls on a descriptor owned by the returned `File` from the start.
+  unsafe {
+    let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+    if fd == -1 {
+      return Err(io::Error::last_os_error());
+    }
+    let master = File::from_raw_fd(fd);
+    // The program mustn't inherit the master side, or it never sees it close.
+    let cloexec = libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+    if cloexec == -1 || libc::grantpt(fd) == -1 || libc::unlockpt(fd) == -1 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(master)
+  }
+}
+
+#[cfg(unix)]
+fn slave_path(master: &File) -> io::Result<PathBuf> {
+  // SAFETY: `ptsname` returns a string owned by libc, copied out before anything else calls it.
+  // Terminals are only opened from the main thread.
+  unsafe {
+    let name = libc::ptsname(master.as_raw_fd());
+    if name.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(PathBuf::from(OsStr::from_bytes(CStr::from_ptr(name).to_bytes())))
+  }
+}
+
+#[cfg(unix)]
+fn set_size(master: &File, rows: u16, columns: u16) -> io::Result<()> {
+  let size = libc::winsize { ws_row: rows, ws_col: columns, ws_xpixel: 0, ws_ypixel: 0 };
+  // SAFETY: TIOCSWINSZ only reads the `winsize` it's given.
+  if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) } == -1 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(())
+}
@@ -0,0 +1,518 @@
+//! The screen of a terminal frame, built from the VT100/xterm escape sequences programs write.
+//! Enough of them are understood for shells and most full screen programs.
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// How many lines scrolled off the top are kept.
+const SCROLLBACK: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+  pub char: char,
+  pub style: Style,
+}
+
+impl Cell {
+  fn blank(style: Style) -> Self {
+    // Erasing keeps the background, not the rest of the pen.
+    Self { char: ' ', style: Style { bg: style.bg, ..Style::default() } }
+  }
+}
+
+type Grid = Vec<Vec<Cell>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+  Ground,
+  Escape,
+  /// After `ESC (` and friends, which pick a character set.
+  Charset,
+  Csi,
+  Osc,
+  /// After an ESC inside an OSC, which ends it as part of `ESC \`.
+  OscEscape,
+}
+
+pub struct Screen {
+  rows: usize,
+  columns: usize,
+  grid: Grid,
+  /// Lines scrolled off the top of the main screen, oldest first.
+  scrollback: Vec<String>,
+  /// Row and column, from the top left.
+  cursor: (usize, usize),
+  saved: ((usize, usize), Style),
+  /// The style of what's written next.
+  pen: Style,
+  /// The first and last row that scroll, set by DECSTBM.
+  region: (usize, usize),
+  /// The main screen's grid and cursor while the alternate screen is up.
+  alternate: Option<(Grid, (usize, usize))>,
+  /// The last char went in the last column, the next one goes on a new line.
+  wrap_pending: bool,
+  pub cursor_visible: bool,
+  /// DECCKM, the arrow keys send `ESC O` sequences.
+  pub application_cursor: bool,
+  pub title: Option<String>,
+
+  state: State,
+  /// The parameters of the CSI sequence being read.
+  params: String,
+  /// The `?` or `>` before them.
+  private: Option<char>,
+  osc: String,
+  /// The start of a char cut off at the end of the last output.
+  utf8: Vec<u8>,
+  /// Answers to queries, for the program to read.
+  replies: Vec<u8>,
+}
+
+impl Screen {
+  pub fn new(rows: usize, columns: usize) -> Self {
+    let (rows, columns) = (rows.max(1), columns.max(1));
+    Self {
+      rows,
+      columns,
+      grid: vec![vec![Cell::blank(Style::default()); columns]; rows],
+      scrollback: Vec::new(),
+      cursor: (0, 0),
+      saved: ((0, 0), Style::default()),
+      pen: Style::default(),
+      region: (0, rows - 1),
+      alternate: None,
+      wrap_pending: false,
+      cursor_visible: true,
+      application_cursor: false,
+      title: None,
+      state: State::Ground,
+      params: String::new(),
+      private: None,
+      osc: String::new(),
+      utf8: Vec::new(),
+      replies: Vec::new(),
+    }
+  }
+
+  pub fn size(&self) -> (usize, usize) {
+    (self.rows, self.columns)
+  }
+
+  pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+    self.grid.iter().map(Vec::as_slice)
+  }
+
+  pub fn cursor(&self) -> (usize, usize) {
+    self.cursor
+  }
+
+  /// Takes the answers to queries the program made, to be written back to it.
+  pub fn take_replies(&mut self) -> Vec<u8> {
+    std::mem::take(&mut self.replies)
+  }
+
+  /// The scrollback and the screen as text, and the line of it the cursor is on.
+  pub fn text(&self) -> (String, usize) {
+    let screen = self.grid.iter().map(|row| {
+      let line: String = row.iter().map(|cell| cell.char).collect();
+      line.trim_end().to_string()
+    });
+    let mut lines: Vec<String> = self.scrollback.iter().cloned().chain(screen).collect();
+    let cursor_line = self.scrollback.len() + self.cursor.0;
+    while lines.len() > cursor_line + 1 && lines.last().is_some_and(String::is_empty) {
+      lines.pop();
+    }
+    (lines.join("\n"), cursor_line)
+  }
+
+  pub fn resize(&mut self, rows: usize, columns: usize) {
+    let (rows, columns) = (rows.max(1), columns.max(1));
+    for row in &mut self.grid {
+      row.resize(columns, Cell::blank(Style::default()));
+    }
+    while self.grid.len() > rows {
+      // Lines below the cursor go first, then the top ones scroll off.
+      if self.cursor.0 + 1 < self.grid.len() {
+        self.grid.pop();
+      } else {
+        let line = self.grid.remove(0);
+        self.push_scrollback(&line);
+        self.cursor.0 -= 1;
+      }
+    }
+    self.grid.resize(rows, vec![Cell::blank(Style::default()); columns]);
+    (self.rows, self.columns) = (rows, columns);
+    self.region = (0, rows - 1);
+    self.cursor = (self.cursor.0.min(rows - 1), self.cursor.1.min(columns - 1));
+    self.wrap_pending = false;
+  }
+
+  /// Reads output of the program.
+  pub fn feed(&mut self, bytes: &[u8]) {
+    let mut pending = std::mem::take(&mut self.utf8);
+    pending.extend_from_slice(bytes);
+    let mut rest = pending.as_slice();
+    loop {
+      match std::str::from_utf8(rest) {
+        Ok(text) => {
+          text.chars().for_each(|char| self.input(char));
+          return;
+        }
+        Err(err) => {
+          let (valid, after) = rest.split_at(err.valid_up_to());
+          std::str::from_utf8(valid).unwrap_or_default().chars().for_each(|char| self.input(char));
+          match err.error_len() {
+            Some(len) => {
+              self.input(char::REPLACEMENT_CHARACTER);
+              rest = &after[len..];
+            }
+            None => {
+              self.utf8 = after.to_vec();
+              return;
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn input(&mut self, char: char) {
+    match self.state {
+      State::Ground => self.ground(char),
+      State::Escape => self.escape(char),
+      State::Charset => self.state = State::Ground,
+      State::Csi => match char {
+        '0'..='9' | ';' | ':' => self.params.push(char),
+        '?' | '>' | '<' | '=' if self.params.is_empty() => self.private = Some(char),
+        '\x1b' => self.state = State::Escape,
+        '@'..='~' => {
+          self.state = State::Ground;
+          self.csi(char);
+        }
+        _ => {}
+      },
+      State::Osc => match char {
+        '\x07' => {
+          self.state = State::Ground;
+          self.osc_done();
+        }
+        '\x1b' => self.state = State::OscEscape,
+        char => self.osc.push(char),
+      },
+      State::OscEscape => {
+        self.osc_done();
+        self.state = State::Ground;
+        if char != '\\' {
+          self.escape(char);
+        }
+      }
+    }
+  }
+
+  fn ground(&mut self, char: char) {
+    match char {
+      '\x1b' => self.state = State::Escape,
+      '\r' => self.carriage_return(),
+      '\n' | '\x0b' | '\x0c' => self.line_feed(),
+      '\x08' => {
+        self.cursor.1 = self.cursor.1.saturating_sub(1);
+        self.wrap_pending = false;
+      }
+      '\t' => self.cursor.1 = ((self.cursor.1 / 8 + 1) * 8).min(self.columns - 1),
+      char if char.is_control() => {}
+      char => self.print(char),
+    }
+  }
+
+  fn escape(&mut self, char: char) {
+    self.state = State::Ground;
+    match char {
+      '[' => {
+        self.params.clear();
+        self.private = None;
+        self.state = State::Csi;
+      }
+      ']' => {
+        self.osc.clear();
+        self.state = State::Osc;
+      }
+      '(' | ')' | '*' | '+' => self.state = State::Charset,
+      '7' => self.saved = (self.cursor, self.pen),
+      '8' => (self.cursor, self.pen) = self.saved,
+      'D' => self.line_feed(),
+      'E' => {
+        self.carriage_return();
+        self.line_feed();
+      }
+      'M' => self.reverse_index(),
+      'c' => *self = Self::new(self.rows, self.columns),
+      _ => {}
+    }
+  }
+
+  fn csi(&mut self, action: char) {
+    let params: Vec<usize> =
+      self.params.split([';', ':']).map(|param| param.parse().unwrap_or(0)).collect();
+    // Missing or zero parameters count as `default`.
+    let param = |idx: usize, default: usize| match params.get(idx) {
+      Some(0) | None => default,
+      Some(value) => *value,
+    };
+    let (row, column) = self.cursor;
+    let last_row = self.rows - 1;
+    let last_column = self.columns - 1;
+    self.wrap_pending = false;
+
+    match (self.private, action) {
+      (None, 'A') => self.cursor.0 = row.saturating_sub(param(0, 1)),
+      (None, 'B') => self.cursor.0 = (row + param(0, 1)).min(last_row),
+      (None, 'C') => self.cursor.1 = (column + param(0, 1)).min(last_column),
+      (None, 'D') => self.cursor.1 = column.saturating_sub(param(0, 1)),
+      (None, 'E') => self.cursor = ((row + param(0, 1)).min(last_row), 0),
+      (None, 'F') => self.cursor = (row.saturating_sub(param(0, 1)), 0),
+      (None, 'G' | '`') => self.cursor.1 = (param(0, 1) - 1).min(last_column),
+      (None, 'd') => self.cursor.0 = (param(0, 1) - 1).min(last_row),
+      (None, 'H' | 'f') => {
+        self.cursor = ((param(0, 1) - 1).min(last_row), (param(1, 1) - 1).min(last_column))
+      }
+      (None, 'J') => match param(0, 0) {
+        0 => {
+          self.erase(row, column..self.columns);
+          (row + 1..self.rows).for_each(|row| self.erase(row, 0..self.columns));
+        }
+        1 => {
+          (0..row).for_each(|row| self.erase(row, 0..self.columns));
+          self.erase(row, 0..column + 1);
+        }
+        2 => (0..self.rows).for_each(|row| self.erase(row, 0..self.columns)),
+        3 => self.scrollback.clear(),
+        _ => {}
+      },
+      (None, 'K') => match param(0, 0) {
+        0 => self.erase(row, column..self.columns),
+        1 => self.erase(row, 0..column + 1),
+        2 => self.erase(row, 0..self.columns),
+        _ => {}
+      },
+      (None, 'L') if (self.region.0..=self.region.1).contains(&row) => {
+        for _ in 0..param(0, 1).min(self.region.1 - row + 1) {
+          self.grid.remove(self.region.1);
+          self.grid.insert(row, vec![Cell::blank(self.pen); self.columns]);
+        }
+      }
+      (None, 'M') if (self.region.0..=self.region.1).contains(&row) => {
+        for _ in 0..param(0, 1).min(self.region.1 - row + 1) {
+          self.grid.remove(row);
+          self.grid.insert(self.region.1, vec![Cell::blank(self.pen); self.columns]);
+        }
+      }
+      (None, 'P') => {
+        let line = &mut self.grid[row];
+        let count = param(0, 1).min(self.columns - column);
+        line.drain(column..column + count);
+        line.resize(self.columns, Cell::blank(self.pen));
+      }
+      (None, '@') => {
+        let line = &mut self.grid[row];
+        for _ in 0..param(0, 1).min(self.columns - column) {
+          line.insert(column, Cell::blank(self.pen));
+        }
+        line.truncate(self.columns);
+      }
+      (None, 'X') => self.erase(row, column..(column + param(0, 1)).min(self.columns)),
+      (None, 'S') => (0..param(0, 1)).for_each(|_| self.scroll_up()),
+      (None, 'T') => (0..param(0, 1)).for_each(|_| self.scroll_down()),
+      (None, 'm') => self.select_graphic_rendition(&params),
+      (None, 'r') => {
+        let (top, bottom) = (param(0, 1) - 1, param(1, self.rows) - 1);
+        if top < bottom && bottom < self.rows {
+          self.region = (top, bottom);
+          self.cursor = (0, 0);
+        }
+      }
+      (None, 's') => self.saved = (self.cursor, self.pen),
+      (None, 'u') => (self.cursor, self.pen) = self.saved,
+      (None, 'n') if param(0, 0) == 6 => {
+        let reply = format!("\x1b[{};{}R", row + 1, column + 1);
+        self.replies.extend_from_slice(reply.as_bytes());
+      }
+      (None, 'n') if param(0, 0) == 5 => self.replies.extend_from_slice(b"\x1b[0n"),
+      (None, 'c') => self.replies.extend_from_slice(b"\x1b[?1;2c"),
+      (Some('?'), 'h' | 'l') => {
+        let set = action == 'h';
+        for mode in &params {
+          match mode {
+            1 => self.application_cursor = set,
+            25 => self.cursor_visible = set,
+            47 | 1047 | 1049 => self.alternate_screen(set),
+            _ => {}
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  /// SGR, the colors and attributes of what's written next.
+  fn select_graphic_rendition(&mut self, params: &[usize]) {
+    let mut params = params.iter().copied();
+    while let Some(param) = params.next() {
+      let pen = self.pen;
+      self.pen = match param {
+        0 => Style::default(),
+        1 => pen.add_modifier(Modifier::BOLD),
+        2 => pen.add_modifier(Modifier::DIM),
+        3 => pen.add_modifier(Modifier::ITALIC),
+        4 => pen.add_modifier(Modifier::UNDERLINED),
+        5 => pen.add_modifier(Modifier::SLOW_BLINK),
+        7 => pen.add_modifier(Modifier::REVERSED),
+        8 => pen.add_modifier(Modifier::HIDDEN),
+        9 => pen.add_modifier(Modifier::CROSSED_OUT),
+        22 => pen.remove_modifier(Modifier::BOLD | Modifier::DIM),
+        23 => pen.remove_modifier(Modifier::ITALIC),
+        24 => pen.remove_modifier(Modifier::UNDERLINED),
+        25 => pen.remove_modifier(Modifier::SLOW_BLINK),
+        27 => pen.remove_modifier(Modifier::REVERSED),
+        28 => pen.remove_modifier(Modifier::HIDDEN),
+        29 => pen.remove_modifier(Modifier::CROSSED_OUT),
+        30..=37 => pen.fg(Color::Indexed(param as u8 - 30)),
+        38 => match extended_color(&mut params) {
+          Some(color) => pen.fg(color),
+          None => pen,
+        },
+        39 => Style { fg: None, ..pen },
+        40..=47 => pen.bg(Color::Indexed(param as u8 - 40)),
+        48 => match extended_color(&mut params) {
+          Some(color) => pen.bg(color),
+          None => pen,
+        },
+        49 => Style { bg: None, ..pen },
+        90..=97 => pen.fg(Color::Indexed(param as u8 - 90 + 8)),
+        100..=107 => pen.bg(Color::Indexed(param as u8 - 100 + 8)),
+        _ => pen,
+      };
+    }
+  }
+
+  fn osc_done(&mut self) {
+    // `0;title` and `2;title` name the window.
+    if let Some(("0" | "2", title)) = self.osc.split_once(';') {
+      self.title = Some(title.to_string());
+    }
+  }
+
+  fn print(&mut self, char: char) {
+    if self.wrap_pending {
+      self.carriage_return();
+      self.line_feed();
+    }
+    let (row, column) = self.cursor;
+    self.grid[row][column] = Cell { char, style: self.pen };
+    match column + 1 < self.columns {
+      true => self.cursor.1 += 1,
+      false => self.wrap_pending = true,
+    }
+  }
+
+  fn carriage_return(&mut self) {
+    self.cursor.1 = 0;
+    self.wrap_pending = false;
+  }
+
+  fn line_feed(&mut self) {
+    self.wrap_pending = false;
+    if self.cursor.0 == self.region.1 {
+      self.scroll_up();
+    } else if self.cursor.0 + 1 < self.rows {
+      self.cursor.0 += 1;
+    }
+  }
+
+  fn reverse_index(&mut self) {
+    if self.cursor.0 == self.region.0 {
+      self.scroll_down();
+    } else {
+      self.cursor.0 = self.cursor.0.saturating_sub(1);
+    }
+  }
+
+  fn scroll_up(&mut self) {
+    let line = self.grid.remove(self.region.0);
+    if self.region.0 == 0 && self.alternate.is_none() {
+      self.push_scrollback(&line);
+    }
+    self.grid.insert(self.region.1, vec![Cell::blank(self.pen); self.columns]);
+  }
+
+  fn scroll_down(&mut self) {
+    self.grid.remove(self.region.1);
+    self.grid.insert(self.region.0, vec![Cell::blank(self.pen); self.columns]);
+  }
+
+  fn push_scrollback(&mut self, line: &[Cell]) {
+    let line: String = line.iter().map(|cell| cell.char).collect();
+    self.scrollback.push(line.trim_end().to_string());
+    if self.scrollback.len() > SCROLLBACK {
+      self.scrollback.remove(0);
+    }
+  }
+
+  fn erase(&mut self, row: usize, columns: std::ops::Range<usize>) {
+    let blank = Cell::blank(self.pen);
+    self.grid[row][columns].fill(blank);
+  }
+
+  fn alternate_screen(&mut self, enter: bool) {
+    let blank = vec![vec![Cell::blank(Style::default()); self.columns]; self.rows];
+    match (enter, self.alternate.take()) {
+      (true, None) => {
+        self.alternate = Some((std::mem::replace(&mut self.grid, blank), self.cursor))
+      }
+      (false, Some((grid, cursor))) => (self.grid, self.cursor) = (grid, cursor),
+      (_, alternate) => self.alternate = alternate,
+    }
+  }
+}
+
+/// The color of `38;5;n` or `38;2;r;g;b`, after the 38 (or 48).
+fn extended_color(params: &mut impl Iterator<Item = usize>) -> Option<Color> {
+  match params.next()? {
+    5 => Some(Color::Indexed(params.next()? as u8)),
+    2 => {
+      let (r, g, b) = (params.next()?, params.next()?, params.next()?);
+      Some(Color::Rgb(r as u8, g as u8, b as u8))
+    }
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn line(screen: &Screen, row: usize) -> String {
+    let line: String = screen.grid[row].iter().map(|cell| cell.char).collect();
+    line.trim_end().to_string()
+  }
+
+  #[test]
+  fn test_screen() {
+    let mut screen = Screen::new(3, 10);
+    screen.feed(b"$ ls\r\nab\x1b[31mcd\x1b[0m\r\n");
+    assert_eq!((line(&screen, 0), line(&screen, 1)), ("$ ls".to_string(), "abcd".to_string()));
+    assert_eq!(screen.grid[1][2].style.fg, Some(Color::Indexed(1)));
+    assert_eq!(screen.cursor(), (2, 0));
+
+    // Scrolling off the top, and a char cut in half between two reads.
+    screen.feed(b"one\r\ntwo \xc3");
+    screen.feed(b"\xa9\x1b[2;3H\x1b[K");
+    assert_eq!(screen.scrollback, Vec::from_iter(["$ ls".to_string()]));
+    assert_eq!((line(&screen, 1), line(&screen, 2)), ("on".to_string(), "two é".to_string()));
+
+    screen.feed(b"\x1b[6n\x1b]0;title\x07");
+    assert_eq!(screen.take_replies(), b"\x1b[2;3R");
+    assert_eq!(screen.title.as_deref(), Some("title"));
+
+    screen.feed(b"\x1b[?1049h\x1b[2Jfull\x1b[?1049l");
+    assert_eq!(screen.text(), ("$ ls\nabcd\non\ntwo é".to_string(), 2));
+  }
+}
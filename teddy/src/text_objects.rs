@@ -0,0 +1,288 @@
+//! Finds the text objects around the cursor for operators and visual mode. The ones that follow the
+//! parse tree are in [`crate::syntax`].
+
+use std::ops::Range;
+
+use ropey::Rope;
+use teddy_core::text_object::{BetweenChars, TextObject};
+
+use crate::syntax::{self, Language};
+
+/// The chars `object` covers around `char_idx`, or only its contents when `inner`. `language` is
+/// needed for the syntax aware objects.
+pub fn select(
+  rope: &Rope,
+  char_idx: usize,
+  object: TextObject,
+  inner: bool,
+  language: Option<Language>,
+) -> Option<Range<usize>> {
+  let char_idx = char_idx.min(rope.len_chars().checked_sub(1)?);
+  match object {
+    TextObject::Word => word(rope, char_idx, false, inner),
+    TextObject::BigWord => word(rope, char_idx, true, inner),
+    TextObject::Quote(quote) => quoted(rope, char_idx, quote, inner),
+    TextObject::Between(chars) => between(rope, char_idx, chars, inner),
+    TextObject::Sentence => sentence(rope, char_idx, inner),
+    TextObject::Paragraph => paragraph(rope, char_idx, inner),
+    object => syntax::select(rope, char_idx, object, inner, language?),
+  }
+}
+
+/// The first char of the line at `char_idx` and the char after its last one, line break excluded.
+fn line_bounds(rope: &Rope, char_idx: usize) -> (usize, usize) {
+  let line = rope.char_to_line(char_idx);
+  let start = rope.line_to_char(line);
+  let len = rope.line(line).chars().take_while(|char| *char != '\n' && *char != '\r').count();
+  (start, start + len)
+}
+
+fn is_blank(char: char) -> bool {
+  char == ' ' || char == '\t'
+}
+
+/// Grows `range` over the blanks after it, or before it when there are none after, staying within
+/// the line `bounds`.
+fn with_blanks(rope: &Rope, range: Range<usize>, (start, end): (usize, usize)) -> Range<usize> {
+  let mut to = range.end;
+  while to < end && is_blank(rope.char(to)) {
+    to += 1;
+  }
+  if to > range.end {
+    return range.start..to;
+  }
+  let mut from = range.start;
+  while from > start && is_blank(rope.char(from - 1)) {
+    from -= 1;
+  }
+  from..range.end
+}
+
+/// The chars between `open` and `close`. When they're alone at the end and start of their lines,
+/// like a block's braces, the line breaks and indent next to them are left out too.
+pub(crate) fn inside(rope: &Rope, open: usize, close: usize) -> Range<usize> {
+  let open_line = rope.char_to_line(open);
+  let close_line = rope.char_to_line(close);
+  let mut range = open + 1..close;
+  if open_line == close_line {
+    return range;
+  }
+  if rope.slice(open + 1..line_bounds(rope, open).1).chars().all(is_blank) {
+    range.start = rope.line_to_char(open_line + 1);
+  }
+  if rope.slice(rope.line_to_char(close_line)..close).chars().all(is_blank) {
+    range.end = rope.line_to_char(close_line).max(range.start);
+  }
+  range
+}
+
+#[derive(PartialEq)]
+enum CharClass {
+  Blank,
+  Word,
+  Punctuation,
+}
+
+fn word(rope: &Rope, char_idx: usize, big: bool, inner: bool) -> Option<Range<usize>> {
+  let bounds = line_bounds(rope, char_idx);
+  let (start, end) = bounds;
+  if char_idx >= end {
+    return None;
+  }
+
+  let class = |idx: usize| match rope.char(idx) {
+    char if is_blank(char) => CharClass::Blank,
+    char if big || char.is_alphanumeric() || char == '_' => CharClass::Word,
+    _ => CharClass::Punctuation,
+  };
+  let run = |idx: usize| {
+    let (mut from, mut to) = (idx, idx + 1);
+    while from > start && class(from - 1) == class(idx) {
+      from -= 1;
+    }
+    while to < end && class(to) == class(idx) {
+      to += 1;
+    }
+    from..to
+  };
+
+  let word = run(char_idx);
+  match inner {
+    true => Some(word),
+    // On blanks, `aw` is the blanks and the word after them.
+    false if class(char_idx) == CharClass::Blank && word.end < end => {
+      Some(word.start..run(word.end).end)
+    }
+    false => Some(with_blanks(rope, word, bounds)),
+  }
+}
+
+fn quoted(rope: &Rope, char_idx: usize, quote: char, inner: bool) -> Option<Range<usize>> {
+  let bounds = line_bounds(rope, char_idx);
+  let mut quotes = Vec::new();
+  let mut escaped = false;
+  for (offset, char) in rope.slice(bounds.0..bounds.1).chars().enumerate() {
+    if char == quote && !escaped {
+      quotes.push(bounds.0 + offset);
+    }
+    escaped = char == '\\' && !escaped;
+  }
+
+  // Quotes pair up from the start of the line, the first pair not before the cursor is taken.
+  let pair = quotes.chunks_exact(2).find(|pair| pair[1] >= char_idx)?;
+  match inner {
+    true => Some(pair[0] + 1..pair[1]),
+    false => Some(with_blanks(rope, pair[0]..pair[1] + 1, bounds)),
+  }
+}
+
+fn between(rope: &Rope, char_idx: usize, chars: BetweenChars, inner: bool) -> Option<Range<usize>> {
+  let (open, close) = chars.chars();
+
+  let mut depth = 0;
+  let mut open_idx = (rope.char(char_idx) == open).then_some(char_idx);
+  for (offset, char) in rope.chars_at(char_idx).reversed().enumerate() {
+    if open_idx.is_some() {
+      break;
+    }
+    match char {
+      char if char == close => depth += 1,
+      char if char == open && depth == 0 => open_idx = Some(char_idx - offset - 1),
+      char if char == open => depth -= 1,
+      _ => {}
+    }
+  }
+  let open_idx = open_idx?;
+
+  let mut depth = 0;
+  let mut close_idx = None;
+  for (offset, char) in rope.chars_at(open_idx + 1).enumerate() {
+    match char {
+      char if char == open => depth += 1,
+      char if char == close && depth == 0 => {
+        close_idx = Some(open_idx + 1 + offset);
+        break;
+      }
+      char if char == close => depth -= 1,
+      _ => {}
+    }
+  }
+  let close_idx = close_idx?;
+
+  match inner {
+    true => Some(inside(rope, open_idx, close_idx)),
+    false => Some(open_idx..close_idx + 1),
+  }
+}
+
+/// Lines that are blank, or not, around `char_idx`. `ap` adds the blank lines after a paragraph,
+/// or before it at the end of the file.
+fn paragraph(rope: &Rope, char_idx: usize, inner: bool) -> Option<Range<usize>> {
+  let lines = rope.len_lines();
+  let blank = |line: usize| rope.line(line).chars().all(char::is_whitespace);
+  let run = |line: usize| {
+    let (mut first, mut last) = (line, line);
+    while first > 0 && blank(first - 1) == blank(line) {
+      first -= 1;
+    }
+    while last + 1 < lines && blank(last + 1) == blank(line) {
+      last += 1;
+    }
+    first..last + 1
+  };
+
+  let mut range = run(rope.char_to_line(char_idx));
+  if !inner && range.end < lines {
+    range.end = run(range.end).end;
+  } else if !inner && range.start > 0 {
+    range.start = run(range.start - 1).start;
+  }
+  Some(rope.line_to_char(range.start)..rope.line_to_char(range.end))
+}
+
+/// Sentences end with `.`, `!` or `?`, maybe followed by closing quotes or brackets, and then
+/// whitespace. They never go past their paragraph.
+fn sentence(rope: &Rope, char_idx: usize, inner: bool) -> Option<Range<usize>> {
+  let lines = paragraph(rope, char_idx, true)?;
+  let text: Vec<char> = rope.slice(lines.clone()).chars().collect();
+  let at = char_idx - lines.start;
+
+  let mut starts = Vec::from_iter([text.iter().take_while(|char| char.is_whitespace()).count()]);
+  for (idx, char) in text.iter().enumerate() {
+    if !matches!(char, '.' | '!' | '?') {
+      continue;
+    }
+    let mut next = idx + 1;
+    while next < text.len() && matches!(text[next], ')' | ']' | '"' | '\'') {
+      next += 1;
+    }
+    if next >= text.len() || !text[next].is_whitespace() {
+      continue;
+    }
+    while next < text.len() && text[next].is_whitespace() {
+      next += 1;
+    }
+    if next < text.len() {
+      starts.push(next);
+    }
+  }
+
+  let current = starts.iter().rposition(|start| *start <= at).unwrap_or(0);
+  let from = starts[current];
+  let next = starts.get(current + 1).copied();
+  let mut to = next.unwrap_or(text.len());
+  while to > from && text[to - 1].is_whitespace() {
+    to -= 1;
+  }
+
+  let range = match (inner, next) {
+    (true, _) => from..to,
+    (false, Some(next)) => from..next,
+    (false, None) => {
+      let mut from = from;
+      while from > 0 && text[from - 1].is_whitespace() {
+        from -= 1;
+      }
+      from..to
+    }
+  };
+  Some(lines.start + range.start..lines.start + range.end)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(rope: &Rope, idx: usize, object: TextObject, inner: bool) -> String {
+    let range = select(rope, idx, object, inner, None).unwrap();
+    rope.slice(range).to_string()
+  }
+
+  #[test]
+  fn test_text_objects() {
+    let rope = Rope::from_str("let x = foo(a, \"b c\");\n\nOne two. Three (four)!\nFive.\n");
+    assert_eq!(text(&rope, 9, TextObject::Word, true), "foo");
+    assert_eq!(text(&rope, 9, TextObject::Word, false), " foo");
+    assert_eq!(text(&rope, 0, TextObject::Word, false), "let ");
+    assert_eq!(text(&rope, 9, TextObject::BigWord, true), "foo(a,");
+    assert_eq!(text(&rope, 17, TextObject::Quote('"'), true), "b c");
+    assert_eq!(text(&rope, 17, TextObject::Quote('"'), false), " \"b c\"");
+
+    let parentheses = TextObject::Between(BetweenChars::Parentheses);
+    assert_eq!(text(&rope, 17, parentheses, true), "a, \"b c\"");
+    assert_eq!(text(&rope, 11, parentheses, false), "(a, \"b c\")");
+    assert_eq!(text(&rope, 40, parentheses, true), "four");
+    assert!(select(&rope, 2, parentheses, true, None).is_none());
+
+    assert_eq!(text(&rope, 26, TextObject::Sentence, true), "One two.");
+    assert_eq!(text(&rope, 26, TextObject::Sentence, false), "One two. ");
+    assert_eq!(text(&rope, 35, TextObject::Sentence, true), "Three (four)!");
+    assert_eq!(text(&rope, 50, TextObject::Sentence, false), "\nFive.");
+    assert_eq!(text(&rope, 26, TextObject::Paragraph, true), "One two. Three (four)!\nFive.\n");
+    assert_eq!(text(&rope, 0, TextObject::Paragraph, false), "let x = foo(a, \"b c\");\n\n");
+
+    let braces = TextObject::Between(BetweenChars::Braces);
+    let block = Rope::from_str("fn main() {\n  body();\n}\n");
+    assert_eq!(text(&block, 14, braces, true), "  body();\n");
+  }
+}
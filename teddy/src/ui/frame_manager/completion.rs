@@ -0,0 +1,88 @@
+use ratatui::{
+  buffer::Buffer,
+  layout::Rect,
+  style::{Color, Modifier, Style},
+  text::{Line, Span},
+  widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+use teddy_config::ThemeConfig;
+
+use crate::{editor::completion::CompletionMenu, prelude::f};
+
+const MAX_HEIGHT: u16 = 10;
+const MAX_WIDTH: u16 = 50;
+const DOCS_WIDTH: u16 = 40;
+
+pub struct CompletionPopup<'a> {
+  pub menu: &'a CompletionMenu,
+  pub config: ThemeConfig,
+}
+
+impl CompletionPopup<'_> {
+  /// Draws the menu under the screen position `anchor`, or above it when there's no room, with
+  /// the documentation of the selected item next to it.
+  pub fn ui(&self, anchor: (u16, u16), area: Rect, buf: &mut Buffer) {
+    let height = (self.menu.len() as u16).min(MAX_HEIGHT).min(area.height);
+    if height == 0 {
+      return;
+    }
+    let label_width = self.menu.items().map(|item| item.label.chars().count()).max().unwrap_or(0);
+    let width = (label_width as u16 + 8).min(MAX_WIDTH).min(area.width);
+
+    let y = if anchor.1 + 1 + height <= area.bottom() {
+      anchor.1 + 1
+    } else {
+      anchor.1.saturating_sub(height).max(area.y)
+    };
+    let x = anchor.0.min(area.right().saturating_sub(width));
+    let menu_area = Rect { x, y, width, height };
+
+    let offset = self.menu.selected_idx().saturating_sub(height as usize - 1);
+    let lines =
+      self.menu.items().enumerate().skip(offset).take(height as usize).map(|(idx, item)| {
+        let style = match idx == self.menu.selected_idx() {
+          true => Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD),
+          false => Style::default(),
+        };
+        let label_width = (width as usize).saturating_sub(7);
+        Line::from_iter([
+          Span::styled(
+            f!(" {:<label_width$.label_width$}", item.label),
+            style.fg(self.config.foreground),
+          ),
+          Span::styled(f!(" {:>4} ", item.kind.label()), style.fg(Color::Gray)),
+        ])
+      });
+
+    Clear.render(menu_area, buf);
+    buf.set_style(menu_area, Style::default().bg(self.config.background_secondary));
+    Paragraph::new(lines.collect::<Vec<_>>()).render(menu_area, buf);
+
+    let Some(item) = self.menu.selected() else {
+      return;
+    };
+    let docs = [item.detail.as_deref(), item.documentation.as_deref()];
+    let docs: Vec<&str> = docs.into_iter().flatten().filter(|docs| !docs.is_empty()).collect();
+    if docs.is_empty() {
+      return;
+    }
+
+    let docs_width = DOCS_WIDTH.min(area.width);
+    let docs_x = if menu_area.right() + docs_width <= area.right() {
+      menu_area.right()
+    } else {
+      menu_area.x.saturating_sub(docs_width).max(area.x)
+    };
+    let docs_area =
+      Rect { x: docs_x, y, width: docs_width, height: MAX_HEIGHT.min(area.bottom() - y) };
+
+    let block = Block::new().borders(Borders::ALL).border_style(Style::default().fg(Color::Gray));
+    let paragraph = Paragraph::new(docs.join("\n\n"))
+      .block(block)
+      .wrap(Wrap { trim: false })
+      .style(Style::default().bg(self.config.background_secondary).fg(self.config.foreground));
+
+    Clear.render(docs_area, buf);
+    paragraph.render(docs_area, buf);
+  }
+}
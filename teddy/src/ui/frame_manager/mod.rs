@@ -1,18 +1,31 @@
+mod completion;
 mod statusbar;
 
+use std::rc::Rc;
+
+use completion::CompletionPopup;
 use ratatui::{
+  buffer::Buffer,
   layout::{Constraint, Layout, Rect},
-  style::{Color, Style},
-  text::{Line, Span, Text},
+  style::{Color, Modifier, Style},
+  text::{Line, Span},
   widgets::Widget,
   Frame,
 };
 use statusbar::StatusBar;
-use teddy_config::Config;
-use teddy_core::buffer::Buffer;
-//use teddy_config::Config;
+use teddy_config::{Config, ThemeConfig};
+use teddy_core::{
+  action::{Diagnostic, DiagnosticLevel},
+  input_mode::InputMode,
+};
 
-use crate::editor::Editor;
+use crate::{
+  diff::{HunkKind, Side},
+  editor::{diagnostics::level_color, Editor},
+  options::window::SignColumn,
+  prelude::f,
+  terminal::Terminal,
+};
 
 pub struct FrameManagerRenderer<'a> {
   pub editor: &'a mut Editor,
@@ -37,45 +50,408 @@ pub struct FrameRenderer<'a> {
 }
 impl FrameRenderer<'_> {
   pub fn ui(&mut self, area: Rect, frame: &mut Frame<'_>) {
+    self.editor.vcs.update();
+    let layout =
+      Layout::default().constraints([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+    let text_area = layout[0];
     let buffer = frame.buffer_mut();
-    let Some(active_frame) = self.editor.frames.active_frame_mut() else { panic!("the fuuuck") };
 
-    let buffer_str = active_frame.buff().to_string();
+    let Some(active) = self.editor.frames.active_id() else { panic!("the fuuuck") };
+    let side = self.editor.update_diff(false);
+    let other = side.zip(self.editor.diff.as_ref()).map(|(side, diff)| diff.id(side.other()));
+    let cursor = match side.zip(other) {
+      Some((side, other)) => {
+        let [left, separator, right] =
+          Layout::horizontal([Constraint::Fill(1), Constraint::Length(1), Constraint::Fill(1)])
+            .areas(text_area);
+        let (active_area, other_area) = match side {
+          Side::Left => (left, right),
+          Side::Right => (right, left),
+        };
+        let cursor = self.text(active, active_area, buffer, None);
+        // The other side scrolls along, to the line across from the top one.
+        let scroll = self.editor.frames.active_frame().map_or(0, |frame| frame.scroll);
+        let scroll = self.editor.diff.as_ref().map_or(scroll, |diff| diff.across(side, scroll));
+        self.text(other, other_area, buffer, Some(scroll));
+        buffer.set_style(separator, Style::default().bg(self.config.theme.background_secondary));
+        cursor
+      }
+      None => self.text(active, text_area, buffer, None),
+    };
 
-    let layout =
-      Layout::default().constraints([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+    frame.set_cursor(cursor.0, cursor.1);
 
-    let buffer_len = buffer_str.len();
-    let max_line_len = count_digits(buffer_len as i32);
+    if let Some(menu) = self.editor.completion.menu() {
+      let popup = CompletionPopup { menu, config: self.config.theme };
+      popup.ui(cursor, text_area, frame.buffer_mut());
+    }
 
-    let render_lines = buffer_str.split("\n").enumerate().map(|(idx, item)| {
-      let this_number_len = max_line_len - count_digits(idx as i32);
-      let line_nmbr_str = format!("{}{} ", " ".repeat(this_number_len + 1), idx + 1);
+    let bar = StatusBar { editor: self.editor, config: self.config.theme };
 
-      let line_nmbr_span = Span::styled(line_nmbr_str, Style::default().fg(Color::Gray));
-      let text_span = Span::styled(item, Style::default().fg(Color::Rgb(255, 255, 255)));
+    bar.ui(layout[1], frame);
+  }
 
-      Line::from_iter([line_nmbr_span, text_span])
-    });
+  /// Draws frame `id` in `text_area`, scrolled to `scroll_to` if given, or else so the cursor is on
+  /// screen. Returns where the cursor is.
+  fn text(
+    &mut self,
+    id: u16,
+    text_area: Rect,
+    buffer: &mut Buffer,
+    scroll_to: Option<usize>,
+  ) -> (u16, u16) {
+    let scrolloff = self.editor.options.global.scrolloff;
+    let focused = self.editor.frames.active_id() == Some(id);
+    let selections = if focused { self.editor.selections() } else { Vec::new() };
+    let diff = self.editor.diff.as_ref().and_then(|diff| Some((diff, diff.side(id)?)));
+    let terminal_mode =
+      *self.editor.macro_key_resolver.input_manager.editor_mode() == InputMode::Terminal;
+    let Some(active_frame) = self.editor.frames.frames.get_mut(&id) else {
+      return (text_area.x, text_area.y);
+    };
+    // Terminals are drawn live, unless their scrollback is being read in normal mode.
+    if let Some(terminal) = active_frame.terminal.as_mut() {
+      if (focused && terminal_mode) || (!focused && terminal.running) {
+        return screen(terminal, text_area, buffer);
+      }
+    }
+    active_frame.update_folds();
 
-    let render_text = Text::from_iter(render_lines);
-    render_text.render(layout[0], buffer);
+    let rope = active_frame.buffer.rope();
+    let revision = active_frame.buffer.revision();
+    let diagnostics = match active_frame.path() {
+      Some(path) => self.editor.diagnostics.get(path),
+      None => &[],
+    };
+    let hunks = match active_frame.path() {
+      Some(path) => self.editor.vcs.hunks(path),
+      None => &[],
+    };
 
-    let (x, y) = active_frame.cursor.cursor.get();
-    frame.set_cursor(x as u16 + max_line_len as u16 + 2, y as u16);
+    let height = text_area.height as usize;
 
-    let bar = StatusBar { editor: self.editor, config: self.config.theme };
+    let window = active_frame.options.clone();
+    let tab_width = active_frame.buffer.context.options.indent.tab_width;
+    let folds = &active_frame.folds;
+    let (cursor_x, cursor_y) = match active_frame.cursor.primary().get() {
+      (_, y) if folds.is_hidden(y) => (0, folds.closed_at(y).map_or(y, |fold| fold.start)),
+      position => position,
+    };
+    let primary = active_frame.cursor.primary_idx();
+    let secondary: Vec<usize> = (0..active_frame.cursor.count())
+      .filter(|cursor| *cursor != primary)
+      .map(|cursor| active_frame.char_idx_of(cursor))
+      .collect();
 
-    bar.ui(layout[1], frame);
+    let show_signs = match window.signcolumn {
+      SignColumn::Yes => true,
+      SignColumn::No => false,
+      SignColumn::Auto => !diagnostics.is_empty(),
+    };
+    let number_width = match window.number || window.relativenumber {
+      true => count_digits(rope.len_lines() as i32).max(3) + 1,
+      false => 0,
+    };
+    let show_folds = !folds.is_empty();
+    let show_hunks = !hunks.is_empty();
+    let gutter_width =
+      show_signs as usize + show_hunks as usize + show_folds as usize + number_width;
+    let text_width = (text_area.width as usize).saturating_sub(gutter_width).max(1);
+
+    let lines = &active_frame.lines;
+    let line_text = |idx: usize| lines.line(rope, revision, idx);
+    let rows = |idx: usize| match window.wrap {
+      _ if folds.is_hidden(idx) => 0,
+      true if folds.closed_at(idx).is_none() => {
+        display_width(&line_text(idx), tab_width).max(1).div_ceil(text_width)
+      }
+      _ => 1,
+    };
+    let cursor_col =
+      display_width(&line_text(cursor_y).chars().take(cursor_x).collect::<String>(), tab_width);
+    let cursor_row = match window.wrap {
+      true => cursor_col / text_width,
+      false => 0,
+    };
+
+    // Keep `scrolloff` lines around the cursor, and the whole cursor row on screen when wrapping.
+    let scrolloff = scrolloff.min(height.saturating_sub(1) / 2);
+    let mut scroll = active_frame.scroll.min(cursor_y.saturating_sub(scrolloff));
+    if cursor_y + scrolloff + 1 > scroll + height {
+      scroll = cursor_y + scrolloff + 1 - height.max(1);
+    }
+    while scroll < cursor_y && (scroll..cursor_y).map(rows).sum::<usize>() + cursor_row + 1 > height
+    {
+      scroll += 1;
+    }
+    if let Some(scroll_to) = scroll_to {
+      scroll = scroll_to.min(rope.len_lines() - 1);
+    }
+    active_frame.scroll = scroll;
+
+    let theme = &self.config.theme;
+    let snippet = self.editor.snippet.as_ref().filter(|_| focused);
+    let placeholders = snippet.map(|session| session.current()).unwrap_or_default();
+
+    let mut row = 0;
+    let mut cursor = (text_area.x + gutter_width as u16, text_area.y);
+    for idx in scroll..rope.len_lines() {
+      if row >= height {
+        break;
+      }
+      if folds.is_hidden(idx) {
+        continue;
+      }
+      let fold = folds.closed_at(idx);
+      let text: Rc<str> = match fold {
+        Some(fold) => {
+          let lines = fold.end - fold.start + 1;
+          f!("{} ··· {lines} lines", line_text(idx).trim_end()).into()
+        }
+        None => line_text(idx),
+      };
+      let line_start = rope.line_to_char(idx);
+      let line_end = line_start + text.chars().count();
+      let on_line: Vec<&Diagnostic> = diagnostics
+        .iter()
+        .filter(|diagnostic| {
+          let starts_here = diagnostic.from >= line_start;
+          diagnostic.from <= line_end && (starts_here || diagnostic.to > line_start)
+        })
+        .collect();
+      let worst = on_line.iter().max_by_key(|diagnostic| diagnostic.level.severity());
+      let diff_kind = diff.and_then(|(diff, side)| diff.kind_at(side, idx));
+      let changed = diff.map(|(diff, side)| diff.changed_chars(side, idx)).unwrap_or_default();
+
+      let cells = cells(&text, tab_width, window.list, |column| {
+        let char_idx = line_start + column;
+        let style = diagnostic_style(char_idx, &on_line, theme);
+        let style = match changed.iter().any(|range| range.contains(&column)) {
+          true => style.bg(Color::Rgb(74, 88, 140)),
+          false => style,
+        };
+        let placeholder = placeholders.iter().any(|range| range.contains(&char_idx));
+        let selected = selections.iter().any(|selection| selection.contains(&char_idx));
+        let style = match placeholder || selected {
+          true => style.bg(theme.background_secondary),
+          false => style,
+        };
+        // Only the primary cursor is the terminal's, the others are drawn.
+        match secondary.contains(&char_idx) {
+          true => style.add_modifier(Modifier::REVERSED),
+          false => style,
+        }
+      });
+      let cells = match fold {
+        Some(_) => {
+          let style = Style::default().fg(Color::Gray).bg(theme.background_secondary);
+          cells.into_iter().map(|(char, _)| (char, style)).collect()
+        }
+        None => cells,
+      };
+      let chunks: Vec<&[(char, Style)]> = match window.wrap {
+        true if !cells.is_empty() => cells.chunks(text_width).collect(),
+        true => Vec::from_iter([&cells[..]]),
+        false => Vec::from_iter([&cells[..cells.len().min(text_width)]]),
+      };
+
+      if idx == cursor_y {
+        let y = text_area.y + (row + cursor_row).min(height - 1) as u16;
+        let x = match window.wrap {
+          true => cursor_col % text_width,
+          false => cursor_col.min(text_width - 1),
+        };
+        cursor = (text_area.x + (gutter_width + x) as u16, y);
+      }
+
+      let last_chunk = chunks.len() - 1;
+      for (chunk_idx, chunk) in chunks.into_iter().enumerate() {
+        if row >= height {
+          break;
+        }
+        let row_area = Rect { y: text_area.y + row as u16, height: 1, ..text_area };
+        if window.cursorline && idx == cursor_y {
+          buffer.set_style(row_area, Style::default().bg(theme.background_secondary));
+        }
+        if let Some(kind) = diff_kind {
+          buffer.set_style(row_area, Style::default().bg(diff_background(kind)));
+        }
+
+        let mut spans = Vec::new();
+        if show_signs {
+          spans.push(match worst {
+            Some(diagnostic) if chunk_idx == 0 => Span::styled(
+              sign(diagnostic.level),
+              Style::default().fg(level_color(theme, diagnostic.level)),
+            ),
+            _ => Span::raw(" "),
+          });
+        }
+        if show_hunks {
+          let hunk = hunks.iter().find(|hunk| hunk.contains(idx));
+          spans.push(match hunk.map(|hunk| (hunk.kind, hunk.sign_line() == idx)) {
+            Some((HunkKind::Added, _)) => Span::styled("▎", Style::default().fg(Color::Green)),
+            Some((HunkKind::Changed, _)) => Span::styled("▎", Style::default().fg(Color::Yellow)),
+            Some((HunkKind::Removed, true)) if chunk_idx == 0 => {
+              Span::styled("▁", Style::default().fg(Color::Red))
+            }
+            _ => Span::raw(" "),
+          });
+        }
+        if show_folds {
+          let start = folds.iter().find(|fold| fold.start == idx);
+          spans.push(match start {
+            Some(fold) if chunk_idx == 0 => {
+              Span::styled(if fold.closed { "+" } else { "-" }, Style::default().fg(Color::Gray))
+            }
+            _ => Span::raw(" "),
+          });
+        }
+        if number_width > 0 {
+          let number = match (window.relativenumber, chunk_idx) {
+            (_, 1..) => String::new(),
+            (true, _) if idx != cursor_y => idx.abs_diff(cursor_y).to_string(),
+            (true, _) if !window.number => "0".to_string(),
+            _ => (idx + 1).to_string(),
+          };
+          let number = f!("{number:>width$} ", width = number_width - 1);
+          spans.push(Span::styled(number, Style::default().fg(Color::Gray)));
+        }
+        spans.extend(styled(chunk));
+
+        if let Some(diagnostic) = worst.filter(|_| chunk_idx == last_chunk) {
+          let message = diagnostic.message.lines().next().unwrap_or_default();
+          let color = level_color(theme, diagnostic.level);
+          spans.push(Span::styled(
+            format!("  {message}"),
+            Style::default().fg(color).add_modifier(Modifier::DIM | Modifier::ITALIC),
+          ));
+        }
+
+        Line::from_iter(spans).render(row_area, buffer);
+        row += 1;
+      }
+    }
+
+    if let Some(max_line_length) = active_frame.buffer.context.options.max_line_length {
+      let x = text_area.x as usize + gutter_width + max_line_length;
+      if x < text_area.right() as usize {
+        let ruler = Rect { x: x as u16, width: 1, ..text_area };
+        buffer.set_style(ruler, Style::default().bg(theme.background_secondary));
+      }
+    }
+
+    cursor
+  }
+}
+
+/// Draws the screen of `terminal`, sized to `area` first, and returns where its cursor is.
+fn screen(terminal: &mut Terminal, area: Rect, buffer: &mut Buffer) -> (u16, u16) {
+  if let Err(err) = terminal.resize(area.height.max(1), area.width.max(1)) {
+    tracing::error!("Couldn't resize the terminal: {err}");
+  }
+  for (row, cells) in terminal.screen.rows().enumerate().take(area.height as usize) {
+    let cells: Vec<(char, Style)> = cells.iter().map(|cell| (cell.char, cell.style)).collect();
+    let row_area = Rect { y: area.y + row as u16, height: 1, ..area };
+    Line::from_iter(styled(&cells)).render(row_area, buffer);
+  }
+  let (row, column) = terminal.screen.cursor();
+  (area.x + column as u16, area.y + row as u16)
+}
+
+/// The background of the lines of a diff that differ from the other side.
+fn diff_background(kind: HunkKind) -> Color {
+  match kind {
+    HunkKind::Added => Color::Rgb(40, 72, 52),
+    HunkKind::Changed => Color::Rgb(44, 52, 86),
+    HunkKind::Removed => Color::Rgb(86, 40, 52),
+  }
+}
+
+/// How many columns `text` takes up on screen.
+fn display_width(text: &str, tab_width: usize) -> usize {
+  text.chars().fold(0, |width, char| match char {
+    '\t' => (width / tab_width + 1) * tab_width,
+    _ => width + 1,
+  })
+}
+
+/// The screen cells of `line`, with tabs expanded. `list` shows tabs, trailing spaces and
+/// non-breaking spaces. `style_at` gets the char index within the line.
+fn cells(
+  line: &str,
+  tab_width: usize,
+  list: bool,
+  style_at: impl Fn(usize) -> Style,
+) -> Vec<(char, Style)> {
+  let list_style = Style::default().fg(Color::DarkGray);
+  let trailing = line.len() - line.trim_end_matches(' ').len();
+  let trailing_from = line.chars().count() - trailing;
+
+  let mut cells = Vec::new();
+  for (column, char) in line.chars().enumerate() {
+    let style = style_at(column);
+    match char {
+      '\t' => {
+        let width = tab_width - cells.len() % tab_width;
+        for idx in 0..width {
+          match list {
+            true => cells.push((if idx == 0 { '>' } else { ' ' }, style.patch(list_style))),
+            false => cells.push((' ', style)),
+          }
+        }
+      }
+      ' ' if list && column >= trailing_from => cells.push(('-', style.patch(list_style))),
+      '\u{a0}' if list => cells.push(('+', style.patch(list_style))),
+      char => cells.push((char, style)),
+    }
+  }
+  cells
+}
+
+/// Groups cells sharing the same style into spans.
+fn styled(cells: &[(char, Style)]) -> Vec<Span<'static>> {
+  let mut spans: Vec<Span> = Vec::new();
+  for (char, style) in cells {
+    match spans.last_mut() {
+      Some(span) if span.style == *style => span.content.to_mut().push(*char),
+      _ => spans.push(Span::styled(char.to_string(), *style)),
+    }
+  }
+  spans
+}
+
+/// The style of the char at `char_idx`, underlined when covered by a diagnostic.
+fn diagnostic_style(char_idx: usize, diagnostics: &[&Diagnostic], theme: &ThemeConfig) -> Style {
+  let text_style = Style::default().fg(Color::Rgb(255, 255, 255));
+  let level = diagnostics
+    .iter()
+    .filter(|diagnostic| diagnostic.from <= char_idx && char_idx < diagnostic.to)
+    .map(|diagnostic| diagnostic.level)
+    .max_by_key(DiagnosticLevel::severity);
+
+  match level {
+    Some(level) => {
+      text_style.add_modifier(Modifier::UNDERLINED).underline_color(level_color(theme, level))
+    }
+    None => text_style,
+  }
+}
+
+fn sign(level: DiagnosticLevel) -> &'static str {
+  match level {
+    DiagnosticLevel::Error => "E",
+    DiagnosticLevel::Warn => "W",
+    DiagnosticLevel::Info => "I",
   }
 }
 
 impl FrameManagerRenderer<'_> {
   pub fn ui(&mut self, area: Rect, frame: &mut Frame<'_>) {
-    let Some(active_frame) = self.editor.frames.active_frame_mut() else {
+    if self.editor.frames.active_frame().is_none() {
       // Nothing to render
       return;
-    };
+    }
 
     let mut frame_renderer = FrameRenderer { editor: self.editor, config: self.config };
 
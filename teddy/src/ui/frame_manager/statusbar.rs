@@ -1,13 +1,13 @@
 use ratatui::{
   layout::{Constraint, Layout, Rect},
-  style::Style,
-  text::Text,
+  style::{Color, Style},
+  text::{Line, Span, Text},
   widgets::Widget,
   Frame,
 };
 use teddy_config::ThemeConfig;
 
-use crate::editor::Editor;
+use crate::{editor::Editor, prelude::f};
 
 use super::super::render_wrappers::InputModeRenderer;
 
@@ -21,7 +21,9 @@ impl StatusBar<'_> {
     let buf = frame.buffer_mut();
 
     let bar_layout =
-      Layout::horizontal([Constraint::Length(8), Constraint::Length(20)]).spacing(1).split(area);
+      Layout::horizontal([Constraint::Length(8), Constraint::Length(20), Constraint::Fill(1)])
+        .spacing(1)
+        .split(area);
 
     buf.set_style(area, Style::default().bg(self.config.background_secondary));
 
@@ -31,6 +33,37 @@ impl StatusBar<'_> {
     if let Some(frame) = self.editor.frames.active_frame() {
       let text = Text::from(frame.buffer.context.name.clone());
       text.render(bar_layout[1], buf);
+
+      let path = frame.path();
+      let (errors, warnings) = match path {
+        Some(path) => self.editor.diagnostics.counts(path),
+        None => (0, 0),
+      };
+      let branch = path.and_then(|path| self.editor.vcs.branch(path));
+      let options = &frame.buffer.context.options;
+      let line_ending =
+        options.line_ending.map(|line_ending| line_ending.name()).unwrap_or("mixed");
+      let mut counts = Line::default();
+      if let Some(branch) = branch {
+        counts.push_span(Span::styled(f!("{branch}  "), Style::default().fg(Color::Magenta)));
+      }
+      counts.spans.extend([
+        Span::styled(f!("E {errors}"), Style::default().fg(self.config.error)),
+        Span::raw("  "),
+        Span::styled(f!("W {warnings}"), Style::default().fg(self.config.warning)),
+        Span::raw(f!("  {}  {line_ending} ", options.encoding.name())),
+      ]);
+      let info_layout =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Length(counts.width() as u16)])
+          .spacing(2)
+          .split(bar_layout[2]);
+      counts.render(info_layout[1], buf);
+
+      let line = frame.cursor.primary().get().1;
+      let blame = path.and_then(|path| self.editor.vcs.blame(path, line, frame.buffer.revision()));
+      if let Some(blame) = blame {
+        Span::styled(blame, Style::default().fg(Color::Gray)).render(info_layout[0], buf);
+      }
     }
   }
 }
@@ -8,6 +8,7 @@ use ratatui::{
   layout::{Constraint, Layout},
   prelude::CrosstermBackend,
   style::Style,
+  widgets::Clear,
   Frame, Terminal,
 };
 use render_wrappers::notification_manager::NotificationManagerRenderer;
@@ -24,7 +25,7 @@ impl Renderer {
     Self(Terminal::new(backend).unwrap(), config)
   }
   pub fn ui(&mut self, editor: &mut Editor) -> Result<(), Box<dyn std::error::Error>> {
-    self.0.draw(|frame| draw(editor, frame, self.1).unwrap())?;
+    self.0.draw(|frame| draw(editor, frame, &self.1).unwrap())?;
 
     Ok(())
   }
@@ -33,12 +34,12 @@ impl Renderer {
 fn draw(
   editor: &mut Editor,
   frame: &mut Frame<'_>,
-  config: Config,
+  config: &Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
   let area = frame.size();
   frame.buffer_mut().set_style(area, Style::default().bg(config.theme.background));
   let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
-  let mut framerenderer = FrameManagerRenderer { editor, config: &config };
+  let mut framerenderer = FrameManagerRenderer { editor, config };
   framerenderer.ui(layout[0], frame);
 
   let underbar = UnderBar { editor, config: config.theme };
@@ -46,6 +47,17 @@ fn draw(
     frame.set_cursor(x, y)
   };
 
+  if let Some(popup) = &editor.popup {
+    let [_, area, _] =
+      Layout::vertical([Constraint::Fill(1), Constraint::Percentage(60), Constraint::Fill(1)])
+        .areas(layout[0]);
+    let [_, area, _] =
+      Layout::horizontal([Constraint::Fill(1), Constraint::Percentage(80), Constraint::Fill(1)])
+        .areas(area);
+    frame.render_widget(Clear, area);
+    popup.draw(frame.buffer_mut(), area)?;
+  }
+
   let notification_renderer =
     NotificationManagerRenderer(editor.frames.notification_manager.clone());
   notification_renderer.ui(frame);
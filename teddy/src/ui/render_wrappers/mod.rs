@@ -21,6 +21,7 @@ impl Widget for InputModeRenderer<'_> {
       InputMode::Command(_) => Color::Gray,
       InputMode::Insert { left_insert: _ } => Color::Green,
       InputMode::Visual(_) => Color::Red,
+      InputMode::Terminal => Color::Magenta,
     };
 
     let style =
@@ -38,11 +38,14 @@ impl NotificationManagerRenderer {
         };
 
         let mut inner_line = vec![Span::from(not.payload.message.clone())];
+        if let Some(spinner) = &not.spinner {
+          inner_line.insert(0, Span::from(format!("{} ", spinner.current())));
+        }
 
         if let Some((style, label)) = status_color {
           inner_line.push(Span::from("  "));
           let text = Span::styled(label, style);
-          inner_line.push(text.into());
+          inner_line.push(text);
         }
 
         let line = Line::from(inner_line).right_aligned();
@@ -65,7 +68,7 @@ impl NotificationManagerRenderer {
         .collect();
     }
 
-    let area = Rect::new(area.width - width as u16, area_height, width, height as u16);
+    let area = Rect::new(area.width - width, area_height, width, height as u16);
 
     let text = Text::from(rendered_text);
     text.render(area, buf);
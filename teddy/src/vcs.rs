@@ -0,0 +1,310 @@
+//! Git integration: buffers of files in a work tree are diffed against the index for the gutter
+//! signs and the hunk commands, and the cursor line is blamed for the status bar. Everything goes
+//! through the `git` command, which runs on a background thread unless the user asked for it, like
+//! staging a hunk.
+
+use std::{
+  cell::RefCell,
+  collections::BTreeMap,
+  io::Write as _,
+  ops::Range,
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+  rc::Rc,
+};
+
+use chrono::DateTime;
+use ropey::Rope;
+use teddy_core::buffer::ChangeListener;
+use teddy_events::{Event, VcsEvent};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+  diff::{hunks, Hunk},
+  prelude::{f, Result},
+};
+
+/// A buffer whose file is in a git work tree.
+struct GitFile {
+  root: PathBuf,
+  /// The path from `root`, the way git names it.
+  relative: String,
+  /// The staged text, `None` while the file isn't tracked.
+  index: Option<String>,
+  hunks: Vec<Hunk>,
+  /// The text after the latest change, left by the change listener until it's diffed.
+  changed: Rc<RefCell<Option<Rope>>>,
+}
+
+impl GitFile {
+  fn diff(&mut self, rope: &Rope) {
+    self.hunks = match &self.index {
+      Some(index) => hunks(index, &rope.to_string()),
+      None => Vec::new(),
+    };
+  }
+}
+
+/// The last blame received, for the line and revision it was asked at.
+struct Blame {
+  path: PathBuf,
+  line: usize,
+  revision: u64,
+  blame: Option<String>,
+}
+
+/// What git knows about the open files.
+#[derive(Default)]
+pub struct Vcs {
+  files: BTreeMap<PathBuf, GitFile>,
+  /// The checked out branch of every work tree, by its root.
+  branches: BTreeMap<PathBuf, String>,
+  blame: Option<Blame>,
+  /// The line blamed last, so it's only asked once.
+  requested: Option<(PathBuf, usize, u64)>,
+}
+
+impl Vcs {
+  /// Finds out in the background whether `path` is in a work tree, and reads its staged text and
+  /// the branch. The answer comes back as a [`VcsEvent::Index`], for [`Vcs::set_index`].
+  pub fn track(&self, path: &Path, events: &UnboundedSender<Event>) {
+    let (path, events) = (path.to_path_buf(), events.clone());
+    std::thread::spawn(move || {
+      if let Some(event) = read_index(&path) {
+        let _ = events.send(Event::Vcs(event));
+      }
+    });
+  }
+
+  /// Reads the index and the branch of a tracked file again, after something else might have
+  /// changed them.
+  pub fn refresh(&self, path: &Path, events: &UnboundedSender<Event>) {
+    if self.files.contains_key(path) {
+      self.track(path, events);
+    }
+  }
+
+  /// Takes in what [`Vcs::track`] read about `path` and diffs `rope`, the text of its buffer,
+  /// against the index. When the file wasn't tracked yet, returns the listener to be given every
+  /// change to its buffer.
+  pub fn set_index(
+    &mut self,
+    path: PathBuf,
+    root: PathBuf,
+    relative: String,
+    branch: String,
+    index: Option<String>,
+    rope: &Rope,
+  ) -> Option<ChangeListener> {
+    self.branches.insert(root.clone(), branch);
+    if let Some(file) = self.files.get_mut(&path) {
+      file.index = index;
+      file.diff(rope);
+      return None;
+    }
+    let changed = Rc::new(RefCell::new(None));
+    let mut file = GitFile { root, relative, index, hunks: Vec::new(), changed: changed.clone() };
+    file.diff(rope);
+    self.files.insert(path, file);
+    Some(Box::new(move |_, rope| *changed.borrow_mut() = Some(rope.clone())))
+  }
+
+  /// Diffs the buffers changed since the last update again.
+  pub fn update(&mut self) {
+    for file in self.files.values_mut() {
+      let changed = file.changed.borrow_mut().take();
+      if let Some(rope) = changed {
+        file.diff(&rope);
+      }
+    }
+  }
+
+  pub fn hunks(&self, path: &Path) -> &[Hunk] {
+    self.files.get(path).map(|file| file.hunks.as_slice()).unwrap_or_default()
+  }
+
+  pub fn branch(&self, path: &Path) -> Option<&str> {
+    let file = self.files.get(path)?;
+    self.branches.get(&file.root).map(String::as_str)
+  }
+
+  /// Stages the hunk at `line` of `rope`, the text of the buffer of `path`.
+  pub fn stage(&mut self, path: &Path, rope: &Rope, line: usize) -> Result<()> {
+    let file = self.files.get_mut(path).ok_or("Not in a git repository")?;
+    let index = file.index.as_deref().ok_or("File isn't tracked by git")?;
+    let hunk = file.hunks.iter().find(|hunk| hunk.contains(line)).ok_or("No hunk at the cursor")?;
+
+    let base: Vec<&str> = index.split_inclusive('\n').collect();
+    let lines = rope.lines_at(hunk.lines.start).take(hunk.lines.len()).map(String::from);
+    let mut staged = base[..hunk.base.start].concat();
+    staged.extend(lines);
+    staged.push_str(&base[hunk.base.end..].concat());
+
+    let mode = git(&file.root, &["ls-files", "--stage", "--", &file.relative], None)?;
+    let mode = mode.split_whitespace().next().unwrap_or("100644").to_string();
+    let hash_object = ["hash-object", "-w", "--stdin", "--path", &file.relative];
+    let blob = git(&file.root, &hash_object, Some(&staged))?;
+    let cacheinfo = f!("{mode},{},{}", blob.trim(), file.relative);
+    git(&file.root, &["update-index", "--cacheinfo", &cacheinfo], None)?;
+
+    file.index = Some(staged);
+    file.diff(rope);
+    Ok(())
+  }
+
+  /// The text putting the hunk at `line` back the way it is in the index, and the char range of
+  /// `rope` it replaces.
+  pub fn reset(&self, path: &Path, rope: &Rope, line: usize) -> Result<(Range<usize>, String)> {
+    let file = self.files.get(path).ok_or("Not in a git repository")?;
+    let index = file.index.as_deref().ok_or("File isn't tracked by git")?;
+    let hunk = file.hunks.iter().find(|hunk| hunk.contains(line)).ok_or("No hunk at the cursor")?;
+
+    let base: Vec<&str> = index.split_inclusive('\n').collect();
+    let chars = rope.line_to_char(hunk.lines.start)..rope.line_to_char(hunk.lines.end);
+    Ok((chars, base[hunk.base.clone()].concat()))
+  }
+
+  /// Blames `line` of the buffer of `path` in the background, unless it was already. The answer
+  /// comes back as [`VcsEvent::Blame`].
+  pub fn request_blame(
+    &mut self,
+    path: &Path,
+    line: usize,
+    rope: &Rope,
+    revision: u64,
+    events: &UnboundedSender<Event>,
+  ) {
+    let Some(file) = self.files.get(path) else {
+      return;
+    };
+    let request = (path.to_path_buf(), line, revision);
+    if self.requested.as_ref() == Some(&request) {
+      return;
+    }
+    self.requested = Some(request);
+
+    let (root, relative, text) = (file.root.clone(), file.relative.clone(), rope.to_string());
+    let (path, events) = (path.to_path_buf(), events.clone());
+    std::thread::spawn(move || {
+      let range = f!("{},{}", line + 1, line + 1);
+      let args = ["blame", "--porcelain", "-L", &range, "--contents", "-", "--", &relative];
+      let blame = git(&root, &args, Some(&text)).ok().and_then(|output| parse_blame(&output));
+      let _ = events.send(Event::Vcs(VcsEvent::Blame { path, line, revision, blame }));
+    });
+  }
+
+  pub fn set_blame(&mut self, path: PathBuf, line: usize, revision: u64, blame: Option<String>) {
+    self.blame = Some(Blame { path, line, revision, blame });
+  }
+
+  /// The blame of `line` of `path`, if it came back for the buffer at `revision`.
+  pub fn blame(&self, path: &Path, line: usize, revision: u64) -> Option<&str> {
+    let blame = self.blame.as_ref()?;
+    let current = blame.path == path && blame.line == line && blame.revision == revision;
+    blame.blame.as_deref().filter(|_| current)
+  }
+}
+
+/// What git knows about `path`, when it's in a work tree.
+fn read_index(path: &Path) -> Option<VcsEvent> {
+  let dir = path.parent()?;
+  let output = git(dir, &["rev-parse", "--show-toplevel", "--show-prefix"], None).ok()?;
+  let mut lines = output.lines();
+  let root = PathBuf::from(lines.next()?);
+  let name = path.file_name()?.to_string_lossy();
+  let relative = f!("{}{name}", lines.next().unwrap_or_default());
+
+  let branch = git(&root, &["branch", "--show-current"], None).unwrap_or_default();
+  let branch = match branch.trim() {
+    "" => "detached".to_string(),
+    branch => branch.to_string(),
+  };
+  let index = git(&root, &["show", &f!(":{relative}")], None).ok();
+  Some(VcsEvent::Index { path: path.to_path_buf(), root, relative, branch, index })
+}
+
+/// Reads `git blame --porcelain` output for one line as "author, date · summary".
+fn parse_blame(output: &str) -> Option<String> {
+  let mut lines = output.lines();
+  let commit = lines.next()?.split_whitespace().next()?;
+  if commit.chars().all(|char| char == '0') {
+    return Some("Not committed yet".to_string());
+  }
+
+  let (mut author, mut time, mut summary) = ("", None, "");
+  for line in lines {
+    let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+    match key {
+      "author" => author = value,
+      "author-time" => time = value.parse().ok().and_then(|time| DateTime::from_timestamp(time, 0)),
+      "summary" => summary = value,
+      _ => {}
+    }
+  }
+  let date = time.map(|time| time.format("%Y-%m-%d").to_string()).unwrap_or_default();
+  Some(f!("{author}, {date} · {summary}"))
+}
+
+/// Runs git in `dir`, with `input` on its stdin, and returns its output.
+fn git(dir: &Path, args: &[&str], input: Option<&str>) -> Result<String> {
+  let mut child = Command::new("git")
+    .arg("-C")
+    .arg(dir)
+    .args(args)
+    .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+    stdin.write_all(input.as_bytes())?;
+  }
+
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    let error = String::from_utf8_lossy(&output.stderr);
+    return Err(error.trim().to_string().into());
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::diff::HunkKind;
+
+  #[test]
+  fn test_stage_hunk() {
+    let dir = std::env::temp_dir().join(f!("teddy-vcs-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+    let setup = [&["init", "-q"][..], &["add", "a.txt"]];
+    if setup.iter().any(|args| git(&dir, args, None).is_err()) {
+      return;
+    }
+
+    let mut vcs = Vcs::default();
+    let rope = Rope::from_str("one\n2\nthree\nfour\n");
+    let Some(VcsEvent::Index { path, root, relative, branch, index }) = read_index(&path) else {
+      panic!("a.txt isn't in a work tree");
+    };
+    let original = Rope::from_str("one\ntwo\nthree\n");
+    let mut listener =
+      vcs.set_index(path.clone(), root, relative, branch, index, &original).unwrap();
+    assert!(vcs.hunks(&path).is_empty());
+    assert!(vcs.branch(&path).is_some());
+
+    let edit = teddy_core::buffer::Edit::insert(0, "");
+    listener(&teddy_core::buffer::BufferChange { edit, revision: 1 }, &rope);
+    vcs.update();
+    assert_eq!(vcs.hunks(&path).len(), 2);
+
+    vcs.stage(&path, &rope, 3).unwrap();
+    assert_eq!(git(&dir, &["show", ":a.txt"], None).unwrap(), "one\ntwo\nthree\nfour\n");
+    assert_eq!(vcs.hunks(&path), &[Hunk { kind: HunkKind::Changed, lines: 1..2, base: 1..2 }]);
+    assert_eq!(vcs.reset(&path, &rope, 1).unwrap(), (4..6, "two\n".to_string()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,8 +1,21 @@
+use std::path::{Path, PathBuf};
+
 use ratatui::style::Color;
 
-#[derive(Default, Clone, Copy)]
+#[derive(Clone)]
 pub struct Config {
   pub theme: ThemeConfig,
+  pub language_servers: Vec<LanguageServerConfig>,
+  pub formatters: Vec<FormatterConfig>,
+  /// Run by `:task <name>`, the first one by `:make`.
+  pub tasks: Vec<TaskConfig>,
+  pub indent: IndentConfig,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 #[derive(Clone, Copy)]
@@ -11,6 +24,10 @@ pub struct ThemeConfig {
   pub background_secondary: Color,
 
   pub foreground: Color,
+
+  pub error: Color,
+  pub warning: Color,
+  pub info: Color,
 }
 impl Default for ThemeConfig {
   fn default() -> Self {
@@ -24,15 +41,213 @@ impl ThemeConfig {
       background: Color::Rgb(36, 39, 58),
       background_secondary: Color::Rgb(49, 50, 68),
       foreground: Color::Rgb(205, 214, 244),
+      error: Color::Rgb(237, 135, 150),
+      warning: Color::Rgb(238, 212, 159),
+      info: Color::Rgb(138, 173, 244),
     }
   }
 }
 
+/// How Insert mode and `>>`/`<<` indent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndentConfig {
+  /// Indent with spaces instead of tabs.
+  pub expand_tab: bool,
+  /// Columns per indent level.
+  pub shift_width: usize,
+  /// Columns a tab takes up.
+  pub tab_width: usize,
+}
+
+impl Default for IndentConfig {
+  fn default() -> Self {
+    Self { expand_tab: true, shift_width: 4, tab_width: 4 }
+  }
+}
+
+/// How to spawn a language server and which files it is responsible for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanguageServerConfig {
+  /// The `languageId` sent to the server when opening documents.
+  pub language_id: String,
+  pub command: String,
+  pub args: Vec<String>,
+  /// File extensions (without the dot) handled by this server.
+  pub file_extensions: Vec<String>,
+}
+
+impl LanguageServerConfig {
+  pub fn new(language_id: &str, command: &str, file_extensions: &[&str]) -> Self {
+    Self {
+      language_id: language_id.to_string(),
+      command: command.to_string(),
+      args: Vec::new(),
+      file_extensions: file_extensions.iter().map(|ext| ext.to_string()).collect(),
+    }
+  }
+
+  #[must_use]
+  pub fn args(mut self, args: &[&str]) -> Self {
+    self.args = args.iter().map(|arg| arg.to_string()).collect();
+    self
+  }
+
+  fn defaults() -> Vec<Self> {
+    Vec::from_iter([
+      Self::new("rust", "rust-analyzer", &["rs"]),
+      Self::new("typescript", "typescript-language-server", &["ts", "tsx", "js", "jsx"])
+        .args(&["--stdio"]),
+      Self::new("python", "pylsp", &["py"]),
+      Self::new("go", "gopls", &["go"]),
+      Self::new("nix", "nil", &["nix"]),
+    ])
+  }
+}
+
+/// What formats the files of a language.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formatter {
+  /// A program reading the text on stdin and writing it formatted to stdout. `{path}` in the
+  /// arguments is replaced with the path of the file.
+  Command { command: String, args: Vec<String> },
+  /// `textDocument/formatting` of the language server.
+  Lsp,
+}
+
+/// The formatter of a language, run by `:format` and, with `on_save`, before every write.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatterConfig {
+  /// A `language_id` of the language server configs.
+  pub language_id: String,
+  pub formatter: Formatter,
+  /// Off unless turned on with [`FormatterConfig::on_save`], writing never changes the text then.
+  pub on_save: bool,
+}
+
+impl FormatterConfig {
+  pub fn command(language_id: &str, command: &str, args: &[&str]) -> Self {
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    let formatter = Formatter::Command { command: command.to_string(), args };
+    Self { language_id: language_id.to_string(), formatter, on_save: false }
+  }
+
+  pub fn lsp(language_id: &str) -> Self {
+    Self { language_id: language_id.to_string(), formatter: Formatter::Lsp, on_save: false }
+  }
+
+  #[must_use]
+  pub fn on_save(mut self, on_save: bool) -> Self {
+    self.on_save = on_save;
+    self
+  }
+
+  fn defaults() -> Vec<Self> {
+    Vec::from_iter([
+      // On stdin rustfmt can't find the crate's edition and would parse it as 2015.
+      Self::command("rust", "rustfmt", &["--edition", "2021"]),
+      Self::command("typescript", "prettier", &["--stdin-filepath", "{path}"]),
+      Self::command("python", "black", &["--quiet", "-"]),
+      Self::lsp("go"),
+      Self::lsp("nix"),
+    ])
+  }
+}
+
+/// How errors are picked out of a task's output.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorFormat {
+  /// A regex matched against each line, like vim's `errorformat`. It names its groups `file`,
+  /// `line` and optionally `column`, `level` (`error`, `warning`, anything else is info) and
+  /// `message`, which is the whole line when left out.
+  Regex(String),
+  /// The JSON lines of cargo's `--message-format=json`.
+  CargoJson,
+}
+
+/// A command run by `:make` or `:task <name>`, whose errors fill the quickfix list. None are set up
+/// by default, a Rust project might use:
+///
+/// ```
+/// use teddy_config::{Config, ErrorFormat, TaskConfig};
+///
+/// let mut config = Config::new();
+/// config.tasks = Vec::from_iter([
+///   TaskConfig::new("check", "cargo check --message-format=short")
+///     .error_format(ErrorFormat::Regex(TaskConfig::RUSTC_SHORT.to_string())),
+///   TaskConfig::new("clippy", "cargo clippy --message-format=json")
+///     .error_format(ErrorFormat::CargoJson),
+///   TaskConfig::new("test", "cargo test")
+///     .error_format(ErrorFormat::Regex(TaskConfig::RUST_PANIC.to_string())),
+/// ]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskConfig {
+  pub name: String,
+  /// Run with `sh -c` in the working directory.
+  pub command: String,
+  pub error_formats: Vec<ErrorFormat>,
+}
+
+impl TaskConfig {
+  /// The errors and warnings of `--message-format=short`, like `src/main.rs:3:5: error: ...`.
+  pub const RUSTC_SHORT: &'static str = concat!(
+    r"^(?P<file>[^\s:][^:]*):(?P<line>\d+):(?P<column>\d+): ",
+    r"(?P<level>error|warning)(?:\[\w+\])?: (?P<message>.+)$"
+  );
+  /// Where a test panicked.
+  pub const RUST_PANIC: &'static str =
+    r"panicked at (?P<file>[^:\s]+):(?P<line>\d+):(?P<column>\d+)";
+
+  pub fn new(name: &str, command: &str) -> Self {
+    Self { name: name.to_string(), command: command.to_string(), error_formats: Vec::new() }
+  }
+
+  #[must_use]
+  pub fn error_format(mut self, error_format: ErrorFormat) -> Self {
+    self.error_formats.push(error_format);
+    self
+  }
+}
+
 impl Config {
   pub fn new() -> Self {
-    Self { theme: ThemeConfig::default() }
+    Self {
+      theme: ThemeConfig::default(),
+      language_servers: LanguageServerConfig::defaults(),
+      formatters: FormatterConfig::defaults(),
+      tasks: Vec::new(),
+      indent: IndentConfig::default(),
+    }
   }
   pub fn from_file() -> Self {
     todo!()
   }
+
+  /// `$XDG_CONFIG_HOME/teddy`, falling back to `~/.config/teddy`.
+  pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+      Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+      _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("teddy"))
+  }
+
+  /// `$XDG_STATE_HOME/teddy`, falling back to `~/.local/state/teddy`. Holds swap files.
+  pub fn state_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+      Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+      _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("state"),
+    };
+    Some(base.join("teddy"))
+  }
+
+  /// The language id of `path`, as known by the language server configs.
+  pub fn language_for(&self, path: &Path) -> Option<&str> {
+    let extension = path.extension()?.to_str()?;
+    self
+      .language_servers
+      .iter()
+      .find(|server| server.file_extensions.iter().any(|ext| ext == extension))
+      .map(|server| server.language_id.as_str())
+  }
 }
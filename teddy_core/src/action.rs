@@ -1,5 +1,7 @@
 use std::{fmt::Debug, path::PathBuf};
 
+use crate::text_object::{Operator, TextObject};
+
 /// Every single action a component can take outside the editor.
 #[derive(PartialEq, Clone)]
 pub enum Action {
@@ -11,6 +13,155 @@ pub enum Action {
   WriteActiveBuffer,
 
   WriteDiagnostic(Diagnostic),
+  /// Drops every diagnostic for a file, sent before a fresh set is written.
+  ClearDiagnostics(PathBuf),
+
+  NextDiagnostic,
+  PreviousDiagnostic,
+  ShowDiagnostics,
+
+  NextHunk,
+  PreviousHunk,
+  /// Stages the git hunk at the cursor.
+  StageHunk,
+  /// Replaces the git hunk at the cursor with the staged lines.
+  ResetHunk,
+
+  /// Opens the file to the right of the active buffer and diffs the two.
+  DiffSplit(PathBuf),
+  DiffOff,
+  /// Moves the cursor to the next change of the diff.
+  NextChange,
+  PreviousChange,
+  /// Replaces the change at the cursor with the other side's version, `do`.
+  DiffGet,
+  /// Replaces the other side's version of the change at the cursor with this one, `dp`.
+  DiffPut,
+  /// Focuses the other side of the diff.
+  SwitchDiffSide,
+
+  /// Formats the active buffer with its language's formatter, see `:format`.
+  Format,
+
+  /// Opens a shell in a new frame, see `:terminal`.
+  OpenTerminal,
+  /// Runs a shell command in the background, see `:!`.
+  Shell {
+    command: String,
+    output: ShellOutput,
+  },
+  /// Stops the shell commands and tasks still running.
+  CancelShell,
+  /// Runs the task with this name, or the first one, see `:task` and `:make`.
+  RunTask(Option<String>),
+  /// Opens the file of the next entry of the quickfix list, `:cnext`.
+  NextQuickfix,
+  PreviousQuickfix,
+  /// Lists the entries of the quickfix list, `:copen`.
+  ShowQuickfix,
+
+  GotoDefinition,
+  Hover,
+  RequestCompletion,
+
+  IndentLine,
+  DedentLine,
+
+  /// Opens the file and moves the cursor to the char index.
+  JumpTo(PathBuf, usize),
+  ClosePopup,
+
+  /// Runs an argument of `:set`, or of `:setlocal` when `local`.
+  SetOption {
+    argument: String,
+    local: bool,
+  },
+
+  /// Settles a change made on disk to the active buffer's file.
+  ResolveDiskChange(DiskChoice),
+  /// Restores the active buffer from the swap file left by a crash, or deletes it if `discard`.
+  Recover {
+    discard: bool,
+  },
+
+  /// Saves the open files to a session file, the working directory's one when `None`.
+  MakeSession(Option<PathBuf>),
+  /// Opens the files of a session file, the working directory's one when `None`.
+  SourceSession(Option<PathBuf>),
+
+  /// Sets a mark at the cursor, `m{a-z}` in the buffer or `m{A-Z}` across files.
+  SetMark(char),
+  /// Moves the cursor to a mark, to the first non-blank of its line when `linewise`.
+  GotoMark {
+    mark: char,
+    linewise: bool,
+  },
+  /// Goes back to where the cursor was before the last jump, or forward again when `newer`.
+  Jump {
+    newer: bool,
+  },
+  ShowMarks,
+  ShowJumps,
+
+  /// Applies an operator to a text object around the cursor, like `diw` or `ca(`.
+  Operate {
+    operator: Operator,
+    object: TextObject,
+    inner: bool,
+  },
+  /// Selects a text object around the cursor in visual mode.
+  SelectObject {
+    object: TextObject,
+    inner: bool,
+  },
+  /// Applies an operator to the visual selection.
+  OperateSelection(Operator),
+  /// Puts the last deleted or yanked text after the cursor.
+  Put,
+  /// Undoes the last change, or redoes the last undone one.
+  Undo {
+    redo: bool,
+  },
+
+  /// Adds a cursor on the next match of the selection, or of the word under the cursor.
+  AddCursorAtNextMatch,
+  /// Splits the visual selections into one per line, each with its own cursor.
+  SplitSelection,
+  /// Drops every cursor but the primary one.
+  KeepPrimaryCursor,
+
+  /// Opens or closes folds, see [`FoldCommand`].
+  Fold(FoldCommand),
+
+  ShowMacros,
+  /// Puts a macro in the register as text, to be edited in a buffer.
+  EditMacro(char),
+  /// Sets a macro from keys in vim key notation, or from the register without them.
+  SetMacro {
+    label: char,
+    keys: Option<String>,
+  },
+}
+
+/// What the `z` commands do with the folds at the cursor.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FoldCommand {
+  Open,
+  Close,
+  Toggle,
+  OpenAll,
+  CloseAll,
+}
+
+/// What to do about a file that changed on disk while its buffer had changes too.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiskChoice {
+  /// Drop the buffer's changes and load the file.
+  Reload,
+  /// Keep the buffer as it is, writing it will overwrite the file.
+  Keep,
+  /// Show how the two differ.
+  Diff,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -24,6 +175,17 @@ pub enum NotificationLevel {
   Fail,
 }
 
+/// Where the output of a shell command goes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ShellOutput {
+  /// A notification, or a scratch buffer when it's longer than a line, `:!cmd`.
+  Show,
+  /// Below the cursor line, `:r !cmd`.
+  Read,
+  /// In place of the lines of the range, which are the command's input, `:{range}!cmd`.
+  Filter(String),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Spinner {
   animation: Vec<char>,
@@ -42,6 +204,16 @@ impl Spinner {
       self.index += 1;
     }
   }
+
+  pub fn current(&self) -> char {
+    self.animation[self.index]
+  }
+}
+
+impl Default for Spinner {
+  fn default() -> Self {
+    Self::new(Vec::from_iter("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏".chars()))
+  }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -71,20 +243,85 @@ impl Notification {
   }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum DiagnosticLevel {
   Info,
   Error,
   Warn,
 }
 
-#[derive(PartialEq, Clone)]
+impl DiagnosticLevel {
+  /// Higher is more severe.
+  pub fn severity(&self) -> u8 {
+    match self {
+      DiagnosticLevel::Info => 0,
+      DiagnosticLevel::Warn => 1,
+      DiagnosticLevel::Error => 2,
+    }
+  }
+}
+
+/// What reported a diagnostic. Each source only ever replaces its own diagnostics.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub enum DiagnosticSource {
+  #[default]
+  LanguageServer,
+  /// The formatter of the file's language, see `:format`.
+  Formatter,
+  /// The task with this name, see `:task`.
+  Task(String),
+}
+
+/// A message attached to the char range `from..to` of a file.
+#[derive(Debug, PartialEq, Clone)]
 pub struct Diagnostic {
-  level: DiagnosticLevel,
-  message: String,
-  file: PathBuf,
-  from: usize,
-  to: usize,
+  pub level: DiagnosticLevel,
+  pub message: String,
+  pub file: PathBuf,
+  pub from: usize,
+  pub to: usize,
+  pub source: DiagnosticSource,
+}
+
+impl Diagnostic {
+  pub fn new(
+    level: DiagnosticLevel,
+    message: String,
+    file: PathBuf,
+    from: usize,
+    to: usize,
+  ) -> Self {
+    Diagnostic { level, message, file, from, to, source: DiagnosticSource::default() }
+  }
+  pub fn error(message: String, file: PathBuf, from: usize, to: usize) -> Self {
+    Self::new(DiagnosticLevel::Error, message, file, from, to)
+  }
+  pub fn warn(message: String, file: PathBuf, from: usize, to: usize) -> Self {
+    Self::new(DiagnosticLevel::Warn, message, file, from, to)
+  }
+  pub fn info(message: String, file: PathBuf, from: usize, to: usize) -> Self {
+    Self::new(DiagnosticLevel::Info, message, file, from, to)
+  }
+
+  #[must_use]
+  pub fn with_source(mut self, source: DiagnosticSource) -> Self {
+    self.source = source;
+    self
+  }
+}
+
+impl Action {
+  /// Whether the action changes the buffer, which makes it what `.` repeats.
+  pub fn is_change(&self) -> bool {
+    match self {
+      Action::Operate { operator, .. } | Action::OperateSelection(operator) => {
+        matches!(operator, Operator::Delete | Operator::Change)
+      }
+      Action::Put | Action::IndentLine | Action::DedentLine => true,
+      Action::DiffGet | Action::DiffPut => true,
+      _ => false,
+    }
+  }
 }
 
 impl Debug for Action {
@@ -98,6 +335,62 @@ impl Debug for Action {
       Action::CloseActiveBuffer => write!(f, "Action::CloseActiveBuffer"),
       Action::WriteActiveBuffer => write!(f, "Action::WriteActiveBuffer"),
       Action::WriteDiagnostic(_) => write!(f, "Action::WriteDiagnostic"),
+      Action::ClearDiagnostics(_) => write!(f, "Action::ClearDiagnostics"),
+      Action::NextDiagnostic => write!(f, "Action::NextDiagnostic"),
+      Action::PreviousDiagnostic => write!(f, "Action::PreviousDiagnostic"),
+      Action::ShowDiagnostics => write!(f, "Action::ShowDiagnostics"),
+      Action::NextHunk => write!(f, "Action::NextHunk"),
+      Action::PreviousHunk => write!(f, "Action::PreviousHunk"),
+      Action::StageHunk => write!(f, "Action::StageHunk"),
+      Action::ResetHunk => write!(f, "Action::ResetHunk"),
+      Action::DiffSplit(path) => write!(f, "Action::DiffSplit({path:?})"),
+      Action::DiffOff => write!(f, "Action::DiffOff"),
+      Action::NextChange => write!(f, "Action::NextChange"),
+      Action::PreviousChange => write!(f, "Action::PreviousChange"),
+      Action::DiffGet => write!(f, "Action::DiffGet"),
+      Action::DiffPut => write!(f, "Action::DiffPut"),
+      Action::SwitchDiffSide => write!(f, "Action::SwitchDiffSide"),
+      Action::Format => write!(f, "Action::Format"),
+      Action::OpenTerminal => write!(f, "Action::OpenTerminal"),
+      Action::Shell { command, output } => write!(f, "Action::Shell({command}, {output:?})"),
+      Action::CancelShell => write!(f, "Action::CancelShell"),
+      Action::RunTask(name) => write!(f, "Action::RunTask({name:?})"),
+      Action::NextQuickfix => write!(f, "Action::NextQuickfix"),
+      Action::PreviousQuickfix => write!(f, "Action::PreviousQuickfix"),
+      Action::ShowQuickfix => write!(f, "Action::ShowQuickfix"),
+      Action::GotoDefinition => write!(f, "Action::GotoDefinition"),
+      Action::Hover => write!(f, "Action::Hover"),
+      Action::RequestCompletion => write!(f, "Action::RequestCompletion"),
+      Action::IndentLine => write!(f, "Action::IndentLine"),
+      Action::DedentLine => write!(f, "Action::DedentLine"),
+      Action::JumpTo(_, _) => write!(f, "Action::JumpTo"),
+      Action::ClosePopup => write!(f, "Action::ClosePopup"),
+      Action::SetOption { argument, .. } => write!(f, "Action::SetOption({argument})"),
+      Action::ResolveDiskChange(choice) => write!(f, "Action::ResolveDiskChange({choice:?})"),
+      Action::Recover { discard } => write!(f, "Action::Recover({discard})"),
+      Action::MakeSession(path) => write!(f, "Action::MakeSession({path:?})"),
+      Action::SourceSession(path) => write!(f, "Action::SourceSession({path:?})"),
+      Action::SetMark(mark) => write!(f, "Action::SetMark({mark})"),
+      Action::GotoMark { mark, .. } => write!(f, "Action::GotoMark({mark})"),
+      Action::Jump { newer } => write!(f, "Action::Jump({newer})"),
+      Action::ShowMarks => write!(f, "Action::ShowMarks"),
+      Action::ShowJumps => write!(f, "Action::ShowJumps"),
+      Action::Operate { operator, object, inner } => {
+        write!(f, "Action::Operate({operator:?}, {object:?}, {inner})")
+      }
+      Action::SelectObject { object, inner } => {
+        write!(f, "Action::SelectObject({object:?}, {inner})")
+      }
+      Action::OperateSelection(operator) => write!(f, "Action::OperateSelection({operator:?})"),
+      Action::Put => write!(f, "Action::Put"),
+      Action::Undo { redo } => write!(f, "Action::Undo({redo})"),
+      Action::AddCursorAtNextMatch => write!(f, "Action::AddCursorAtNextMatch"),
+      Action::SplitSelection => write!(f, "Action::SplitSelection"),
+      Action::KeepPrimaryCursor => write!(f, "Action::KeepPrimaryCursor"),
+      Action::Fold(command) => write!(f, "Action::Fold({command:?})"),
+      Action::ShowMacros => write!(f, "Action::ShowMacros"),
+      Action::EditMacro(label) => write!(f, "Action::EditMacro({label:?})"),
+      Action::SetMacro { label, keys } => write!(f, "Action::SetMacro({label:?}, {keys:?})"),
     }
   }
 }
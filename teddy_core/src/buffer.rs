@@ -1,9 +1,134 @@
-use ropey::Rope;
+use std::ops::Range;
 
+use ropey::{Rope, RopeSlice};
+
+/// Read access to the text of a buffer. Everything is borrowed, and [`Buffer::revision`] tells
+/// whether anything changed since the last look, so callers can cache what they derive from it.
 pub trait Buffer {
-  fn buff(&self) -> Rope;
+  fn rope(&self) -> &Rope;
+
+  /// Goes up with every change to the text.
+  fn revision(&self) -> u64;
+
+  fn slice(&self, chars: Range<usize>) -> RopeSlice<'_> {
+    self.rope().slice(chars)
+  }
+}
+
+/// Called after every change to a buffer, with the change and the new text.
+pub type ChangeListener = Box<dyn FnMut(&BufferChange, &Rope)>;
+
+/// All changes go through [`WritableBuffer::apply`], which bumps the revision and tells the
+/// listeners.
+pub trait WritableBuffer: Buffer {
+  fn apply(&mut self, edit: &Edit) -> Result<(), ropey::Error>;
+
+  fn on_change(&mut self, listener: ChangeListener);
+}
+
+/// An [`Edit`] made to a buffer, and the revision it produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferChange {
+  pub edit: Edit,
+  pub revision: u64,
+}
+
+/// A rope keeping count of its changes, which writable buffers build on.
+#[derive(Default)]
+pub struct TextBuffer {
+  rope: Rope,
+  revision: u64,
+  listeners: Vec<ChangeListener>,
+}
+
+impl TextBuffer {
+  pub fn new(rope: Rope) -> Self {
+    Self { rope, revision: 0, listeners: Vec::new() }
+  }
+}
+
+impl Buffer for TextBuffer {
+  fn rope(&self) -> &Rope {
+    &self.rope
+  }
+
+  fn revision(&self) -> u64 {
+    self.revision
+  }
+}
+
+impl WritableBuffer for TextBuffer {
+  fn apply(&mut self, edit: &Edit) -> Result<(), ropey::Error> {
+    self.rope.try_remove(edit.from..edit.to)?;
+    self.rope.try_insert(edit.from, &edit.text)?;
+    self.revision += 1;
+
+    let change = BufferChange { edit: edit.clone(), revision: self.revision };
+    for listener in &mut self.listeners {
+      listener(&change, &self.rope);
+    }
+    Ok(())
+  }
+
+  fn on_change(&mut self, listener: ChangeListener) {
+    self.listeners.push(listener);
+  }
+}
+
+/// A single change made to a buffer. `from..to` are char indexes into the text as it was before
+/// the change, and `text` is what replaced that range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+  pub from: usize,
+  pub to: usize,
+  pub text: String,
 }
 
-pub trait WritableBuffer {
-  fn buff_mut(&mut self) -> &mut Rope;
+impl Edit {
+  pub fn insert(at: usize, text: impl Into<String>) -> Self {
+    Self { from: at, to: at, text: text.into() }
+  }
+
+  pub fn remove(from: usize, to: usize) -> Self {
+    Self { from, to, text: String::new() }
+  }
+
+  /// Where the char at `idx` ends up after the edit. Positions at an insertion move past it, and
+  /// positions inside the removed range end up after the new text.
+  pub fn shift(&self, idx: usize) -> usize {
+    let inserted = self.text.chars().count();
+    if idx < self.from {
+      idx
+    } else if idx < self.to {
+      self.from + inserted
+    } else {
+      idx - (self.to - self.from) + inserted
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+
+  #[test]
+  fn test_text_buffer() {
+    let mut buffer = TextBuffer::new(Rope::from_str("hello world"));
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    let seen = changes.clone();
+    buffer.on_change(Box::new(move |change, rope| {
+      seen.borrow_mut().push((change.clone(), rope.to_string()));
+    }));
+
+    buffer.apply(&Edit { from: 0, to: 5, text: "goodbye".to_string() }).unwrap();
+    assert!(buffer.apply(&Edit::remove(4, 100)).is_err());
+    assert_eq!(buffer.revision(), 1);
+    assert_eq!(buffer.slice(8..13), "world");
+
+    let edit = Edit { from: 0, to: 5, text: "goodbye".to_string() };
+    let change = BufferChange { edit, revision: 1 };
+    assert_eq!(*changes.borrow(), [(change, "goodbye world".to_string())]);
+  }
 }
@@ -11,6 +11,8 @@ pub enum InputMode {
   },
   Visual(VisualSelection),
   Command(CommandModeData),
+  /// Keys go to the program in the active terminal frame.
+  Terminal,
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -22,6 +24,22 @@ impl Default for VisualSelection {
   }
 }
 
+impl VisualSelection {
+  /// A selection from `anchor`, which stays put, to `head`, which follows the cursor.
+  pub fn new(anchor: usize, head: usize) -> Self {
+    Self(anchor, head)
+  }
+
+  pub fn set_head(&mut self, head: usize) {
+    self.1 = head;
+  }
+
+  /// The first and last selected char, whichever way the selection was made.
+  pub fn range(&self) -> (usize, usize) {
+    (self.0.min(self.1), self.0.max(self.1))
+  }
+}
+
 #[derive(Default, PartialEq, Debug, Clone)]
 pub struct CommandModeData {
   value: Rope,
@@ -35,6 +53,7 @@ impl Display for InputMode {
       InputMode::Insert { left_insert: _ } => "Insert",
       InputMode::Command(_) => "Cmd",
       InputMode::Visual(_) => "Visual",
+      InputMode::Terminal => "Term",
     };
 
     f.write_str(text)
@@ -42,8 +61,24 @@ impl Display for InputMode {
 }
 
 impl CommandModeData {
+  /// A command line with `text` already typed.
+  pub fn with_text(text: &str) -> Self {
+    Self { value: Rope::from_str(text), cursor: text.chars().count() as u8 }
+  }
+
   pub fn insert(&mut self, char: char) {
     self.value.insert_char(self.cursor.into(), char);
+    self.cursor += 1;
+  }
+
+  /// Removes the char before the cursor, returns false if there was nothing to remove.
+  pub fn backspace(&mut self) -> bool {
+    if self.cursor == 0 {
+      return false;
+    }
+    self.value.remove(self.cursor as usize - 1..self.cursor as usize);
+    self.cursor -= 1;
+    true
   }
 
   pub fn value(&self) -> &ropey::Rope {
@@ -1,3 +1,4 @@
+#[allow(async_fn_in_trait)]
 pub trait EventLoop {
   type Events;
   type Error;
@@ -9,5 +10,6 @@ pub mod action;
 pub mod buffer;
 pub mod component;
 pub mod input_mode;
+pub mod text_object;
 
 pub use ropey::Rope;
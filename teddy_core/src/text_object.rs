@@ -0,0 +1,62 @@
+/// A region around the cursor that operators and visual mode can act on, like `iw` or `a(`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TextObject {
+  /// Letters, digits and underscores, or a run of other punctuation.
+  Word,
+  /// Anything but whitespace.
+  BigWord,
+  /// Between two of the same quote on a line.
+  Quote(char),
+  Between(BetweenChars),
+  Sentence,
+  Paragraph,
+
+  /// The objects below come from the parse tree, so they're only there for languages with one.
+  Function,
+  Class,
+  Argument,
+  Comment,
+}
+
+impl TextObject {
+  /// Whether the parse tree is needed to find the object.
+  pub fn is_syntax(&self) -> bool {
+    matches!(self, Self::Function | Self::Class | Self::Argument | Self::Comment)
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum BetweenChars {
+  /// '(' and ')'
+  Parentheses,
+  /// '[' and ']'
+  Brackets,
+  /// '{' and '}'
+  Braces,
+  /// '<' and '>'
+  AngleBrackets,
+}
+
+impl BetweenChars {
+  pub fn chars(&self) -> (char, char) {
+    match self {
+      Self::Parentheses => ('(', ')'),
+      Self::Brackets => ('[', ']'),
+      Self::Braces => ('{', '}'),
+      Self::AngleBrackets => ('<', '>'),
+    }
+  }
+}
+
+/// What to do with a text object or the visual selection.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operator {
+  Delete,
+  /// Deletes, then starts insert mode.
+  Change,
+  Yank,
+  /// Makes a closed fold over the lines, like `zf`.
+  Fold,
+  /// Starts a `:{range}!` command line for the lines, to filter them through a program.
+  Filter,
+}
@@ -1,5 +1,17 @@
 use teddy_core::{input_mode::InputMode, Rope};
 
+/// Length of line `y` without its line break.
+fn line_len(rope: &Rope, y: usize) -> usize {
+  let line = rope.line(y);
+  let len = line.len_chars();
+
+  if len > 0 && line.char(len - 1) == '\n' {
+    len - 1
+  } else {
+    len
+  }
+}
+
 #[derive(Default, Debug)]
 pub struct Cursor {
   y: usize,
@@ -15,12 +27,7 @@ impl Cursor {
     self.real_x = None;
   }
   pub fn move_right(&mut self, rope: &Rope, mode: &InputMode) {
-    let line = rope.line(self.y);
-    let mut len = line.len_chars();
-
-    if line.char(len - 1) == '\n' && line.len_chars() != 0 {
-      len -= 1;
-    }
+    let mut len = line_len(rope, self.y);
 
     if let &InputMode::Insert { left_insert: _ } = mode {
       len += 1;
@@ -37,34 +44,36 @@ impl Cursor {
       self.x = 0;
       return;
     }
-    let line_before = rope.line(self.y - 1);
-    let line_before_len = line_before.len_chars() - 1; // -1 for \n
-
-    if line_before_len < self.x {
-      self.real_x = Some(self.x);
-      self.x = line_before_len - 1;
-    } else if let Some(x) = self.real_x {
-      self.x = x;
-      self.real_x = None;
-    }
-    self.y -= 1;
+    self.move_vertically(rope, self.y - 1);
   }
 
   pub fn move_down(&mut self, rope: &Rope) {
-    if self.y + 1 == rope.len_lines() {
+    if self.y + 1 >= rope.len_lines() {
       return;
     }
-    let line_after = rope.line(self.y + 1);
-    let line_after_len = line_after.len_chars() - 1; // -1 for \n
-
-    if line_after_len < self.x {
-      self.real_x = Some(self.x);
-      self.x = line_after_len;
-    } else if let Some(x) = self.real_x {
-      self.x = x;
+    self.move_vertically(rope, self.y + 1);
+  }
+
+  /// Moves to line `y`, remembering the column if the line is too short for it so that it can be
+  /// restored on a later, longer line.
+  fn move_vertically(&mut self, rope: &Rope, y: usize) {
+    let wanted_x = self.real_x.unwrap_or(self.x);
+    let last_x = line_len(rope, y).saturating_sub(1);
+
+    if wanted_x > last_x {
+      self.real_x = Some(wanted_x);
+      self.x = last_x;
+    } else {
+      self.x = wanted_x;
       self.real_x = None;
     }
-    self.y += 1;
+    self.y = y;
+  }
+
+  pub fn goto(&mut self, x: usize, y: usize) {
+    self.x = x;
+    self.y = y;
+    self.real_x = None;
   }
 
   pub fn readjust(&mut self, rope: &Rope) {
@@ -1,15 +1,23 @@
 use crossterm::event::Event as CrosstermEvent;
 use futures::{Stream, StreamExt as _};
-use std::{io::Error as IoError, pin::Pin, time::Duration};
-use tokio::time;
-use tokio_stream::{wrappers::IntervalStream, StreamMap};
+use std::{io::Error as IoError, path::PathBuf, pin::Pin, time::Duration};
+use tokio::{sync::mpsc::UnboundedReceiver, time};
+use tokio_stream::{
+  wrappers::{IntervalStream, UnboundedReceiverStream},
+  StreamMap,
+};
 
-use crate::crossterm::crossterm_stream;
+use crate::{
+  crossterm::crossterm_stream, FileLoadEvent, FormatEvent, LspEvent, ShellEvent, TaskEvent,
+  TerminalEvent, VcsEvent,
+};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-enum EventName {
+pub enum EventName {
   Crossterm,
   Render,
+  /// Everything sent by background tasks, like language servers and file loading.
+  Background,
 }
 
 #[derive(Debug)]
@@ -17,6 +25,16 @@ pub enum Event {
   Crossterm(CrosstermEvent),
   EventStreamError(IoError),
   Render,
+  Lsp(LspEvent),
+  FileLoad(FileLoadEvent),
+  /// A watched file changed on disk, see [`crate::FileWatcher`].
+  FileChanged(PathBuf),
+  Vcs(VcsEvent),
+  /// Output of a program in a terminal frame, read by [`crate::read_pty`].
+  Terminal(TerminalEvent),
+  Shell(ShellEvent),
+  Task(TaskEvent),
+  Format(FormatEvent),
 }
 
 pub struct EventStream {
@@ -36,6 +54,12 @@ impl EventStream {
   pub async fn next(&mut self) -> Option<Event> {
     self.streams.next().await.map(|(_name, event)| event)
   }
+
+  /// Merges events sent from a background task into the stream, replacing any earlier source with
+  /// the same name.
+  pub fn attach(&mut self, name: EventName, receiver: UnboundedReceiver<Event>) {
+    self.streams.insert(name, Box::pin(UnboundedReceiverStream::new(receiver)));
+  }
 }
 
 fn render_stream() -> Pin<Box<dyn Stream<Item = Event>>> {
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+/// Progress of a file being read on a background thread, see large-file mode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileLoadEvent {
+  /// The next piece of `path`, already decoded. `loaded` of `total` bytes have been read so far.
+  Chunk {
+    path: PathBuf,
+    text: String,
+    loaded: u64,
+    total: u64,
+  },
+  /// The whole file was read. `encoding` names what it was decoded from.
+  Done {
+    path: PathBuf,
+    encoding: String,
+  },
+  Failed {
+    path: PathBuf,
+    error: String,
+  },
+}
@@ -0,0 +1,20 @@
+/// How an external formatter started by `:format` or a write ended. `id` is the run's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatEvent {
+  Formatted {
+    id: u64,
+    text: String,
+  },
+  /// The formatter refused the text, usually over a syntax error. `position` is the line and
+  /// column it pointed at, both from zero.
+  Rejected {
+    id: u64,
+    message: String,
+    position: Option<(usize, usize)>,
+  },
+  /// The formatter couldn't be run, or didn't finish in time.
+  Failed {
+    id: u64,
+    error: String,
+  },
+}
@@ -1,4 +1,20 @@
 mod crossterm;
 mod events;
+mod file;
+mod format;
+mod lsp;
+mod shell;
+mod task;
+mod terminal;
+mod vcs;
+mod watcher;
 
 pub use events::*;
+pub use file::*;
+pub use format::*;
+pub use lsp::*;
+pub use shell::*;
+pub use task::*;
+pub use terminal::*;
+pub use vcs::*;
+pub use watcher::*;
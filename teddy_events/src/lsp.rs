@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+/// A position as reported by a language server: a zero based line and a character offset in the
+/// server's negotiated position encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspPosition {
+  pub line: usize,
+  pub character: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LspSeverity {
+  Error,
+  Warning,
+  Information,
+  Hint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspDiagnostic {
+  pub severity: LspSeverity,
+  pub message: String,
+  pub start: LspPosition,
+  pub end: LspPosition,
+}
+
+/// A replacement of the text between two positions, as in a formatting result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspTextEdit {
+  pub start: LspPosition,
+  pub end: LspPosition,
+  pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspCompletionItem {
+  pub label: String,
+  pub detail: Option<String>,
+  pub documentation: Option<String>,
+  pub insert_text: Option<String>,
+}
+
+/// Results and notifications coming back from a running language server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LspEvent {
+  /// `textDocument/publishDiagnostics`, replaces every diagnostic for `path`.
+  Diagnostics {
+    path: PathBuf,
+    diagnostics: Vec<LspDiagnostic>,
+  },
+  Hover {
+    contents: String,
+  },
+  Definition {
+    path: PathBuf,
+    position: LspPosition,
+  },
+  Completion {
+    items: Vec<LspCompletionItem>,
+  },
+  /// `textDocument/formatting`, edits to the document as it was when it was asked.
+  Formatting {
+    edits: Vec<LspTextEdit>,
+  },
+  /// `window/showMessage` and friends.
+  Message {
+    severity: LspSeverity,
+    message: String,
+  },
+  /// The server process for `language` went away.
+  Exited {
+    language: String,
+  },
+}
@@ -0,0 +1,15 @@
+/// How a shell command started with `:!` ended. `id` is the job's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShellEvent {
+  Finished {
+    id: u64,
+    success: bool,
+    stdout: String,
+    stderr: String,
+  },
+  /// The command couldn't be run at all.
+  Failed {
+    id: u64,
+    error: String,
+  },
+}
@@ -0,0 +1,18 @@
+/// Output of a task started with `:make` or `:task`, as it comes. `id` is the run's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskEvent {
+  /// A line the task wrote, to stdout or stderr.
+  Line {
+    id: u64,
+    line: String,
+  },
+  Finished {
+    id: u64,
+    success: bool,
+  },
+  /// The task couldn't be run at all.
+  Failed {
+    id: u64,
+    error: String,
+  },
+}
@@ -0,0 +1,42 @@
+use std::{
+  fs::File,
+  io::{ErrorKind, Read},
+};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::Event;
+
+/// What a program running in a terminal frame writes, see [`read_pty`]. `id` is the frame's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminalEvent {
+  Output {
+    id: u16,
+    bytes: Vec<u8>,
+  },
+  /// The program closed its end of the pseudo terminal, usually by exiting.
+  Closed {
+    id: u16,
+  },
+}
+
+/// Reads the master side of a pseudo terminal on a background thread, sending everything the
+/// program on the other side writes to `events` as it comes.
+pub fn read_pty(id: u16, mut pty: File, events: UnboundedSender<Event>) {
+  std::thread::spawn(move || {
+    let mut buffer = [0; 8192];
+    loop {
+      let bytes = match pty.read(&mut buffer) {
+        // Linux fails reads with EIO once the other side is closed.
+        Ok(0) => break,
+        Ok(read) => buffer[..read].to_vec(),
+        Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+        Err(_) => break,
+      };
+      if events.send(Event::Terminal(TerminalEvent::Output { id, bytes })).is_err() {
+        return;
+      }
+    }
+    let _ = events.send(Event::Terminal(TerminalEvent::Closed { id }));
+  });
+}
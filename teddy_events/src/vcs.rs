@@ -0,0 +1,11 @@
+use std::path::PathBuf;
+
+/// Results of git commands run on a background thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VcsEvent {
+  /// Who last changed `line` of `path`, as it was at `revision` of its buffer.
+  Blame { path: PathBuf, line: usize, revision: u64, blame: Option<String> },
+  /// The work tree `path` is in, the path from its `root` the way git names it, the checked out
+  /// branch and the staged text, `None` while the file isn't tracked.
+  Index { path: PathBuf, root: PathBuf, relative: String, branch: String, index: Option<String> },
+}
@@ -0,0 +1,93 @@
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::Event;
+
+/// How long to wait for more changes after one, so a save made of several writes is reported once.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Sends [`Event::FileChanged`] when a watched file is written, replaced or removed. Directories
+/// are watched rather than the files themselves, so files replaced by a rename, like git and most
+/// formatters do, keep being watched.
+pub struct FileWatcher {
+  watcher: Option<RecommendedWatcher>,
+  directories: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+  /// Forwards changes to `events` from a tokio task. Nothing is watched outside a tokio runtime,
+  /// or when the OS can't watch files.
+  pub fn new(events: UnboundedSender<Event>) -> Self {
+    let watcher = match tokio::runtime::Handle::try_current() {
+      Ok(_) => Self::spawn(events),
+      Err(_) => None,
+    };
+    Self { watcher, directories: HashSet::new() }
+  }
+
+  fn spawn(events: UnboundedSender<Event>) -> Option<RecommendedWatcher> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+      let Ok(event) = event else {
+        return;
+      };
+      if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+        for path in event.paths {
+          let _ = sender.send(path);
+        }
+      }
+    });
+    let watcher = match watcher {
+      Ok(watcher) => watcher,
+      Err(err) => {
+        tracing::warn!("Failed to watch files: {err}");
+        return None;
+      }
+    };
+
+    tokio::spawn(async move {
+      while let Some(path) = receiver.recv().await {
+        let mut changed = Vec::from_iter([path]);
+        let deadline = tokio::time::sleep(DEBOUNCE);
+        tokio::pin!(deadline);
+        loop {
+          tokio::select! {
+            _ = &mut deadline => break,
+            Some(path) = receiver.recv() => changed.push(path),
+          }
+        }
+
+        changed.sort();
+        changed.dedup();
+        for path in changed {
+          if events.send(Event::FileChanged(path)).is_err() {
+            return;
+          }
+        }
+      }
+    });
+    Some(watcher)
+  }
+
+  /// Starts watching `path`, which should be absolute.
+  pub fn watch(&mut self, path: &Path) {
+    let (Some(watcher), Some(directory)) = (self.watcher.as_mut(), path.parent()) else {
+      return;
+    };
+    if self.directories.contains(directory) {
+      return;
+    }
+    match watcher.watch(directory, RecursiveMode::NonRecursive) {
+      Ok(()) => {
+        self.directories.insert(directory.to_path_buf());
+      }
+      Err(err) => tracing::warn!("Failed to watch {}: {err}", directory.display()),
+    }
+  }
+}
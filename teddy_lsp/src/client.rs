@@ -0,0 +1,415 @@
+use std::{
+  collections::HashMap,
+  io,
+  path::Path,
+  process::Stdio,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use ropey::Rope;
+use serde_json::{json, Value};
+use teddy_config::LanguageServerConfig;
+use teddy_core::buffer::Edit;
+use teddy_events::{Event, LspEvent, LspSeverity};
+use tokio::{
+  io::{AsyncBufReadExt as _, BufReader},
+  process::{ChildStderr, ChildStdin, ChildStdout, Command},
+  sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    oneshot,
+  },
+  time::timeout,
+};
+use url::Url;
+
+use crate::{
+  char_to_position,
+  protocol::{self, Capabilities, SyncKind},
+  transport::{read_message, write_message},
+  PositionEncoding,
+};
+
+/// A message waiting to be written to the server. It is built lazily so that positions can be
+/// converted with the encoding the server picked during `initialize`.
+type Outgoing = Box<dyn FnOnce(&Capabilities) -> Option<Value> + Send>;
+
+type Pending = Arc<Mutex<HashMap<u64, &'static str>>>;
+
+/// How long a server gets to answer `shutdown`, and then to exit, before it's killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A running language server speaking JSON-RPC over stdio.
+///
+/// Reading and writing happens on tokio tasks, every result or notification from the server is
+/// sent back to the editor as an [`Event::Lsp`].
+pub struct LanguageServer {
+  language: String,
+  next_id: u64,
+  outgoing: UnboundedSender<Outgoing>,
+  pending: Pending,
+  capabilities: Arc<Mutex<Capabilities>>,
+  /// Resolved once the server has answered `shutdown`.
+  shut_down: oneshot::Receiver<()>,
+  /// Resolved once the process has exited.
+  exited: oneshot::Receiver<()>,
+  kill: oneshot::Sender<()>,
+}
+
+impl LanguageServer {
+  pub fn start(
+    config: &LanguageServerConfig,
+    root: &Path,
+    events: UnboundedSender<Event>,
+  ) -> io::Result<Self> {
+    let mut child = Command::new(&config.command)
+      .args(&config.args)
+      .current_dir(root)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .kill_on_drop(true)
+      .spawn()?;
+
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let language = config.language_id.clone();
+    let (outgoing, outgoing_receiver) = mpsc::unbounded_channel();
+    let (initialized_sender, initialized_receiver) = oneshot::channel();
+    let (shut_down_sender, shut_down) = oneshot::channel();
+    let pending: Pending = Arc::new(Mutex::new(HashMap::from_iter([(0, "initialize")])));
+    let capabilities = Arc::new(Mutex::new(Capabilities::default()));
+
+    let initialize = protocol::request(0, "initialize", protocol::initialize_params(root));
+    tokio::spawn(write_loop(
+      stdin,
+      outgoing_receiver,
+      initialize,
+      initialized_receiver,
+      capabilities.clone(),
+    ));
+
+    let incoming = Incoming {
+      language: language.clone(),
+      events: events.clone(),
+      outgoing: outgoing.clone(),
+      pending: pending.clone(),
+      capabilities: capabilities.clone(),
+      initialized: Some(initialized_sender),
+      shut_down: Some(shut_down_sender),
+    };
+    tokio::spawn(incoming.run(stdout));
+    tokio::spawn(log_stderr(stderr, language.clone()));
+
+    let (kill, kill_receiver) = oneshot::channel();
+    let (exited_sender, exited) = oneshot::channel();
+    let exited_language = language.clone();
+    tokio::spawn(async move {
+      let status = tokio::select! {
+        status = child.wait() => status,
+        Ok(()) = kill_receiver => {
+          let _ = child.kill().await;
+          child.wait().await
+        }
+      };
+      tracing::info!("{exited_language} language server exited: {status:?}");
+      let _ = events.send(Event::Lsp(LspEvent::Exited { language: exited_language }));
+      let _ = exited_sender.send(());
+    });
+
+    Ok(Self { language, next_id: 1, outgoing, pending, capabilities, shut_down, exited, kill })
+  }
+
+  pub fn language(&self) -> &str {
+    &self.language
+  }
+
+  /// The position encoding in use. This is UTF-16 until the server has answered `initialize`.
+  pub fn encoding(&self) -> PositionEncoding {
+    self.capabilities.lock().unwrap().encoding
+  }
+
+  fn send(&self, message: impl FnOnce(&Capabilities) -> Option<Value> + Send + 'static) {
+    if self.outgoing.send(Box::new(message)).is_err() {
+      tracing::warn!("{} language server is not running", self.language);
+    }
+  }
+
+  fn notify<F>(&self, method: &'static str, params: F)
+  where
+    F: FnOnce(&Capabilities) -> Option<Value> + Send + 'static,
+  {
+    self.send(move |capabilities| {
+      params(capabilities).map(|params| protocol::notification(method, params))
+    })
+  }
+
+  fn request<F>(&mut self, method: &'static str, params: F)
+  where
+    F: FnOnce(&Capabilities) -> Value + Send + 'static,
+  {
+    let id = self.next_id;
+    self.next_id += 1;
+
+    self.pending.lock().unwrap().insert(id, method);
+    self.send(move |capabilities| Some(protocol::request(id, method, params(capabilities))))
+  }
+
+  pub fn did_open(&self, uri: &Url, version: i32, text: &Rope) {
+    let params = json!({
+      "textDocument": {
+        "uri": uri.as_str(),
+        "languageId": self.language,
+        "version": version,
+        "text": text.to_string()
+      }
+    });
+    self.notify("textDocument/didOpen", move |_| Some(params));
+  }
+
+  /// `old` is the document before `edit` was applied and `new` the document after.
+  pub fn did_change(&self, uri: &Url, version: i32, old: &Rope, new: &Rope, edit: &Edit) {
+    let (uri, old, new, edit) = (uri.clone(), old.clone(), new.clone(), edit.clone());
+
+    self.notify("textDocument/didChange", move |capabilities| {
+      let change = match capabilities.sync {
+        SyncKind::None => return None,
+        SyncKind::Full => json!({ "text": new.to_string() }),
+        SyncKind::Incremental => {
+          let start = char_to_position(&old, edit.from, capabilities.encoding);
+          let end = char_to_position(&old, edit.to, capabilities.encoding);
+          json!({
+            "range": { "start": protocol::position(start), "end": protocol::position(end) },
+            "text": edit.text
+          })
+        }
+      };
+
+      Some(json!({
+        "textDocument": { "uri": uri.as_str(), "version": version },
+        "contentChanges": [change]
+      }))
+    });
+  }
+
+  pub fn did_save(&self, uri: &Url) {
+    let params = json!({ "textDocument": { "uri": uri.as_str() } });
+    self.notify("textDocument/didSave", move |_| Some(params));
+  }
+
+  pub fn did_close(&self, uri: &Url) {
+    let params = json!({ "textDocument": { "uri": uri.as_str() } });
+    self.notify("textDocument/didClose", move |_| Some(params));
+  }
+
+  fn position_request(&mut self, method: &'static str, uri: &Url, text: &Rope, char_idx: usize) {
+    let (uri, text) = (uri.clone(), text.clone());
+    self.request(method, move |capabilities| {
+      let position = char_to_position(&text, char_idx, capabilities.encoding);
+      json!({ "textDocument": { "uri": uri.as_str() }, "position": protocol::position(position) })
+    });
+  }
+
+  pub fn hover(&mut self, uri: &Url, text: &Rope, char_idx: usize) {
+    self.position_request("textDocument/hover", uri, text, char_idx);
+  }
+
+  pub fn goto_definition(&mut self, uri: &Url, text: &Rope, char_idx: usize) {
+    self.position_request("textDocument/definition", uri, text, char_idx);
+  }
+
+  pub fn completion(&mut self, uri: &Url, text: &Rope, char_idx: usize) {
+    self.position_request("textDocument/completion", uri, text, char_idx);
+  }
+
+  pub fn formatting(&mut self, uri: &Url, tab_size: usize, insert_spaces: bool) {
+    let uri = uri.clone();
+    self.request("textDocument/formatting", move |_| {
+      json!({
+        "textDocument": { "uri": uri.as_str() },
+        "options": { "tabSize": tab_size, "insertSpaces": insert_spaces }
+      })
+    });
+  }
+
+  /// Asks the server to shut down and then to exit, once it has answered or after
+  /// [`SHUTDOWN_TIMEOUT`]. Returns once the process is gone, killing it when it doesn't exit in time.
+  pub async fn shutdown(mut self) {
+    self.request("shutdown", |_| Value::Null);
+    if timeout(SHUTDOWN_TIMEOUT, &mut self.shut_down).await.is_err() {
+      tracing::warn!("{} language server didn't answer shutdown", self.language);
+    }
+    self.notify("exit", |_| Some(Value::Null));
+
+    if timeout(SHUTDOWN_TIMEOUT, &mut self.exited).await.is_err() {
+      tracing::warn!("{} language server didn't exit, killing it", self.language);
+      let _ = self.kill.send(());
+      let _ = self.exited.await;
+    }
+  }
+}
+
+async fn write_loop(
+  mut stdin: ChildStdin,
+  mut outgoing: UnboundedReceiver<Outgoing>,
+  initialize: Value,
+  initialized: oneshot::Receiver<()>,
+  capabilities: Arc<Mutex<Capabilities>>,
+) -> io::Result<()> {
+  write_message(&mut stdin, &initialize).await?;
+
+  // Nothing but `initialize` may be sent before the server has answered it.
+  if initialized.await.is_err() {
+    return Ok(());
+  }
+  write_message(&mut stdin, &protocol::notification("initialized", json!({}))).await?;
+
+  let capabilities = *capabilities.lock().unwrap();
+  while let Some(message) = outgoing.recv().await {
+    if let Some(message) = message(&capabilities) {
+      write_message(&mut stdin, &message).await?;
+    }
+  }
+
+  Ok(())
+}
+
+async fn log_stderr(stderr: ChildStderr, language: String) {
+  let mut lines = BufReader::new(stderr).lines();
+  while let Ok(Some(line)) = lines.next_line().await {
+    tracing::debug!("[{language} lsp] {line}");
+  }
+}
+
+struct Incoming {
+  language: String,
+  events: UnboundedSender<Event>,
+  outgoing: UnboundedSender<Outgoing>,
+  pending: Pending,
+  capabilities: Arc<Mutex<Capabilities>>,
+  initialized: Option<oneshot::Sender<()>>,
+  shut_down: Option<oneshot::Sender<()>>,
+}
+
+impl Incoming {
+  async fn run(mut self, stdout: ChildStdout) {
+    let mut reader = BufReader::new(stdout);
+
+    loop {
+      match read_message(&mut reader).await {
+        Ok(Some(message)) => self.dispatch(message),
+        Ok(None) => break,
+        Err(err) => {
+          tracing::error!("Failed to read from {} language server: {err}", self.language);
+          break;
+        }
+      }
+    }
+  }
+
+  fn send(&self, event: LspEvent) {
+    let _ = self.events.send(Event::Lsp(event));
+  }
+
+  fn dispatch(&mut self, message: Value) {
+    let method = message["method"].as_str();
+    let id = message.get("id").cloned();
+
+    match (method, id) {
+      (Some(method), Some(id)) => self.server_request(method, id, &message["params"]),
+      (Some(method), None) => self.notification(method, &message["params"]),
+      (None, Some(id)) => self.response(id, &message),
+      (None, None) => tracing::warn!("Invalid message from language server: {message}"),
+    }
+  }
+
+  fn response(&mut self, id: Value, message: &Value) {
+    let method = id.as_u64().and_then(|id| self.pending.lock().unwrap().remove(&id));
+    let Some(method) = method else {
+      tracing::warn!("Response to unknown request {id}");
+      return;
+    };
+    // Even an error means the server is done with the request and can be told to exit.
+    if method == "shutdown" {
+      if let Some(shut_down) = self.shut_down.take() {
+        let _ = shut_down.send(());
+      }
+    }
+
+    if let Some(error) = message.get("error") {
+      let message = format!("{method} failed: {}", error["message"].as_str().unwrap_or_default());
+      self.send(LspEvent::Message { severity: LspSeverity::Error, message });
+      return;
+    }
+
+    let result = &message["result"];
+    let event = match method {
+      "initialize" => {
+        *self.capabilities.lock().unwrap() = protocol::parse_capabilities(result);
+        if let Some(initialized) = self.initialized.take() {
+          let _ = initialized.send(());
+        }
+        None
+      }
+      "textDocument/hover" => Some(match protocol::parse_hover(result) {
+        Some(contents) => LspEvent::Hover { contents },
+        None => LspEvent::Message {
+          severity: LspSeverity::Information,
+          message: "No hover information".to_string(),
+        },
+      }),
+      "textDocument/definition" => Some(match protocol::parse_definition(result) {
+        Some((path, position)) => LspEvent::Definition { path, position },
+        None => LspEvent::Message {
+          severity: LspSeverity::Information,
+          message: "No definition found".to_string(),
+        },
+      }),
+      "textDocument/completion" => {
+        Some(LspEvent::Completion { items: protocol::parse_completion(result) })
+      }
+      "textDocument/formatting" => {
+        Some(LspEvent::Formatting { edits: protocol::parse_text_edits(result) })
+      }
+      _ => None,
+    };
+
+    if let Some(event) = event {
+      self.send(event);
+    }
+  }
+
+  /// Teddy doesn't provide anything servers ask for, but they still expect an answer.
+  fn server_request(&self, method: &str, id: Value, params: &Value) {
+    let result = match method {
+      "workspace/configuration" => {
+        let items = params["items"].as_array().map_or(0, Vec::len);
+        Value::Array(vec![Value::Null; items])
+      }
+      _ => Value::Null,
+    };
+
+    let reply = protocol::response(id, result);
+    let _ = self.outgoing.send(Box::new(move |_| Some(reply)));
+  }
+
+  fn notification(&self, method: &str, params: &Value) {
+    let event = match method {
+      "textDocument/publishDiagnostics" => protocol::parse_diagnostics(params)
+        .map(|(path, diagnostics)| LspEvent::Diagnostics { path, diagnostics }),
+      "window/showMessage" => protocol::parse_message(params)
+        .map(|(severity, message)| LspEvent::Message { severity, message }),
+      "window/logMessage" => {
+        tracing::debug!("[{} lsp] {}", self.language, params["message"]);
+        None
+      }
+      _ => None,
+    };
+
+    if let Some(event) = event {
+      self.send(event);
+    }
+  }
+}
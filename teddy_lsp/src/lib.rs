@@ -0,0 +1,13 @@
+//! A Language Server Protocol client. Servers are spawned per language from
+//! [`teddy_config::Config::language_servers`] and talk JSON-RPC over stdio.
+
+mod client;
+mod manager;
+mod position;
+mod protocol;
+mod transport;
+
+pub use client::*;
+pub use manager::*;
+pub use position::*;
+pub use protocol::{path_to_uri, uri_to_path};
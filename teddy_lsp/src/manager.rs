@@ -0,0 +1,200 @@
+use std::{
+  collections::{HashMap, HashSet},
+  io,
+  path::{Path, PathBuf},
+};
+
+use ropey::Rope;
+use teddy_config::LanguageServerConfig;
+use teddy_core::buffer::Edit;
+use teddy_events::{Event, LspPosition};
+use tokio::sync::mpsc::UnboundedSender;
+use url::Url;
+
+use crate::{path_to_uri, position_to_char, LanguageServer};
+
+struct Document {
+  uri: Url,
+  language: String,
+  version: i32,
+  text: Rope,
+}
+
+/// Owns every running language server and keeps them in sync with the open documents.
+///
+/// Servers are started lazily the first time a document of their language is opened.
+pub struct LspManager {
+  configs: Vec<LanguageServerConfig>,
+  root: PathBuf,
+  events: UnboundedSender<Event>,
+
+  servers: HashMap<String, LanguageServer>,
+  failed: HashSet<String>,
+  documents: HashMap<PathBuf, Document>,
+}
+
+impl LspManager {
+  pub fn new(
+    configs: Vec<LanguageServerConfig>,
+    root: PathBuf,
+    events: UnboundedSender<Event>,
+  ) -> Self {
+    Self {
+      configs,
+      root,
+      events,
+      servers: HashMap::new(),
+      failed: HashSet::new(),
+      documents: HashMap::new(),
+    }
+  }
+
+  pub fn config_for(&self, path: &Path) -> Option<&LanguageServerConfig> {
+    let extension = path.extension()?.to_str()?;
+    self.configs.iter().find(|config| config.file_extensions.iter().any(|ext| ext == extension))
+  }
+
+  fn server_for(&mut self, path: &Path) -> Option<&mut LanguageServer> {
+    let language = &self.documents.get(path)?.language;
+    self.servers.get_mut(language)
+  }
+
+  /// Starts tracking `path`, spawning its language server if it isn't running. Files without a
+  /// configured server are ignored.
+  pub fn did_open(&mut self, path: &Path, text: &Rope) -> io::Result<()> {
+    let Some(config) = self.config_for(path).cloned() else {
+      return Ok(());
+    };
+    if self.failed.contains(&config.language_id) {
+      return Ok(());
+    }
+    let Some(uri) = path_to_uri(path) else {
+      return Ok(());
+    };
+
+    if !self.servers.contains_key(&config.language_id) {
+      match LanguageServer::start(&config, &self.root, self.events.clone()) {
+        Ok(server) => {
+          self.servers.insert(config.language_id.clone(), server);
+        }
+        Err(err) => {
+          self.failed.insert(config.language_id.clone());
+          return Err(io::Error::new(err.kind(), format!("{}: {err}", config.command)));
+        }
+      }
+    }
+
+    let document = Document { uri, language: config.language_id, version: 0, text: text.clone() };
+    self.servers[&document.language].did_open(&document.uri, document.version, text);
+    self.documents.insert(path.to_path_buf(), document);
+
+    Ok(())
+  }
+
+  /// `text` is the document after `edit` has been applied.
+  pub fn did_change(&mut self, path: &Path, edit: &Edit, text: &Rope) {
+    let Some(document) = self.documents.get_mut(path) else {
+      return;
+    };
+    let Some(server) = self.servers.get(&document.language) else {
+      return;
+    };
+
+    document.version += 1;
+    server.did_change(&document.uri, document.version, &document.text, text, edit);
+    document.text = text.clone();
+  }
+
+  pub fn did_save(&mut self, path: &Path) {
+    let Some(document) = self.documents.get(path) else {
+      return;
+    };
+    if let Some(server) = self.servers.get(&document.language) {
+      server.did_save(&document.uri);
+    }
+  }
+
+  pub fn did_close(&mut self, path: &Path) {
+    let Some(document) = self.documents.remove(path) else {
+      return;
+    };
+    if let Some(server) = self.servers.get(&document.language) {
+      server.did_close(&document.uri);
+    }
+  }
+
+  /// Returns false if no server is attached to `path`.
+  pub fn hover(&mut self, path: &Path, char_idx: usize) -> bool {
+    self.position_request(path, char_idx, LanguageServer::hover)
+  }
+
+  pub fn goto_definition(&mut self, path: &Path, char_idx: usize) -> bool {
+    self.position_request(path, char_idx, LanguageServer::goto_definition)
+  }
+
+  pub fn completion(&mut self, path: &Path, char_idx: usize) -> bool {
+    self.position_request(path, char_idx, LanguageServer::completion)
+  }
+
+  /// Asks the server of `path` to format it. Returns false if no server is attached to `path`.
+  pub fn formatting(&mut self, path: &Path, tab_size: usize, insert_spaces: bool) -> bool {
+    let Some(uri) = self.documents.get(path).map(|document| document.uri.clone()) else {
+      return false;
+    };
+    match self.server_for(path) {
+      Some(server) => {
+        server.formatting(&uri, tab_size, insert_spaces);
+        true
+      }
+      None => false,
+    }
+  }
+
+  fn position_request<F>(&mut self, path: &Path, char_idx: usize, request: F) -> bool
+  where
+    F: FnOnce(&mut LanguageServer, &Url, &Rope, usize),
+  {
+    let Some(document) = self.documents.get(path) else {
+      return false;
+    };
+    let (uri, text) = (document.uri.clone(), document.text.clone());
+
+    match self.server_for(path) {
+      Some(server) => {
+        request(server, &uri, &text, char_idx);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Converts a position reported by the server attached to `path` into a char index of `text`.
+  pub fn position_to_char(&self, path: &Path, text: &Rope, position: LspPosition) -> usize {
+    let encoding = self
+      .documents
+      .get(path)
+      .and_then(|document| self.servers.get(&document.language))
+      .map(LanguageServer::encoding)
+      .or_else(|| {
+        let language = &self.config_for(path)?.language_id;
+        self.servers.get(language).map(LanguageServer::encoding)
+      })
+      .unwrap_or_default();
+
+    position_to_char(text, position, encoding)
+  }
+
+  /// Forgets a server that has exited so that it is started again on the next open.
+  pub fn server_exited(&mut self, language: &str) {
+    self.servers.remove(language);
+  }
+
+  /// Shuts every server down at once, see [`LanguageServer::shutdown`].
+  pub async fn shutdown(&mut self) {
+    let shutdowns: Vec<_> =
+      self.servers.drain().map(|(_, server)| tokio::spawn(server.shutdown())).collect();
+    for shutdown in shutdowns {
+      let _ = shutdown.await;
+    }
+  }
+}
@@ -0,0 +1,85 @@
+use ropey::Rope;
+use teddy_events::LspPosition;
+
+/// How the `character` part of an [`LspPosition`] is counted, negotiated during `initialize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+  Utf8,
+  #[default]
+  Utf16,
+  Utf32,
+}
+
+impl PositionEncoding {
+  pub(crate) fn from_lsp(name: &str) -> Option<Self> {
+    match name {
+      "utf-8" => Some(Self::Utf8),
+      "utf-16" => Some(Self::Utf16),
+      "utf-32" => Some(Self::Utf32),
+      _ => None,
+    }
+  }
+}
+
+pub fn char_to_position(rope: &Rope, char_idx: usize, encoding: PositionEncoding) -> LspPosition {
+  let char_idx = char_idx.min(rope.len_chars());
+  let line = rope.char_to_line(char_idx);
+  let line_start = rope.line_to_char(line);
+
+  let character = match encoding {
+    PositionEncoding::Utf32 => char_idx - line_start,
+    PositionEncoding::Utf16 => rope.char_to_utf16_cu(char_idx) - rope.char_to_utf16_cu(line_start),
+    PositionEncoding::Utf8 => rope.char_to_byte(char_idx) - rope.char_to_byte(line_start),
+  };
+
+  LspPosition { line, character }
+}
+
+/// Converts a server position into a char index, clamping positions that are past the end of a
+/// line or of the document.
+pub fn position_to_char(rope: &Rope, position: LspPosition, encoding: PositionEncoding) -> usize {
+  if position.line >= rope.len_lines() {
+    return rope.len_chars();
+  }
+
+  let line_start = rope.line_to_char(position.line);
+  let line = rope.line(position.line);
+
+  let offset = match encoding {
+    PositionEncoding::Utf32 => position.character.min(line.len_chars()),
+    PositionEncoding::Utf16 => line.utf16_cu_to_char(position.character.min(line.len_utf16_cu())),
+    PositionEncoding::Utf8 => line.byte_to_char(position.character.min(line.len_bytes())),
+  };
+
+  line_start + offset
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_position_encodings() {
+    let rope = Rope::from_str("fn main() {\n  \"😀\" + x\n}\n");
+    let x = rope.to_string().find('x').map(|byte| rope.byte_to_char(byte)).unwrap();
+
+    let utf32 = char_to_position(&rope, x, PositionEncoding::Utf32);
+    let utf16 = char_to_position(&rope, x, PositionEncoding::Utf16);
+    let utf8 = char_to_position(&rope, x, PositionEncoding::Utf8);
+
+    assert_eq!(utf32, LspPosition { line: 1, character: 8 });
+    assert_eq!(utf16, LspPosition { line: 1, character: 9 });
+    assert_eq!(utf8, LspPosition { line: 1, character: 11 });
+
+    for (position, encoding) in [
+      (utf32, PositionEncoding::Utf32),
+      (utf16, PositionEncoding::Utf16),
+      (utf8, PositionEncoding::Utf8),
+    ] {
+      assert_eq!(position_to_char(&rope, position, encoding), x);
+    }
+
+    let past_end = LspPosition { line: 10, character: 0 };
+    assert_eq!(position_to_char(&rope, past_end, PositionEncoding::Utf16), rope.len_chars());
+  }
+}
@@ -0,0 +1,215 @@
+//! Just enough of the protocol for the features teddy uses. Messages are kept as
+//! [`serde_json::Value`] and picked apart here.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use teddy_events::{LspCompletionItem, LspDiagnostic, LspPosition, LspSeverity, LspTextEdit};
+use url::Url;
+
+use crate::PositionEncoding;
+
+/// The parts of the server's capabilities the client cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+  pub encoding: PositionEncoding,
+  pub sync: SyncKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncKind {
+  None,
+  #[default]
+  Full,
+  Incremental,
+}
+
+pub fn path_to_uri(path: &Path) -> Option<Url> {
+  Url::from_file_path(path).ok()
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+  Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+pub(crate) fn request(id: u64, method: &str, params: Value) -> Value {
+  json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+pub(crate) fn notification(method: &str, params: Value) -> Value {
+  if params.is_null() {
+    return json!({ "jsonrpc": "2.0", "method": method });
+  }
+  json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+pub(crate) fn response(id: Value, result: Value) -> Value {
+  json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub(crate) fn position(position: LspPosition) -> Value {
+  json!({ "line": position.line, "character": position.character })
+}
+
+pub(crate) fn initialize_params(root: &Path) -> Value {
+  let root_uri = path_to_uri(root).map(|uri| uri.to_string());
+  let name = root.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+  json!({
+    "processId": std::process::id(),
+    "clientInfo": { "name": "teddy" },
+    "rootUri": root_uri,
+    "workspaceFolders": root_uri.as_ref().map(|uri| json!([{ "uri": uri, "name": name }])),
+    "capabilities": {
+      "general": { "positionEncodings": ["utf-32", "utf-16"] },
+      "textDocument": {
+        "synchronization": { "didSave": true, "dynamicRegistration": false },
+        "hover": { "contentFormat": ["plaintext", "markdown"] },
+        "definition": { "linkSupport": true },
+        "completion": {
+          "completionItem": {
+            "snippetSupport": false,
+            "documentationFormat": ["plaintext", "markdown"]
+          }
+        },
+        "publishDiagnostics": { "relatedInformation": false }
+      },
+      "window": { "workDoneProgress": false }
+    }
+  })
+}
+
+pub(crate) fn parse_capabilities(result: &Value) -> Capabilities {
+  let capabilities = &result["capabilities"];
+
+  let encoding = capabilities["positionEncoding"]
+    .as_str()
+    .and_then(PositionEncoding::from_lsp)
+    .unwrap_or_default();
+
+  let sync = match &capabilities["textDocumentSync"] {
+    Value::Number(kind) => kind.as_u64(),
+    Value::Object(options) => options.get("change").and_then(Value::as_u64),
+    _ => None,
+  };
+  let sync = match sync {
+    Some(0) => SyncKind::None,
+    Some(2) => SyncKind::Incremental,
+    _ => SyncKind::Full,
+  };
+
+  Capabilities { encoding, sync }
+}
+
+fn parse_position(value: &Value) -> Option<LspPosition> {
+  let line = value["line"].as_u64()?;
+  let character = value["character"].as_u64()?;
+  Some(LspPosition { line: line as usize, character: character as usize })
+}
+
+fn parse_severity(value: &Value) -> LspSeverity {
+  match value.as_u64() {
+    Some(1) => LspSeverity::Error,
+    Some(2) => LspSeverity::Warning,
+    Some(4) => LspSeverity::Hint,
+    _ => LspSeverity::Information,
+  }
+}
+
+pub(crate) fn parse_diagnostics(params: &Value) -> Option<(PathBuf, Vec<LspDiagnostic>)> {
+  let path = uri_to_path(params["uri"].as_str()?)?;
+
+  let diagnostics = params["diagnostics"]
+    .as_array()?
+    .iter()
+    .filter_map(|diagnostic| {
+      Some(LspDiagnostic {
+        severity: parse_severity(&diagnostic["severity"]),
+        message: diagnostic["message"].as_str()?.to_string(),
+        start: parse_position(&diagnostic["range"]["start"])?,
+        end: parse_position(&diagnostic["range"]["end"])?,
+      })
+    })
+    .collect();
+
+  Some((path, diagnostics))
+}
+
+pub(crate) fn parse_message(params: &Value) -> Option<(LspSeverity, String)> {
+  Some((parse_severity(&params["type"]), params["message"].as_str()?.to_string()))
+}
+
+/// Flattens `MarkupContent`, `MarkedString` and arrays of the latter into plain text.
+fn markup_to_string(value: &Value) -> Option<String> {
+  match value {
+    Value::String(text) => Some(text.clone()),
+    Value::Object(markup) => markup.get("value").and_then(Value::as_str).map(str::to_string),
+    Value::Array(parts) => {
+      let parts: Vec<String> = parts.iter().filter_map(markup_to_string).collect();
+      Some(parts.join("\n\n"))
+    }
+    _ => None,
+  }
+}
+
+pub(crate) fn parse_hover(result: &Value) -> Option<String> {
+  markup_to_string(&result["contents"]).filter(|contents| !contents.trim().is_empty())
+}
+
+pub(crate) fn parse_definition(result: &Value) -> Option<(PathBuf, LspPosition)> {
+  let location = match result {
+    Value::Array(locations) => locations.first()?,
+    location => location,
+  };
+
+  // `LocationLink`
+  if let Some(uri) = location["targetUri"].as_str() {
+    let position = parse_position(&location["targetSelectionRange"]["start"])?;
+    return Some((uri_to_path(uri)?, position));
+  }
+
+  let position = parse_position(&location["range"]["start"])?;
+  Some((uri_to_path(location["uri"].as_str()?)?, position))
+}
+
+pub(crate) fn parse_completion(result: &Value) -> Vec<LspCompletionItem> {
+  let items = match result {
+    Value::Array(items) => items,
+    Value::Object(list) => match list.get("items") {
+      Some(Value::Array(items)) => items,
+      _ => return Vec::new(),
+    },
+    _ => return Vec::new(),
+  };
+
+  items
+    .iter()
+    .filter_map(|item| {
+      Some(LspCompletionItem {
+        label: item["label"].as_str()?.to_string(),
+        detail: item["detail"].as_str().map(str::to_string),
+        documentation: markup_to_string(&item["documentation"]),
+        insert_text: item["textEdit"]["newText"]
+          .as_str()
+          .or(item["insertText"].as_str())
+          .map(str::to_string),
+      })
+    })
+    .collect()
+}
+
+pub(crate) fn parse_text_edits(result: &Value) -> Vec<LspTextEdit> {
+  let Value::Array(edits) = result else {
+    return Vec::new();
+  };
+  edits
+    .iter()
+    .filter_map(|edit| {
+      Some(LspTextEdit {
+        start: parse_position(&edit["range"]["start"])?,
+        end: parse_position(&edit["range"]["end"])?,
+        text: edit["newText"].as_str()?.to_string(),
+      })
+    })
+    .collect()
+}
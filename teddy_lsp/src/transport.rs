@@ -0,0 +1,54 @@
+use std::io;
+
+use serde_json::Value;
+use tokio::io::{
+  AsyncBufRead, AsyncBufReadExt as _, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _,
+};
+
+/// Reads a single `Content-Length` framed JSON-RPC message. Returns `None` once the stream is
+/// closed.
+pub(crate) async fn read_message<R>(reader: &mut R) -> io::Result<Option<Value>>
+where
+  R: AsyncBufRead + Unpin,
+{
+  let mut content_length = None;
+  let mut line = String::new();
+
+  loop {
+    line.clear();
+    if reader.read_line(&mut line).await? == 0 {
+      return Ok(None);
+    }
+
+    let header = line.trim_end();
+    if header.is_empty() {
+      break;
+    }
+
+    if let Some(value) = header.strip_prefix("Content-Length:") {
+      let length = value.trim().parse::<usize>().map_err(io::Error::other)?;
+      content_length = Some(length);
+    }
+  }
+
+  let Some(content_length) = content_length else {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header"));
+  };
+
+  let mut body = vec![0; content_length];
+  reader.read_exact(&mut body).await?;
+
+  serde_json::from_slice(&body).map(Some).map_err(io::Error::other)
+}
+
+pub(crate) async fn write_message<W>(writer: &mut W, message: &Value) -> io::Result<()>
+where
+  W: AsyncWrite + Unpin,
+{
+  let body = serde_json::to_vec(message)?;
+  let header = format!("Content-Length: {}\r\n\r\n", body.len());
+
+  writer.write_all(header.as_bytes()).await?;
+  writer.write_all(&body).await?;
+  writer.flush().await
+}
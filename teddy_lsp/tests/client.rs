@@ -0,0 +1,95 @@
+use std::{path::PathBuf, time::Duration};
+
+use ropey::Rope;
+use teddy_config::LanguageServerConfig;
+use teddy_core::buffer::Edit;
+use teddy_events::{Event, LspEvent, LspPosition};
+use teddy_lsp::LspManager;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+async fn next_event(receiver: &mut UnboundedReceiver<Event>) -> LspEvent {
+  let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+    .await
+    .expect("Timed out waiting for the language server")
+    .expect("Event channel closed");
+
+  match event {
+    Event::Lsp(event) => event,
+    other => panic!("Unexpected event {other:?}"),
+  }
+}
+
+fn diagnostic_lines(event: LspEvent) -> Vec<usize> {
+  let LspEvent::Diagnostics { diagnostics, .. } = event else {
+    panic!("Expected diagnostics, got {event:?}");
+  };
+  diagnostics.iter().map(|diagnostic| diagnostic.start.line).collect()
+}
+
+#[tokio::test]
+async fn test_mock_server() {
+  let config = LanguageServerConfig::new("mock", env!("CARGO_BIN_EXE_teddy-mock-lsp"), &["mock"]);
+  let root = std::env::temp_dir();
+  let path = root.join("teddy_lsp_test.mock");
+
+  let (sender, mut receiver) = mpsc::unbounded_channel();
+  let mut manager = LspManager::new(vec![config], root, sender);
+
+  let mut text = Rope::from_str("fn main() {\n  // TODO\n}\n");
+  manager.did_open(&path, &text).unwrap();
+  assert_eq!(diagnostic_lines(next_event(&mut receiver).await), [1]);
+
+  // Incremental edits must leave the server with the same text as the editor.
+  let at = text.line_to_char(2);
+  let edit = Edit::insert(at, "TODO\n");
+  text.insert(at, &edit.text);
+  manager.did_change(&path, &edit, &text);
+  assert_eq!(diagnostic_lines(next_event(&mut receiver).await), [1, 2]);
+
+  let edit = Edit::remove(text.line_to_char(1), text.line_to_char(2));
+  text.remove(edit.from..edit.to);
+  manager.did_change(&path, &edit, &text);
+  assert_eq!(diagnostic_lines(next_event(&mut receiver).await), [1]);
+
+  assert!(manager.hover(&path, 3));
+  assert_eq!(next_event(&mut receiver).await, LspEvent::Hover { contents: "hover: main".into() });
+
+  assert!(manager.goto_definition(&path, 0));
+  assert_eq!(
+    next_event(&mut receiver).await,
+    LspEvent::Definition { path: path.clone(), position: LspPosition { line: 0, character: 3 } }
+  );
+
+  assert!(manager.completion(&path, 0));
+  let LspEvent::Completion { items } = next_event(&mut receiver).await else {
+    panic!("Expected completion items");
+  };
+  assert_eq!(items[0].label, "mock_completion");
+
+  assert!(!manager.hover(&PathBuf::from("/not/open.mock"), 0));
+
+  // The server has exited by the time shutting down returns.
+  manager.shutdown().await;
+  let Ok(Event::Lsp(event)) = receiver.try_recv() else {
+    panic!("Expected the server to have exited");
+  };
+  assert_eq!(event, LspEvent::Exited { language: "mock".into() });
+}
+
+#[tokio::test]
+async fn test_shutdown_kills_a_stuck_server() {
+  let config = LanguageServerConfig::new("mock", env!("CARGO_BIN_EXE_teddy-mock-lsp"), &["mock"])
+    .args(&["--hang"]);
+  let root = std::env::temp_dir();
+  let path = root.join("teddy_lsp_stuck.mock");
+
+  let (sender, mut receiver) = mpsc::unbounded_channel();
+  let mut manager = LspManager::new(vec![config], root, sender);
+  manager.did_open(&path, &Rope::from_str("")).unwrap();
+  assert_eq!(diagnostic_lines(next_event(&mut receiver).await), Vec::<usize>::new());
+
+  tokio::time::timeout(Duration::from_secs(5), manager.shutdown())
+    .await
+    .expect("Timed out shutting down the language server");
+  assert_eq!(next_event(&mut receiver).await, LspEvent::Exited { language: "mock".into() });
+}
@@ -0,0 +1,135 @@
+//! A tiny language server used by the integration tests.
+//!
+//! It keeps a copy of every open document by applying incremental changes and publishes a warning
+//! for every `TODO` it finds. Positions are treated as char offsets, so keep test documents ASCII.
+//! With `--hang` it ignores `shutdown` and `exit`, like a server that's stuck.
+
+use std::{
+  collections::HashMap,
+  io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+fn read_message(stdin: &mut impl BufRead) -> Option<Value> {
+  let mut content_length = 0;
+  loop {
+    let mut line = String::new();
+    if stdin.read_line(&mut line).ok()? == 0 {
+      return None;
+    }
+    let line = line.trim_end();
+    if line.is_empty() {
+      break;
+    }
+    if let Some(length) = line.strip_prefix("Content-Length:") {
+      content_length = length.trim().parse().ok()?;
+    }
+  }
+
+  let mut body = vec![0; content_length];
+  stdin.read_exact(&mut body).ok()?;
+  serde_json::from_slice(&body).ok()
+}
+
+fn write_message(message: Value) {
+  let body = message.to_string();
+  let mut stdout = io::stdout().lock();
+  write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+  stdout.flush().unwrap();
+}
+
+fn offset(text: &str, position: &Value) -> usize {
+  let line = position["line"].as_u64().unwrap() as usize;
+  let character = position["character"].as_u64().unwrap() as usize;
+
+  let line_start: usize = text.split_inclusive('\n').take(line).map(str::len).sum();
+  line_start + character
+}
+
+fn publish_diagnostics(uri: &Value, text: &str) {
+  let diagnostics: Vec<Value> = text
+    .lines()
+    .enumerate()
+    .filter_map(|(line, content)| {
+      let character = content.find("TODO")?;
+      Some(json!({
+        "severity": 2,
+        "message": "TODO found",
+        "range": {
+          "start": { "line": line, "character": character },
+          "end": { "line": line, "character": character + 4 }
+        }
+      }))
+    })
+    .collect();
+
+  write_message(json!({
+    "jsonrpc": "2.0",
+    "method": "textDocument/publishDiagnostics",
+    "params": { "uri": uri, "diagnostics": diagnostics }
+  }));
+}
+
+fn main() {
+  let mut documents: HashMap<String, String> = HashMap::new();
+  let mut stdin = io::stdin().lock();
+  let hang = std::env::args().any(|arg| arg == "--hang");
+
+  while let Some(message) = read_message(&mut stdin) {
+    let params = &message["params"];
+    let uri = &params["textDocument"]["uri"];
+
+    let result = match message["method"].as_str().unwrap_or_default() {
+      "initialize" => json!({
+        "capabilities": {
+          "textDocumentSync": 2,
+          "hoverProvider": true,
+          "definitionProvider": true,
+          "completionProvider": {}
+        }
+      }),
+      "textDocument/didOpen" => {
+        let text = params["textDocument"]["text"].as_str().unwrap().to_string();
+        publish_diagnostics(uri, &text);
+        documents.insert(uri.to_string(), text);
+        continue;
+      }
+      "textDocument/didChange" => {
+        let text = documents.get_mut(&uri.to_string()).unwrap();
+        for change in params["contentChanges"].as_array().unwrap() {
+          let new_text = change["text"].as_str().unwrap();
+          if change["range"].is_null() {
+            *text = new_text.to_string();
+          } else {
+            let start = offset(text, &change["range"]["start"]);
+            let end = offset(text, &change["range"]["end"]);
+            text.replace_range(start..end, new_text);
+          }
+        }
+        publish_diagnostics(uri, text);
+        continue;
+      }
+      "textDocument/hover" => {
+        let text = &documents[&uri.to_string()];
+        let offset = offset(text, &params["position"]);
+        let word: String = text[offset..].chars().take_while(|c| c.is_alphanumeric()).collect();
+        json!({ "contents": { "kind": "plaintext", "value": format!("hover: {word}") } })
+      }
+      "textDocument/definition" => json!({
+        "uri": uri,
+        "range": {
+          "start": { "line": 0, "character": 3 },
+          "end": { "line": 0, "character": 7 }
+        }
+      }),
+      "textDocument/completion" => json!([{ "label": "mock_completion", "detail": "fn()" }]),
+      "shutdown" | "exit" if hang => continue,
+      "shutdown" => Value::Null,
+      "exit" => return,
+      _ => continue,
+    };
+
+    write_message(json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }));
+  }
+}